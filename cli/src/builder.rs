@@ -1,7 +1,7 @@
-use std::{collections::HashSet, fs, path::PathBuf, sync::Arc};
+use std::{collections::HashSet, fs, path::{Path, PathBuf}, sync::Arc};
 
-use chrono::{Datelike, NaiveDate, NaiveDateTime};
-use meta::{Extractors, Meta, MetaClass, MetaSource};
+use chrono::{Datelike, NaiveDate};
+use meta::{CaptureResolver, CaptureTime, Extractors, LiveKind, Location, Meta, MetaAttribute, MetaClass, MetaSource, MetaType, MetaValue, Options, XMP_DATES};
 use storage::{Asset, Database, Partition};
 use uuid::Uuid;
 
@@ -18,6 +18,15 @@ impl Default for NoValue {
 
 struct XmpMeta(rexiv2::Metadata);
 
+/// A file archived next to the asset, read before any row is created.
+struct ChildFile {
+    path: String,
+    name: String,
+    extension: String,
+    relation: &'static str,
+    meta: Meta
+}
+
 #[derive(Debug)]
 pub struct AssetParts {
     pub extension: String,
@@ -82,14 +91,24 @@ impl AssetParts {
 pub struct AssetBuilder<A, P>{
     file: String,
     extractors: Arc<Extractors>,
+    options: Options,
     meta: Meta,
     xmp: Arc<XmpMeta>,
     parts: P,
-    pub asset: A
+    pub asset: A,
+
+    /// Sidecars archived with the asset as (current path, child asset).
+    pub sidecars: Vec<(String, Asset)>,
+
+    /// Live Photo video archived with its still as (current path, child asset).
+    pub live: Option<(String, Asset)>,
+
+    /// Catalog asset whose sampled pre-hash matches the file, see [`AssetBuilder::get_candidate`].
+    candidate: Option<Asset>
 }
 
 impl<A, P> AssetBuilder<A, P> {
-    pub fn open(file: &str) -> Result<AssetBuilder<NoValue, NoValue>, BuilderError> {
+    pub fn open(file: &str, options: &Options) -> Result<AssetBuilder<NoValue, NoValue>, BuilderError> {
         if !fs::metadata(&file).is_ok() {
             return Err("file not found".into());
         }
@@ -101,31 +120,58 @@ impl<A, P> AssetBuilder<A, P> {
         // }
 
         // Get Extractors for Assets
-        let extractors: Extractors = meta::get_extractors(&file)?;
+        let extractors: Extractors = meta::get_extractors_with(&file, options)?;
 
         Ok(AssetBuilder{ 
             file: file.to_string(),
             extractors: Arc::new(extractors),
+            options: options.clone(),
             meta: Meta::new(),
             xmp: Arc::new(XmpMeta(meta)),
             parts: NoValue,
-            asset: NoValue
+            asset: NoValue,
+            sidecars: Vec::new(),
+            live: None,
+            candidate: None
         })
     }
 
-    pub fn get_hash(&self) -> Result<String, BuilderError> {
+    /// All digests for the file as (tag, hash), starting with the primary `hash`.
+    pub fn get_hashes(&self) -> Result<Vec<(String, String)>, BuilderError> {
         let mut meta: Meta = Meta::new();
         
         let extractor = self.extractors.find("HASH")?;
-        extractor.extract(&mut meta).unwrap();
+        extractor.extract(&mut meta)?;
 
-        let hash: String = match meta.find_one(MetaSource::Hash, "hash") {
-            Ok(a) => String::from(a.value),
-            Err(e) => {
-                return Err(format!("error extracting hash {}", e).into());
-            }
+        let hashes: Vec<(String, String)> = meta.0
+            .into_iter()
+            .filter(|x| x.source == MetaSource::Hash)
+            .map(|x| (x.tag, String::from(x.value)))
+            .collect();
+
+        if !hashes.iter().any(|(tag, _)| tag == "hash") {
+            return Err("error extracting hash".into());
+        }
+        return Ok(hashes);
+    }
+
+    /// Catalog asset whose sampled pre-hash matches the file, without hashing the whole file.
+    /// Requires `Options::sample_chunks`, a match still has to be confirmed by the full hash.
+    /// The sample is taken once and kept for [`AssetBuilder::build`].
+    pub fn get_candidate(&mut self, db: &Database) -> Result<Option<Asset>, BuilderError> {
+        if self.meta.find_one(MetaSource::Hash, "hash.sample").is_ok() {
+            return Ok(self.candidate.clone());
+        }
+
+        let extractor = match self.extractors.find("SAMPLE") {
+            Ok(e) => e,
+            Err(_) => return Ok(None)
         };
-        return Ok(hash);
+        extractor.extract(&mut self.meta)?;
+
+        let sample: String = String::from(self.meta.find_one(MetaSource::Hash, "hash.sample")?.value);
+        self.candidate = Asset::find_by_digest(db, "sample", &sample)?;
+        Ok(self.candidate.clone())
     }
 
     pub fn scrub(&self) -> Result<(), BuilderError> {
@@ -137,9 +183,25 @@ impl<A, P> AssetBuilder<A, P> {
     }
 
     pub fn build(&mut self, db: &Database) -> Result<AssetBuilder<Asset, AssetParts>, BuilderError> {
+        let candidate: Option<Asset> = self.get_candidate(db)?;
+
         println!("Extracting meta from {}...", &self.file);
-        let meta: Meta = self.extractors.extract()?;
-        
+        let mut meta: Meta = self.extractors.extract_skipping(&["SAMPLE"])?;
+        for attribute in self.meta.0.drain(..) {
+            meta.add(attribute);
+        }
+
+        // XMP is read through exiv2, its dates join the capture date fallbacks
+        for tag in XMP_DATES {
+            if let Ok(value) = self.xmp.0.get_tag_string(tag) {
+                meta.add(MetaAttribute {
+                    source: MetaSource::Xmp,
+                    tag: tag.to_string(),
+                    value: MetaType::String(MetaValue::from(value)),
+                });
+            }
+        }
+
         self.meta = meta.clone();
 
         let hash: String = String::from(self.meta.find_one(MetaSource::Hash, "hash").unwrap().value);
@@ -147,7 +209,16 @@ impl<A, P> AssetBuilder<A, P> {
         let size: i64 = i64::from(self.meta.find_one( MetaSource::Basic,"size").unwrap().value);
         let meta_class: MetaClass = MetaClass::from(self.meta.find_one(MetaSource::Basic,"class").unwrap().value);
 
-        let parts: AssetParts = self.path_parts()?;
+        let capture: Option<CaptureTime> = CaptureResolver::from(&self.options).file(&self.file).resolve(&self.meta);
+        if let Some(capture) = &capture {
+            println!("DATE {} ({}, {})", capture, String::from(capture.source), String::from(capture.confidence));
+        }
+        let location: Option<Location> = Location::resolve(&self.meta);
+        if let Some(location) = &location {
+            println!("LOCATION {}, {} ({})", location.latitude, location.longitude, String::from(location.source));
+        }
+
+        let parts: AssetParts = self.path_parts(capture.as_ref())?;
 
         let mut asset: Asset = Asset{
             id: 0,
@@ -160,9 +231,41 @@ impl<A, P> AssetBuilder<A, P> {
             status: storage::Status::Created,
             path: None,
             partition: None,
+            captured_at: capture.as_ref().map(|c| c.to_string()),
+            captured_source: capture.as_ref().map(|c| String::from(c.source)),
+            relation: None,
+            latitude: location.as_ref().map(|l| l.latitude),
+            longitude: location.as_ref().map(|l| l.longitude),
+            location_source: location.as_ref().map(|l| String::from(l.source)),
+            content_id: Self::content_id(&self.meta),
+        };
+
+        // a sampled candidate confirmed by the full hash saves the other lookups
+        let mut parent_asset: Option<Asset> = match &candidate {
+            Some(c) if c.hash == asset.hash => Some(c.clone()),
+            _ => Asset::find_by_hash(&db, &asset.hash)?
         };
 
-        let parent_asset: Option<Asset> = Asset::find_by_hash(&db, &asset.hash)?;
+        // same picture re-encoded, rotated losslessly or converted to another format
+        if parent_asset.is_none() {
+            if let Ok(pixel) = self.meta.find_one(MetaSource::Hash, "hash.pixel") {
+                parent_asset = Asset::find_by_digest(db, "pixel", &String::from(pixel.value))?;
+            }
+        }
+
+        // same vector drawing, re-saved or re-indented
+        if parent_asset.is_none() {
+            if let Ok(canonical) = self.meta.find_one(MetaSource::Hash, "hash.canonical") {
+                parent_asset = Asset::find_by_digest(db, "canonical", &String::from(canonical.value))?;
+            }
+        }
+
+        // same media with edited tags
+        if parent_asset.is_none() {
+            if let Ok(essence) = self.meta.find_one(MetaSource::Hash, "hash.essence") {
+                parent_asset = Asset::find_by_hash(db, &String::from(essence.value))?;
+            }
+        }
         asset.parent_id = parent_asset.map_or(None, |x| Some(x.id));
 
         let prefix: String = asset.parent_id.map_or("Primary".to_string(), |_| "Secondary".to_string());
@@ -187,48 +290,142 @@ impl<A, P> AssetBuilder<A, P> {
             }
         }
         
+        // the video of a Live Photo whose still is already archived joins it under the same name
+        if asset.parent_id.is_none() && LiveKind::of(&self.file) == Some(LiveKind::Video) {
+            if let Some(content_id) = &asset.content_id {
+                let stills: Vec<Asset> = Asset::find_by_content_id(db, content_id)?;
+                if let Some(still) = stills.iter().find(|a| LiveKind::of(&a.name) == Some(LiveKind::Still)) {
+                    println!("Live Photo video of asset {} ({})", still.id, still.name);
+                    asset.parent_id = Some(still.id);
+                    asset.relation = Some("live".to_string());
+                    asset.name = Self::paired_name(&still.name, &asset.extension);
+                    asset.path = still.path.clone();
+                    asset.partition = still.partition;
+                }
+            }
+        }
+
+        // sidecars go next to the asset under the same generated stem, and so does the video
+        // of a Live Photo. They are read first so a file that fails leaves no rows behind.
+        let mut files: Vec<ChildFile> = Vec::new();
+        for sidecar in meta::find_sidecars(&self.file) {
+            files.push(ChildFile {
+                name: sidecar.rename(&self.file, &asset.name),
+                extension: String::from(sidecar.kind),
+                relation: "sidecar",
+                meta: self.child_meta(&sidecar.path)?,
+                path: sidecar.path
+            });
+        }
+        if let Some(pair) = meta::find_live_pair(&self.file).filter(|p| p.kind == LiveKind::Video) {
+            let extension: String = Path::new(&pair.path).extension().map_or(String::new(), |e| e.to_string_lossy().to_lowercase());
+            files.push(ChildFile {
+                name: Self::paired_name(&asset.name, &extension),
+                extension,
+                relation: "live",
+                meta: self.child_meta(&pair.path)?,
+                path: pair.path
+            });
+        }
+
         asset.create(&db)?;
+        Self::add_hashes(db, &asset, &self.meta)?;
+
+        let mut sidecars: Vec<(String, Asset)> = Vec::new();
+        let mut live: Option<(String, Asset)> = None;
+        for file in files {
+            let child: Asset = Self::child(db, &asset, &file)?;
+            match file.relation {
+                "live" => live = Some((file.path, child)),
+                _ => sidecars.push((file.path, child))
+            }
+        }
 
         Ok(AssetBuilder{ 
             file: self.file.to_string(),
             extractors: self.extractors.clone(),
+            options: self.options.clone(),
             meta,
             xmp: self.xmp.clone(),
             parts,
-            asset
+            asset,
+            sidecars,
+            live,
+            candidate
         })
     }
 
-    fn path_parts(&self) -> Result<AssetParts, BuilderError> {
+    fn content_id(meta: &Meta) -> Option<String> {
+        meta.find_one(MetaSource::Live, "content_id").ok().map(|a| String::from(a.value))
+    }
+
+    /// The name of a Live Photo half archived next to the other, e.g. 0b7e.heic and 0b7e.mov.
+    fn paired_name(name: &str, extension: &str) -> String {
+        let stem: &str = Path::new(name).file_stem().and_then(|s| s.to_str()).unwrap_or(name);
+        format!("{}.{}", stem, extension.to_lowercase())
+    }
+
+    /// Additional digests of a created asset, e.g. hash.sha256 -> sha256.
+    fn add_hashes(db: &Database, asset: &Asset, meta: &Meta) -> Result<(), BuilderError> {
+        for attribute in meta.0.iter().filter(|x| x.source == MetaSource::Hash) {
+            if let Some(algorithm) = attribute.tag.strip_prefix("hash.") {
+                asset.add_hash(db, algorithm, &String::from(attribute.value.clone()))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Meta of a file archived next to the asset, extracted with the same options.
+    fn child_meta(&self, path: &str) -> Result<Meta, BuilderError> {
+        let meta: Meta = meta::get_extractors_with(path, &self.options)?.extract()?;
+        meta.find_one(MetaSource::Basic, "class")?;
+        meta.find_one(MetaSource::Basic, "size")?;
+        meta.find_one(MetaSource::Hash, "hash")?;
+        Ok(meta)
+    }
+
+    /// A file archived next to `asset` and attached to it with its relation.
+    fn child(db: &Database, asset: &Asset, file: &ChildFile) -> Result<Asset, BuilderError> {
+        let meta: &Meta = &file.meta;
+
+        let mut child: Asset = Asset{
+            id: 0,
+            parent_id: Some(asset.id),
+            name: file.name.clone(),
+            extension: file.extension.clone(),
+            class: MetaClass::from(meta.find_one(MetaSource::Basic, "class")?.value),
+            hash: String::from(meta.find_one(MetaSource::Hash, "hash")?.value),
+            size_in_bytes: i64::from(meta.find_one(MetaSource::Basic, "size")?.value),
+            status: storage::Status::Created,
+            path: asset.path.clone(),
+            partition: asset.partition,
+            captured_at: asset.captured_at.clone(),
+            captured_source: asset.captured_source.clone(),
+            relation: Some(file.relation.to_string()),
+            latitude: asset.latitude,
+            longitude: asset.longitude,
+            location_source: asset.location_source.clone(),
+            content_id: Self::content_id(meta),
+        };
+        child.create(db)?;
+        Self::add_hashes(db, &child, meta)?;
+        Ok(child)
+    }
+
+    fn path_parts(&self, capture: Option<&CaptureTime>) -> Result<AssetParts, BuilderError> {
         let make: String = String::from(self.meta.find_one( MetaSource::Exif,"Make").unwrap_or_default().value);
         let model: String = String::from(self.meta.find_one( MetaSource::Exif,"Model").unwrap_or_default().value);
 
-        let datetime_str: String = String::from(self.meta.find_one( MetaSource::Exif,"DateTimeOriginal").unwrap_or_default().value);
-
-        if datetime_str.len() > 0 {
-            println!("DATE {}", datetime_str);
-            let naive_datetime: NaiveDateTime = NaiveDateTime::parse_from_str(&datetime_str, "%Y-%m-%d %H:%M:%S")
-                .expect(&format!("Failed to parse date time {:?}", datetime_str));
-    
-            let date: NaiveDate = naive_datetime.date();
-    
-            return Ok(AssetParts{
-                extension: String::from(self.meta.find_one( MetaSource::Basic,"extension").unwrap_or_default().value),
-                make: (!make.is_empty()).then(|| make),
-                model: (!model.is_empty()).then(|| model),
-                year: Some(date.year()),
-                month: Some(date.month0() + 1)
-            });
-        }
+        // wall-clock date of the capture, which is what the photographer would file it under
+        let date: Option<NaiveDate> = capture.map(|c| c.local.date());
 
         Ok(AssetParts{
             extension: String::from(self.meta.find_one( MetaSource::Basic,"extension").unwrap_or_default().value),
             make: (!make.is_empty()).then(|| make),
             model: (!model.is_empty()).then(|| model),
-            year: None,
-            month: None
+            year: date.map(|d| d.year()),
+            month: date.map(|d| d.month())
         })
-
     }
 }
 
@@ -2,6 +2,7 @@ mod builder;
 
 use builder::{AssetBuilder, AssetParts, Builder};
 use storage::{Asset, Database};
+use meta::{FilenamePattern, HashAlgorithm, LiveKind, Options, Zone};
 use std::{env, error::Error, fs, path::PathBuf};
 use clap::{Parser, Subcommand};
 
@@ -10,6 +11,21 @@ use clap::{Parser, Subcommand};
 struct Cli {
     file: String,
 
+    #[arg(long = "hash", global = true, help = "Additional hash algorithm (sha256, md5)")]
+    hashes: Vec<String>,
+
+    #[arg(long, global = true, help = "Sampled pre-hash with N chunks for duplicate triage")]
+    sample: Option<usize>,
+
+    #[arg(long, global = true, help = "Extract meta from each member of zip and tar archives")]
+    members: bool,
+
+    #[arg(long, global = true, help = "Time zone for capture times without an offset (local, UTC, +02:00)")]
+    zone: Option<String>,
+
+    #[arg(long = "filename-pattern", global = true, help = "Extra file name date pattern as HINT=REGEX with year, month, day named groups")]
+    filename_patterns: Vec<String>,
+
     #[command(subcommand)]
     command: Commands
 }
@@ -39,13 +55,32 @@ pub struct ArchiveOpts {
 fn main() -> Result<(), Box<dyn Error>>{
     let cli: Cli = Cli::parse();
 
+    let mut options: Options = Options::default();
+    for hash in &cli.hashes {
+        options = options.with_hash(HashAlgorithm::try_from(hash.as_str())?);
+    }
+    if let Some(chunks) = cli.sample {
+        options = options.with_sample(chunks);
+    }
+    if cli.members {
+        options = options.with_members();
+    }
+    if let Some(zone) = &cli.zone {
+        options = options.with_zone(Zone::try_from(zone.as_str())?);
+    }
+    for pattern in &cli.filename_patterns {
+        let (hint, regex) = pattern.split_once('=').ok_or("filename pattern must be HINT=REGEX")?;
+        options = options.with_filename_pattern(FilenamePattern::new(hint, regex)?);
+    }
+
     match &cli.command {
         Commands::Hash => {
-            let hash: String = Builder::open(&cli.file)?.get_hash()?;
-            println!("HASH: {}", hash);
+            for (tag, hash) in Builder::open(&cli.file, &options)?.get_hashes()? {
+                println!("{}: {}", tag.to_uppercase(), hash);
+            }
         }, 
         Commands::Scrub => {
-            Builder::open(&cli.file)?.scrub()?;
+            Builder::open(&cli.file, &options)?.scrub()?;
             println!("Scrubbed: {}", &cli.file);
         }
         Commands::Archive(opts) => {
@@ -75,8 +110,22 @@ fn main() -> Result<(), Box<dyn Error>>{
                 db.migrate()?;
             }
 
+            // a Live Photo video is archived with its still
+            let file: String = match meta::find_live_pair(&cli.file).filter(|p| p.kind == LiveKind::Still) {
+                Some(still) => {
+                    println!("Live Photo video of {}, archiving the pair.", still.path);
+                    still.path
+                },
+                None => cli.file.clone()
+            };
+
             // builder
-            let mut builder: AssetBuilder<Asset, AssetParts> = Builder::open(&cli.file)?.build(&db)?;
+            let mut builder: Builder = Builder::open(&file, &options)?;
+            if let Some(candidate) = builder.get_candidate(&db)? {
+                println!("Candidate duplicate of asset {} ({}), confirming with full hash...", candidate.id, candidate.name);
+            }
+
+            let mut builder: AssetBuilder<Asset, AssetParts> = builder.build(&db)?;
 
             // only normalize tags on applicable file types
             // if !builder.asset.extension.contains("gif") {
@@ -92,13 +141,40 @@ fn main() -> Result<(), Box<dyn Error>>{
             real_path.push(&builder.asset.name);
 
             let full_path: String = real_path.display().to_string();
-            println!("Renaming {} to {}", cli.file, full_path);
+
+            // Relocate the Live Photo video under the name of its still first,
+            // so the pair is either moved together or not at all
+            let mut video_path: PathBuf = real_path.clone();
+            if let Some((source, video)) = builder.live.as_ref() {
+                video_path.set_file_name(&video.name);
+                println!("Renaming {} to {}", source, video_path.display());
+                fs::rename(source, &video_path)?;
+            }
 
             // Relocate Asset
-            fs::rename(cli.file, full_path)?;
+            println!("Renaming {} to {}", file, full_path);
+            if let Err(e) = fs::rename(&file, &full_path) {
+                if let Some((source, _)) = builder.live.as_ref() {
+                    if let Err(undo) = fs::rename(&video_path, source) {
+                        eprintln!("Live Photo video left at {}, could not move it back to {}: {}", video_path.display(), source, undo);
+                    }
+                }
+                return Err(e.into());
+            }
 
-            // Update Asset record
-            builder.asset.available(&db)?
+            // Update Asset records
+            builder.asset.available(&db)?;
+            if let Some((_, video)) = builder.live.as_mut() {
+                video.available(&db)?;
+            }
+
+            // Relocate sidecars next to the asset
+            for (source, sidecar) in builder.sidecars.iter_mut() {
+                real_path.set_file_name(&sidecar.name);
+                println!("Renaming {} to {}", source, real_path.display());
+                fs::rename(&source, &real_path)?;
+                sidecar.available(&db)?;
+            }
         }
     }
 
@@ -0,0 +1,406 @@
+use std::{fs::File, path::Path};
+
+use rustfft::{num_complex::Complex, Fft, FftPlanner};
+use symphonia::core::{
+    audio::SampleBuffer,
+    codecs::{DecoderOptions, CODEC_TYPE_NULL},
+    errors::Error,
+    formats::FormatOptions,
+    io::MediaSourceStream,
+    meta::MetadataOptions,
+    probe::Hint,
+};
+
+use crate::{
+    meta::{MetaAttribute, MetaSource, MetaType, MetaValue},
+    FromFile, Extractor, Meta, MetaError};
+
+/// Audio is downmixed and resampled to this rate before fingerprinting.
+const SAMPLE_RATE: u32 = 11025;
+
+/// FFT window and hop, roughly 370ms windows every 124ms.
+const FRAME_SIZE: usize = 4096;
+const HOP_SIZE: usize = 1365;
+
+/// Only the start of the track is fingerprinted, the duration covers all of it.
+const MAX_SECONDS: u32 = 120;
+
+/// Frequency range mapped onto the 12 pitch classes.
+const MIN_FREQ: f32 = 28.0;
+const MAX_FREQ: f32 = 3520.0;
+
+/// Largest offset, in frames, tried when aligning two fingerprints.
+const MAX_SHIFT: isize = 16;
+
+/// Chromaprint-style fingerprint of the decoded audio stored as `hash.acoustic`,
+/// plus the exact PCM duration as `acoustic.duration` from [`MetaSource::Fingerprint`].
+/// The fingerprint is best effort, audio that fails to decode gets none.
+///
+/// Every ~124ms of audio becomes a 32-bit word describing how the energy of
+/// the 12 pitch classes changes, so the same recording ripped to mp3, m4a and
+/// flac gives nearly identical fingerprints. Compare them with [`acoustic_similarity`].
+pub struct AcousticFingerprint {
+    path: String,
+}
+
+impl FromFile for AcousticFingerprint {
+    fn file(path: &str) -> Self {
+        Self { path: path.to_string() }
+    }
+}
+
+/// Downmix to mono and box-filter resample to `SAMPLE_RATE`.
+struct Resampler {
+    step: f64,
+    next: f64,
+    position: u64,
+    sum: f32,
+    count: u32,
+    output: Vec<f32>,
+    limit: usize,
+}
+
+impl Resampler {
+    fn new(source_rate: u32) -> Self {
+        let step: f64 = source_rate as f64 / SAMPLE_RATE as f64;
+        Self {
+            step,
+            next: step,
+            position: 0,
+            sum: 0.0,
+            count: 0,
+            output: Vec::new(),
+            limit: (SAMPLE_RATE * MAX_SECONDS) as usize,
+        }
+    }
+
+    fn push(&mut self, sample: f32) {
+        if self.output.len() >= self.limit {
+            return;
+        }
+
+        self.sum += sample;
+        self.count += 1;
+        self.position += 1;
+
+        if self.position as f64 >= self.next {
+            self.output.push(self.sum / self.count as f32);
+            self.sum = 0.0;
+            self.count = 0;
+            self.next += self.step;
+        }
+    }
+}
+
+/// Decoded audio: resampled mono samples and the full length in source frames.
+struct Decoded {
+    samples: Vec<f32>,
+    frames: u64,
+    sample_rate: u32,
+}
+
+impl AcousticFingerprint {
+    fn decode(&self) -> Result<Option<Decoded>, MetaError> {
+        let file: File = File::open(&self.path)?;
+        let stream: MediaSourceStream = MediaSourceStream::new(Box::new(file), Default::default());
+
+        let mut hint: Hint = Hint::new();
+        if let Some(extension) = Path::new(&self.path).extension().and_then(|x| x.to_str()) {
+            hint.with_extension(extension);
+        }
+
+        let format_options: FormatOptions = FormatOptions { enable_gapless: true, ..Default::default() };
+        let probed = match symphonia::default::get_probe().format(&hint, stream, &format_options, &MetadataOptions::default()) {
+            Ok(p) => p,
+            Err(_) => return Ok(None)
+        };
+        let mut format = probed.format;
+
+        let track = match format.tracks().iter().find(|t| t.codec_params.codec != CODEC_TYPE_NULL) {
+            Some(t) => t.clone(),
+            None => return Ok(None)
+        };
+        let sample_rate: u32 = match track.codec_params.sample_rate {
+            Some(rate) if rate > 0 => rate,
+            _ => return Ok(None)
+        };
+
+        let mut decoder = match symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default()) {
+            Ok(d) => d,
+            Err(_) => return Ok(None)
+        };
+
+        let mut resampler: Resampler = Resampler::new(sample_rate);
+        let mut frames: u64 = 0;
+        let mut buffer: Option<SampleBuffer<f32>> = None;
+
+        loop {
+            let packet = match format.next_packet() {
+                Ok(p) => p,
+                Err(Error::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                Err(Error::ResetRequired) => break,
+                Err(_) => return Ok(None)
+            };
+            if packet.track_id() != track.id {
+                continue;
+            }
+
+            let decoded = match decoder.decode(&packet) {
+                Ok(d) => d,
+                // skip corrupt packets, like players do
+                Err(Error::DecodeError(_)) => continue,
+                Err(_) => return Ok(None)
+            };
+
+            let spec = *decoded.spec();
+            let channels: usize = spec.channels.count().max(1);
+            frames += decoded.frames() as u64;
+
+            if buffer.as_ref().map_or(true, |b| b.capacity() < decoded.capacity() * channels) {
+                buffer = Some(SampleBuffer::new(decoded.capacity() as u64, spec));
+            }
+            let samples: &mut SampleBuffer<f32> = buffer.as_mut().unwrap();
+            samples.copy_interleaved_ref(decoded);
+
+            for frame in samples.samples().chunks(channels) {
+                resampler.push(frame.iter().sum::<f32>() / channels as f32);
+            }
+        }
+
+        Ok(Some(Decoded { samples: resampler.output, frames, sample_rate }))
+    }
+}
+
+/// 12-bin chroma vector per window, L2 normalised.
+fn chroma(samples: &[f32]) -> Vec<[f32; 12]> {
+    let fft: std::sync::Arc<dyn Fft<f32>> = FftPlanner::new().plan_fft_forward(FRAME_SIZE);
+    let window: Vec<f32> = (0..FRAME_SIZE)
+        .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / FRAME_SIZE as f32).cos())
+        .collect();
+
+    // pitch class of each FFT bin, A = 0
+    let classes: Vec<Option<usize>> = (0..FRAME_SIZE / 2)
+        .map(|bin| {
+            let freq: f32 = bin as f32 * SAMPLE_RATE as f32 / FRAME_SIZE as f32;
+            (MIN_FREQ..=MAX_FREQ).contains(&freq)
+                .then(|| ((12.0 * (freq / 27.5).log2()).round() as i64).rem_euclid(12) as usize)
+        })
+        .collect();
+
+    let mut vectors: Vec<[f32; 12]> = Vec::new();
+    let mut buffer: Vec<Complex<f32>> = vec![Complex::new(0.0, 0.0); FRAME_SIZE];
+    let mut start: usize = 0;
+
+    while start + FRAME_SIZE <= samples.len() {
+        for (i, value) in buffer.iter_mut().enumerate() {
+            *value = Complex::new(samples[start + i] * window[i], 0.0);
+        }
+        fft.process(&mut buffer);
+
+        let mut vector: [f32; 12] = [0.0; 12];
+        for (bin, class) in classes.iter().enumerate() {
+            if let Some(class) = class {
+                vector[*class] += buffer[bin].norm_sqr();
+            }
+        }
+
+        let norm: f32 = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if norm > 1e-9 {
+            vector.iter_mut().for_each(|x| *x /= norm);
+        }
+
+        vectors.push(vector);
+        start += HOP_SIZE;
+    }
+
+    vectors
+}
+
+/// One 32-bit word per window (after the first):
+/// bits 0-11 pitch class got louder, bits 12-23 class louder than its upper
+/// neighbour, bits 24-29 class louder than its tritone, bit 30 dominant class
+/// unchanged, bit 31 frame is not silent.
+fn fingerprint(samples: &[f32]) -> Vec<u32> {
+    let vectors: Vec<[f32; 12]> = chroma(samples);
+    let dominant = |v: &[f32; 12]| (0..12).max_by(|a, b| v[*a].total_cmp(&v[*b])).unwrap_or(0);
+
+    vectors
+        .windows(2)
+        .map(|pair| {
+            let (previous, current) = (&pair[0], &pair[1]);
+            let mut word: u32 = 0;
+
+            for c in 0..12 {
+                word |= u32::from(current[c] > previous[c]) << c;
+                word |= u32::from(current[c] > current[(c + 1) % 12]) << (12 + c);
+            }
+            for c in 0..6 {
+                word |= u32::from(current[c] > current[c + 6]) << (24 + c);
+            }
+            word |= u32::from(dominant(current) == dominant(previous)) << 30;
+            word |= u32::from(current.iter().any(|x| *x > 0.0)) << 31;
+            word
+        })
+        .collect()
+}
+
+fn parse(fingerprint: &str) -> Result<Vec<u32>, MetaError> {
+    let fingerprint: &str = fingerprint.trim();
+    if fingerprint.is_empty() || fingerprint.len() % 8 != 0 || !fingerprint.is_ascii() {
+        return Err(format!("invalid acoustic fingerprint of length {}", fingerprint.len()).into());
+    }
+
+    (0..fingerprint.len())
+        .step_by(8)
+        .map(|i| u32::from_str_radix(&fingerprint[i..i + 8], 16)
+            .map_err(|e| format!("invalid acoustic fingerprint: {}", e).into()))
+        .collect()
+}
+
+/// Similarity between two acoustic fingerprints from 0.0 (unrelated) to 1.0 (identical).
+/// The fingerprints are aligned first, so encoder delay and leading silence do not matter.
+pub fn acoustic_similarity(a: &str, b: &str) -> Result<f64, MetaError> {
+    let (a, b) = (parse(a)?, parse(b)?);
+    let min_overlap: usize = (a.len().min(b.len()) / 2).max(1);
+    let mut best: f64 = 0.0;
+
+    for shift in -MAX_SHIFT..=MAX_SHIFT {
+        let pairs: Vec<(&u32, &u32)> = if shift >= 0 {
+            a.iter().skip(shift as usize).zip(b.iter()).collect()
+        } else {
+            a.iter().zip(b.iter().skip((-shift) as usize)).collect()
+        };
+        if pairs.len() < min_overlap {
+            continue;
+        }
+
+        let errors: u32 = pairs.iter().map(|(x, y)| (*x ^ *y).count_ones()).sum();
+        let score: f64 = 1.0 - errors as f64 / (pairs.len() * 32) as f64;
+        best = best.max(score);
+    }
+
+    Ok(best)
+}
+
+impl Extractor for AcousticFingerprint {
+    fn name(&self) -> String {
+        return "ACOUSTIC".to_string();
+    }
+
+    fn extract(&self, meta: &mut Meta) -> Result<(), MetaError> {
+        let decoded: Decoded = match self.decode()? {
+            Some(d) => d,
+            None => return Ok(())
+        };
+
+        meta.add(MetaAttribute {
+            source: MetaSource::Fingerprint,
+            tag: "acoustic.duration".to_string(),
+            value: MetaType::Rational(MetaValue::from(decoded.frames as f64 / decoded.sample_rate as f64)),
+        });
+
+        let words: Vec<u32> = fingerprint(&decoded.samples);
+        if words.is_empty() {
+            return Ok(());
+        }
+
+        meta.add(MetaAttribute {
+            source: MetaSource::Hash,
+            tag: "hash.acoustic".to_string(),
+            value: MetaType::String(MetaValue::from(words.iter().map(|w| format!("{:08x}", w)).collect::<String>())),
+        });
+
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use super::{acoustic_similarity, AcousticFingerprint};
+    use crate::{Extractor, FromFile, Meta, MetaSource, MetaType};
+
+    /// Mono 16-bit PCM WAV playing `notes` (Hz) for half a second each.
+    fn wav(notes: &[f32], sample_rate: u32, delay: usize) -> Vec<u8> {
+        let mut pcm: Vec<i16> = vec![0; delay];
+        for note in notes {
+            for i in 0..sample_rate / 2 {
+                let t: f32 = i as f32 / sample_rate as f32;
+                let v: f32 = (2.0 * std::f32::consts::PI * note * t).sin() * 0.5
+                    + (2.0 * std::f32::consts::PI * note * 2.0 * t).sin() * 0.2;
+                pcm.push((v * 16000.0) as i16);
+            }
+        }
+
+        let data: Vec<u8> = pcm.iter().flat_map(|s| s.to_le_bytes()).collect();
+        let mut fmt: Vec<u8> = Vec::new();
+        fmt.extend_from_slice(&1u16.to_le_bytes());
+        fmt.extend_from_slice(&1u16.to_le_bytes());
+        fmt.extend_from_slice(&sample_rate.to_le_bytes());
+        fmt.extend_from_slice(&(sample_rate * 2).to_le_bytes());
+        fmt.extend_from_slice(&2u16.to_le_bytes());
+        fmt.extend_from_slice(&16u16.to_le_bytes());
+
+        let mut file: Vec<u8> = b"RIFF".to_vec();
+        file.extend_from_slice(&((4 + 8 + fmt.len() + 8 + data.len()) as u32).to_le_bytes());
+        file.extend_from_slice(b"WAVE");
+        file.extend_from_slice(b"fmt ");
+        file.extend_from_slice(&(fmt.len() as u32).to_le_bytes());
+        file.extend_from_slice(&fmt);
+        file.extend_from_slice(b"data");
+        file.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        file.extend_from_slice(&data);
+        file
+    }
+
+    fn extract(name: &str, content: Vec<u8>) -> Meta {
+        let path = std::env::temp_dir().join(format!("informant_{}_{}.wav", name, std::process::id()));
+        fs::write(&path, content).unwrap();
+        let mut meta: Meta = Meta::new();
+        AcousticFingerprint::file(path.to_str().unwrap()).extract(&mut meta).unwrap();
+        fs::remove_file(&path).unwrap();
+        meta
+    }
+
+    fn fingerprint(meta: &Meta) -> String {
+        String::from(meta.find_one(MetaSource::Hash, "hash.acoustic").unwrap().value)
+    }
+
+    #[test]
+    fn test_same_audio_different_encoding() {
+        let melody: [f32; 12] = [261.6, 293.7, 329.6, 349.2, 392.0, 440.0, 493.9, 523.3, 440.0, 349.2, 293.7, 261.6];
+        let other: [f32; 12] = [392.0, 392.0, 261.6, 261.6, 493.9, 311.1, 370.0, 207.7, 277.2, 415.3, 233.1, 466.2];
+
+        let original: Meta = extract("acoustic_a", wav(&melody, 44100, 0));
+        let resampled: Meta = extract("acoustic_b", wav(&melody, 22050, 1000));
+        let different: Meta = extract("acoustic_c", wav(&other, 44100, 0));
+
+        match original.find_one(MetaSource::Fingerprint, "acoustic.duration").unwrap().value {
+            MetaType::Rational(duration) => assert_eq!(duration.value, 6.0),
+            _ => panic!("duration should be rational")
+        }
+
+        let same: f64 = acoustic_similarity(&fingerprint(&original), &fingerprint(&resampled)).unwrap();
+        let unrelated: f64 = acoustic_similarity(&fingerprint(&original), &fingerprint(&different)).unwrap();
+        assert!(same > 0.9, "same {}", same);
+        assert!(unrelated < same - 0.1, "unrelated {} same {}", unrelated, same);
+    }
+
+    #[test]
+    fn test_similarity_rejects_garbage() {
+        assert!(acoustic_similarity("", "00000000").is_err());
+        assert!(acoustic_similarity("0000000", "00000000").is_err());
+        assert_eq!(acoustic_similarity("deadbeef00000000", "deadbeef00000000").unwrap(), 1.0);
+    }
+
+    #[test]
+    fn test_broken_audio() {
+        let mut truncated: Vec<u8> = wav(&[440.0], 44100, 0);
+        truncated.truncate(30);
+
+        assert!(extract("acoustic_d", b"RIFF\x10\0\0\0WAVEjunkjunk".to_vec()).0.is_empty());
+        assert!(extract("acoustic_e", truncated).0.is_empty());
+    }
+}
@@ -0,0 +1,315 @@
+use std::{fs::File, io::{BufReader, Read, Seek}};
+
+use crate::{
+    meta::{MetaAttribute, MetaSource, MetaType, MetaValue},
+    riff::{self, Chunk},
+    Extractor, FromFile, Meta, MetaError};
+
+
+pub struct AVI { path: String }
+
+impl AVI {
+    fn add_str(meta: &mut Meta, tag: String, value: String) {
+        if value.is_empty() {
+            return;
+        }
+        meta.add(MetaAttribute{
+            source: MetaSource::AVI,
+            tag,
+            value: MetaType::String(MetaValue::from(value)),
+        });
+    }
+
+    fn add_u64(meta: &mut Meta, tag: String, value: u64) {
+        meta.add(MetaAttribute{
+            source: MetaSource::AVI,
+            tag,
+            value: MetaType::UInt64(MetaValue::from(value)),
+        });
+    }
+
+    fn add_f64(meta: &mut Meta, tag: String, value: f64) {
+        if !value.is_finite() {
+            return;
+        }
+        meta.add(MetaAttribute{
+            source: MetaSource::AVI,
+            tag,
+            value: MetaType::Rational(MetaValue::from(value)),
+        });
+    }
+
+    /// Main AVI header (AVIMAINHEADER)
+    fn get_avih(&self, data: &[u8], meta: &mut Meta) {
+        let micro_sec_per_frame: u32 = riff::read_u32(data, 0).unwrap_or(0);
+        let total_frames: u32 = riff::read_u32(data, 16).unwrap_or(0);
+
+        Self::add_u64(meta, "avih.micro_sec_per_frame".to_string(), micro_sec_per_frame as u64);
+        Self::add_u64(meta, "avih.total_frames".to_string(), total_frames as u64);
+
+        if let Some(streams) = riff::read_u32(data, 24) {
+            Self::add_u64(meta, "avih.streams".to_string(), streams as u64);
+        }
+        if let Some(width) = riff::read_u32(data, 32) {
+            Self::add_u64(meta, "avih.width".to_string(), width as u64);
+        }
+        if let Some(height) = riff::read_u32(data, 36) {
+            Self::add_u64(meta, "avih.height".to_string(), height as u64);
+        }
+
+        if micro_sec_per_frame > 0 {
+            Self::add_f64(meta, "avih.frame_rate".to_string(), 1_000_000.0 / micro_sec_per_frame as f64);
+            Self::add_f64(meta, "duration".to_string(), total_frames as f64 * micro_sec_per_frame as f64 / 1_000_000.0);
+        }
+    }
+
+    /// Stream header (AVISTREAMHEADER)
+    fn get_strh(&self, stream_no: usize, data: &[u8], meta: &mut Meta) {
+        let stream_type: String = data.get(0..4).map(riff::fourcc).unwrap_or_default();
+        let handler: String = data.get(4..8).map(riff::fourcc).unwrap_or_default();
+
+        Self::add_str(meta, format!("stream_{}.type", stream_no), stream_type.clone());
+        Self::add_str(meta, format!("stream_{}.handler", stream_no), handler);
+
+        let scale: u32 = riff::read_u32(data, 20).unwrap_or(0);
+        let rate: u32 = riff::read_u32(data, 24).unwrap_or(0);
+        let length: u32 = riff::read_u32(data, 32).unwrap_or(0);
+
+        Self::add_u64(meta, format!("stream_{}.length", stream_no), length as u64);
+
+        if scale > 0 && rate > 0 {
+            let per_second: f64 = rate as f64 / scale as f64;
+            if stream_type == "vids" {
+                Self::add_f64(meta, format!("stream_{}.frame_rate", stream_no), per_second);
+            }
+            Self::add_f64(meta, format!("stream_{}.duration", stream_no), length as f64 / per_second);
+        }
+    }
+
+    /// Stream format: BITMAPINFOHEADER for video, WAVEFORMATEX for audio.
+    fn get_strf(&self, stream_no: usize, stream_type: &str, data: &[u8], meta: &mut Meta) {
+        match stream_type {
+            "vids" => {
+                if let Some(width) = riff::read_i32(data, 4) {
+                    Self::add_u64(meta, format!("stream_{}.width", stream_no), width.unsigned_abs() as u64);
+                }
+                if let Some(height) = riff::read_i32(data, 8) {
+                    Self::add_u64(meta, format!("stream_{}.height", stream_no), height.unsigned_abs() as u64);
+                }
+                if let Some(bit_count) = riff::read_u16(data, 14) {
+                    Self::add_u64(meta, format!("stream_{}.bit_count", stream_no), bit_count as u64);
+                }
+                if let Some(compression) = data.get(16..20) {
+                    Self::add_str(meta, format!("stream_{}.compression", stream_no), riff::fourcc(compression));
+                }
+            },
+            "auds" => {
+                if let Some(format_tag) = riff::read_u16(data, 0) {
+                    Self::add_u64(meta, format!("stream_{}.format_tag", stream_no), format_tag as u64);
+                    Self::add_str(meta, format!("stream_{}.audio_format", stream_no), riff::audio_format(format_tag).to_string());
+                }
+                if let Some(channels) = riff::read_u16(data, 2) {
+                    Self::add_u64(meta, format!("stream_{}.channels", stream_no), channels as u64);
+                }
+                if let Some(sample_rate) = riff::read_u32(data, 4) {
+                    Self::add_u64(meta, format!("stream_{}.sample_rate", stream_no), sample_rate as u64);
+                }
+                if let Some(avg_bytes) = riff::read_u32(data, 8) {
+                    Self::add_u64(meta, format!("stream_{}.bitrate", stream_no), avg_bytes as u64 * 8);
+                }
+                if let Some(bits) = riff::read_u16(data, 14) {
+                    Self::add_u64(meta, format!("stream_{}.bits_per_sample", stream_no), bits as u64);
+                }
+            },
+            _ => ()
+        }
+    }
+
+    fn get_info<R: Read + Seek>(&self, reader: &mut R, list: &Chunk, file_size: u64, meta: &mut Meta) -> Result<(), MetaError> {
+        for item in riff::children(reader, list, file_size)? {
+            let data: Vec<u8> = riff::read_payload(reader, &item, 4096)?;
+            Self::add_str(meta, format!("info.{}", riff::info_tag(&item.id)), riff::read_text(&data));
+        }
+        Ok(())
+    }
+
+    fn get_hdrl<R: Read + Seek>(&self, reader: &mut R, hdrl: &Chunk, file_size: u64, meta: &mut Meta) -> Result<(), MetaError> {
+        let mut stream_no: usize = 0;
+
+        for chunk in riff::children(reader, hdrl, file_size)? {
+            if chunk.is(b"avih") {
+                let data: Vec<u8> = riff::read_payload(reader, &chunk, 64)?;
+                self.get_avih(&data, meta);
+            }
+            else if chunk.is_list(b"strl") {
+                let mut stream_type: String = String::new();
+
+                for item in riff::children(reader, &chunk, file_size)? {
+                    if item.is(b"strh") {
+                        let data: Vec<u8> = riff::read_payload(reader, &item, 64)?;
+                        stream_type = data.get(0..4).map(riff::fourcc).unwrap_or_default();
+                        self.get_strh(stream_no, &data, meta);
+                    }
+                    else if item.is(b"strf") {
+                        let data: Vec<u8> = riff::read_payload(reader, &item, 64)?;
+                        self.get_strf(stream_no, &stream_type, &data, meta);
+                    }
+                    else if item.is(b"strn") {
+                        let data: Vec<u8> = riff::read_payload(reader, &item, 256)?;
+                        Self::add_str(meta, format!("stream_{}.name", stream_no), riff::read_text(&data));
+                    }
+                }
+                stream_no += 1;
+            }
+            else if chunk.is_list(b"INFO") {
+                self.get_info(reader, &chunk, file_size, meta)?;
+            }
+            else if chunk.is_list(b"odml") {
+                // OpenDML extended header holds the real frame count for >1GB files.
+                for item in riff::children(reader, &chunk, file_size)? {
+                    if item.is(b"dmlh") {
+                        let data: Vec<u8> = riff::read_payload(reader, &item, 4)?;
+                        if let Some(frames) = riff::read_u32(&data, 0) {
+                            Self::add_u64(meta, "odml.total_frames".to_string(), frames as u64);
+                        }
+                    }
+                }
+            }
+            else if chunk.is(b"IDIT") {
+                self.get_idit(reader, &chunk, meta)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Camera creation date, e.g. "THU OCT 26 16:46:04 2006" or "2005:08:17 11:42:43".
+    fn get_idit<R: Read + Seek>(&self, reader: &mut R, chunk: &Chunk, meta: &mut Meta) -> Result<(), MetaError> {
+        let data: Vec<u8> = riff::read_payload(reader, chunk, 64)?;
+        Self::add_str(meta, "idit".to_string(), riff::read_text(&data));
+        Ok(())
+    }
+
+    pub fn from_reader<R: Read + Seek>(&self, reader: &mut R, file_size: u64, meta: &mut Meta) -> Result<(), MetaError> {
+        let riff: Chunk = match riff::read_chunk(reader, 0, file_size)? {
+            Some(c) if c.is(b"RIFF") && c.is_list(b"AVI ") => c,
+            _ => {
+                return Err(MetaError::from("not an AVI (RIFF) file"));
+            }
+        };
+
+        for chunk in riff::children(reader, &riff, file_size)? {
+            if chunk.is_list(b"hdrl") {
+                self.get_hdrl(reader, &chunk, file_size, meta)?;
+            }
+            else if chunk.is_list(b"INFO") {
+                self.get_info(reader, &chunk, file_size, meta)?;
+            }
+            else if chunk.is(b"IDIT") {
+                self.get_idit(reader, &chunk, meta)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl FromFile for AVI {
+    fn file(path: &str) -> Self {
+        Self { path: path.to_string() }
+    }
+}
+
+impl Extractor for AVI {
+    fn name(&self) -> String {
+        return "VIDEO".to_string();
+    }
+
+    fn extract(&self, meta: &mut Meta) -> Result<(), MetaError> {
+        let file: File = File::open(&self.path)?;
+        let size: u64 = file.metadata()?.len();
+        let mut reader: BufReader<File> = BufReader::new(file);
+
+        self.from_reader(&mut reader, size, meta)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+    use super::AVI;
+    use crate::{MetaError, FromFile, Extractor, Meta, MetaSource};
+
+    const TEST_VIDEO: &str = "../testdata/Video/test.avi";
+
+    fn chunk(id: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut data: Vec<u8> = Vec::new();
+        data.extend_from_slice(id);
+        data.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        data.extend_from_slice(payload);
+        if payload.len() % 2 == 1 {
+            data.push(0);
+        }
+        data
+    }
+
+    fn list(list_type: &[u8; 4], children: &[Vec<u8>]) -> Vec<u8> {
+        let mut payload: Vec<u8> = list_type.to_vec();
+        children.iter().for_each(|c| payload.extend_from_slice(c));
+        let mut data: Vec<u8> = chunk(b"LIST", &payload);
+        if list_type == b"AVI " {
+            data[0..4].copy_from_slice(b"RIFF");
+        }
+        data
+    }
+
+    #[test]
+    fn test_parse_synthetic() {
+        let mut avih: Vec<u8> = vec![0; 56];
+        avih[0..4].copy_from_slice(&40_000u32.to_le_bytes());
+        avih[16..20].copy_from_slice(&250u32.to_le_bytes());
+        avih[24..28].copy_from_slice(&1u32.to_le_bytes());
+        avih[32..36].copy_from_slice(&640u32.to_le_bytes());
+        avih[36..40].copy_from_slice(&480u32.to_le_bytes());
+
+        let mut strh: Vec<u8> = vec![0; 56];
+        strh[0..4].copy_from_slice(b"vids");
+        strh[4..8].copy_from_slice(b"MJPG");
+        strh[20..24].copy_from_slice(&1u32.to_le_bytes());
+        strh[24..28].copy_from_slice(&25u32.to_le_bytes());
+        strh[32..36].copy_from_slice(&250u32.to_le_bytes());
+
+        let data: Vec<u8> = list(b"AVI ", &[
+            list(b"hdrl", &[
+                chunk(b"avih", &avih),
+                list(b"strl", &[chunk(b"strh", &strh)]),
+                chunk(b"IDIT", b"THU OCT 26 16:46:04 2006\n\0"),
+            ]),
+            list(b"INFO", &[chunk(b"ISFT", b"CanonMVI06\0")]),
+        ]);
+
+        let size: u64 = data.len() as u64;
+        let mut meta: Meta = Meta::new();
+        AVI::file(TEST_VIDEO).from_reader(&mut Cursor::new(data), size, &mut meta).unwrap();
+
+        assert_eq!(u64::from(meta.find_one(MetaSource::AVI, "avih.width").unwrap().value), 640);
+        assert_eq!(String::from(meta.find_one(MetaSource::AVI, "duration").unwrap().value), "10");
+        assert_eq!(String::from(meta.find_one(MetaSource::AVI, "stream_0.handler").unwrap().value), "MJPG");
+        assert_eq!(String::from(meta.find_one(MetaSource::AVI, "idit").unwrap().value), "THU OCT 26 16:46:04 2006");
+        assert_eq!(String::from(meta.find_one(MetaSource::AVI, "info.software").unwrap().value), "CanonMVI06");
+    }
+
+    #[test]
+    fn test_parse() {
+        let mut meta: Meta = Meta::new();
+        let extractor: AVI = AVI::file(TEST_VIDEO);
+        let result: Result<(), MetaError> = extractor.extract(&mut meta);
+        match result {
+            Ok(_) => {
+                println!("{:#?}", meta);
+            },
+            Err(e) => {
+                println!("test error {:#?}", e);
+            }
+        }
+    }
+}
@@ -0,0 +1,505 @@
+use std::{fmt, fs, path::Path};
+
+use chrono::{DateTime, Duration, FixedOffset, Local, NaiveDate, NaiveDateTime, NaiveTime, Offset, SecondsFormat, TimeZone, Timelike, Utc};
+
+use crate::{
+    datetime::{parse_datetime, parse_offset, parse_subsec, MAX_OFFSET},
+    filename, Meta, MetaError, MetaSource, Options};
+
+/// Offsets are whole quarter hours. GPS fixes may lag the shutter a little,
+/// a larger remainder means the clocks do not belong together.
+const QUARTER_HOUR: i64 = 15 * 60;
+const GPS_TOLERANCE: i64 = 5 * 60;
+
+/// EXIF dates with their sub-second and offset tags, most specific first.
+const EXIF_DATES: [(&str, &str, &str); 2] = [
+    ("DateTimeOriginal", "SubSecTimeOriginal", "OffsetTimeOriginal"),
+    ("DateTimeDigitized", "SubSecTimeDigitized", "OffsetTimeDigitized"),
+];
+
+/// ModifyDate, rewritten by every editor that saves the file. Only better than the mtime.
+const EXIF_MODIFIED: [(&str, &str, &str); 1] = [("DateTime", "SubSecTime", "OffsetTime")];
+
+/// XMP dates read by the asset builder, most specific first.
+pub const XMP_DATES: [&str; 3] = ["Xmp.exif.DateTimeOriginal", "Xmp.photoshop.DateCreated", "Xmp.xmp.CreateDate"];
+
+/// Seconds from the Unix epoch to the Matroska epoch (2001-01-01).
+const MATROSKA_EPOCH: i64 = 978_307_200;
+
+/// Zone assumed for capture times that carry no offset.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Zone {
+    /// The zone of this machine, daylight saving time included.
+    Local,
+    Fixed(FixedOffset),
+}
+
+impl Zone {
+    fn offset_at(&self, local: &NaiveDateTime) -> Option<FixedOffset> {
+        match self {
+            Zone::Fixed(offset) => Some(*offset),
+            Zone::Local => Local.from_local_datetime(local).earliest().map(|t| t.offset().fix()),
+        }
+    }
+
+    fn offset_at_utc(&self, utc: &DateTime<Utc>) -> FixedOffset {
+        match self {
+            Zone::Fixed(offset) => *offset,
+            Zone::Local => Local.offset_from_utc_datetime(&utc.naive_utc()).fix(),
+        }
+    }
+}
+
+impl TryFrom<&str> for Zone {
+    type Error = MetaError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.trim().to_lowercase().as_str() {
+            "local" => Ok(Zone::Local),
+            "utc" | "z" => Ok(Zone::Fixed(Utc.fix())),
+            v => match parse_offset(v) {
+                Some(offset) => Ok(Zone::Fixed(offset)),
+                None => Err(format!("unsupported time zone: {}", value).into())
+            }
+        }
+    }
+}
+
+/// How the offset of a capture time was established, most certain first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Confidence {
+    /// Recorded by the camera (OffsetTimeOriginal).
+    Recorded,
+    /// Inferred from the GPS (UTC) time against the local time.
+    Gps,
+    /// Taken from the configured default zone.
+    Assumed,
+    /// The instant is known but not the zone, the time is given in UTC.
+    Utc,
+    /// Unknown, only the wall-clock time is available.
+    Local,
+}
+
+impl From<Confidence> for String {
+    fn from(value: Confidence) -> Self {
+        String::from(match value {
+            Confidence::Recorded => "recorded",
+            Confidence::Gps => "gps",
+            Confidence::Assumed => "assumed",
+            Confidence::Utc => "utc",
+            Confidence::Local => "local",
+        })
+    }
+}
+
+/// Where a capture time was found, in the order they are tried.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CaptureSource {
+    Exif,
+    Xmp,
+    /// `photoTakenTime` of a Google Takeout JSON.
+    Takeout,
+    /// `mvhd` creation time of MP4 and QuickTime files.
+    QuickTime,
+    Matroska,
+    MTS,
+    AVI,
+    ID3,
+    /// Origination date of Broadcast Wave files.
+    Bext,
+    Filename,
+    /// EXIF ModifyDate, the last time an editor saved the file.
+    ExifModified,
+    /// Modification time of the file itself.
+    Modified,
+}
+
+impl From<CaptureSource> for String {
+    fn from(value: CaptureSource) -> Self {
+        String::from(match value {
+            CaptureSource::Exif => "exif",
+            CaptureSource::Xmp => "xmp",
+            CaptureSource::Takeout => "takeout",
+            CaptureSource::QuickTime => "quicktime",
+            CaptureSource::Matroska => "matroska",
+            CaptureSource::MTS => "mts",
+            CaptureSource::AVI => "avi",
+            CaptureSource::ID3 => "id3",
+            CaptureSource::Bext => "bext",
+            CaptureSource::Filename => "filename",
+            CaptureSource::ExifModified => "exif.modified",
+            CaptureSource::Modified => "mtime",
+        })
+    }
+}
+
+/// When an asset was captured.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CaptureTime {
+    /// Wall-clock time of the camera, sub-seconds included.
+    pub local: NaiveDateTime,
+    pub offset: Option<FixedOffset>,
+    pub confidence: Confidence,
+    pub source: CaptureSource,
+}
+
+impl CaptureTime {
+    /// The instant of capture, when the offset is known.
+    pub fn timestamp(&self) -> Option<DateTime<FixedOffset>> {
+        self.offset.and_then(|o| o.from_local_datetime(&self.local).single())
+    }
+
+    pub fn utc(&self) -> Option<DateTime<Utc>> {
+        self.timestamp().map(|t| t.with_timezone(&Utc))
+    }
+}
+
+/// RFC 3339, without the offset when it is unknown.
+impl fmt::Display for CaptureTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.timestamp() {
+            Some(t) => write!(f, "{}", t.to_rfc3339_opts(SecondsFormat::AutoSi, false)),
+            None => write!(f, "{}", self.local.format("%Y-%m-%dT%H:%M:%S%.f"))
+        }
+    }
+}
+
+/// Finds the capture time of an asset, trying EXIF, XMP (embedded, then sidecar), Google Takeout, the container dates of
+/// video and audio formats, the file name, the EXIF ModifyDate and finally the modification time.
+/// The EXIF date is combined with its sub-seconds and offset. Without a recorded
+/// offset, it is inferred from the GPS time or taken from the default zone.
+#[derive(Clone, Debug, Default)]
+pub struct CaptureResolver {
+    zone: Option<Zone>,
+    path: Option<String>,
+}
+
+impl From<&Options> for CaptureResolver {
+    fn from(options: &Options) -> Self {
+        Self { zone: options.default_zone, path: None }
+    }
+}
+
+/// A value of the meta, if present and not blank.
+fn value(meta: &Meta, source: MetaSource, tag: &str) -> Option<String> {
+    meta.find_one(source, tag)
+        .ok()
+        .map(|a| String::from(a.value))
+        .filter(|v| !v.trim().is_empty())
+}
+
+fn exif(meta: &Meta, tag: &str) -> Option<String> {
+    value(meta, MetaSource::Exif, tag)
+}
+
+/// A date of the meta with its zone. Values that are present but no usable
+/// date are skipped with a warning, the next source is tried instead.
+fn date(meta: &Meta, source: MetaSource, tag: &str) -> Option<(NaiveDateTime, Option<FixedOffset>)> {
+    match parse_datetime(&value(meta, source.clone(), tag)?) {
+        Ok(date) => date,
+        Err(e) => {
+            eprintln!("date warning ({} {}): {}", String::from(source), tag, e);
+            None
+        }
+    }
+}
+
+/// GPS time of the asset in UTC. Without a GPS date, the day nearest to the local time.
+fn gps_utc(meta: &Meta, local: &NaiveDateTime) -> Option<NaiveDateTime> {
+    let time: NaiveTime = NaiveTime::parse_from_str(&exif(meta, "GPSTimeStamp")?, "%H:%M:%S%.f").ok()?;
+    let date: Option<NaiveDate> = exif(meta, "GPSDateStamp").and_then(|d| {
+        ["%Y-%m-%d", "%Y:%m:%d"].iter().find_map(|f| NaiveDate::parse_from_str(d.trim(), f).ok())
+    });
+
+    match date {
+        Some(date) => Some(date.and_time(time)),
+        None => [-1, 0, 1]
+            .iter()
+            .map(|days| (local.date() + Duration::days(*days)).and_time(time))
+            .min_by_key(|utc| (*local - *utc).num_seconds().abs())
+    }
+}
+
+/// Offset between the local and GPS clocks, rounded to a quarter hour.
+fn gps_offset(local: &NaiveDateTime, utc: &NaiveDateTime) -> Option<FixedOffset> {
+    let difference: i64 = (*local - *utc).num_seconds();
+    let quarters: i64 = (difference as f64 / QUARTER_HOUR as f64).round() as i64;
+    if (difference - quarters * QUARTER_HOUR).abs() > GPS_TOLERANCE {
+        return None;
+    }
+    let seconds: i32 = i32::try_from(quarters * QUARTER_HOUR).ok().filter(|s| s.abs() <= MAX_OFFSET)?;
+    FixedOffset::east_opt(seconds)
+}
+
+impl CaptureResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn default_zone(mut self, zone: Zone) -> Self {
+        self.zone = Some(zone);
+        return self;
+    }
+
+    /// The file the meta belongs to, for its name and modification time.
+    pub fn file(mut self, path: &str) -> Self {
+        self.path = Some(path.to_string());
+        return self;
+    }
+
+    pub fn resolve(&self, meta: &Meta) -> Option<CaptureTime> {
+        self.exif_date(meta, &EXIF_DATES, CaptureSource::Exif)
+            .or_else(|| self.tag_date(meta, MetaSource::Xmp, &XMP_DATES, CaptureSource::Xmp))
+            .or_else(|| self.tag_date(meta, MetaSource::Sidecar, &XMP_DATES, CaptureSource::Xmp))
+            .or_else(|| {
+                let seconds: i64 = i64::from(meta.find_one(MetaSource::Takeout, "photo_taken_time").ok()?.value);
+                self.instant_date(seconds, 0, CaptureSource::Takeout)
+            })
+            .or_else(|| {
+                // already moved from the 1904 epoch by the MP4 reader
+                let seconds: u64 = u64::from(meta.find_one(MetaSource::MP4, "creation_time").ok()?.value);
+                self.instant_date(i64::try_from(seconds).ok().filter(|s| *s > 0)?, 0, CaptureSource::QuickTime)
+            })
+            .or_else(|| {
+                let nanos: i64 = i64::from(meta.find_one(MetaSource::Matroska, "info.date_utc").ok()?.value);
+                let seconds: i64 = MATROSKA_EPOCH + nanos.div_euclid(1_000_000_000);
+                self.instant_date(seconds, nanos.rem_euclid(1_000_000_000) as u32, CaptureSource::Matroska).filter(|_| nanos != 0)
+            })
+            .or_else(|| self.tag_date(meta, MetaSource::MTS, &["mdpm.date_time_original"], CaptureSource::MTS))
+            .or_else(|| self.tag_date(meta, MetaSource::AVI, &["idit"], CaptureSource::AVI))
+            .or_else(|| self.tag_date(meta, MetaSource::ID3, &["date_recorded"], CaptureSource::ID3))
+            .or_else(|| self.tag_date(meta, MetaSource::Wave, &["bext.origination"], CaptureSource::Bext))
+            .or_else(|| self.filename_date(meta))
+            .or_else(|| self.exif_date(meta, &EXIF_MODIFIED, CaptureSource::ExifModified))
+            .or_else(|| {
+                let modified: DateTime<Utc> = DateTime::from(fs::metadata(self.path.as_ref()?).ok()?.modified().ok()?);
+                self.instant_date(modified.timestamp(), modified.timestamp_subsec_nanos(), CaptureSource::Modified)
+            })
+    }
+
+    fn exif_date(&self, meta: &Meta, tags: &[(&str, &str, &str)], capture: CaptureSource) -> Option<CaptureTime> {
+        tags.iter().find_map(|(date_tag, subsec, offset)| {
+            let (local, embedded) = date(meta, MetaSource::Exif, date_tag)?;
+            let local: NaiveDateTime = exif(meta, subsec)
+                .and_then(|s| parse_subsec(&s))
+                .and_then(|nanos| local.with_nanosecond(nanos))
+                .unwrap_or(local);
+            let recorded: Option<FixedOffset> = exif(meta, offset).and_then(|o| parse_offset(&o)).or(embedded);
+            Some(self.with_offset(meta, local, recorded, capture))
+        })
+    }
+
+    /// The first of `tags` holding a date, with its zone if it has one.
+    fn tag_date(&self, meta: &Meta, source: MetaSource, tags: &[&str], capture: CaptureSource) -> Option<CaptureTime> {
+        tags.iter().find_map(|tag| {
+            let (local, recorded) = date(meta, source.clone(), tag)?;
+            Some(self.with_offset(meta, local, recorded, capture))
+        })
+    }
+
+    /// The date found by the filename extractor, or in the path when it did not run.
+    fn filename_date(&self, meta: &Meta) -> Option<CaptureTime> {
+        let (local, minutes) = match date(meta, MetaSource::Filename, "date") {
+            Some((local, _)) => {
+                let minutes: Option<i64> = meta.find_one(MetaSource::Filename, "date.offset").ok().map(|a| i64::from(a.value));
+                (local, minutes)
+            },
+            None => {
+                let found = filename::parse(Path::new(self.path.as_ref()?).file_name()?.to_str()?, &[])?;
+                (found.local, found.offset)
+            }
+        };
+        let recorded: Option<FixedOffset> = minutes.and_then(|m| FixedOffset::east_opt(i32::try_from(m * 60).ok()?));
+        Some(self.with_offset(meta, local, recorded, CaptureSource::Filename))
+    }
+
+    /// A UTC instant, shown in the default zone when there is one.
+    fn instant_date(&self, seconds: i64, nanos: u32, source: CaptureSource) -> Option<CaptureTime> {
+        let utc: DateTime<Utc> = Utc.timestamp_opt(seconds, nanos).single()?;
+        let (offset, confidence) = match self.zone {
+            Some(zone) => (zone.offset_at_utc(&utc), Confidence::Assumed),
+            None => (Utc.fix(), Confidence::Utc)
+        };
+        Some(CaptureTime { local: utc.with_timezone(&offset).naive_local(), offset: Some(offset), confidence, source })
+    }
+
+    fn with_offset(&self, meta: &Meta, local: NaiveDateTime, recorded: Option<FixedOffset>, source: CaptureSource) -> CaptureTime {
+        let (offset, confidence) = match recorded {
+            Some(offset) => (Some(offset), Confidence::Recorded),
+            None => match gps_utc(meta, &local).and_then(|utc| gps_offset(&local, &utc)) {
+                Some(offset) => (Some(offset), Confidence::Gps),
+                None => match self.zone.and_then(|z| z.offset_at(&local)) {
+                    Some(offset) => (Some(offset), Confidence::Assumed),
+                    None => (None, Confidence::Local)
+                }
+            }
+        };
+        CaptureTime { local, offset, confidence, source }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use chrono::FixedOffset;
+
+    use std::fs;
+
+    use super::{CaptureResolver, CaptureSource, Confidence, Zone};
+    use crate::{Meta, MetaAttribute, MetaSource, MetaType, MetaValue};
+
+    /// Meta as the extractors emit it, values passed through the sanitizer.
+    fn tagged(source: MetaSource, tags: &[(&str, &str)]) -> Meta {
+        let mut meta: Meta = Meta::new();
+        for (tag, value) in tags {
+            meta.add(MetaAttribute {
+                source: source.clone(),
+                tag: tag.to_string(),
+                value: MetaType::String(MetaValue::from(value.to_string())),
+            });
+        }
+        meta
+    }
+
+    fn exif(tags: &[(&str, &str)]) -> Meta {
+        tagged(MetaSource::Exif, tags)
+    }
+
+    #[test]
+    fn test_recorded_offset() {
+        let meta: Meta = exif(&[
+            ("DateTimeOriginal", "2021-06-15 13:45:30"),
+            ("SubSecTimeOriginal", "\"045\""),
+            ("OffsetTimeOriginal", "\"+02:00\""),
+            ("GPSTimeStamp", "03:00:00"),
+        ]);
+        let capture = CaptureResolver::new().resolve(&meta).unwrap();
+        assert_eq!(capture.confidence, Confidence::Recorded);
+        assert_eq!(capture.to_string(), "2021-06-15T13:45:30.045+02:00");
+        assert_eq!(capture.utc().unwrap().to_rfc3339(), "2021-06-15T11:45:30.045+00:00");
+
+        // an unset clock is skipped for the next date
+        let meta: Meta = exif(&[
+            ("DateTimeOriginal", "0000:00:00 00:00:00"),
+            ("DateTimeDigitized", "2021:06:15 13:45"),
+        ]);
+        assert_eq!(CaptureResolver::new().resolve(&meta).unwrap().to_string(), "2021-06-15T13:45:00");
+    }
+
+    #[test]
+    fn test_gps_offset() {
+        // a stale fix a minute behind still rounds to -05:00, across midnight UTC
+        let meta: Meta = exif(&[
+            ("DateTimeOriginal", "2021:06:15 21:30:00"),
+            ("GPSDateStamp", "2021:06:16"),
+            ("GPSTimeStamp", "02:29:00"),
+        ]);
+        let capture = CaptureResolver::new().resolve(&meta).unwrap();
+        assert_eq!(capture.confidence, Confidence::Gps);
+        assert_eq!(capture.offset, FixedOffset::west_opt(5 * 3600));
+
+        // without a GPS date, and a GPS time unrelated to the clock
+        let meta: Meta = exif(&[("DateTimeOriginal", "2021-06-15 00:10:00"), ("GPSTimeStamp", "18:40:30.5")]);
+        assert_eq!(CaptureResolver::new().resolve(&meta).unwrap().offset, FixedOffset::east_opt(5 * 3600 + 1800));
+        let meta: Meta = exif(&[("DateTimeOriginal", "2021-06-15 00:10:00"), ("GPSTimeStamp", "18:32:00")]);
+        assert_eq!(CaptureResolver::new().resolve(&meta).unwrap().confidence, Confidence::Local);
+    }
+
+    #[test]
+    fn test_default_zone() {
+        let meta: Meta = exif(&[("DateTimeDigitized", "2021-06-15 13:45:30"), ("DateTimeOriginal", "unknown")]);
+
+        let capture = CaptureResolver::new().resolve(&meta).unwrap();
+        assert_eq!(capture.confidence, Confidence::Local);
+        assert_eq!(capture.to_string(), "2021-06-15T13:45:30");
+        assert!(capture.utc().is_none());
+
+        let resolver: CaptureResolver = CaptureResolver::new().default_zone(Zone::try_from("-03:00").unwrap());
+        let capture = resolver.resolve(&meta).unwrap();
+        assert_eq!(capture.confidence, Confidence::Assumed);
+        assert_eq!(capture.to_string(), "2021-06-15T13:45:30-03:00");
+
+        assert!(CaptureResolver::new().resolve(&exif(&[("Model", "X100")])).is_none());
+    }
+
+    #[test]
+    fn test_modify_date() {
+        // an edited scan: the name still knows when it was taken
+        let meta: Meta = exif(&[("DateTime", "2024:02:01 09:00:00"), ("OffsetTime", "\"+01:00\"")]);
+        let named = CaptureResolver::new().file("/nowhere/IMG_20210615_134530.jpg").resolve(&meta).unwrap();
+        assert_eq!((named.source, named.to_string()), (CaptureSource::Filename, "2021-06-15T13:45:30".to_string()));
+
+        let edited = CaptureResolver::new().file("/nowhere/scan.jpg").resolve(&meta).unwrap();
+        assert_eq!((edited.source, edited.to_string()), (CaptureSource::ExifModified, "2024-02-01T09:00:00+01:00".to_string()));
+
+        let mut meta: Meta = meta;
+        meta.add(MetaAttribute { source: MetaSource::Exif, tag: "DateTimeDigitized".to_string(), value: MetaType::String(MetaValue::from("2020:01:01 12:00:00".to_string())) });
+        assert_eq!(CaptureResolver::new().resolve(&meta).unwrap().to_string(), "2020-01-01T12:00:00");
+    }
+
+    #[test]
+    fn test_fallback_chain() {
+        let resolver: CaptureResolver = CaptureResolver::new();
+
+        let mut meta: Meta = tagged(MetaSource::Xmp, &[("Xmp.xmp.CreateDate", "2019-05-04T10:20:30.25+02:00")]);
+        meta.add(MetaAttribute { source: MetaSource::MP4, tag: "creation_time".to_string(), value: MetaType::UInt64(MetaValue::from(0u64)) });
+        let capture = resolver.resolve(&meta).unwrap();
+        assert_eq!((capture.source, capture.confidence), (CaptureSource::Xmp, Confidence::Recorded));
+        assert_eq!(capture.to_string(), "2019-05-04T10:20:30.250+02:00");
+
+        // an unset creation time falls through, a set one is UTC
+        meta.0.retain(|a| a.source != MetaSource::Xmp);
+        assert!(resolver.resolve(&meta).is_none());
+        meta.0[0].value = MetaType::UInt64(MetaValue::from(1_623_764_730u64));
+        let capture = resolver.resolve(&meta).unwrap();
+        assert_eq!((capture.source, capture.confidence), (CaptureSource::QuickTime, Confidence::Utc));
+        assert_eq!(capture.to_string(), "2021-06-15T13:45:30+00:00");
+        let zoned = CaptureResolver::new().default_zone(Zone::try_from("+02:00").unwrap()).resolve(&meta).unwrap();
+        assert_eq!((zoned.to_string(), zoned.confidence), ("2021-06-15T15:45:30+02:00".to_string(), Confidence::Assumed));
+
+        // Takeout keeps the date of files it stripped, ahead of the container dates
+        meta.add(MetaAttribute { source: MetaSource::Takeout, tag: "photo_taken_time".to_string(), value: MetaType::Int64(MetaValue::from(1_623_757_530i64)) });
+        let capture = resolver.resolve(&meta).unwrap();
+        assert_eq!((capture.source, capture.to_string()), (CaptureSource::Takeout, "2021-06-15T11:45:30+00:00".to_string()));
+
+        let nanos: i64 = (1_623_764_730 - 978_307_200) * 1_000_000_000;
+        let mut meta: Meta = Meta::new();
+        meta.add(MetaAttribute { source: MetaSource::Matroska, tag: "info.date_utc".to_string(), value: MetaType::Int64(MetaValue::from(nanos)) });
+        assert_eq!(resolver.resolve(&meta).unwrap().to_string(), "2021-06-15T13:45:30+00:00");
+
+        let capture = resolver.resolve(&tagged(MetaSource::AVI, &[("idit", "THU OCT 26 16:46:04 2006\n")])).unwrap();
+        assert_eq!((capture.source, capture.to_string()), (CaptureSource::AVI, "2006-10-26T16:46:04".to_string()));
+
+        let capture = resolver.resolve(&tagged(MetaSource::MTS, &[("mdpm.date_time_original", "2010:05:12 14:33:10+01:00")])).unwrap();
+        assert_eq!((capture.source, capture.to_string()), (CaptureSource::MTS, "2010-05-12T14:33:10+01:00".to_string()));
+
+        let capture = resolver.resolve(&tagged(MetaSource::ID3, &[("date_recorded", "2003-06-01T12:00")])).unwrap();
+        assert_eq!(capture.source, CaptureSource::ID3);
+        assert!(resolver.resolve(&tagged(MetaSource::ID3, &[("date_recorded", "2003")])).is_none());
+    }
+
+    #[test]
+    fn test_file_dates() {
+        let named = CaptureResolver::new().file("/nowhere/IMG_20210615_134530.jpg").resolve(&Meta::new()).unwrap();
+        assert_eq!((named.source, named.to_string()), (CaptureSource::Filename, "2021-06-15T13:45:30".to_string()));
+
+        let path = std::env::temp_dir().join(format!("informant-capture-{}.bin", std::process::id()));
+        fs::write(&path, b"undated").unwrap();
+        let modified = CaptureResolver::new().file(path.to_str().unwrap()).resolve(&Meta::new()).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!((modified.source, modified.confidence), (CaptureSource::Modified, Confidence::Utc));
+        assert!(CaptureResolver::new().file("/nowhere/undated.bin").resolve(&Meta::new()).is_none());
+
+        let mut meta: Meta = tagged(MetaSource::Filename, &[("date", "2023:01:01 10:15:30")]);
+        meta.add(MetaAttribute { source: MetaSource::Filename, tag: "date.offset".to_string(), value: MetaType::Int64(MetaValue::from(0i64)) });
+        let pixel = CaptureResolver::new().file("/nowhere/PXL_20230101_101530123.jpg").resolve(&meta).unwrap();
+        assert_eq!((pixel.confidence, pixel.to_string()), (Confidence::Recorded, "2023-01-01T10:15:30+00:00".to_string()));
+    }
+
+    #[test]
+    fn test_parse_zone() {
+        assert_eq!(Zone::try_from("UTC").unwrap(), Zone::Fixed(FixedOffset::east_opt(0).unwrap()));
+        assert_eq!(Zone::try_from("Local").unwrap(), Zone::Local);
+        assert!(Zone::try_from("Europe/Paris").is_err());
+    }
+}
@@ -0,0 +1,561 @@
+use std::{fs::File, io::{BufReader, Read, Seek, SeekFrom}};
+
+use crate::{
+    meta::{MetaAttribute, MetaSource, MetaType, MetaValue},
+    Extractor, FromFile, Meta, MetaError};
+
+/// Frames scanned before giving up on finding a valid sync.
+const MAX_SYNC_SEARCH: u64 = 64 * 1024;
+
+/// MPEG audio bitrates (kbps), indexed by [table][bitrate index]
+const BITRATES: [[u32; 16]; 5] = [
+    // V1 L1
+    [0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448, 0],
+    // V1 L2
+    [0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384, 0],
+    // V1 L3
+    [0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 0],
+    // V2/V2.5 L1
+    [0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256, 0],
+    // V2/V2.5 L2 & L3
+    [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160, 0],
+];
+
+const ADTS_SAMPLE_RATES: [u32; 13] = [96000, 88200, 64000, 48000, 44100, 32000, 24000, 22050, 16000, 12000, 11025, 8000, 7350];
+
+/// AMR payload sizes (bytes, excluding the 1 byte frame header) by frame type.
+const AMR_NB_SIZES: [u64; 16] = [12, 13, 15, 17, 19, 20, 26, 31, 5, 0, 0, 0, 0, 0, 0, 0];
+const AMR_WB_SIZES: [u64; 16] = [17, 23, 32, 36, 40, 46, 50, 58, 60, 5, 0, 0, 0, 0, 0, 0];
+
+/// Technical properties of the audio stream (duration, bitrate, sample rate, ...)
+/// reported under the same tags for every codec.
+#[derive(Debug, Default, PartialEq)]
+struct StreamInfo {
+    codec: String,
+    duration: f64,
+    bitrate: u64,
+    sample_rate: u64,
+    channels: u64,
+    channel_mode: Option<String>,
+    encoder: Option<String>,
+    vbr: bool,
+    frames: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct MpegHeader {
+    version: u8,    // 1, 2, or 25 (MPEG 2.5)
+    layer: u8,
+    bitrate: u32,   // kbps
+    sample_rate: u32,
+    padding: u32,
+    channel_mode: u8,
+}
+
+impl MpegHeader {
+    fn parse(h: &[u8]) -> Option<Self> {
+        if h.len() < 4 || h[0] != 0xff || h[1] & 0xe0 != 0xe0 {
+            return None;
+        }
+        let version: u8 = match (h[1] >> 3) & 0x03 {
+            0 => 25,
+            2 => 2,
+            3 => 1,
+            _ => return None
+        };
+        let layer: u8 = match (h[1] >> 1) & 0x03 {
+            1 => 3,
+            2 => 2,
+            3 => 1,
+            _ => return None
+        };
+        let bitrate_index: usize = (h[2] >> 4) as usize;
+        let rate_index: usize = ((h[2] >> 2) & 0x03) as usize;
+        if bitrate_index == 0 || bitrate_index == 15 || rate_index == 3 {
+            return None;
+        }
+
+        let table: usize = match (version, layer) {
+            (1, l) => (l - 1) as usize,
+            (_, 1) => 3,
+            _ => 4,
+        };
+        let base_rate: u32 = [44100, 48000, 32000][rate_index];
+        let sample_rate: u32 = match version {
+            1 => base_rate,
+            2 => base_rate / 2,
+            _ => base_rate / 4,
+        };
+
+        Some(Self {
+            version,
+            layer,
+            bitrate: BITRATES[table][bitrate_index],
+            sample_rate,
+            padding: ((h[2] >> 1) & 0x01) as u32,
+            channel_mode: h[3] >> 6,
+        })
+    }
+
+    fn samples(&self) -> u32 {
+        match (self.layer, self.version) {
+            (1, _) => 384,
+            (3, 2) | (3, 25) => 576,
+            _ => 1152,
+        }
+    }
+
+    fn length(&self) -> u64 {
+        let length: u32 = match self.layer {
+            1 => (12 * self.bitrate * 1000 / self.sample_rate + self.padding) * 4,
+            _ => self.samples() / 8 * self.bitrate * 1000 / self.sample_rate + self.padding,
+        };
+        length as u64
+    }
+
+    fn channels(&self) -> u64 {
+        if self.channel_mode == 3 { 1 } else { 2 }
+    }
+
+    fn channel_mode(&self) -> &'static str {
+        match self.channel_mode {
+            0 => "stereo",
+            1 => "joint stereo",
+            2 => "dual channel",
+            _ => "mono",
+        }
+    }
+
+    /// Offset of the Xing/Info header, right after the side information.
+    fn xing_offset(&self) -> usize {
+        match (self.version, self.channel_mode) {
+            (1, 3) => 4 + 17,
+            (1, _) => 4 + 32,
+            (_, 3) => 4 + 9,
+            _ => 4 + 17,
+        }
+    }
+
+    /// Two headers belong to the same stream when the fixed fields match.
+    fn same_stream(&self, other: &Self) -> bool {
+        self.version == other.version && self.layer == other.layer && self.sample_rate == other.sample_rate
+    }
+}
+
+pub struct AudioCodec { path: String }
+
+impl AudioCodec {
+    fn add(meta: &mut Meta, info: &StreamInfo) {
+        let add_u64 = |meta: &mut Meta, tag: &str, value: u64| {
+            meta.add(MetaAttribute{
+                source: MetaSource::Codec,
+                tag: tag.to_string(),
+                value: MetaType::UInt64(MetaValue::from(value)),
+            });
+        };
+
+        add_u64(meta, "bitrate", info.bitrate);
+        add_u64(meta, "sample_rate", info.sample_rate);
+        add_u64(meta, "channels", info.channels);
+        add_u64(meta, "frame_count", info.frames);
+        add_u64(meta, "vbr", info.vbr as u64);
+
+        meta.add(MetaAttribute{
+            source: MetaSource::Codec,
+            tag: "duration".to_string(),
+            value: MetaType::Rational(MetaValue::from(info.duration)),
+        });
+
+        let strings: [(&str, Option<&String>); 3] = [
+            ("codec", Some(&info.codec)),
+            ("channel_mode", info.channel_mode.as_ref()),
+            ("encoder", info.encoder.as_ref()),
+        ];
+        for (tag, value) in strings {
+            if let Some(v) = value {
+                meta.add(MetaAttribute{
+                    source: MetaSource::Codec,
+                    tag: tag.to_string(),
+                    value: MetaType::String(MetaValue::from(v.to_owned())),
+                });
+            }
+        }
+    }
+
+    fn read_at<R: Read + Seek>(reader: &mut R, offset: u64, len: usize) -> Result<Vec<u8>, MetaError> {
+        let mut data: Vec<u8> = Vec::with_capacity(len);
+        reader.seek(SeekFrom::Start(offset))?;
+        reader.take(len as u64).read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    /// Byte range holding audio frames, excluding ID3v2 (front), ID3v1 and APEv2 (back).
+    fn audio_range<R: Read + Seek>(reader: &mut R, file_size: u64) -> Result<(u64, u64), MetaError> {
+        let mut start: u64 = 0;
+        let head: Vec<u8> = Self::read_at(reader, 0, 10)?;
+        if head.len() == 10 && &head[0..3] == b"ID3" {
+            let size: u64 = head[6..10].iter().fold(0u64, |acc, b| (acc << 7) | (*b & 0x7f) as u64);
+            let footer: u64 = if head[5] & 0x10 != 0 { 10 } else { 0 };
+            start = 10 + size + footer;
+        }
+
+        let mut end: u64 = file_size;
+        if end >= 128 && Self::read_at(reader, end - 128, 3)? == b"TAG" {
+            end -= 128;
+        }
+        if end >= 32 {
+            let footer: Vec<u8> = Self::read_at(reader, end - 32, 32)?;
+            if footer.len() == 32 && &footer[0..8] == b"APETAGEX" {
+                let size: u64 = u32::from_le_bytes([footer[12], footer[13], footer[14], footer[15]]) as u64;
+                let has_header: bool = footer[23] & 0x80 != 0;
+                end = end.saturating_sub(size + if has_header { 32 } else { 0 });
+            }
+        }
+
+        Ok((start, end.max(start)))
+    }
+
+    /// Find the first frame that is followed by a second consistent frame.
+    fn mpeg_sync<R: Read + Seek>(reader: &mut R, start: u64, end: u64) -> Result<Option<(u64, MpegHeader)>, MetaError> {
+        let search: Vec<u8> = Self::read_at(reader, start, MAX_SYNC_SEARCH.min(end - start) as usize + 4)?;
+
+        for i in 0..search.len().saturating_sub(4) {
+            let first: MpegHeader = match MpegHeader::parse(&search[i..]) {
+                Some(h) => h,
+                None => continue
+            };
+            let next: u64 = start + i as u64 + first.length();
+            if next + 4 > end {
+                return Ok(Some((start + i as u64, first)));
+            }
+            let confirm: Vec<u8> = Self::read_at(reader, next, 4)?;
+            if MpegHeader::parse(&confirm).is_some_and(|h| h.same_stream(&first)) {
+                return Ok(Some((start + i as u64, first)));
+            }
+        }
+        Ok(None)
+    }
+
+    fn from_mpeg<R: Read + Seek>(reader: &mut R, file_size: u64) -> Result<StreamInfo, MetaError> {
+        let (start, end) = Self::audio_range(reader, file_size)?;
+        let (offset, first) = match Self::mpeg_sync(reader, start, end)? {
+            Some(x) => x,
+            None => return Err(MetaError::from("no MPEG audio frames found"))
+        };
+
+        let mut info: StreamInfo = StreamInfo {
+            codec: format!("MPEG-{} Layer {}", if first.version == 25 { "2.5".to_string() } else { first.version.to_string() }, first.layer),
+            sample_rate: first.sample_rate as u64,
+            channels: first.channels(),
+            channel_mode: Some(first.channel_mode().to_string()),
+            ..Default::default()
+        };
+
+        let frame: Vec<u8> = Self::read_at(reader, offset, first.length().max(200) as usize)?;
+        let samples: f64 = first.samples() as f64;
+
+        // Xing / Info (LAME) header
+        let x: usize = first.xing_offset();
+        if frame.len() >= x + 8 && (&frame[x..x + 4] == b"Xing" || &frame[x..x + 4] == b"Info") {
+            info.vbr = &frame[x..x + 4] == b"Xing";
+            let flags: u32 = u32::from_be_bytes([frame[x + 4], frame[x + 5], frame[x + 6], frame[x + 7]]);
+            let mut at: usize = x + 8;
+            let mut frames: Option<u64> = None;
+            let mut bytes: Option<u64> = None;
+
+            if flags & 0x01 != 0 && frame.len() >= at + 4 {
+                frames = Some(u32::from_be_bytes([frame[at], frame[at + 1], frame[at + 2], frame[at + 3]]) as u64);
+                at += 4;
+            }
+            if flags & 0x02 != 0 && frame.len() >= at + 4 {
+                bytes = Some(u32::from_be_bytes([frame[at], frame[at + 1], frame[at + 2], frame[at + 3]]) as u64);
+                at += 4;
+            }
+            if flags & 0x04 != 0 {
+                at += 100;
+            }
+            if flags & 0x08 != 0 {
+                at += 4;
+            }
+            if let Some(encoder) = frame.get(at..at + 9) {
+                let encoder: String = String::from_utf8_lossy(encoder).trim_end_matches(['\0', ' ']).to_string();
+                if encoder.chars().all(|c| c.is_ascii_graphic() || c == ' ') && !encoder.is_empty() {
+                    info.encoder = Some(encoder);
+                }
+            }
+
+            if let Some(frames) = frames {
+                info.frames = frames;
+                info.duration = frames as f64 * samples / first.sample_rate as f64;
+                let bytes: u64 = bytes.unwrap_or(end - offset);
+                if info.duration > 0.0 {
+                    info.bitrate = (bytes as f64 * 8.0 / info.duration) as u64;
+                }
+                return Ok(info);
+            }
+        }
+
+        // VBRI (Fraunhofer)
+        if frame.len() >= 36 + 18 && &frame[36..40] == b"VBRI" {
+            let bytes: u64 = u32::from_be_bytes([frame[46], frame[47], frame[48], frame[49]]) as u64;
+            let frames: u64 = u32::from_be_bytes([frame[50], frame[51], frame[52], frame[53]]) as u64;
+            info.vbr = true;
+            info.encoder = Some("FhG".to_string());
+            info.frames = frames;
+            info.duration = frames as f64 * samples / first.sample_rate as f64;
+            if info.duration > 0.0 {
+                info.bitrate = (bytes as f64 * 8.0 / info.duration) as u64;
+            }
+            return Ok(info);
+        }
+
+        // No header: walk the frames.
+        let mut position: u64 = offset;
+        let mut bytes: u64 = 0;
+        let mut header: [u8; 4] = [0; 4];
+        let mut bitrate: Option<u32> = None;
+        reader.seek(SeekFrom::Start(position))?;
+
+        while position + 4 <= end {
+            if reader.read_exact(&mut header).is_err() {
+                break;
+            }
+            let h: MpegHeader = match MpegHeader::parse(&header) {
+                Some(h) if h.same_stream(&first) => h,
+                _ => break
+            };
+            if bitrate.is_some_and(|b| b != h.bitrate) {
+                info.vbr = true;
+            }
+            bitrate = Some(h.bitrate);
+            info.frames += 1;
+            bytes += h.length();
+            position += h.length();
+            // stay within the read buffer, the header was already consumed
+            reader.seek_relative(h.length() as i64 - 4)?;
+        }
+
+        info.duration = info.frames as f64 * samples / first.sample_rate as f64;
+        if info.duration > 0.0 {
+            info.bitrate = (bytes as f64 * 8.0 / info.duration) as u64;
+        }
+        Ok(info)
+    }
+
+    fn from_adts<R: Read + Seek>(reader: &mut R, file_size: u64) -> Result<StreamInfo, MetaError> {
+        let (start, end) = Self::audio_range(reader, file_size)?;
+        let mut info: StreamInfo = StreamInfo::default();
+        let mut position: u64 = start;
+        let mut samples: u64 = 0;
+        let mut bytes: u64 = 0;
+        let mut header: [u8; 7] = [0; 7];
+
+        reader.seek(SeekFrom::Start(position))?;
+        while position + 7 <= end {
+            if reader.read_exact(&mut header).is_err() {
+                break;
+            }
+            // 12 bit sync, layer must be 0
+            if header[0] != 0xff || header[1] & 0xf6 != 0xf0 {
+                break;
+            }
+            let profile: u8 = header[2] >> 6;
+            let rate_index: usize = ((header[2] >> 2) & 0x0f) as usize;
+            let channels: u64 = ((((header[2] & 0x01) << 2) | (header[3] >> 6)) & 0x07) as u64;
+            let length: u64 = ((((header[3] & 0x03) as u32) << 11) | ((header[4] as u32) << 3) | ((header[5] >> 5) as u32)) as u64;
+            let blocks: u64 = (header[6] & 0x03) as u64 + 1;
+
+            if length < 7 || rate_index >= ADTS_SAMPLE_RATES.len() {
+                break;
+            }
+
+            if info.frames == 0 {
+                info.codec = format!("AAC {}", ["Main", "LC", "SSR", "LTP"][profile as usize]);
+                info.sample_rate = ADTS_SAMPLE_RATES[rate_index] as u64;
+                info.channels = channels;
+                info.channel_mode = Some(match channels {
+                    1 => "mono".to_string(),
+                    2 => "stereo".to_string(),
+                    0 => "program defined".to_string(),
+                    x => format!("{} channels", x),
+                });
+            }
+
+            info.frames += 1;
+            samples += blocks * 1024;
+            bytes += length;
+            position += length;
+            reader.seek_relative(length as i64 - 7)?;
+        }
+
+        if info.frames == 0 || info.sample_rate == 0 {
+            return Err(MetaError::from("no ADTS frames found"));
+        }
+
+        info.duration = samples as f64 / info.sample_rate as f64;
+        info.bitrate = (bytes as f64 * 8.0 / info.duration) as u64;
+        Ok(info)
+    }
+
+    fn from_amr<R: Read + Seek>(reader: &mut R, file_size: u64) -> Result<StreamInfo, MetaError> {
+        let magic: Vec<u8> = Self::read_at(reader, 0, 9)?;
+        let (wideband, mut position) = if magic.starts_with(b"#!AMR-WB\n") {
+            (true, 9u64)
+        } else if magic.starts_with(b"#!AMR\n") {
+            (false, 6u64)
+        } else {
+            return Err(MetaError::from("not an AMR file"));
+        };
+        let sizes: &[u64; 16] = if wideband { &AMR_WB_SIZES } else { &AMR_NB_SIZES };
+
+        let mut info: StreamInfo = StreamInfo {
+            codec: if wideband { "AMR-WB".to_string() } else { "AMR-NB".to_string() },
+            sample_rate: if wideband { 16_000 } else { 8_000 },
+            channels: 1,
+            channel_mode: Some("mono".to_string()),
+            ..Default::default()
+        };
+
+        let mut bytes: u64 = 0;
+        let mut frame_type: Option<u8> = None;
+        let mut header: [u8; 1] = [0; 1];
+        reader.seek(SeekFrom::Start(position))?;
+
+        while position < file_size {
+            if reader.read_exact(&mut header).is_err() {
+                break;
+            }
+            let ft: u8 = (header[0] >> 3) & 0x0f;
+            if frame_type.is_some_and(|x| x != ft) {
+                info.vbr = true;
+            }
+            frame_type = Some(ft);
+
+            let size: u64 = 1 + sizes[ft as usize];
+            info.frames += 1;
+            bytes += size;
+            position += size;
+            reader.seek_relative(size as i64 - 1)?;
+        }
+
+        // every AMR frame is 20ms
+        info.duration = info.frames as f64 * 0.02;
+        if info.duration > 0.0 {
+            info.bitrate = (bytes as f64 * 8.0 / info.duration) as u64;
+        }
+        Ok(info)
+    }
+}
+
+impl FromFile for AudioCodec {
+    fn file(path: &str) -> Self {
+        Self { path: path.to_string() }
+    }
+}
+
+impl Extractor for AudioCodec {
+    fn name(&self) -> String {
+        return "CODEC".to_string();
+    }
+
+    fn extract(&self, meta: &mut Meta) -> Result<(), MetaError> {
+        let file: File = File::open(&self.path)?;
+        let size: u64 = file.metadata()?.len();
+        let mut reader: BufReader<File> = BufReader::new(file);
+
+        let head: Vec<u8> = Self::read_at(&mut reader, 0, 10)?;
+        let info: StreamInfo = if head.starts_with(b"#!AMR") {
+            Self::from_amr(&mut reader, size)?
+        }
+        else {
+            let (start, _) = Self::audio_range(&mut reader, size)?;
+            let first: Vec<u8> = Self::read_at(&mut reader, start, 2)?;
+            // ADTS has the layer bits set to 0, MPEG audio never does
+            if first.len() == 2 && first[0] == 0xff && first[1] & 0xf6 == 0xf0 {
+                Self::from_adts(&mut reader, size)?
+            } else {
+                Self::from_mpeg(&mut reader, size)?
+            }
+        };
+
+        Self::add(meta, &info);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{BufReader, Cursor};
+    use super::{AudioCodec, MpegHeader};
+    use crate::{FromFile, Extractor, Meta};
+
+    const TEST_AUDIO: &str = "../testdata/Audio/test.mp3";
+
+    #[test]
+    fn test_mpeg_header() {
+        // MPEG-1 Layer 3, 128kbps, 44.1kHz, joint stereo
+        let h: MpegHeader = MpegHeader::parse(&[0xff, 0xfb, 0x90, 0x64]).unwrap();
+        assert_eq!(h.version, 1);
+        assert_eq!(h.layer, 3);
+        assert_eq!(h.bitrate, 128);
+        assert_eq!(h.sample_rate, 44_100);
+        assert_eq!(h.length(), 417);
+        assert_eq!(h.channel_mode(), "joint stereo");
+    }
+
+    #[test]
+    fn test_cbr_walk() {
+        let mut data: Vec<u8> = Vec::new();
+        for _ in 0..100 {
+            let mut frame: Vec<u8> = vec![0; 417];
+            frame[0..4].copy_from_slice(&[0xff, 0xfb, 0x90, 0x64]);
+            data.extend_from_slice(&frame);
+        }
+        let size: u64 = data.len() as u64;
+        let info = AudioCodec::from_mpeg(&mut Cursor::new(data), size).unwrap();
+        assert_eq!(info.frames, 100);
+        assert!(!info.vbr);
+        assert!((info.duration - 100.0 * 1152.0 / 44_100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_adts_walk() {
+        let mut data: Vec<u8> = Vec::new();
+        for _ in 0..43 {
+            // AAC LC, 44.1 kHz, stereo, one block in a 100 byte frame
+            let mut frame: Vec<u8> = vec![0; 100];
+            frame[0..7].copy_from_slice(&[0xff, 0xf1, 0x50, 0x80, 0x0c, 0x9f, 0xfc]);
+            data.extend_from_slice(&frame);
+        }
+        let size: u64 = data.len() as u64;
+        let info = AudioCodec::from_adts(&mut BufReader::with_capacity(256, Cursor::new(data)), size).unwrap();
+        assert_eq!(info.codec, "AAC LC");
+        assert_eq!(info.frames, 43);
+        assert_eq!(info.channels, 2);
+        assert!((info.duration - 43.0 * 1024.0 / 44_100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_amr() {
+        let mut data: Vec<u8> = b"#!AMR\n".to_vec();
+        for _ in 0..50 {
+            // 12.2 kbps frame type 7, 31 byte payload
+            data.push(7 << 3 | 0x04);
+            data.extend_from_slice(&[0; 31]);
+        }
+        let size: u64 = data.len() as u64;
+        let info = AudioCodec::from_amr(&mut Cursor::new(data), size).unwrap();
+        assert_eq!(info.frames, 50);
+        assert_eq!(info.duration, 1.0);
+        assert_eq!(info.bitrate, 12_800);
+    }
+
+    #[test]
+    fn test_parse() {
+        let mut meta: Meta = Meta::new();
+        match AudioCodec::file(TEST_AUDIO).extract(&mut meta) {
+            Ok(_) => println!("{:#?}", meta),
+            Err(e) => println!("test error {:#?}", e)
+        }
+    }
+}
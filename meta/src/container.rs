@@ -0,0 +1,652 @@
+use std::{
+    env,
+    ffi::OsStr,
+    fs::{self, File},
+    io::{self, BufReader, Cursor, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::SystemTime};
+
+use chrono::{DateTime, Local, NaiveDateTime};
+use flate2::read::GzDecoder;
+
+use crate::{
+    get_extractors_with,
+    meta::{MetaAttribute, MetaSource, MetaType, MetaValue},
+    zip, Extractor, FromFile, Meta, MetaError, Options};
+
+const BLOCK: usize = 512;
+
+/// Members beyond this are counted but not listed in the meta.
+const MAX_LISTED: usize = 1000;
+
+/// Members larger than this are listed but not inspected.
+const MAX_INSPECTED: u64 = 512 * 1024 * 1024;
+
+/// pax extended headers and GNU long names larger than this are skipped.
+const MAX_EXTENDED: u64 = 1024 * 1024;
+
+/// Format of [`Member::modified`].
+const MODIFIED: &str = "%Y:%m:%d %H:%M:%S";
+
+/// Keeps temporary member files of concurrent inspections apart.
+static TEMP_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// A file or directory inside an archive.
+#[derive(Debug, Clone)]
+pub struct Member {
+    pub name: String,
+    pub size: u64,
+
+    /// "YYYY:MM:DD HH:MM:SS", UTC for tar and gzip, local time for zip.
+    pub modified: Option<String>,
+    pub is_dir: bool,
+    pub encrypted: bool,
+
+    /// Meta of the member itself, when inspection is enabled.
+    pub meta: Option<Meta>,
+
+    /// Why the member could not be inspected.
+    pub warning: Option<String>,
+}
+
+/// Zip, tar, tar.gz and gzip archives: the member listing with sizes and
+/// timestamps, and optionally the meta of each member, so that the contents
+/// of a Takeout or a phone backup are searchable without unpacking it.
+pub struct Container {
+    path: String,
+    options: Options
+}
+
+impl FromFile for Container {
+    fn file(path: &str) -> Self {
+        Self { path: path.to_string(), options: Options::default() }
+    }
+}
+
+/// Seconds since the Unix epoch as "YYYY:MM:DD HH:MM:SS".
+fn unix_time(seconds: i64) -> Option<String> {
+    DateTime::from_timestamp(seconds, 0).map(|t| t.format(MODIFIED).to_string())
+}
+
+/// Seconds since the Unix epoch, as tar and gzip headers store them.
+fn unix_system_time(seconds: i64) -> Option<SystemTime> {
+    DateTime::from_timestamp(seconds, 0).map(SystemTime::from)
+}
+
+/// A zip timestamp, which is in the local time of whoever wrote the archive.
+fn local_system_time(modified: &str) -> Option<SystemTime> {
+    NaiveDateTime::parse_from_str(modified, MODIFIED).ok()?
+        .and_local_timezone(Local)
+        .earliest()
+        .map(SystemTime::from)
+}
+
+/// Read until `buffer` is full or the stream ends, returning the length read.
+fn fill<R: Read + ?Sized>(reader: &mut R, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut read: usize = 0;
+    while read < buffer.len() {
+        match reader.read(&mut buffer[read..])? {
+            0 => break,
+            n => read += n
+        }
+    }
+    Ok(read)
+}
+
+/// A NUL terminated header field.
+fn tar_string(field: &[u8]) -> String {
+    let end: usize = field.iter().position(|b| *b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+/// An octal header number, or GNU base-256 when the high bit is set.
+fn tar_number(field: &[u8]) -> Option<u64> {
+    if field.first().is_some_and(|b| b & 0x80 != 0) {
+        return field[1..].iter().try_fold(u64::from(field[0] & 0x7F), |n, b| n.checked_mul(256).map(|n| n + u64::from(*b)));
+    }
+    let digits: String = tar_string(field);
+    let digits: &str = digits.trim_matches(|c: char| c == ' ' || c == '\0');
+    match digits.is_empty() {
+        true => Some(0),
+        false => u64::from_str_radix(digits, 8).ok()
+    }
+}
+
+/// Whether a block is a tar header, by the ustar magic or, for v7 archives, the checksum.
+fn is_tar(block: &[u8]) -> bool {
+    if block.len() < BLOCK || block.iter().all(|b| *b == 0) {
+        return false;
+    }
+    if &block[257..262] == b"ustar" {
+        return true;
+    }
+    let sum: u64 = block.iter().enumerate()
+        .map(|(i, b)| if (148..156).contains(&i) { 0x20 } else { u64::from(*b) })
+        .sum();
+    tar_number(&block[148..156]) == Some(sum)
+}
+
+/// Skip `length` bytes of a stream.
+fn skip<R: Read>(reader: &mut R, length: u64) -> io::Result<()> {
+    io::copy(&mut reader.take(length), &mut io::sink())?;
+    Ok(())
+}
+
+/// Member fields overridden by a pax extended header.
+#[derive(Default)]
+struct Pax {
+    path: Option<String>,
+    size: Option<u64>,
+    mtime: Option<i64>,
+}
+
+impl Pax {
+    /// Records are `<length> <key>=<value>\n`, the length counting the whole record.
+    fn parse(data: &[u8]) -> Self {
+        let mut pax: Pax = Pax::default();
+        let mut rest: &[u8] = data;
+        while let Some(space) = rest.iter().position(|b| *b == b' ') {
+            let length: usize = match std::str::from_utf8(&rest[..space]).ok().and_then(|l| l.parse().ok()) {
+                Some(l) if l > space && l <= rest.len() => l,
+                _ => break
+            };
+            let record: String = String::from_utf8_lossy(&rest[space + 1..length]).trim_end_matches('\n').to_string();
+            if let Some((key, value)) = record.split_once('=') {
+                match key {
+                    "path" => pax.path = Some(value.to_string()),
+                    "size" => pax.size = value.parse().ok(),
+                    // fractional seconds are allowed
+                    "mtime" => pax.mtime = value.split('.').next().and_then(|s| s.parse().ok()),
+                    _ => ()
+                }
+            }
+            rest = &rest[length..];
+        }
+        pax
+    }
+}
+
+impl Container {
+    /// Run the extractors on every member. Members are inspected one level deep,
+    /// archives inside the archive are only listed.
+    pub fn inspect_members(mut self, inspect: bool) -> Self {
+        self.options.inspect_members = inspect;
+        return self;
+    }
+
+    /// Options of the archive, members are extracted with the same ones.
+    pub fn options(mut self, options: &Options) -> Self {
+        self.options = options.clone();
+        return self;
+    }
+
+    /// The members of the archive, empty when the file is not an archive.
+    pub fn members(&self) -> Result<Vec<Member>, MetaError> {
+        Ok(self.open()?.map_or(Vec::new(), |(_, members)| members))
+    }
+
+    fn add_str(meta: &mut Meta, tag: &str, value: String) {
+        if value.trim().is_empty() {
+            return;
+        }
+        meta.add(MetaAttribute{
+            source: MetaSource::Container,
+            tag: tag.to_string(),
+            value: MetaType::String(MetaValue::from(value)),
+        });
+    }
+
+    fn add_u64(meta: &mut Meta, tag: &str, value: u64) {
+        meta.add(MetaAttribute{
+            source: MetaSource::Container,
+            tag: tag.to_string(),
+            value: MetaType::UInt64(MetaValue::from(value)),
+        });
+    }
+
+    /// The archive format and its members, `None` when the content is no archive.
+    fn open(&self) -> Result<Option<(&'static str, Vec<Member>)>, MetaError> {
+        let mut file: BufReader<File> = BufReader::new(File::open(&self.path)?);
+        if zip::is_zip(&mut file)? {
+            return Ok(Some(("zip", self.zip_members(&mut file)?)));
+        }
+
+        let mut magic: [u8; 2] = [0; 2];
+        file.seek(SeekFrom::Start(0))?;
+        let gzip: bool = fill(&mut file, &mut magic)? == 2 && magic == [0x1F, 0x8B];
+        file.seek(SeekFrom::Start(0))?;
+
+        if !gzip {
+            let mut block: [u8; BLOCK] = [0; BLOCK];
+            let read: usize = fill(&mut file, &mut block)?;
+            if !is_tar(&block[..read]) {
+                return Ok(None);
+            }
+            return Ok(Some(("tar", self.tar_members(Cursor::new(block).chain(file))?)));
+        }
+
+        // the first block tells a compressed tar from a single compressed file
+        let mut decoder: GzDecoder<BufReader<File>> = GzDecoder::new(file);
+        let mut block: Vec<u8> = vec![0; BLOCK];
+        let read: usize = fill(&mut decoder, &mut block)?;
+        block.truncate(read);
+        if is_tar(&block) {
+            return Ok(Some(("tar.gz", self.tar_members(Cursor::new(block).chain(decoder))?)));
+        }
+
+        let header = decoder.header();
+        let name: String = match header.and_then(|h| h.filename()) {
+            Some(n) => String::from_utf8_lossy(n).into_owned(),
+            None => Path::new(&self.path).file_stem().map_or(String::new(), |s| s.to_string_lossy().into_owned())
+        };
+        let mtime: Option<i64> = header
+            .map(|h| i64::from(h.mtime()))
+            .filter(|m| *m != 0);
+        let modified: Option<String> = mtime.and_then(unix_time);
+
+        let mut content = Cursor::new(block).chain(decoder);
+        let mut counted = CountingReader { inner: &mut content, count: 0 };
+        let (meta, warning) = match self.inspectable(&name, 0) {
+            Some(_) => self.inspect(&name, mtime.and_then(unix_system_time), &mut counted),
+            None => (None, None)
+        };
+        io::copy(&mut counted, &mut io::sink())?;
+
+        Ok(Some(("gzip", vec![Member { name, size: counted.count, modified, is_dir: false, encrypted: false, meta, warning }])))
+    }
+
+    fn zip_members(&self, file: &mut BufReader<File>) -> Result<Vec<Member>, MetaError> {
+        let mut members: Vec<Member> = Vec::new();
+        for entry in zip::entries(file)? {
+            let (mut meta, mut warning): (Option<Meta>, Option<String>) = (None, None);
+            if !entry.is_dir && !entry.encrypted && self.inspectable(&entry.name, entry.size).is_some() {
+                let mtime: Option<SystemTime> = entry.modified.as_deref().and_then(local_system_time);
+                (meta, warning) = match zip::reader(file, &entry) {
+                    Ok(mut reader) => {
+                        // the declared size bounds what a crafted deflate stream may expand to
+                        let mut limited = (&mut reader).take(entry.size.min(MAX_INSPECTED));
+                        let inspected: (Option<Meta>, Option<String>) = self.inspect(&entry.name, mtime, &mut limited);
+                        match reader.read(&mut [0; 1]) {
+                            Ok(0) => inspected,
+                            _ => (inspected.0, Some(format!("zip member {} is longer than its declared {} bytes", entry.name, entry.size)))
+                        }
+                    },
+                    Err(e) => (None, Some(e.to_string()))
+                };
+            }
+            members.push(Member {
+                name: entry.name,
+                size: entry.size,
+                modified: entry.modified,
+                is_dir: entry.is_dir,
+                encrypted: entry.encrypted,
+                meta,
+                warning
+            });
+        }
+        Ok(members)
+    }
+
+    /// Walk the headers of a tar stream, reading it once from start to end.
+    fn tar_members<R: Read>(&self, mut reader: R) -> Result<Vec<Member>, MetaError> {
+        let mut members: Vec<Member> = Vec::new();
+        let mut long_name: Option<String> = None;
+        let mut pax: Pax = Pax::default();
+        let mut header: [u8; BLOCK] = [0; BLOCK];
+
+        // two zero blocks end the archive, a truncated one simply stops
+        while fill(&mut reader, &mut header)? == BLOCK && header.iter().any(|b| *b != 0) {
+            let typeflag: u8 = header[156];
+            let length: u64 = tar_number(&header[124..136]).ok_or("tar header has an invalid size")?;
+            let padding: u64 = length.checked_next_multiple_of(BLOCK as u64).ok_or("tar header has an invalid size")? - length;
+
+            // GNU long names and pax headers describe the member that follows
+            if matches!(typeflag, b'L' | b'x') && length <= MAX_EXTENDED {
+                let mut data: Vec<u8> = Vec::new();
+                (&mut reader).take(length).read_to_end(&mut data)?;
+                skip(&mut reader, padding)?;
+                match typeflag {
+                    b'L' => long_name = Some(tar_string(&data)),
+                    _ => pax = Pax::parse(&data)
+                }
+                continue;
+            }
+
+            let size: u64 = pax.size.take().unwrap_or(length);
+            let padding: u64 = size.checked_next_multiple_of(BLOCK as u64).ok_or("tar header has an invalid size")? - size;
+            let name: String = match (pax.path.take(), long_name.take()) {
+                (Some(path), _) => path,
+                (None, Some(long)) => long,
+                // POSIX ustar splits long paths into a prefix and a name
+                (None, None) if &header[257..263] == b"ustar\0" && header[345] != 0 => {
+                    format!("{}/{}", tar_string(&header[345..500]), tar_string(&header[..100]))
+                },
+                (None, None) => tar_string(&header[..100])
+            };
+            let mtime: Option<i64> = pax.mtime.take().or_else(|| tar_number(&header[136..148]).map(|m| m as i64));
+
+            // links, devices and fifos have no content of their own
+            let is_file: bool = matches!(typeflag, 0 | b'0' | b'7');
+            let is_dir: bool = typeflag == b'5';
+
+            let mut content = (&mut reader).take(size);
+            let (meta, warning) = match self.inspectable(&name, size) {
+                Some(_) if is_file => self.inspect(&name, mtime.and_then(unix_system_time), &mut content),
+                _ => (None, None)
+            };
+            io::copy(&mut content, &mut io::sink())?;
+            skip(&mut reader, padding)?;
+
+            if is_file || is_dir {
+                members.push(Member {
+                    name,
+                    size: if is_file { size } else { 0 },
+                    modified: mtime.filter(|m| *m != 0).and_then(unix_time),
+                    is_dir,
+                    encrypted: false,
+                    meta,
+                    warning
+                });
+            }
+        }
+        Ok(members)
+    }
+
+    /// The extension to extract the member with, when it should be inspected.
+    fn inspectable(&self, name: &str, size: u64) -> Option<String> {
+        if !self.options.inspect_members || size > MAX_INSPECTED {
+            return None;
+        }
+        Path::new(name)
+            .extension()
+            .and_then(|e| e.to_str())
+            .filter(|e| !e.is_empty() && e.chars().all(|c| c.is_ascii_alphanumeric()))
+            .map(|e| e.to_ascii_lowercase())
+    }
+
+    /// Stream a member to a temporary file under its own name and modification
+    /// time, and run its extractors. Failures come back as a warning and do not
+    /// fail the archive.
+    fn inspect(&self, name: &str, modified: Option<SystemTime>, content: &mut dyn Read) -> (Option<Meta>, Option<String>) {
+        let directory: PathBuf = env::temp_dir().join(format!(
+            "informant-{}-{}", process::id(), TEMP_FILES.fetch_add(1, Ordering::Relaxed)));
+        let file_name: &OsStr = Path::new(name).file_name().unwrap_or_default();
+
+        let result: Result<Meta, MetaError> = fs::create_dir_all(&directory)
+            .and_then(|_| File::create(directory.join(file_name)))
+            .and_then(|mut file| {
+                io::copy(content, &mut file)?;
+                match modified {
+                    Some(time) => file.set_modified(time),
+                    None => Ok(())
+                }
+            })
+            .map_err(MetaError::from)
+            .and_then(|_| {
+                let path: PathBuf = directory.join(file_name);
+                let temp: &str = path.to_str().ok_or("temporary path is not valid UTF-8")?;
+                // members are inspected one level deep
+                let options: Options = Options { inspect_members: false, ..self.options.clone() };
+                get_extractors_with(temp, &options)?.extract()
+            });
+        let _ = fs::remove_dir_all(&directory);
+
+        match result {
+            Ok(meta) => (Some(meta), None),
+            Err(e) => (None, Some(e.to_string()))
+        }
+    }
+}
+
+/// Counts the bytes of a stream whose length is not known up front.
+struct CountingReader<'a, R: Read> {
+    inner: &'a mut R,
+    count: u64,
+}
+
+impl<R: Read> Read for CountingReader<'_, R> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        let read: usize = self.inner.read(buffer)?;
+        self.count += read as u64;
+        Ok(read)
+    }
+}
+
+impl Extractor for Container {
+    fn name(&self) -> String {
+        return "CONTAINER".to_string();
+    }
+
+    fn extract(&self, meta: &mut Meta) -> Result<(), MetaError> {
+        // e.g. a .gz that is neither tar nor gzip, nothing to report
+        let (format, members) = match self.open()? {
+            Some(m) => m,
+            None => return Ok(())
+        };
+
+        Self::add_str(meta, "format", format.to_string());
+        Self::add_u64(meta, "members", members.iter().filter(|m| !m.is_dir).count() as u64);
+        Self::add_u64(meta, "directories", members.iter().filter(|m| m.is_dir).count() as u64);
+        Self::add_u64(meta, "total_size", members.iter().map(|m| m.size).sum());
+
+        let encrypted: usize = members.iter().filter(|m| m.encrypted).count();
+        if encrypted > 0 {
+            Self::add_u64(meta, "encrypted", encrypted as u64);
+        }
+
+        for (index, member) in members.into_iter().take(MAX_LISTED).enumerate() {
+            let prefix: String = format!("member.{}", index);
+            Self::add_str(meta, &format!("{}.name", prefix), member.name);
+            if !member.is_dir {
+                Self::add_u64(meta, &format!("{}.size", prefix), member.size);
+            }
+            if let Some(modified) = member.modified {
+                Self::add_str(meta, &format!("{}.modified", prefix), modified);
+            }
+            if let Some(warning) = member.warning {
+                Self::add_str(meta, &format!("{}.warning", prefix), warning);
+            }
+
+            // the member's own meta, nested as it came from its extractors
+            if let Some(member_meta) = member.meta {
+                meta.add(MetaAttribute{
+                    source: MetaSource::Container,
+                    tag: format!("{}.meta", prefix),
+                    value: MetaType::Meta(MetaValue::from(member_meta)),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::{fs, io::Write};
+
+    use flate2::{write::GzEncoder, Compression, GzBuilder};
+
+    use super::{is_tar, tar_number, Container, Pax};
+    use crate::{zip::test::archive, Extractor, FromFile, Meta, MetaSource, MetaType};
+
+    /// A ustar member header with its content, padded to the block size.
+    fn tar_member(name: &str, typeflag: u8, content: &[u8]) -> Vec<u8> {
+        let mut header: Vec<u8> = vec![0; 512];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        header[100..107].copy_from_slice(b"0000644");
+        header[124..135].copy_from_slice(format!("{:011o}", content.len()).as_bytes());
+        header[136..147].copy_from_slice(format!("{:011o}", 1_623_764_730).as_bytes());
+        header[148..156].copy_from_slice(b"        ");
+        header[156] = typeflag;
+        header[257..263].copy_from_slice(b"ustar\0");
+        header[263..265].copy_from_slice(b"00");
+        let sum: u32 = header.iter().map(|b| u32::from(*b)).sum();
+        header[148..155].copy_from_slice(format!("{:06o}\0", sum).as_bytes());
+
+        header.extend_from_slice(content);
+        header.resize(header.len().next_multiple_of(512), 0);
+        header
+    }
+
+    fn tar(members: &[(&str, u8, &[u8])]) -> Vec<u8> {
+        let mut data: Vec<u8> = members.iter().flat_map(|(n, t, c)| tar_member(n, *t, c)).collect();
+        data.extend_from_slice(&[0; 1024]);
+        data
+    }
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn extract(data: &[u8], file: &str, inspect: bool) -> Meta {
+        let path = std::env::temp_dir().join(format!("informant-container-{}-{}", std::process::id(), file));
+        fs::write(&path, data).unwrap();
+        let mut meta: Meta = Meta::new();
+        Container::file(path.to_str().unwrap()).inspect_members(inspect).extract(&mut meta).unwrap();
+        fs::remove_file(&path).unwrap();
+        meta
+    }
+
+    fn text(meta: &Meta, tag: &str) -> String {
+        String::from(meta.find_one(MetaSource::Container, tag).unwrap().value)
+    }
+
+    fn number(meta: &Meta, tag: &str) -> u64 {
+        u64::from(meta.find_one(MetaSource::Container, tag).unwrap().value)
+    }
+
+    /// The nested meta of the member at `index`.
+    fn member(meta: &Meta, index: usize) -> Meta {
+        match meta.find_one(MetaSource::Container, &format!("member.{}.meta", index)).unwrap().value {
+            MetaType::Meta(m) => m.value,
+            _ => panic!("member meta should be nested")
+        }
+    }
+
+    #[test]
+    fn test_zip() {
+        let data: Vec<u8> = archive(&[("Takeout/", b""), ("Takeout/notes.txt", b"hello world\nagain\n")], true);
+        let meta: Meta = extract(&data, "takeout.zip", false);
+
+        assert_eq!(text(&meta, "format"), "zip");
+        assert_eq!(number(&meta, "members"), 1);
+        assert_eq!(number(&meta, "directories"), 1);
+        assert_eq!(number(&meta, "total_size"), 18);
+        assert_eq!(text(&meta, "member.1.name"), "Takeout/notes.txt");
+        assert_eq!(text(&meta, "member.1.modified"), "2021:06:15 13:45:30");
+        assert!(meta.find("member.1.size").len() == 1 && meta.find("member.0.size").is_empty());
+        assert!(meta.find("member.1.meta").is_empty());
+    }
+
+    #[test]
+    fn test_zip_inspected() {
+        let data: Vec<u8> = archive(&[("notes.txt", b"hello world\nagain\n"), ("README", b"no extension")], false);
+        let meta: Meta = extract(&data, "inspected.zip", true);
+
+        let notes: Meta = member(&meta, 0);
+        assert_eq!(String::from(notes.find_one(MetaSource::Basic, "extension").unwrap().value), "txt");
+        assert_eq!(u64::from(notes.find_one(MetaSource::Basic, "size").unwrap().value), 18);
+        assert!(meta.find("member.1.meta").is_empty());
+        assert!(meta.find_one(MetaSource::Basic, "extension").is_err());
+
+        #[cfg(feature = "text")]
+        assert_eq!(u64::from(notes.find_one(MetaSource::Text, "lines").unwrap().value), 2);
+    }
+
+    #[test]
+    fn test_zip_member_longer_than_declared() {
+        let mut data: Vec<u8> = archive(&[("notes.txt", b"hello world\nagain\n")], false);
+        // the central directory claims 5 bytes, the stored data holds 18
+        let central: usize = data.windows(4).position(|w| w == b"PK\x01\x02").unwrap();
+        data[central + 24..central + 28].copy_from_slice(&5u32.to_le_bytes());
+        let meta: Meta = extract(&data, "longer.zip", true);
+
+        assert_eq!(u64::from(member(&meta, 0).find_one(MetaSource::Basic, "size").unwrap().value), 5);
+        assert_eq!(text(&meta, "member.0.warning"), "zip member notes.txt is longer than its declared 5 bytes");
+    }
+
+    #[test]
+    fn test_tar() {
+        let long_name: String = format!("{}/IMG_0001.jpg", "d".repeat(120));
+        let data: Vec<u8> = tar(&[
+            ("photos/", b'5', b""),
+            ("././@LongLink", b'L', long_name.as_bytes()),
+            ("truncated", b'0', &[7; 700]),
+            ("pax", b'x', b"28 path=photos/renamed.json\n"),
+            ("photos/original.json", b'0', b"{\"title\": \"IMG_0001.jpg\"}"),
+            ("photos/link", b'2', b""),
+        ]);
+        assert!(is_tar(&data[..512]));
+
+        for (bytes, file, format) in [(data.clone(), "backup.tar", "tar"), (gzip(&data), "backup.tgz", "tar.gz")] {
+            let meta: Meta = extract(&bytes, file, true);
+            assert_eq!(text(&meta, "format"), format);
+            assert_eq!(number(&meta, "members"), 2);
+            assert_eq!(number(&meta, "directories"), 1);
+            assert_eq!(number(&meta, "total_size"), 725);
+            assert_eq!(text(&meta, "member.1.name"), long_name);
+            assert_eq!(number(&meta, "member.1.size"), 700);
+            assert_eq!(text(&meta, "member.2.name"), "photos/renamed.json");
+            assert_eq!(text(&meta, "member.2.modified"), "2021:06:15 13:45:30");
+            assert!(meta.find("member.3.name").is_empty());
+        }
+    }
+
+    #[test]
+    fn test_tar_size_out_of_range() {
+        let mut data: Vec<u8> = tar(&[("huge.bin", b'0', b"")]);
+        // base-256 size of u64::MAX, which has no next block boundary
+        data[124..136].copy_from_slice(&[0x80, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+        let path = std::env::temp_dir().join(format!("informant-container-{}-huge.tar", std::process::id()));
+        fs::write(&path, &data).unwrap();
+        let result = Container::file(path.to_str().unwrap()).extract(&mut Meta::new());
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(result.unwrap_err().to_string(), "tar header has an invalid size");
+    }
+
+    #[test]
+    fn test_members_keep_their_name() {
+        let data: Vec<u8> = tar(&[("DCIM/IMG_20190704_153012.txt", b'0', b"hello\n"), ("broken.pdf", b'0', b"not a pdf")]);
+        let meta: Meta = extract(&data, "names.tar", true);
+
+        let date = member(&meta, 0).find_one(MetaSource::Filename, "date").unwrap();
+        assert_eq!(String::from(date.value), "2019:07:04 15:30:12");
+        assert!(meta.find("member.0.warning").is_empty());
+
+        #[cfg(feature = "pdf")]
+        assert!(!text(&meta, "member.1.warning").is_empty());
+    }
+
+    #[test]
+    fn test_gzip_and_others() {
+        let mut encoder = GzBuilder::new().filename("notes.txt").mtime(1_623_764_730).write(Vec::new(), Compression::default());
+        encoder.write_all(b"just some text\n").unwrap();
+        let meta: Meta = extract(&encoder.finish().unwrap(), "download.gz", true);
+        assert_eq!(text(&meta, "format"), "gzip");
+        assert_eq!(text(&meta, "member.0.name"), "notes.txt");
+        assert_eq!(number(&meta, "member.0.size"), 15);
+        assert_eq!(text(&meta, "member.0.modified"), "2021:06:15 13:45:30");
+        assert!(member(&meta, 0).find_one(MetaSource::Basic, "extension").is_ok());
+
+        assert!(extract(b"not an archive at all", "fake.zip", true).0.is_empty());
+    }
+
+    #[test]
+    fn test_headers() {
+        assert_eq!(tar_number(b"0000644 \0"), Some(0o644));
+        assert_eq!(tar_number(&[0x80, 0, 0, 0, 0, 0, 0, 1, 0]), Some(256));
+        assert_eq!(tar_number(b"\0\0\0"), Some(0));
+        assert_eq!(tar_number(b"12x"), None);
+
+        let pax: Pax = Pax::parse(b"27 mtime=1623764730.123456\n13 size=4096\n99 broken");
+        assert_eq!(pax.mtime, Some(1_623_764_730));
+        assert_eq!(pax.size, Some(4096));
+        assert_eq!(pax.path, None);
+    }
+}
@@ -0,0 +1,187 @@
+//! Tolerant parsing of the dates found in EXIF, XMP and container tags.
+
+use std::sync::LazyLock;
+
+use chrono::{FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, Offset, Utc};
+use regex::Regex;
+
+use crate::MetaError;
+
+/// Largest offset in use (Kiribati, +14:00).
+pub const MAX_OFFSET: i32 = 14 * 3600;
+
+/// Dates written for an unset clock or a zeroed counter.
+const SENTINELS: [&str; 6] = [
+    "0001-01-01 00:00:00",
+    "1601-01-01 00:00:00",
+    "1904-01-01 00:00:00",
+    "1970-01-01 00:00:00",
+    "1980-01-01 00:00:00",
+    "9999-12-31 23:59:59",
+];
+
+/// Date, optional time and zone with separators and padding as cameras write them.
+static DATETIME: LazyLock<Regex> = LazyLock::new(|| Regex::new(concat!(
+    r"(?i)^(?P<year>\d{4})[:\-/.]\s*(?P<month>\d{1,2})[:\-/.]\s*(?P<day>\d{1,2})",
+    r"(?:[T\s]+(?P<hour>\d{1,2})[:.]\s*(?P<minute>\d{1,2})(?:[:.]\s*(?P<second>\d{1,2})(?:[.,](?P<fraction>\d+))?)?(?:\s*(?P<ampm>[AP]M))?)?",
+    r"[\s:.]*(?P<zone>Z|UTC|GMT|[+-]\d{1,2}(?::?\d{2})?)?$"
+)).unwrap());
+
+/// Formats the [`DATETIME`] pattern does not cover: ctime
+/// ("THU OCT 26 16:46:04 2006") and ISO 8601 basic ("20210615T134530").
+const DATETIME_FORMATS: [&str; 3] = [
+    "%a %b %e %H:%M:%S %Y",
+    "%Y%m%dT%H%M%S",
+    "%Y%m%dT%H%M",
+];
+
+/// A date with optional time and zone, e.g. EXIF "2021:06:15 13:45:30",
+/// "2021-06-15T13:45:30.5+02:00", "2021/06/15 1:45 PM" or "2021-06-15".
+/// Missing seconds, blank padding ("2021:06:15   :  :  ") and trailing
+/// NULs are accepted. Blank values are `None`, zeroed and sentinel dates
+/// such as 1970-01-01 00:00:00 are errors, as are values that are no date.
+pub fn parse_datetime(value: &str) -> Result<Option<(NaiveDateTime, Option<FixedOffset>)>, MetaError> {
+    let value: &str = value.trim_matches(|c: char| c.is_whitespace() || c == '\0');
+    if !value.chars().any(|c| c.is_ascii_digit()) {
+        return Ok(None);
+    }
+    if value.chars().all(|c| !c.is_ascii_digit() || c == '0') {
+        return Err(format!("zeroed date {:?}", value).into());
+    }
+
+    let (local, offset) = match DATETIME.captures(value) {
+        Some(c) => {
+            let field = |group: &str| c.name(group).and_then(|m| m.as_str().parse::<u32>().ok());
+            let invalid = || format!("invalid date {:?}", value);
+
+            let date: NaiveDate = NaiveDate::from_ymd_opt(field("year").ok_or_else(invalid)? as i32, field("month").ok_or_else(invalid)?, field("day").ok_or_else(invalid)?)
+                .ok_or_else(invalid)?;
+            let hour: u32 = match (field("hour").unwrap_or(0), c.name("ampm").map(|m| m.as_str().to_ascii_uppercase())) {
+                (12, Some(ampm)) if ampm == "AM" => 0,
+                (h, Some(ampm)) if ampm == "PM" && h < 12 => h + 12,
+                (h, _) => h
+            };
+            let nanos: u32 = c.name("fraction").and_then(|f| parse_subsec(f.as_str())).unwrap_or(0);
+            let time: NaiveTime = NaiveTime::from_hms_nano_opt(hour, field("minute").unwrap_or(0), field("second").unwrap_or(0), nanos)
+                .ok_or_else(invalid)?;
+
+            let offset: Option<FixedOffset> = match c.name("zone").map(|z| z.as_str().to_ascii_uppercase()) {
+                Some(zone) if ["Z", "UTC", "GMT"].contains(&zone.as_str()) => Some(Utc.fix()),
+                Some(zone) => Some(parse_offset(&zone).ok_or_else(|| format!("invalid offset in date {:?}", value))?),
+                None => None
+            };
+            (date.and_time(time), offset)
+        },
+        None => {
+            let local: NaiveDateTime = DATETIME_FORMATS
+                .iter()
+                .find_map(|f| NaiveDateTime::parse_from_str(value, f).ok())
+                .or_else(|| NaiveDate::parse_from_str(value, "%Y%m%d").ok().and_then(|d| d.and_hms_opt(0, 0, 0)))
+                .ok_or_else(|| format!("unrecognised date {:?}", value))?;
+            (local, None)
+        }
+    };
+
+    if SENTINELS.contains(&local.to_string().as_str()) {
+        return Err(format!("sentinel date {:?}", value).into());
+    }
+    Ok(Some((local, offset)))
+}
+
+/// Sub-second digits ("123" is 0.123s) as nanoseconds.
+pub fn parse_subsec(value: &str) -> Option<u32> {
+    let digits: &str = value.trim();
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    format!("{:0<9}", &digits[..digits.len().min(9)]).parse().ok()
+}
+
+/// An offset as "+02:00", "-0530" or "+2". The meta sanitizer drops a leading
+/// plus sign, so an unsigned offset is east of UTC.
+pub fn parse_offset(value: &str) -> Option<FixedOffset> {
+    let value: &str = value.trim();
+    let (sign, rest) = match value.strip_prefix('-') {
+        Some(rest) => (-1, rest),
+        None => (1, value.strip_prefix('+').unwrap_or(value))
+    };
+    if rest.is_empty() || !rest.chars().all(|c| c.is_ascii_digit() || c == ':') {
+        return None;
+    }
+
+    let (hours, minutes): (i32, i32) = match rest.split_once(':') {
+        Some((h, m)) => (h.parse().ok()?, m.parse().ok()?),
+        None if rest.len() <= 2 => (rest.parse().ok()?, 0),
+        None if rest.len() == 4 => (rest[..2].parse().ok()?, rest[2..].parse().ok()?),
+        None => return None
+    };
+    if hours > MAX_OFFSET / 3600 || minutes >= 60 {
+        return None;
+    }
+    let seconds: i32 = hours * 3600 + minutes * 60;
+    if seconds > MAX_OFFSET {
+        return None;
+    }
+    FixedOffset::east_opt(sign * seconds)
+}
+
+
+#[cfg(test)]
+mod test {
+    use chrono::FixedOffset;
+
+    use super::{parse_datetime, parse_offset};
+
+    fn parsed(value: &str) -> String {
+        let (local, offset) = parse_datetime(value).unwrap().unwrap();
+        match offset {
+            Some(o) => format!("{} {}", local, o),
+            None => local.to_string()
+        }
+    }
+
+    #[test]
+    fn test_datetime_variants() {
+        assert_eq!(parsed("2021:06:15 13:45:30"), "2021-06-15 13:45:30");
+        assert_eq!(parsed("2021-06-15 13:45:30"), "2021-06-15 13:45:30");
+        assert_eq!(parsed("2021-06-15T13:45:30.5+02:00"), "2021-06-15 13:45:30.500 +02:00");
+        assert_eq!(parsed("2021-06-15T13:45:30Z"), "2021-06-15 13:45:30 +00:00");
+        assert_eq!(parsed("2010:05:12 14:33:10-0530"), "2010-05-12 14:33:10 -05:30");
+        assert_eq!(parsed("2021:06:15 13:45"), "2021-06-15 13:45:00");
+        assert_eq!(parsed("2021/06/15 1:45 PM"), "2021-06-15 13:45:00");
+        assert_eq!(parsed("2021.06.15 13.45.30 UTC"), "2021-06-15 13:45:30 +00:00");
+        assert_eq!(parsed("2021: 6: 5  9: 5: 0"), "2021-06-05 09:05:00");
+        assert_eq!(parsed("2021:06:15   :  :  "), "2021-06-15 00:00:00");
+        assert_eq!(parsed(" 2021:06:15 13:45:30\0\0"), "2021-06-15 13:45:30");
+        assert_eq!(parsed("2021:06:15"), "2021-06-15 00:00:00");
+        assert_eq!(parsed("THU OCT 26 16:46:04 2006"), "2006-10-26 16:46:04");
+        assert_eq!(parsed("20210615T134530"), "2021-06-15 13:45:30");
+        assert_eq!(parsed("20210615"), "2021-06-15 00:00:00");
+    }
+
+    #[test]
+    fn test_datetime_rejected() {
+        assert!(parse_datetime("").unwrap().is_none());
+        assert!(parse_datetime("    :  :     :  :  ").unwrap().is_none());
+
+        assert!(parse_datetime("0000:00:00 00:00:00").is_err());
+        assert!(parse_datetime("1970:01:01 00:00:00").is_err());
+        assert!(parse_datetime("1904-01-01T00:00:00Z").is_err());
+        assert!(parse_datetime("2021:13:01 00:00:00").is_err());
+        assert!(parse_datetime("2021:06:15 25:00:00").is_err());
+        assert!(parse_datetime("2021:06:15 13:45:30+15:00").is_err());
+        assert!(parse_datetime("2003").is_err());
+        assert!(parse_datetime("yesterday 5").is_err());
+    }
+
+    #[test]
+    fn test_parse_offset() {
+        assert_eq!(parse_offset("02:00"), FixedOffset::east_opt(7200));
+        assert_eq!(parse_offset("-0530"), FixedOffset::west_opt(19_800));
+        assert_eq!(parse_offset("+9"), FixedOffset::east_opt(32_400));
+        assert_eq!(parse_offset("  :  "), None);
+        assert_eq!(parse_offset("+15:00"), None);
+        assert_eq!(parse_offset("+999999:00"), None);
+        assert_eq!(parse_offset("+01:99999999"), None);
+    }
+}
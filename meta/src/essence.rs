@@ -0,0 +1,368 @@
+use std::{fs::File, io::{BufReader, Read, Seek, SeekFrom}, path::Path};
+
+use crate::{
+    hash::BUFFER_SIZE,
+    meta::{MetaAttribute, MetaSource, MetaType, MetaValue},
+    FromFile, Extractor, Meta, MetaError};
+
+/// Hash of the media payload only, so editing tags does not change it.
+///
+/// * JPEG: every segment except APPn and COM, plus the entropy coded scans up to EOI.
+/// * MP4/MOV: the payload of each top level `mdat` box.
+/// * MP3/AAC: the frames between a leading ID3v2 and trailing ID3v1/APEv2 tags.
+/// * FLAC: the frames after the metadata blocks (STREAMINFO, VORBIS_COMMENT, PICTURE, ...).
+///
+/// Other formats produce no `hash.essence` attribute.
+pub struct Essence {
+    path: String,
+}
+
+impl FromFile for Essence {
+    fn file(path: &str) -> Self {
+        Self { path: path.to_string() }
+    }
+}
+
+impl Essence {
+    fn hash_range<R: Read + Seek>(reader: &mut R, hasher: &mut blake3::Hasher, start: u64, end: u64) -> Result<(), MetaError> {
+        reader.seek(SeekFrom::Start(start))?;
+        let mut remaining: u64 = end.saturating_sub(start);
+        let mut buffer: Vec<u8> = vec![0; BUFFER_SIZE];
+
+        while remaining > 0 {
+            let want: usize = remaining.min(BUFFER_SIZE as u64) as usize;
+            let read: usize = reader.read(&mut buffer[..want])?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+            remaining -= read as u64;
+        }
+        Ok(())
+    }
+
+    fn read_at<R: Read + Seek>(reader: &mut R, offset: u64, len: usize) -> Result<Vec<u8>, MetaError> {
+        reader.seek(SeekFrom::Start(offset))?;
+        let mut buffer: Vec<u8> = Vec::with_capacity(len);
+        reader.take(len as u64).read_to_end(&mut buffer)?;
+        Ok(buffer)
+    }
+
+    /// Size of a leading ID3v2 tag, including its footer.
+    fn id3v2_size<R: Read + Seek>(reader: &mut R) -> Result<u64, MetaError> {
+        let head: Vec<u8> = Self::read_at(reader, 0, 10)?;
+        if head.len() == 10 && &head[0..3] == b"ID3" {
+            let size: u64 = head[6..10].iter().fold(0u64, |acc, b| (acc << 7) | (*b & 0x7f) as u64);
+            let footer: u64 = if head[5] & 0x10 != 0 { 10 } else { 0 };
+            return Ok(10 + size + footer);
+        }
+        Ok(0)
+    }
+
+    /// End of the audio data before trailing ID3v1 and APEv2 tags.
+    fn trailing_tags<R: Read + Seek>(reader: &mut R, file_size: u64) -> Result<u64, MetaError> {
+        let mut end: u64 = file_size;
+        if end >= 128 && Self::read_at(reader, end - 128, 3)? == b"TAG" {
+            end -= 128;
+        }
+        if end >= 32 {
+            let footer: Vec<u8> = Self::read_at(reader, end - 32, 32)?;
+            if footer.len() == 32 && &footer[0..8] == b"APETAGEX" {
+                let size: u64 = u32::from_le_bytes([footer[12], footer[13], footer[14], footer[15]]) as u64;
+                let has_header: bool = footer[23] & 0x80 != 0;
+                end = end.saturating_sub(size + if has_header { 32 } else { 0 });
+            }
+        }
+        Ok(end)
+    }
+
+    fn from_tagged_audio<R: Read + Seek>(reader: &mut R, file_size: u64) -> Result<Option<blake3::Hash>, MetaError> {
+        let start: u64 = Self::id3v2_size(reader)?;
+        let end: u64 = Self::trailing_tags(reader, file_size)?;
+        if end <= start {
+            return Ok(None);
+        }
+
+        let mut hasher: blake3::Hasher = blake3::Hasher::new();
+        Self::hash_range(reader, &mut hasher, start, end)?;
+        Ok(Some(hasher.finalize()))
+    }
+
+    fn from_flac<R: Read + Seek>(reader: &mut R, file_size: u64) -> Result<Option<blake3::Hash>, MetaError> {
+        let mut offset: u64 = Self::id3v2_size(reader)?;
+        if Self::read_at(reader, offset, 4)? != b"fLaC" {
+            return Ok(None);
+        }
+        offset += 4;
+
+        loop {
+            let header: Vec<u8> = Self::read_at(reader, offset, 4)?;
+            if header.len() < 4 {
+                return Ok(None);
+            }
+            let length: u64 = u32::from_be_bytes([0, header[1], header[2], header[3]]) as u64;
+            offset += 4 + length;
+            if header[0] & 0x80 != 0 {
+                break;
+            }
+        }
+
+        let end: u64 = Self::trailing_tags(reader, file_size)?;
+        if end <= offset {
+            return Ok(None);
+        }
+
+        let mut hasher: blake3::Hasher = blake3::Hasher::new();
+        Self::hash_range(reader, &mut hasher, offset, end)?;
+        Ok(Some(hasher.finalize()))
+    }
+
+    fn from_mp4<R: Read + Seek>(reader: &mut R, file_size: u64) -> Result<Option<blake3::Hash>, MetaError> {
+        let mut hasher: blake3::Hasher = blake3::Hasher::new();
+        let mut found: bool = false;
+        let mut offset: u64 = 0;
+
+        while file_size.saturating_sub(offset) >= 8 {
+            let header: Vec<u8> = Self::read_at(reader, offset, 16)?;
+            if header.len() < 8 {
+                break;
+            }
+            let mut size: u64 = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as u64;
+            let kind: &[u8] = &header[4..8];
+            let mut header_size: u64 = 8;
+
+            if size == 1 {
+                if header.len() < 16 {
+                    break;
+                }
+                size = u64::from_be_bytes(header[8..16].try_into()?);
+                header_size = 16;
+            } else if size == 0 {
+                size = file_size - offset;
+            }
+
+            if size < header_size || !kind.iter().all(|c| c.is_ascii_graphic() || *c == b' ') {
+                break;
+            }
+
+            let end: u64 = match offset.checked_add(size) {
+                Some(end) => end,
+                None => break
+            };
+            if kind == b"mdat" {
+                Self::hash_range(reader, &mut hasher, offset + header_size, end.min(file_size))?;
+                found = true;
+            }
+            offset = end;
+        }
+
+        Ok(found.then(|| hasher.finalize()))
+    }
+
+    fn from_jpeg(data: &[u8]) -> Option<blake3::Hash> {
+        if data.len() < 4 || data[0] != 0xFF || data[1] != 0xD8 {
+            return None;
+        }
+
+        let mut hasher: blake3::Hasher = blake3::Hasher::new();
+        hasher.update(&data[0..2]);
+        let mut pos: usize = 2;
+
+        while pos + 1 < data.len() {
+            if data[pos] != 0xFF {
+                return None;
+            }
+            // fill bytes before a marker
+            while pos + 1 < data.len() && data[pos + 1] == 0xFF {
+                pos += 1;
+            }
+            let marker: u8 = *data.get(pos + 1)?;
+
+            match marker {
+                0xD9 => {
+                    hasher.update(&data[pos..pos + 2]);
+                    break;
+                },
+                0x01 | 0xD0..=0xD7 => {
+                    hasher.update(&data[pos..pos + 2]);
+                    pos += 2;
+                    continue;
+                },
+                _ => ()
+            }
+
+            let length: usize = u16::from_be_bytes([*data.get(pos + 2)?, *data.get(pos + 3)?]) as usize;
+            let end: usize = (pos + 2 + length).min(data.len());
+
+            let is_metadata: bool = matches!(marker, 0xE0..=0xEF | 0xFE);
+            if !is_metadata {
+                hasher.update(&data[pos..end]);
+            }
+            pos = end;
+
+            if marker == 0xDA {
+                // entropy coded data runs until a marker other than stuffing or restart
+                let mut scan: usize = pos;
+                while scan + 1 < data.len() {
+                    if data[scan] == 0xFF && data[scan + 1] != 0x00 && !(0xD0..=0xD7).contains(&data[scan + 1]) {
+                        break;
+                    }
+                    scan += 1;
+                }
+                if scan + 1 >= data.len() {
+                    scan = data.len();
+                }
+                hasher.update(&data[pos..scan]);
+                pos = scan;
+            }
+        }
+
+        Some(hasher.finalize())
+    }
+}
+
+impl Extractor for Essence {
+    fn name(&self) -> String {
+        return "ESSENCE".to_string();
+    }
+
+    fn extract(&self, meta: &mut Meta) -> Result<(), MetaError> {
+        let extension: String = Path::new(&self.path)
+            .extension()
+            .map_or(String::new(), |x| x.to_string_lossy().to_ascii_lowercase());
+
+        let file: File = File::open(&self.path)?;
+        let file_size: u64 = file.metadata()?.len();
+        let mut reader: BufReader<File> = BufReader::new(file);
+
+        let hash: Option<blake3::Hash> = match extension.as_str() {
+            "jpg" | "jpeg" | "jfif" => {
+                let mut data: Vec<u8> = Vec::new();
+                reader.read_to_end(&mut data)?;
+                Self::from_jpeg(&data)
+            },
+            "mp4" | "mov" | "m4v" | "m4a" | "3gp" | "3gpp" => Self::from_mp4(&mut reader, file_size)?,
+            "mp3" | "aac" => Self::from_tagged_audio(&mut reader, file_size)?,
+            "flac" => Self::from_flac(&mut reader, file_size)?,
+            _ => None
+        };
+
+        if let Some(hash) = hash {
+            meta.add(MetaAttribute {
+                source: MetaSource::Hash,
+                tag: "hash.essence".to_string(),
+                value: MetaType::String(MetaValue::from(hash.to_string())),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use super::Essence;
+
+    fn segment(marker: u8, payload: &[u8]) -> Vec<u8> {
+        let mut data: Vec<u8> = vec![0xFF, marker];
+        data.extend_from_slice(&((payload.len() + 2) as u16).to_be_bytes());
+        data.extend_from_slice(payload);
+        data
+    }
+
+    fn jpeg(app: &[u8], comment: &[u8]) -> Vec<u8> {
+        let mut data: Vec<u8> = vec![0xFF, 0xD8];
+        data.extend(segment(0xE1, app));
+        data.extend(segment(0xDB, &[0; 65]));
+        data.extend(segment(0xFE, comment));
+        data.extend(segment(0xDA, &[1, 1, 0, 0, 63, 0]));
+        data.extend_from_slice(&[0x12, 0xFF, 0x00, 0x34, 0xFF, 0xD0, 0x56]);
+        data.extend_from_slice(&[0xFF, 0xD9]);
+        data
+    }
+
+    #[test]
+    fn test_jpeg_ignores_app_segments() {
+        let one = Essence::from_jpeg(&jpeg(b"Exif\0\0one", b"first")).unwrap();
+        let two = Essence::from_jpeg(&jpeg(b"Exif\0\0a longer exif block", b"")).unwrap();
+        assert_eq!(one, two);
+
+        let mut edited: Vec<u8> = jpeg(b"Exif\0\0one", b"first");
+        let len: usize = edited.len();
+        edited[len - 3] = 0x57;
+        assert_ne!(one, Essence::from_jpeg(&edited).unwrap());
+    }
+
+    #[test]
+    fn test_mp3_ignores_tags() {
+        let frames: Vec<u8> = (0..5000).map(|x| (x % 253) as u8).collect();
+
+        let mut tagged: Vec<u8> = vec![b'I', b'D', b'3', 4, 0, 0, 0, 0, 0, 5, 1, 2, 3, 4, 5];
+        tagged.extend_from_slice(&frames);
+        let mut v1: Vec<u8> = b"TAG".to_vec();
+        v1.resize(128, b' ');
+        tagged.extend(v1);
+
+        let size: u64 = tagged.len() as u64;
+        let one = Essence::from_tagged_audio(&mut Cursor::new(tagged), size).unwrap().unwrap();
+        let two = Essence::from_tagged_audio(&mut Cursor::new(frames.clone()), frames.len() as u64).unwrap().unwrap();
+        assert_eq!(one, two);
+    }
+
+    #[test]
+    fn test_flac_skips_metadata_blocks() {
+        let frames: Vec<u8> = vec![0xFF, 0xF8, 1, 2, 3, 4];
+        let build = |comment: &[u8]| {
+            let mut data: Vec<u8> = b"fLaC".to_vec();
+            data.extend_from_slice(&[0, 0, 0, 34]);
+            data.extend_from_slice(&[0; 34]);
+            data.extend_from_slice(&[0x84, 0, 0, comment.len() as u8]);
+            data.extend_from_slice(comment);
+            data.extend_from_slice(&frames);
+            data
+        };
+
+        let one: Vec<u8> = build(b"TITLE=one");
+        let two: Vec<u8> = build(b"TITLE=a different title");
+        let (s1, s2) = (one.len() as u64, two.len() as u64);
+        assert_eq!(
+            Essence::from_flac(&mut Cursor::new(one), s1).unwrap(),
+            Essence::from_flac(&mut Cursor::new(two), s2).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_mp4_hashes_mdat() {
+        let build = |udta: &[u8]| {
+            let mut data: Vec<u8> = Vec::new();
+            data.extend_from_slice(&16u32.to_be_bytes());
+            data.extend_from_slice(b"ftypisom\0\0\0\0");
+            data.extend_from_slice(&((udta.len() + 8) as u32).to_be_bytes());
+            data.extend_from_slice(b"moov");
+            data.extend_from_slice(udta);
+            data.extend_from_slice(&12u32.to_be_bytes());
+            data.extend_from_slice(b"mdat");
+            data.extend_from_slice(&[9, 8, 7, 6]);
+            data
+        };
+
+        let one: Vec<u8> = build(b"title one");
+        let two: Vec<u8> = build(b"another title");
+        let (s1, s2) = (one.len() as u64, two.len() as u64);
+        let h1 = Essence::from_mp4(&mut Cursor::new(one), s1).unwrap().unwrap();
+        let h2 = Essence::from_mp4(&mut Cursor::new(two), s2).unwrap().unwrap();
+        assert_eq!(h1, h2);
+        assert_eq!(h1, blake3::hash(&[9, 8, 7, 6]));
+
+        // a 64-bit size reaching past u64::MAX ends the walk
+        let mut data: Vec<u8> = build(b"");
+        data.extend_from_slice(&1u32.to_be_bytes());
+        data.extend_from_slice(b"free");
+        data.extend_from_slice(&u64::MAX.to_be_bytes());
+        let size: u64 = data.len() as u64;
+        assert_eq!(Essence::from_mp4(&mut Cursor::new(data), size).unwrap(), Some(blake3::hash(&[9, 8, 7, 6])));
+    }
+}
@@ -179,6 +179,18 @@ fn get_value_f64(field: Option<&exif::Field>) -> Option<f64> {
 }
 
 
+/// EXIF orientation (1-8) of the primary image, if the file carries one.
+pub(crate) fn orientation(path: &str) -> Option<u32> {
+    let file: File = std::fs::File::open(path).ok()?;
+    let mut buf_reader: BufReader<&File> = std::io::BufReader::new(&file);
+    let exif: Exif = exif::Reader::new().read_from_container(&mut buf_reader).ok()?;
+
+    exif.get_field(Tag::Orientation, In::PRIMARY)?
+        .value
+        .get_uint(0)
+        .filter(|x| (1..=8).contains(x))
+}
+
 pub struct ExifExtractor { path: String }
 
 impl FromFile for ExifExtractor {
@@ -0,0 +1,232 @@
+use std::{path::Path, sync::LazyLock};
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use regex::Regex;
+
+use crate::{
+    meta::{MetaAttribute, MetaSource, MetaType, MetaValue},
+    Extractor, FromFile, Meta, MetaError};
+
+/// Built-in patterns as (hint, regex, utc), the more specific first.
+/// Dates come from the named groups `year`, `month`, `day`, `hour`, `minute`,
+/// `second` and `ampm`, or from `unix` seconds.
+const PATTERNS: [(&str, &str, bool); 8] = [
+    // Pixel camera names are in UTC
+    ("pixel", r"^PXL_(?P<year>\d{4})(?P<month>\d{2})(?P<day>\d{2})_(?P<hour>\d{2})(?P<minute>\d{2})(?P<second>\d{2})", true),
+    ("whatsapp", r"^WhatsApp (?:Image|Video|Audio|Ptt) (?P<year>\d{4})-(?P<month>\d{2})-(?P<day>\d{2}) at (?P<hour>\d{1,2})\.(?P<minute>\d{2})\.(?P<second>\d{2})(?:\s*(?P<ampm>[AaPp][Mm]))?", false),
+    ("whatsapp", r"^(?:IMG|VID|AUD|PTT|STK|DOC)-(?P<year>\d{4})(?P<month>\d{2})(?P<day>\d{2})-WA\d+", false),
+    ("signal", r"^signal-(?P<year>\d{4})-(?P<month>\d{2})-(?P<day>\d{2})-(?P<hour>\d{2})-?(?P<minute>\d{2})-?(?P<second>\d{2})", false),
+    ("screenshot", r"^Screen ?[Ss]hot[ _-](?:from )?(?P<year>\d{4})-?(?P<month>\d{2})-?(?P<day>\d{2})(?:[ _-]|\sat\s)(?P<hour>\d{1,2})[.:-]?(?P<minute>\d{2})[.:-]?(?P<second>\d{2})(?:\s*(?P<ampm>[AaPp][Mm]))?", false),
+    ("camera", r"^(?:IMG|VID|PANO|MVIMG|BURST\d*)_?(?P<year>\d{4})(?P<month>\d{2})(?P<day>\d{2})_(?P<hour>\d{2})(?P<minute>\d{2})(?P<second>\d{2})", false),
+    // e.g. 1623764730123.jpg from messengers and web downloads
+    ("timestamp", r"^(?P<unix>1\d{9})(?:\d{3})?(?:\D|$)", true),
+    ("", r"(?:^|\D)(?P<year>(?:19|20)\d{2})[-_.]?(?P<month>\d{2})[-_.]?(?P<day>\d{2})(?:\D{1,5}(?P<hour>\d{2})[-_.:h]?(?P<minute>\d{2})[-_.:m]?(?P<second>\d{2}))?(?:\D|$)", false),
+];
+
+/// [`PATTERNS`], compiled once.
+static BUILTIN: LazyLock<Vec<FilenamePattern>> = LazyLock::new(|| {
+    PATTERNS
+        .iter()
+        .map(|(hint, pattern, utc)| FilenamePattern { hint: hint.to_string(), regex: Regex::new(pattern).unwrap(), utc: *utc })
+        .collect()
+});
+
+/// UUIDs, as in names generated on archiving, hold digit runs that read as dates.
+static UUID: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}").unwrap()
+});
+
+/// A date found in a file name.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FilenameDate {
+    pub local: NaiveDateTime,
+
+    /// Whether the name carries the time of day, not just the date.
+    pub has_time: bool,
+
+    /// Offset in minutes, for names written in UTC.
+    pub offset: Option<i64>,
+
+    /// The app or device that names files this way, e.g. "whatsapp".
+    pub hint: Option<String>,
+}
+
+/// A file name pattern with named groups for the date, see [`PATTERNS`].
+#[derive(Clone, Debug)]
+pub struct FilenamePattern {
+    hint: String,
+    regex: Regex,
+    utc: bool,
+}
+
+impl FilenamePattern {
+    pub fn new(hint: &str, pattern: &str) -> Result<Self, MetaError> {
+        let regex: Regex = Regex::new(pattern)?;
+        let groups: Vec<&str> = regex.capture_names().flatten().collect();
+        let dated: bool = ["year", "month", "day"].iter().all(|g| groups.contains(g)) || groups.contains(&"unix");
+        if !dated {
+            return Err(format!("filename pattern {} needs year, month and day groups, or unix", pattern).into());
+        }
+        Ok(Self { hint: hint.to_string(), regex, utc: false })
+    }
+
+    /// The names are written in UTC rather than local time.
+    pub fn utc(mut self) -> Self {
+        self.utc = true;
+        return self;
+    }
+
+    /// The first match with a valid date, a name can hold other digit runs.
+    fn parse(&self, name: &str) -> Option<FilenameDate> {
+        self.regex.captures_iter(name).find_map(|c| {
+            let field = |group: &str| c.name(group).and_then(|m| m.as_str().parse::<u32>().ok());
+
+            let (local, has_time) = match c.name("unix") {
+                Some(unix) => (DateTime::<Utc>::from_timestamp(unix.as_str().parse().ok()?, 0)?.naive_utc(), true),
+                None => {
+                    let date: NaiveDate = NaiveDate::from_ymd_opt(field("year")? as i32, field("month")?, field("day")?)?;
+                    let hour: Option<u32> = match (field("hour"), c.name("ampm").map(|m| m.as_str().to_ascii_lowercase())) {
+                        (Some(12), Some(ampm)) if ampm == "am" => Some(0),
+                        (Some(h), Some(ampm)) if ampm == "pm" && h < 12 => Some(h + 12),
+                        (hour, _) => hour
+                    };
+                    let time: Option<NaiveTime> = match (hour, field("minute"), field("second")) {
+                        (Some(h), Some(m), s) => NaiveTime::from_hms_opt(h, m, s.unwrap_or(0)),
+                        _ => None
+                    };
+                    (date.and_time(time.unwrap_or_default()), time.is_some())
+                }
+            };
+
+            Some(FilenameDate {
+                local,
+                has_time,
+                offset: self.utc.then_some(0),
+                hint: (!self.hint.is_empty()).then(|| self.hint.clone()),
+            })
+        })
+    }
+}
+
+/// The date in a file name, trying `patterns` before the built-in ones.
+pub fn parse(name: &str, patterns: &[FilenamePattern]) -> Option<FilenameDate> {
+    let name = UUID.replace_all(name, "_");
+    patterns
+        .iter()
+        .chain(BUILTIN.iter())
+        .find_map(|p| p.parse(&name))
+}
+
+/// Dates and app hints in file names, e.g. IMG_20190704_153012.jpg,
+/// "WhatsApp Image 2021-03-04 at 10.11.12.jpeg" or VID-20180101-WA0003.mp4.
+pub struct Filename {
+    path: String,
+    patterns: Vec<FilenamePattern>
+}
+
+impl FromFile for Filename {
+    fn file(path: &str) -> Self {
+        Self { path: path.to_string(), patterns: Vec::new() }
+    }
+}
+
+impl Filename {
+    /// User-defined patterns, tried before the built-in ones.
+    pub fn patterns(mut self, patterns: &[FilenamePattern]) -> Self {
+        self.patterns = patterns.to_vec();
+        return self;
+    }
+}
+
+impl Extractor for Filename {
+    fn name(&self) -> String {
+        return "FILENAME".to_string();
+    }
+
+    fn extract(&self, meta: &mut Meta) -> Result<(), MetaError> {
+        let name: &str = match Path::new(&self.path).file_name().and_then(|n| n.to_str()) {
+            Some(n) => n,
+            None => return Ok(())
+        };
+        let found: FilenameDate = match parse(name, &self.patterns) {
+            Some(f) => f,
+            None => return Ok(())
+        };
+
+        let format: &str = if found.has_time { "%Y:%m:%d %H:%M:%S" } else { "%Y:%m:%d" };
+        meta.add(MetaAttribute{
+            source: MetaSource::Filename,
+            tag: "date".to_string(),
+            value: MetaType::String(MetaValue::from(found.local.format(format).to_string())),
+        });
+        if let Some(offset) = found.offset {
+            meta.add(MetaAttribute{
+                source: MetaSource::Filename,
+                tag: "date.offset".to_string(),
+                value: MetaType::Int64(MetaValue::from(offset)),
+            });
+        }
+        if let Some(hint) = found.hint {
+            meta.add(MetaAttribute{
+                source: MetaSource::Filename,
+                tag: "hint".to_string(),
+                value: MetaType::String(MetaValue::from(hint)),
+            });
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{parse, Filename, FilenamePattern};
+    use crate::{Extractor, FromFile, Meta, MetaSource};
+
+    fn found(name: &str) -> (String, Option<String>) {
+        let date = parse(name, &[]).unwrap();
+        (date.local.to_string(), date.hint)
+    }
+
+    #[test]
+    fn test_builtin_patterns() {
+        assert_eq!(found("IMG_20190704_153012.jpg"), ("2019-07-04 15:30:12".to_string(), Some("camera".to_string())));
+        assert_eq!(found("PXL_20230101_101530123.MP.jpg"), ("2023-01-01 10:15:30".to_string(), Some("pixel".to_string())));
+        assert_eq!(found("WhatsApp Image 2021-03-04 at 10.11.12.jpeg").0, "2021-03-04 10:11:12");
+        assert_eq!(found("WhatsApp Video 2021-03-04 at 8.11.12 PM.mp4").0, "2021-03-04 20:11:12");
+        assert_eq!(found("Screenshot_2020-05-06-07-08-09-123_com.example.png"), ("2020-05-06 07:08:09".to_string(), Some("screenshot".to_string())));
+        assert_eq!(found("Screenshot 2021-06-15 at 12.45.30 AM.png").0, "2021-06-15 00:45:30");
+        assert_eq!(found("signal-2022-11-30-235959.jpg").1.as_deref(), Some("signal"));
+        assert_eq!(found("1623764730123.jpg"), ("2021-06-15 13:45:30".to_string(), Some("timestamp".to_string())));
+        assert_eq!(found("holiday 2018.07.21.jpg"), ("2018-07-21 00:00:00".to_string(), None));
+
+        let whatsapp = parse("VID-20180101-WA0003.mp4", &[]).unwrap();
+        assert_eq!((whatsapp.has_time, whatsapp.hint.as_deref()), (false, Some("whatsapp")));
+        assert_eq!(parse("PXL_20230101_101530123.jpg", &[]).unwrap().offset, Some(0));
+
+        assert!(parse("DSC01234.JPG", &[]).is_none());
+        assert!(parse("IMG_20211345_000000.jpg", &[]).is_none());
+        assert!(parse("3f2a1c9e-2019-0612-a716-446655440000.jpg", &[]).is_none());
+        assert_eq!(found("IMG_20190704_153012_3F2A1C9E-2019-0612-A716-446655440000.jpg").0, "2019-07-04 15:30:12");
+    }
+
+    #[test]
+    fn test_user_patterns() {
+        assert!(FilenamePattern::new("scanner", r"scan(\d+)").is_err());
+        assert!(FilenamePattern::new("scanner", r"scan(").is_err());
+
+        let scanner: FilenamePattern = FilenamePattern::new("scanner", r"^scan_(?P<day>\d{2})(?P<month>\d{2})(?P<year>\d{4})").unwrap().utc();
+        let date = parse("scan_04072019_0001.tif", std::slice::from_ref(&scanner)).unwrap();
+        assert_eq!((date.local.to_string(), date.offset), ("2019-07-04 00:00:00".to_string(), Some(0)));
+
+        let mut meta: Meta = Meta::new();
+        Filename::file("/photos/scan_04072019_0001.tif").patterns(&[scanner]).extract(&mut meta).unwrap();
+        assert_eq!(String::from(meta.find_one(MetaSource::Filename, "date").unwrap().value), "2019:07:04");
+        assert_eq!(i64::from(meta.find_one(MetaSource::Filename, "date.offset").unwrap().value), 0);
+        assert_eq!(String::from(meta.find_one(MetaSource::Filename, "hint").unwrap().value), "scanner");
+
+        let mut meta: Meta = Meta::new();
+        Filename::file("/photos/DSC01234.JPG").extract(&mut meta).unwrap();
+        assert!(meta.0.is_empty());
+    }
+}
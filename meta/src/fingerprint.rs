@@ -0,0 +1,407 @@
+use std::{fs::File, io::{BufReader, Read, Seek}, path::Path};
+
+use image::{GrayImage, ImageFormat};
+
+#[cfg(feature = "mp4")]
+use mp4::MediaType;
+
+#[cfg(feature = "mp4")]
+use crate::h264::Decoder;
+use crate::{
+    meta::{MetaAttribute, MetaSource, MetaType, MetaValue},
+    phash::dhash,
+    riff::{self, Chunk},
+    FromFile, Extractor, Meta, MetaError};
+
+/// Number of frames sampled across the duration of a video.
+const FRAMES: usize = 8;
+
+/// Largest frame payload decoded, anything bigger is not a sane MJPEG frame.
+const MAX_FRAME: usize = 16 * 1024 * 1024;
+
+/// Perceptual fingerprint of a video: the dHash of frames decoded at fixed
+/// fractions of the duration, concatenated into `hash.video`, plus the duration
+/// it covers as `video.duration` from [`MetaSource::Fingerprint`].
+/// Fingerprints compare with the same [`crate::similarity`] API as image hashes,
+/// so re-encoded or resized copies land close to each other.
+///
+/// Motion-JPEG frames are decoded with the image codecs already linked, in AVI
+/// and QuickTime/MP4. H.264 in QuickTime/MP4 is sampled at its keyframes, the
+/// sync samples preceding each offset, with the intra-only decoder of `h264`.
+/// HEVC, VP9 and other codecs produce no fingerprint.
+pub struct VideoFingerprint {
+    path: String,
+}
+
+/// Decoded frames and the duration they were sampled from.
+struct Frames {
+    duration: f64,
+    frames: Vec<GrayImage>,
+}
+
+impl FromFile for VideoFingerprint {
+    fn file(path: &str) -> Self {
+        Self { path: path.to_string() }
+    }
+}
+
+/// Frame indices at the middle of `FRAMES` equal slices of `count` frames.
+fn sample_indices(count: u64) -> Vec<u64> {
+    (0..FRAMES as u64)
+        .map(|i| ((2 * i + 1) * count / (2 * FRAMES as u64)).min(count.saturating_sub(1)))
+        .collect()
+}
+
+/// Decode a Motion-JPEG frame to grayscale. Returns `None` for anything but JPEG.
+fn decode_jpeg(data: &[u8]) -> Option<GrayImage> {
+    if data.len() < 4 || data[0] != 0xFF || data[1] != 0xD8 {
+        return None;
+    }
+    image::load_from_memory_with_format(data, ImageFormat::Jpeg)
+        .ok()
+        .map(|img| img.to_luma8())
+}
+
+impl VideoFingerprint {
+    /// MJPEG frames from an AVI `movi` list.
+    fn from_avi<R: Read + Seek>(reader: &mut R, file_size: u64) -> Result<Option<Frames>, MetaError> {
+        // the AVI extractor reports broken files, the fingerprint is best effort
+        Ok(Self::read_avi(reader, file_size).unwrap_or(None))
+    }
+
+    fn read_avi<R: Read + Seek>(reader: &mut R, file_size: u64) -> Result<Option<Frames>, MetaError> {
+        let avi: Chunk = match riff::read_chunk(reader, 0, file_size)? {
+            Some(c) if c.is(b"RIFF") && c.is_list(b"AVI ") => c,
+            _ => return Ok(None)
+        };
+
+        let mut stream: Option<(usize, f64)> = None;
+        let mut frames: Vec<Chunk> = Vec::new();
+
+        for chunk in riff::children(reader, &avi, file_size)? {
+            if chunk.is_list(b"hdrl") {
+                let strls: Vec<Chunk> = riff::children(reader, &chunk, file_size)?
+                    .into_iter()
+                    .filter(|c| c.is_list(b"strl"))
+                    .collect();
+
+                for (stream_no, strl) in strls.iter().enumerate() {
+                    for strh in riff::children(reader, strl, file_size)?.into_iter().filter(|c| c.is(b"strh")) {
+                        let data: Vec<u8> = riff::read_payload(reader, &strh, 64)?;
+                        let scale: u32 = riff::read_u32(&data, 20).unwrap_or(0);
+                        let rate: u32 = riff::read_u32(&data, 24).unwrap_or(0);
+                        if stream.is_none() && data.get(0..4) == Some(b"vids") && scale > 0 && rate > 0 {
+                            stream = Some((stream_no, scale as f64 / rate as f64));
+                        }
+                    }
+                }
+            }
+            else if chunk.is_list(b"movi") {
+                let (stream_no, _) = match stream {
+                    Some(s) => s,
+                    None => return Ok(None)
+                };
+                let prefix: Vec<u8> = format!("{:02}", stream_no).into_bytes();
+
+                let mut lists: Vec<Chunk> = vec![chunk];
+                while let Some(list) = lists.pop() {
+                    for item in riff::children(reader, &list, file_size)? {
+                        if item.is_list(b"rec ") {
+                            lists.push(item);
+                        }
+                        else if item.id[0..2] == prefix[..] && (&item.id[2..4] == b"dc" || &item.id[2..4] == b"db") && item.size > 0 {
+                            frames.push(item);
+                        }
+                    }
+                }
+            }
+        }
+
+        let frame_time: f64 = match stream {
+            Some((_, t)) => t,
+            None => return Ok(None)
+        };
+        if frames.is_empty() {
+            return Ok(None);
+        }
+        frames.sort_by_key(|c| c.offset);
+
+        let mut decoded: Vec<GrayImage> = Vec::new();
+        for index in sample_indices(frames.len() as u64) {
+            let data: Vec<u8> = riff::read_payload(reader, &frames[index as usize], MAX_FRAME)?;
+            match decode_jpeg(&data) {
+                Some(img) => decoded.push(img),
+                None => return Ok(None)
+            }
+        }
+
+        Ok(Some(Frames { duration: frames.len() as f64 * frame_time, frames: decoded }))
+    }
+
+    /// Keyframes from the first decodable video track of a QuickTime/MP4 file:
+    /// every sample of Motion-JPEG, the nearest preceding sync sample of H.264.
+    #[cfg(feature = "mp4")]
+    fn from_mp4<R: Read + Seek>(reader: R, file_size: u64) -> Result<Option<Frames>, MetaError> {
+        // the MP4 extractor reports broken files, the fingerprint is best effort
+        let mut mp4 = match mp4::Mp4Reader::read_header(reader, file_size) {
+            Ok(m) => m,
+            Err(_) => return Ok(None)
+        };
+
+        let mut tracks: Vec<&mp4::Mp4Track> = mp4.tracks()
+            .values()
+            .filter(|t| t.trak.mdia.hdlr.handler_type.to_string() == "vide" && t.sample_count() > 0)
+            .collect();
+        tracks.sort_by_key(|t| t.track_id());
+
+        // the mp4 crate only recognizes avc1, hev1 and vp09, anything else may be Motion-JPEG
+        let (track_id, sample_count, duration, mut decoder, sync) = match tracks.iter().find_map(|t| {
+            let decoder: Option<Decoder> = match (t.media_type(), &t.trak.mdia.minf.stbl.stsd.avc1) {
+                (Err(_), _) => None,
+                (Ok(MediaType::H264), Some(avc1)) => {
+                    let mut decoder: Decoder = Decoder::new(avc1.avcc.length_size_minus_one as usize % 4 + 1);
+                    avc1.avcc.sequence_parameter_sets
+                        .iter()
+                        .chain(avc1.avcc.picture_parameter_sets.iter())
+                        .for_each(|nal| decoder.parameter_set(&nal.bytes));
+                    Some(decoder)
+                },
+                _ => return None
+            };
+            let sync: Option<Vec<u32>> = t.trak.mdia.minf.stbl.stss.as_ref().map(|stss| stss.entries.clone());
+            Some((t.track_id(), t.sample_count(), t.duration().as_secs_f64(), decoder, sync))
+        }) {
+            Some(t) => t,
+            None => return Ok(None)
+        };
+
+        let mut decoded: Vec<GrayImage> = Vec::new();
+        let mut last: Option<(u32, GrayImage)> = None;
+        for index in sample_indices(sample_count as u64) {
+            let mut number: u32 = index as u32 + 1;
+            if decoder.is_some() {
+                // without a sync sample table every sample is a keyframe
+                if let Some(sync) = &sync {
+                    number = match sync.iter().rev().find(|n| **n <= number).or(sync.first()) {
+                        Some(n) => *n,
+                        None => return Ok(None)
+                    };
+                }
+            }
+            if let Some((n, img)) = &last {
+                if *n == number {
+                    decoded.push(img.clone());
+                    continue;
+                }
+            }
+
+            let sample = match mp4.read_sample(track_id, number) {
+                Ok(Some(s)) => s,
+                _ => return Ok(None)
+            };
+            let img: GrayImage = match decoder.as_mut().map_or_else(|| decode_jpeg(&sample.bytes), |d| d.decode(&sample.bytes)) {
+                Some(img) => img,
+                None => return Ok(None)
+            };
+            decoded.push(img.clone());
+            last = Some((number, img));
+        }
+
+        Ok(Some(Frames { duration, frames: decoded }))
+    }
+}
+
+impl Extractor for VideoFingerprint {
+    fn name(&self) -> String {
+        return "VIDEOHASH".to_string();
+    }
+
+    fn extract(&self, meta: &mut Meta) -> Result<(), MetaError> {
+        let extension: String = Path::new(&self.path)
+            .extension()
+            .map_or(String::new(), |x| x.to_string_lossy().to_ascii_lowercase());
+
+        let file: File = File::open(&self.path)?;
+        let file_size: u64 = file.metadata()?.len();
+        let mut reader: BufReader<File> = BufReader::new(file);
+
+        let frames: Option<Frames> = match extension.as_str() {
+            "avi" => Self::from_avi(&mut reader, file_size)?,
+            #[cfg(feature = "mp4")]
+            "mp4" | "mov" | "m4v" => Self::from_mp4(reader, file_size)?,
+            _ => None
+        };
+
+        let frames: Frames = match frames {
+            Some(f) if f.frames.len() == FRAMES => f,
+            _ => return Ok(())
+        };
+
+        let fingerprint: String = frames.frames
+            .iter()
+            .map(|img| format!("{:016x}", dhash(img)))
+            .collect();
+
+        meta.add(MetaAttribute {
+            source: MetaSource::Hash,
+            tag: "hash.video".to_string(),
+            value: MetaType::String(MetaValue::from(fingerprint)),
+        });
+
+        meta.add(MetaAttribute {
+            source: MetaSource::Fingerprint,
+            tag: "video.duration".to_string(),
+            value: MetaType::Rational(MetaValue::from(frames.duration)),
+        });
+
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use image::{codecs::jpeg::JpegEncoder, imageops::{self, FilterType}, ColorType, GrayImage, Luma};
+
+    #[cfg(feature = "mp4")]
+    use mp4::{AvcConfig, Bytes, MediaConfig, Mp4Config, Mp4Sample, Mp4Writer, TrackConfig, TrackType};
+
+    use super::{sample_indices, Frames, VideoFingerprint, FRAMES};
+    #[cfg(feature = "mp4")]
+    use crate::h264::test::{parameter_sets, pcm_sample};
+    use crate::similarity;
+
+    fn chunk(id: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut data: Vec<u8> = Vec::new();
+        data.extend_from_slice(id);
+        data.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        data.extend_from_slice(payload);
+        if payload.len() % 2 == 1 {
+            data.push(0);
+        }
+        data
+    }
+
+    fn list(list_type: &[u8; 4], children: &[Vec<u8>]) -> Vec<u8> {
+        let mut payload: Vec<u8> = list_type.to_vec();
+        children.iter().for_each(|c| payload.extend_from_slice(c));
+        let mut data: Vec<u8> = chunk(b"LIST", &payload);
+        if list_type == b"AVI " {
+            data[0..4].copy_from_slice(b"RIFF");
+        }
+        data
+    }
+
+    fn image(n: u32, width: u32, height: u32) -> GrayImage {
+        let img: GrayImage = GrayImage::from_fn(160, 120, |x, y| Luma([((x * (n + 1) + y * 3) % 256) as u8]));
+        imageops::resize(&img, width, height, FilterType::Triangle)
+    }
+
+    fn frame(n: u32, width: u32, height: u32, quality: u8) -> Vec<u8> {
+        let img: GrayImage = image(n, width, height);
+
+        let mut data: Vec<u8> = Vec::new();
+        JpegEncoder::new_with_quality(&mut data, quality)
+            .encode(img.as_raw(), width, height, ColorType::L8)
+            .unwrap();
+        data
+    }
+
+    fn avi(width: u32, height: u32, quality: u8) -> Vec<u8> {
+        let mut strh: Vec<u8> = vec![0; 56];
+        strh[0..4].copy_from_slice(b"vids");
+        strh[4..8].copy_from_slice(b"MJPG");
+        strh[20..24].copy_from_slice(&1u32.to_le_bytes());
+        strh[24..28].copy_from_slice(&10u32.to_le_bytes());
+
+        let frames: Vec<Vec<u8>> = (0..16).map(|n| chunk(b"00dc", &frame(n, width, height, quality))).collect();
+
+        list(b"AVI ", &[
+            list(b"hdrl", &[list(b"strl", &[chunk(b"strh", &strh)])]),
+            list(b"movi", &frames),
+        ])
+    }
+
+    /// H.264 in MP4 where only every fourth frame is a keyframe, the others can't be decoded.
+    #[cfg(feature = "mp4")]
+    fn h264(width: u32, height: u32) -> Vec<u8> {
+        let (sps, pps) = parameter_sets(width, height);
+        let config: Mp4Config = Mp4Config {
+            major_brand: "isom".parse().unwrap(),
+            minor_version: 512,
+            compatible_brands: vec!["isom".parse().unwrap(), "avc1".parse().unwrap()],
+            timescale: 1000,
+        };
+        let mut writer = Mp4Writer::write_start(Cursor::new(Vec::new()), &config).unwrap();
+        writer.add_track(&TrackConfig {
+            track_type: TrackType::Video,
+            timescale: 10,
+            language: "und".to_string(),
+            media_conf: MediaConfig::AvcConfig(AvcConfig { width: width as u16, height: height as u16, seq_param_set: sps, pic_param_set: pps }),
+        }).unwrap();
+
+        for n in 0..16 {
+            let is_sync: bool = n % 4 == 0;
+            let bytes: Vec<u8> = if is_sync { pcm_sample(&image(n, width, height)) } else { vec![0, 0, 0, 3, 0x41, 0x9A, 0x80] };
+            writer.write_sample(1, &Mp4Sample { start_time: n as u64, duration: 1, rendering_offset: 0, is_sync, bytes: Bytes::from(bytes) }).unwrap();
+        }
+        writer.write_end().unwrap();
+        writer.into_writer().into_inner()
+    }
+
+    fn hash(frames: &Frames) -> String {
+        frames.frames.iter().map(|img| format!("{:016x}", super::dhash(img))).collect()
+    }
+
+    fn fingerprint(data: Vec<u8>) -> (String, f64) {
+        let size: u64 = data.len() as u64;
+        let frames: Frames = VideoFingerprint::from_avi(&mut Cursor::new(data), size).unwrap().unwrap();
+        (hash(&frames), frames.duration)
+    }
+
+    #[test]
+    fn test_sample_indices() {
+        assert_eq!(sample_indices(16), vec![1, 3, 5, 7, 9, 11, 13, 15]);
+        assert_eq!(sample_indices(3).len(), FRAMES);
+        assert!(sample_indices(3).iter().all(|i| *i < 3));
+    }
+
+    #[test]
+    fn test_reencoded_avi_is_similar() {
+        let (original, duration) = fingerprint(avi(160, 120, 90));
+        let (smaller, _) = fingerprint(avi(80, 60, 40));
+
+        assert_eq!(duration, 1.6);
+        assert_eq!(original.len(), 16 * FRAMES);
+        assert!(similarity(&original, &smaller).unwrap() > 0.9);
+    }
+
+    #[test]
+    fn test_truncated_avi() {
+        let mut data: Vec<u8> = avi(160, 120, 90);
+        data.truncate(data.len() / 2);
+        let size: u64 = data.len() as u64 + 4096;
+
+        assert!(VideoFingerprint::from_avi(&mut Cursor::new(data), size).unwrap().is_none());
+    }
+
+    #[cfg(feature = "mp4")]
+    #[test]
+    fn test_reencoded_h264_is_similar() {
+        let data: Vec<u8> = h264(160, 120);
+        let size: u64 = data.len() as u64;
+        let original: Frames = VideoFingerprint::from_mp4(Cursor::new(data), size).unwrap().unwrap();
+        let data: Vec<u8> = h264(80, 60);
+        let size: u64 = data.len() as u64;
+        let smaller: Frames = VideoFingerprint::from_mp4(Cursor::new(data), size).unwrap().unwrap();
+
+        assert_eq!(original.duration, 1.6);
+        assert_eq!(original.frames.len(), FRAMES);
+        assert_eq!(original.frames[0], original.frames[1]);
+        assert_eq!(original.frames[1], image(0, 160, 120));
+        assert_eq!(original.frames[2], image(4, 160, 120));
+        assert!(similarity(&hash(&original), &hash(&smaller)).unwrap() > 0.9);
+    }
+}
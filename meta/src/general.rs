@@ -8,17 +8,22 @@ pub struct General { path: String }
 impl General {
     fn get_class(&self, extension: &str) -> MetaClass {
         match extension {
-            "mp4" | "mov" | "m4v" | "mkv" | "3gp" | "3gpp" | "avi" | "flv" | "wmv" | "mpe" | "mpeg" | "mpg" | "webm" | "vob" | "mts" => {
+            "mp4" | "mov" | "m4v" | "mkv" | "3gp" | "3gpp" | "avi" | "flv" | "wmv" | "mpe" | "mpeg" | "mpg" | "webm" | "vob" | "mts" | "m2ts" | "m2t" => {
                 return MetaClass::Video;
             },
-            "amr" | "m4a" | "mp3" | "wav" | "flac" | "wma" | "m4r" => {
+            "amr" | "m4a" | "mp3" | "aac" | "wav" | "flac" | "wma" | "m4r" | "ogg" | "oga" | "opus" | "bwf" | "aif" | "aiff" | "aifc" => {
                 return MetaClass::Audio;
             },
-            "heic" | "heif" | "jpeg" | "jpg" | "png" | "dng" | "gif" | "hdr" | "raf" | "tif" | "tiff" | "cr2" | "jfif" | "svg" | "mp" | "pcx" | "bmp" => {
+            "heic" | "heif" | "jpeg" | "jpg" | "png" | "dng" | "gif" | "hdr" | "raf" | "tif" | "tiff" | "cr2" | "jfif" | "svg" | "svgz" | "mp" | "pcx" | "bmp" | "webp" => {
                 return MetaClass::Image;
             },
-            "doc" | "docx" | "pdf" | "xls" | "pptx" | "ppt" | "txt" | "xml" => {
+            "doc" | "docx" | "pdf" | "xls" | "pptx" | "ppt" | "txt" | "xml"
+            | "docm" | "dot" | "dotx" | "xlsx" | "xlsm" | "pptm" | "pps" | "ppsx" | "odt" | "ods" | "odp" | "odg"
+            | "text" | "md" | "log" | "csv" | "tsv" | "json" | "vcf" | "vcard" | "ics" | "ical" => {
                 return MetaClass::Document;
+            },
+            "zip" | "tar" | "tgz" | "gz" | "7z" | "rar" => {
+                return MetaClass::Archive;
             }
             _ => {
                 return MetaClass::Unknown;
@@ -0,0 +1,2003 @@
+use std::collections::HashMap;
+
+use image::GrayImage;
+
+/// Largest picture decoded, in macroblocks (the level 6.2 limit, 8192x4320).
+const MAX_MACROBLOCKS: usize = 139_264;
+
+/// 4x4 blocks of a macroblock in decoding order, as (x, y) in 4x4 units.
+const BLOCK_XY: [(usize, usize); 16] = [
+    (0, 0), (1, 0), (0, 1), (1, 1), (2, 0), (3, 0), (2, 1), (3, 1),
+    (0, 2), (1, 2), (0, 3), (1, 3), (2, 2), (3, 2), (2, 3), (3, 3),
+];
+
+/// Decoding order of the 4x4 block at [y][x] in 4x4 units.
+const BLOCK_INDEX: [[usize; 4]; 4] = [[0, 1, 4, 5], [2, 3, 6, 7], [8, 9, 12, 13], [10, 11, 14, 15]];
+
+/// Zig-zag scan of a 4x4 block, as raster positions.
+const ZIGZAG_4X4: [usize; 16] = [0, 1, 4, 8, 5, 2, 3, 6, 9, 12, 13, 10, 7, 11, 14, 15];
+
+/// Zig-zag scan of an 8x8 block, as raster positions.
+const ZIGZAG_8X8: [usize; 64] = [
+    0, 1, 8, 16, 9, 2, 3, 10, 17, 24, 32, 25, 18, 11, 4, 5,
+    12, 19, 26, 33, 40, 48, 41, 34, 27, 20, 13, 6, 7, 14, 21, 28,
+    35, 42, 49, 56, 57, 50, 43, 36, 29, 22, 15, 23, 30, 37, 44, 51,
+    58, 59, 52, 45, 38, 31, 39, 46, 53, 60, 61, 54, 47, 55, 62, 63,
+];
+
+/// Default scaling lists (Table 7-3 and 7-4), in zig-zag order.
+const DEFAULT_4X4_INTRA: [u8; 16] = [6, 13, 13, 20, 20, 20, 28, 28, 28, 28, 32, 32, 32, 37, 37, 42];
+const DEFAULT_4X4_INTER: [u8; 16] = [10, 14, 14, 20, 20, 20, 24, 24, 24, 24, 27, 27, 27, 30, 30, 34];
+const DEFAULT_8X8_INTRA: [u8; 64] = [
+    6, 10, 10, 13, 11, 13, 16, 16, 16, 16, 18, 18, 18, 18, 18, 23,
+    23, 23, 23, 23, 23, 25, 25, 25, 25, 25, 25, 25, 27, 27, 27, 27,
+    27, 27, 27, 27, 29, 29, 29, 29, 29, 29, 29, 31, 31, 31, 31, 31,
+    31, 33, 33, 33, 33, 33, 36, 36, 36, 36, 38, 38, 38, 40, 40, 42,
+];
+const DEFAULT_8X8_INTER: [u8; 64] = [
+    9, 13, 13, 15, 13, 15, 17, 17, 17, 17, 19, 19, 19, 19, 19, 21,
+    21, 21, 21, 21, 21, 22, 22, 22, 22, 22, 22, 22, 24, 24, 24, 24,
+    24, 24, 24, 24, 25, 25, 25, 25, 25, 25, 25, 27, 27, 27, 27, 27,
+    27, 28, 28, 28, 28, 28, 30, 30, 30, 30, 32, 32, 32, 33, 33, 35,
+];
+
+/// Dequantisation factors by QP % 6 (8.5.9).
+const NORM_4X4: [[i64; 3]; 6] = [[10, 13, 16], [11, 14, 18], [13, 16, 20], [14, 18, 23], [16, 20, 25], [18, 23, 29]];
+const NORM_8X8: [[i64; 6]; 6] = [
+    [20, 18, 32, 19, 25, 24], [22, 19, 35, 21, 28, 26], [26, 23, 42, 24, 33, 31],
+    [28, 25, 45, 26, 35, 33], [32, 28, 51, 30, 40, 38], [36, 32, 58, 34, 46, 43],
+];
+
+/// Intra coded_block_pattern by codeNum for 4:2:0 and monochrome (Table 9-4).
+const CBP_INTRA: [u8; 48] = [
+    47, 31, 15, 0, 23, 27, 29, 30, 7, 11, 13, 14, 39, 43, 45, 46,
+    16, 3, 5, 10, 12, 19, 21, 26, 28, 35, 37, 42, 44, 1, 2, 4,
+    8, 17, 18, 20, 24, 6, 9, 22, 25, 32, 33, 34, 36, 40, 38, 41,
+];
+const CBP_INTRA_MONO: [u8; 16] = [15, 0, 7, 11, 13, 14, 3, 5, 10, 12, 1, 2, 4, 8, 6, 9];
+
+/// coeff_token code lengths and values by TotalCoeff * 4 + TrailingOnes,
+/// for 0 <= nC < 2, 2 <= nC < 4 and 4 <= nC < 8 (Table 9-5).
+const COEFF_TOKEN_LENGTH: [[u8; 68]; 3] = [
+    [
+        1, 0, 0, 0, 6, 2, 0, 0, 8, 6, 3, 0, 9, 8, 7, 5, 10, 9, 8, 6,
+        11, 10, 9, 7, 13, 11, 10, 8, 13, 13, 11, 9, 13, 13, 13, 10,
+        14, 14, 13, 11, 14, 14, 14, 13, 15, 15, 14, 14, 15, 15, 15, 14,
+        16, 15, 15, 15, 16, 16, 16, 15, 16, 16, 16, 16, 16, 16, 16, 16,
+    ],
+    [
+        2, 0, 0, 0, 6, 2, 0, 0, 6, 5, 3, 0, 7, 6, 6, 4, 8, 6, 6, 4,
+        8, 7, 7, 5, 9, 8, 8, 6, 11, 9, 9, 6, 11, 11, 11, 7,
+        12, 11, 11, 9, 12, 12, 12, 11, 12, 12, 12, 11, 13, 13, 13, 12,
+        13, 13, 13, 13, 13, 14, 13, 13, 14, 14, 14, 13, 14, 14, 14, 14,
+    ],
+    [
+        4, 0, 0, 0, 6, 4, 0, 0, 6, 5, 4, 0, 6, 5, 5, 4, 7, 5, 5, 4,
+        7, 5, 5, 4, 7, 6, 6, 4, 7, 6, 6, 4, 8, 7, 7, 5,
+        8, 8, 7, 6, 9, 8, 8, 7, 9, 9, 8, 8, 9, 9, 9, 8,
+        10, 9, 9, 9, 10, 10, 10, 10, 10, 10, 10, 10, 10, 10, 10, 10,
+    ],
+];
+const COEFF_TOKEN_CODE: [[u8; 68]; 3] = [
+    [
+        1, 0, 0, 0, 5, 1, 0, 0, 7, 4, 1, 0, 7, 6, 5, 3, 7, 6, 5, 3,
+        7, 6, 5, 4, 15, 6, 5, 4, 11, 14, 5, 4, 8, 10, 13, 4,
+        15, 14, 9, 4, 11, 10, 13, 12, 15, 14, 9, 12, 11, 10, 13, 8,
+        15, 1, 9, 12, 11, 14, 13, 8, 7, 10, 9, 12, 4, 6, 5, 8,
+    ],
+    [
+        3, 0, 0, 0, 11, 2, 0, 0, 7, 7, 3, 0, 7, 10, 9, 5, 7, 6, 5, 4,
+        4, 6, 5, 6, 7, 6, 5, 8, 15, 6, 5, 4, 11, 14, 13, 4,
+        15, 10, 9, 4, 11, 14, 13, 12, 8, 10, 9, 8, 15, 14, 13, 12,
+        11, 10, 9, 12, 7, 11, 6, 8, 9, 8, 10, 1, 7, 6, 5, 4,
+    ],
+    [
+        15, 0, 0, 0, 15, 14, 0, 0, 11, 15, 13, 0, 8, 12, 14, 12, 15, 10, 11, 11,
+        11, 8, 9, 10, 9, 14, 13, 9, 8, 10, 9, 8, 15, 14, 13, 13,
+        11, 14, 10, 12, 15, 10, 13, 12, 11, 14, 9, 12, 8, 10, 13, 8,
+        13, 7, 9, 12, 9, 12, 11, 10, 5, 8, 7, 6, 1, 4, 3, 2,
+    ],
+];
+
+/// coeff_token of 4:2:0 chroma DC (nC == -1).
+const CHROMA_DC_TOKEN_LENGTH: [u8; 20] = [2, 0, 0, 0, 6, 1, 0, 0, 6, 6, 3, 0, 6, 7, 7, 6, 6, 8, 8, 7];
+const CHROMA_DC_TOKEN_CODE: [u8; 20] = [1, 0, 0, 0, 7, 1, 0, 0, 4, 6, 1, 0, 3, 3, 2, 5, 2, 3, 2, 0];
+
+/// total_zeros by TotalCoeff - 1 (Table 9-7 and 9-8).
+const TOTAL_ZEROS_LENGTH: [[u8; 16]; 15] = [
+    [1, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 9],
+    [3, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 6, 6, 6, 6, 0],
+    [4, 3, 3, 3, 4, 4, 3, 3, 4, 5, 5, 6, 5, 6, 0, 0],
+    [5, 3, 4, 4, 3, 3, 3, 4, 3, 4, 5, 5, 5, 0, 0, 0],
+    [4, 4, 4, 3, 3, 3, 3, 3, 4, 5, 4, 5, 0, 0, 0, 0],
+    [6, 5, 3, 3, 3, 3, 3, 3, 4, 3, 6, 0, 0, 0, 0, 0],
+    [6, 5, 3, 3, 3, 2, 3, 4, 3, 6, 0, 0, 0, 0, 0, 0],
+    [6, 4, 5, 3, 2, 2, 3, 3, 6, 0, 0, 0, 0, 0, 0, 0],
+    [6, 6, 4, 2, 2, 3, 2, 5, 0, 0, 0, 0, 0, 0, 0, 0],
+    [5, 5, 3, 2, 2, 2, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [4, 4, 3, 3, 1, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [4, 4, 2, 1, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [3, 3, 1, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [2, 2, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+];
+const TOTAL_ZEROS_CODE: [[u8; 16]; 15] = [
+    [1, 3, 2, 3, 2, 3, 2, 3, 2, 3, 2, 3, 2, 3, 2, 1],
+    [7, 6, 5, 4, 3, 5, 4, 3, 2, 3, 2, 3, 2, 1, 0, 0],
+    [5, 7, 6, 5, 4, 3, 4, 3, 2, 3, 2, 1, 1, 0, 0, 0],
+    [3, 7, 5, 4, 6, 5, 4, 3, 3, 2, 2, 1, 0, 0, 0, 0],
+    [5, 4, 3, 7, 6, 5, 4, 3, 2, 1, 1, 0, 0, 0, 0, 0],
+    [1, 1, 7, 6, 5, 4, 3, 2, 1, 1, 0, 0, 0, 0, 0, 0],
+    [1, 1, 5, 4, 3, 3, 2, 1, 1, 0, 0, 0, 0, 0, 0, 0],
+    [1, 1, 1, 3, 3, 2, 2, 1, 0, 0, 0, 0, 0, 0, 0, 0],
+    [1, 0, 1, 3, 2, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0],
+    [1, 0, 1, 3, 2, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 1, 1, 2, 1, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+];
+
+/// total_zeros of 4:2:0 chroma DC by TotalCoeff - 1.
+const CHROMA_DC_ZEROS_LENGTH: [[u8; 4]; 3] = [[1, 2, 3, 3], [1, 2, 2, 0], [1, 1, 0, 0]];
+const CHROMA_DC_ZEROS_CODE: [[u8; 4]; 3] = [[1, 1, 1, 0], [1, 1, 0, 0], [1, 0, 0, 0]];
+
+/// run_before by zerosLeft - 1, the last row for more than 6 zeros left (Table 9-10).
+const RUN_BEFORE_LENGTH: [[u8; 15]; 7] = [
+    [1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [1, 2, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [2, 2, 2, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [2, 2, 2, 3, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [2, 2, 3, 3, 3, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [2, 3, 3, 3, 3, 3, 3, 0, 0, 0, 0, 0, 0, 0, 0],
+    [3, 3, 3, 3, 3, 3, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+];
+const RUN_BEFORE_CODE: [[u8; 15]; 7] = [
+    [1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [3, 2, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [3, 2, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [3, 2, 3, 2, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [3, 0, 1, 3, 2, 5, 4, 0, 0, 0, 0, 0, 0, 0, 0],
+    [7, 6, 5, 4, 3, 2, 1, 1, 1, 1, 1, 1, 1, 1, 1],
+];
+
+/// CABAC rangeTabLPS by pStateIdx and qCodIRangeIdx (Table 9-44).
+const RANGE_LPS: [[u8; 4]; 64] = [
+    [128, 176, 208, 240], [128, 167, 197, 227], [128, 158, 187, 216], [123, 150, 178, 205],
+    [116, 142, 169, 195], [111, 135, 160, 185], [105, 128, 152, 175], [100, 122, 144, 166],
+    [95, 116, 137, 158], [90, 110, 130, 150], [85, 104, 123, 142], [81, 99, 117, 135],
+    [77, 94, 111, 128], [73, 89, 105, 122], [69, 85, 100, 116], [66, 80, 95, 110],
+    [62, 76, 90, 104], [59, 72, 86, 99], [56, 69, 81, 94], [53, 65, 77, 89],
+    [51, 62, 73, 85], [48, 59, 69, 80], [46, 56, 66, 76], [43, 53, 63, 72],
+    [41, 50, 59, 69], [39, 48, 56, 65], [37, 45, 54, 62], [35, 43, 51, 59],
+    [33, 41, 48, 56], [32, 39, 46, 53], [30, 37, 43, 50], [29, 35, 41, 48],
+    [27, 33, 39, 45], [26, 31, 37, 43], [24, 30, 35, 41], [23, 28, 33, 39],
+    [22, 27, 32, 37], [21, 26, 30, 35], [20, 24, 29, 33], [19, 23, 27, 31],
+    [18, 22, 26, 30], [17, 21, 25, 28], [16, 20, 23, 27], [15, 19, 22, 25],
+    [14, 18, 21, 24], [14, 17, 20, 23], [13, 16, 19, 22], [12, 15, 18, 21],
+    [12, 14, 17, 20], [11, 14, 16, 19], [11, 13, 15, 18], [10, 12, 15, 17],
+    [10, 12, 14, 16], [9, 11, 13, 15], [9, 11, 12, 14], [8, 10, 12, 14],
+    [8, 9, 11, 13], [7, 9, 11, 12], [7, 9, 10, 12], [7, 8, 10, 11],
+    [6, 8, 9, 11], [6, 7, 9, 10], [6, 7, 8, 9], [2, 2, 2, 2],
+];
+
+/// CABAC transIdxLPS by pStateIdx (Table 9-45).
+const NEXT_LPS: [u8; 64] = [
+    0, 0, 1, 2, 2, 4, 4, 5, 6, 7, 8, 9, 9, 11, 11, 12,
+    13, 13, 15, 15, 16, 16, 18, 18, 19, 19, 21, 21, 22, 22, 23, 24,
+    24, 25, 26, 26, 27, 27, 28, 29, 29, 30, 30, 30, 31, 32, 32, 33,
+    33, 33, 34, 34, 35, 35, 35, 36, 36, 36, 37, 37, 37, 38, 38, 63,
+];
+
+/// Number of CABAC contexts of a frame coded 4:2:0 I slice.
+const CONTEXTS: usize = 436;
+
+/// (m, n) initialisation of the CABAC contexts used by I slices, by first ctxIdx
+/// (Tables 9-12 to 9-24). Contexts of P/B slices, fields and 4:4:4 are not listed.
+const CONTEXT_INIT: [(usize, &[(i8, i8)]); 7] = [
+    (0, &[(20, -15), (2, 54), (3, 74), (20, -15), (2, 54), (3, 74), (-28, 127), (-23, 104), (-6, 53), (-1, 54), (7, 51)]),
+    (60, &[(0, 41), (0, 63), (0, 63), (0, 63), (-9, 83), (4, 86), (0, 97), (-7, 72), (13, 41), (3, 62)]),
+    (70, &[
+        (0, 11), (1, 55), (0, 69), (-17, 127), (-13, 102), (0, 82), (-7, 74), (-21, 107),
+        (-27, 127), (-31, 127), (-24, 127), (-18, 95), (-27, 127), (-21, 114), (-30, 127), (-17, 123),
+        (-12, 115), (-16, 122), (-11, 115), (-12, 63), (-2, 68), (-15, 84), (-13, 104), (-3, 70),
+        (-8, 93), (-10, 90), (-30, 127), (-1, 74), (-6, 97), (-7, 91), (-20, 127), (-4, 56),
+        (-5, 82), (-7, 76), (-22, 125),
+    ]),
+    (105, &[
+        (-7, 93), (-11, 87), (-3, 77), (-5, 71), (-4, 63), (-4, 68), (-12, 84), (-7, 62),
+        (-7, 65), (8, 61), (5, 56), (-2, 66), (1, 64), (0, 61), (-2, 78), (1, 50),
+        (7, 52), (10, 35), (0, 44), (11, 38), (1, 45), (0, 46), (5, 44), (31, 17),
+        (1, 51), (7, 50), (28, 19), (16, 33), (14, 62), (-13, 108), (-15, 100), (-13, 101),
+        (-13, 91), (-12, 94), (-10, 88), (-16, 84), (-10, 86), (-7, 83), (-13, 87), (-19, 94),
+        (1, 70), (0, 72), (-5, 74), (18, 59), (-8, 102), (-15, 100), (0, 95), (-4, 75),
+        (2, 72), (-11, 75), (-3, 71), (15, 46), (-13, 69), (0, 62), (0, 65), (21, 37),
+        (-15, 72), (9, 57), (16, 54), (0, 62), (12, 72),
+    ]),
+    (166, &[
+        (24, 0), (15, 9), (8, 25), (13, 18), (15, 9), (13, 19), (10, 37), (12, 18),
+        (6, 29), (20, 33), (15, 30), (4, 45), (1, 58), (0, 62), (7, 61), (12, 38),
+        (11, 45), (15, 39), (11, 42), (13, 44), (16, 45), (12, 41), (10, 49), (30, 34),
+        (18, 42), (10, 55), (17, 51), (17, 46), (0, 89), (26, -19), (22, -17), (26, -17),
+        (30, -25), (28, -20), (33, -23), (37, -27), (33, -23), (40, -28), (38, -17), (33, -11),
+        (40, -15), (41, -6), (38, 1), (41, 17), (30, -6), (27, 3), (26, 22), (37, -16),
+        (35, -4), (38, -8), (38, -3), (37, 3), (38, 5), (42, 0), (35, 16), (39, 22),
+        (14, 48), (27, 37), (21, 60), (12, 68), (2, 97),
+    ]),
+    (227, &[
+        (-3, 71), (-6, 42), (-5, 50), (-3, 54), (-2, 62), (0, 58), (1, 63), (-2, 72),
+        (-1, 74), (-9, 91), (-5, 67), (-5, 27), (-3, 39), (-2, 44), (0, 46), (-16, 64),
+        (-8, 68), (-10, 78), (-6, 77), (-10, 86), (-12, 92), (-15, 55), (-10, 60), (-6, 62),
+        (-4, 65), (-12, 73), (-8, 76), (-7, 80), (-9, 88), (-17, 110), (-11, 97), (-20, 84),
+        (-11, 79), (-6, 73), (-4, 74), (-13, 86), (-13, 96), (-11, 97), (-19, 117), (-8, 78),
+        (-5, 33), (-4, 48), (-2, 53), (-3, 62), (-13, 71), (-10, 79), (-12, 86), (-13, 90),
+        (-14, 97),
+    ]),
+    (399, &[
+        (31, 21), (31, 31), (25, 50),
+        (-17, 120), (-20, 112), (-18, 114), (-11, 85), (-15, 92), (-14, 89), (-26, 71), (-15, 81),
+        (-14, 80), (0, 68), (-14, 70), (-24, 56), (-23, 68), (-24, 50), (-11, 74),
+        (23, -13), (26, -13), (40, -15), (49, -14), (44, 3), (45, 6), (44, 34), (33, 54), (19, 82),
+        (-3, 75), (-1, 23), (1, 34), (1, 43), (0, 54), (-2, 55), (0, 61), (1, 64), (0, 68), (-9, 92),
+    ]),
+];
+
+/// ctxIdxOffset of coded_block_flag, significant_coeff_flag, last_significant_coeff_flag
+/// and coeff_abs_level_minus1, plus their ctxBlockCatOffset by ctxBlockCat.
+const CODED_BLOCK_FLAG: usize = 85;
+const SIGNIFICANT: usize = 105;
+const LAST_SIGNIFICANT: usize = 166;
+const ABS_LEVEL: usize = 227;
+const CODED_BLOCK_OFFSET: [usize; 5] = [0, 4, 8, 12, 16];
+const SIGNIFICANT_OFFSET: [usize; 5] = [0, 15, 29, 44, 47];
+const ABS_LEVEL_OFFSET: [usize; 5] = [0, 10, 20, 30, 39];
+
+/// Contexts of the 8x8 luma significance map and levels (ctxBlockCat 5).
+const SIGNIFICANT_8X8: usize = 402;
+const LAST_SIGNIFICANT_8X8: usize = 417;
+const ABS_LEVEL_8X8: usize = 426;
+const SIGNIFICANT_8X8_INC: [u8; 63] = [
+    0, 1, 2, 3, 4, 5, 5, 4, 4, 3, 3, 4, 4, 4, 5, 5,
+    4, 4, 4, 4, 3, 3, 6, 7, 7, 7, 8, 9, 10, 9, 8, 7,
+    7, 6, 11, 12, 13, 11, 6, 7, 8, 9, 14, 10, 9, 8, 6, 11,
+    12, 13, 11, 6, 9, 14, 10, 9, 11, 12, 13, 11, 14, 10, 12,
+];
+const LAST_SIGNIFICANT_8X8_INC: [u8; 63] = [
+    0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2,
+    3, 3, 3, 3, 3, 3, 3, 3, 4, 4, 4, 4, 4, 4, 4, 4,
+    5, 5, 5, 5, 6, 6, 6, 6, 7, 7, 7, 7, 8, 8, 8,
+];
+
+/// ctxBlockCat of the residual blocks.
+const LUMA_DC: usize = 0;
+const LUMA_AC: usize = 1;
+const LUMA_4X4: usize = 2;
+const CHROMA_DC: usize = 3;
+const CHROMA_AC: usize = 4;
+const LUMA_8X8: usize = 5;
+
+/// Removes the emulation prevention bytes of a NAL unit payload.
+fn unescape(nal: &[u8]) -> Vec<u8> {
+    let mut data: Vec<u8> = Vec::with_capacity(nal.len());
+    let mut zeros: usize = 0;
+    for &byte in nal {
+        if zeros >= 2 && byte == 3 {
+            zeros = 0;
+            continue;
+        }
+        zeros = if byte == 0 { zeros + 1 } else { 0 };
+        data.push(byte);
+    }
+    data
+}
+
+/// Bit reader over an RBSP, most significant bit first.
+struct Bits<'a> {
+    data: &'a [u8],
+    position: usize,
+    /// Position of the rbsp_stop_one_bit.
+    end: usize,
+}
+
+impl<'a> Bits<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        let end: usize = data.iter()
+            .rposition(|b| *b != 0)
+            .map_or(0, |at| at * 8 + 7 - data[at].trailing_zeros() as usize);
+        Self { data, position: 0, end }
+    }
+
+    fn bit(&mut self) -> Option<u32> {
+        let byte: u8 = *self.data.get(self.position / 8)?;
+        let bit: u8 = (byte >> (7 - self.position % 8)) & 1;
+        self.position += 1;
+        Some(bit as u32)
+    }
+
+    fn bits(&mut self, count: u32) -> Option<u32> {
+        let mut value: u32 = 0;
+        for _ in 0..count {
+            value = (value << 1) | self.bit()?;
+        }
+        Some(value)
+    }
+
+    fn flag(&mut self) -> Option<bool> {
+        self.bit().map(|b| b == 1)
+    }
+
+    /// Unsigned Exp-Golomb code, ue(v).
+    fn ue(&mut self) -> Option<u32> {
+        let mut zeros: u32 = 0;
+        while self.bit()? == 0 {
+            zeros += 1;
+            if zeros > 31 {
+                return None;
+            }
+        }
+        u32::try_from((1u64 << zeros) - 1 + self.bits(zeros)? as u64).ok()
+    }
+
+    /// Signed Exp-Golomb code, se(v).
+    fn se(&mut self) -> Option<i32> {
+        let code: i64 = self.ue()? as i64;
+        Some((if code % 2 == 1 { (code + 1) / 2 } else { -(code / 2) }) as i32)
+    }
+
+    fn align(&mut self) {
+        self.position = self.position.div_ceil(8) * 8;
+    }
+
+    /// more_rbsp_data(): whether anything but the trailing bits is left.
+    fn more_data(&self) -> bool {
+        self.position < self.end
+    }
+
+    /// A variable length code, returned as its index in `lengths` and `codes`.
+    fn vlc(&mut self, lengths: &[u8], codes: &[u8]) -> Option<usize> {
+        let mut code: u32 = 0;
+        for length in 1..=16 {
+            code = (code << 1) | self.bit()?;
+            if let Some(index) = (0..lengths.len()).find(|i| lengths[*i] == length && codes[*i] as u32 == code) {
+                return Some(index);
+            }
+        }
+        None
+    }
+}
+
+/// Scaling lists 0-5 (4x4) and 6-7 (8x8), in zig-zag order.
+type ScalingLists = [[u8; 64]; 8];
+
+fn flat_lists() -> ScalingLists {
+    [[16; 64]; 8]
+}
+
+/// The default list for scaling list `index`.
+fn default_list(index: usize) -> [u8; 64] {
+    let mut list: [u8; 64] = [0; 64];
+    match index {
+        0..=2 => list[..16].copy_from_slice(&DEFAULT_4X4_INTRA),
+        3..=5 => list[..16].copy_from_slice(&DEFAULT_4X4_INTER),
+        6 => list.copy_from_slice(&DEFAULT_8X8_INTRA),
+        _ => list.copy_from_slice(&DEFAULT_8X8_INTER),
+    }
+    list
+}
+
+/// scaling_list() syntax for `count` lists, with `fallback` for lists 0, 3, 6 and 7
+/// when absent (7.4.2.1.1, fall-back rules A and B).
+fn scaling_lists(bits: &mut Bits, count: usize, fallback: &ScalingLists) -> Option<ScalingLists> {
+    let mut lists: ScalingLists = *fallback;
+    for index in 0..8 {
+        let present: bool = index < count && bits.flag()?;
+        if !present {
+            if !matches!(index, 0 | 3 | 6 | 7) {
+                lists[index] = lists[index - 1];
+            }
+            continue;
+        }
+
+        let size: usize = if index < 6 { 16 } else { 64 };
+        let mut last: i32 = 8;
+        let mut next: i32 = 8;
+        for j in 0..size {
+            if next != 0 {
+                next = (last + bits.se()? + 256).rem_euclid(256);
+                if j == 0 && next == 0 {
+                    lists[index] = default_list(index);
+                    break;
+                }
+            }
+            lists[index][j] = (if next == 0 { last } else { next }) as u8;
+            last = lists[index][j] as i32;
+        }
+    }
+    Some(lists)
+}
+
+/// The sequence parameter set fields needed to decode intra pictures.
+#[derive(Clone)]
+struct Sps {
+    chroma_format: u32,
+    log2_max_frame_num: u32,
+    poc_type: u32,
+    log2_max_poc_lsb: u32,
+    delta_pic_order_always_zero: bool,
+    width: usize,
+    height: usize,
+    /// Cropping of the luma plane as left, right, top, bottom.
+    crop: [usize; 4],
+    matrix: bool,
+    scaling: ScalingLists,
+}
+
+impl Sps {
+    /// Parses the RBSP of a SPS. `None` for what is not 8-bit progressive 4:2:0 or monochrome.
+    fn parse(data: &[u8]) -> Option<(u32, Sps)> {
+        let mut bits: Bits = Bits::new(data);
+        let profile: u32 = bits.bits(8)?;
+        bits.bits(16)?;
+        let id: u32 = bits.ue()?;
+
+        let mut chroma_format: u32 = 1;
+        let mut matrix: bool = false;
+        let mut scaling: ScalingLists = flat_lists();
+        if matches!(profile, 100 | 110 | 122 | 244 | 44 | 83 | 86 | 118 | 128 | 138 | 139 | 134 | 135) {
+            chroma_format = bits.ue()?;
+            if chroma_format > 1 {
+                return None;
+            }
+            let depth_luma: u32 = bits.ue()?;
+            let depth_chroma: u32 = bits.ue()?;
+            let bypass: bool = bits.flag()?;
+            if depth_luma != 0 || depth_chroma != 0 || bypass {
+                return None;
+            }
+            matrix = bits.flag()?;
+            if matrix {
+                let defaults: ScalingLists = std::array::from_fn(default_list);
+                scaling = scaling_lists(&mut bits, 8, &defaults)?;
+            }
+        }
+
+        let log2_max_frame_num: u32 = bits.ue()? + 4;
+        let poc_type: u32 = bits.ue()?;
+        let mut log2_max_poc_lsb: u32 = 0;
+        let mut delta_pic_order_always_zero: bool = false;
+        if poc_type == 0 {
+            log2_max_poc_lsb = bits.ue()? + 4;
+        }
+        else if poc_type == 1 {
+            delta_pic_order_always_zero = bits.flag()?;
+            bits.se()?;
+            bits.se()?;
+            for _ in 0..bits.ue()?.min(255) {
+                bits.se()?;
+            }
+        }
+        if log2_max_frame_num > 16 || log2_max_poc_lsb > 16 {
+            return None;
+        }
+
+        bits.ue()?;
+        bits.flag()?;
+        let width: usize = bits.ue()? as usize + 1;
+        let height: usize = bits.ue()? as usize + 1;
+        let frame_mbs_only: bool = bits.flag()?;
+        if !frame_mbs_only || width.saturating_mul(height) > MAX_MACROBLOCKS {
+            return None;
+        }
+        bits.flag()?;
+
+        let mut crop: [usize; 4] = [0; 4];
+        if bits.flag()? {
+            let unit: usize = if chroma_format == 0 { 1 } else { 2 };
+            for side in crop.iter_mut() {
+                *side = bits.ue()? as usize * unit;
+            }
+            if crop[0] + crop[1] >= width * 16 || crop[2] + crop[3] >= height * 16 {
+                return None;
+            }
+        }
+
+        Some((id, Sps { chroma_format, log2_max_frame_num, poc_type, log2_max_poc_lsb,
+            delta_pic_order_always_zero, width, height, crop, matrix, scaling }))
+    }
+}
+
+/// The picture parameter set fields needed to decode intra pictures, with the
+/// luma dequantisation factors of its scaling lists.
+#[derive(Clone)]
+struct Pps {
+    sps: u32,
+    cabac: bool,
+    bottom_field_pic_order: bool,
+    init_qp: i32,
+    deblocking_control: bool,
+    redundant_pic_cnt: bool,
+    transform_8x8: bool,
+    /// LevelScale4x4 of the intra luma list by QP % 6, raster order.
+    level_4x4: [[i64; 16]; 6],
+    /// LevelScale8x8 of the intra luma list by QP % 6, raster order.
+    level_8x8: [[i64; 64]; 6],
+}
+
+impl Pps {
+    /// Parses the RBSP of a PPS against the already known SPS.
+    fn parse(data: &[u8], sps: &HashMap<u32, Sps>) -> Option<(u32, Pps)> {
+        let mut bits: Bits = Bits::new(data);
+        let id: u32 = bits.ue()?;
+        let sps_id: u32 = bits.ue()?;
+        let seq: &Sps = sps.get(&sps_id)?;
+
+        let cabac: bool = bits.flag()?;
+        let bottom_field_pic_order: bool = bits.flag()?;
+        if bits.ue()? != 0 {
+            // slice groups (FMO) are a baseline curiosity no camera or app emits
+            return None;
+        }
+        bits.ue()?;
+        bits.ue()?;
+        bits.bits(3)?;
+        let init_qp: i32 = 26 + bits.se()?;
+        bits.se()?;
+        bits.se()?;
+        let deblocking_control: bool = bits.flag()?;
+        bits.flag()?;
+        let redundant_pic_cnt: bool = bits.flag()?;
+
+        let mut transform_8x8: bool = false;
+        let mut scaling: ScalingLists = seq.scaling;
+        if bits.more_data() {
+            transform_8x8 = bits.flag()?;
+            if bits.flag()? {
+                let fallback: ScalingLists = if seq.matrix { seq.scaling } else { std::array::from_fn(default_list) };
+                scaling = scaling_lists(&mut bits, if transform_8x8 { 8 } else { 6 }, &fallback)?;
+            }
+        }
+
+        let mut level_4x4: [[i64; 16]; 6] = [[0; 16]; 6];
+        let mut level_8x8: [[i64; 64]; 6] = [[0; 64]; 6];
+        for m in 0..6 {
+            for (k, position) in ZIGZAG_4X4.iter().enumerate() {
+                let (i, j) = (position / 4, position % 4);
+                let norm: i64 = match (i % 2, j % 2) {
+                    (0, 0) => NORM_4X4[m][0],
+                    (1, 1) => NORM_4X4[m][1],
+                    _ => NORM_4X4[m][2],
+                };
+                level_4x4[m][*position] = scaling[0][k] as i64 * norm;
+            }
+            for (k, position) in ZIGZAG_8X8.iter().enumerate() {
+                let (i, j) = (position / 8, position % 8);
+                let norm: i64 = if i % 4 == 0 && j % 4 == 0 {
+                    NORM_8X8[m][0]
+                } else if i % 2 == 1 && j % 2 == 1 {
+                    NORM_8X8[m][1]
+                } else if i % 4 == 2 && j % 4 == 2 {
+                    NORM_8X8[m][2]
+                } else if (i % 4 == 0 && j % 2 == 1) || (i % 2 == 1 && j % 4 == 0) {
+                    NORM_8X8[m][3]
+                } else if (i % 4 == 0 && j % 4 == 2) || (i % 4 == 2 && j % 4 == 0) {
+                    NORM_8X8[m][4]
+                } else {
+                    NORM_8X8[m][5]
+                };
+                level_8x8[m][*position] = scaling[6][k] as i64 * norm;
+            }
+        }
+
+        Some((id, Pps { sps: sps_id, cabac, bottom_field_pic_order, init_qp, deblocking_control,
+            redundant_pic_cnt, transform_8x8, level_4x4, level_8x8 }))
+    }
+}
+
+/// CABAC arithmetic decoding engine and context states.
+struct Cabac<'a> {
+    bits: Bits<'a>,
+    range: u32,
+    offset: u32,
+    /// pStateIdx and valMPS by ctxIdx.
+    states: [(u8, u8); CONTEXTS],
+}
+
+impl<'a> Cabac<'a> {
+    /// Initialises the contexts for `qp` and the engine at the current, aligned position.
+    fn new(bits: Bits<'a>, qp: i32) -> Option<Self> {
+        let mut states: [(u8, u8); CONTEXTS] = [(0, 0); CONTEXTS];
+        for (first, init) in CONTEXT_INIT {
+            for (i, (m, n)) in init.iter().enumerate() {
+                let state: i32 = (((*m as i32 * qp.clamp(0, 51)) >> 4) + *n as i32).clamp(1, 126);
+                states[first + i] = if state <= 63 { ((63 - state) as u8, 0) } else { ((state - 64) as u8, 1) };
+            }
+        }
+        let mut cabac: Cabac = Cabac { bits, range: 510, offset: 0, states };
+        cabac.start()?;
+        Some(cabac)
+    }
+
+    fn start(&mut self) -> Option<()> {
+        self.range = 510;
+        self.offset = self.bits.bits(9)?;
+        if self.offset >= 510 {
+            return None;
+        }
+        Some(())
+    }
+
+    fn decision(&mut self, context: usize) -> Option<u32> {
+        let (state, mps) = self.states[context];
+        let lps: u32 = RANGE_LPS[state as usize][((self.range >> 6) & 3) as usize] as u32;
+        self.range -= lps;
+        let bin: u32;
+        if self.offset >= self.range {
+            bin = 1 - mps as u32;
+            self.offset -= self.range;
+            self.range = lps;
+            self.states[context] = (NEXT_LPS[state as usize], if state == 0 { 1 - mps } else { mps });
+        }
+        else {
+            bin = mps as u32;
+            self.states[context] = ((state + 1).min(62), mps);
+        }
+        while self.range < 256 {
+            self.range <<= 1;
+            self.offset = (self.offset << 1) | self.bits.bit()?;
+        }
+        Some(bin)
+    }
+
+    fn bypass(&mut self) -> Option<u32> {
+        self.offset = (self.offset << 1) | self.bits.bit()?;
+        if self.offset >= self.range {
+            self.offset -= self.range;
+            return Some(1);
+        }
+        Some(0)
+    }
+
+    fn terminate(&mut self) -> Option<u32> {
+        self.range -= 2;
+        if self.offset >= self.range {
+            return Some(1);
+        }
+        while self.range < 256 {
+            self.range <<= 1;
+            self.offset = (self.offset << 1) | self.bits.bit()?;
+        }
+        Some(0)
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq)]
+enum Kind {
+    #[default]
+    NxN,
+    Intra16x16,
+    Pcm,
+}
+
+/// What later macroblocks need to know about a decoded one.
+#[derive(Clone, Copy, Default)]
+struct Macroblock {
+    /// Slice number plus one, zero while not decoded.
+    slice: u32,
+    kind: Kind,
+    transform_8x8: bool,
+    /// CodedBlockPatternLuma in the low bits, CodedBlockPatternChroma above.
+    cbp: u8,
+    chroma_mode: u8,
+    /// Intra4x4PredMode by 4x4 block, Intra8x8PredMode repeated over its 4x4 blocks,
+    /// DC for the other kinds.
+    modes: [u8; 16],
+    /// Non-zero coefficients by 4x4 luma block, also the coded_block_flag.
+    coefficients: [u8; 16],
+    /// Non-zero AC coefficients by 4x4 chroma block of Cb and Cr.
+    chroma: [[u8; 4]; 2],
+    /// coded_block_flag of the luma, Cb and Cr DC blocks as bits 0-2.
+    dc: u8,
+}
+
+/// Coefficient levels of a macroblock, in scan order.
+struct Residual {
+    dc: [i32; 16],
+    blocks: [[i32; 16]; 16],
+    blocks_8x8: [[i32; 64]; 4],
+}
+
+/// Syntax of a macroblock left for reconstruction.
+struct Parsed {
+    /// prev_intra_pred_mode_flag as `None`, else rem_intra_pred_mode, by 4x4 or 8x8 block.
+    modes: [Option<u8>; 16],
+    intra_16x16_mode: u8,
+    pcm: Vec<u8>,
+    residual: Residual,
+}
+
+/// Slice header fields needed for the slice data.
+struct Header {
+    first_mb: usize,
+    intra: bool,
+    pps: u32,
+    redundant: bool,
+    qp: i32,
+}
+
+/// The luma plane and macroblock state of the picture being decoded.
+struct Picture {
+    width: usize,
+    height: usize,
+    luma: Vec<u8>,
+    macroblocks: Vec<Macroblock>,
+}
+
+/// Decodes the slices of one intra picture into a [`Picture`].
+struct SliceDecoder<'a> {
+    sps: &'a Sps,
+    pps: &'a Pps,
+    picture: &'a mut Picture,
+    slice: u32,
+    qp: i32,
+    /// mb_qp_delta of the previous macroblock of the slice.
+    last_qp_delta: i32,
+}
+
+/// Intra prediction of an `n`x`n` block (4x4 and 8x8 modes, 8.3.1.2 and 8.3.2.2) from
+/// `top` (2n samples including above-right), `left` (n samples) and `corner`.
+fn predict(mode: u8, n: usize, top: &[i32], left: &[i32], corner: i32, has_top: bool, has_left: bool) -> [i32; 64] {
+    let t = |i: isize| if i < 0 { corner } else { top[i as usize] };
+    let l = |i: isize| if i < 0 { corner } else { left[i as usize] };
+    let size: isize = n as isize;
+    let mut out: [i32; 64] = [128; 64];
+
+    if mode == 2 {
+        let shift: u32 = n.trailing_zeros();
+        let sum_top: i32 = top[..n].iter().sum();
+        let sum_left: i32 = left[..n].iter().sum();
+        let dc: i32 = match (has_top, has_left) {
+            (true, true) => (sum_top + sum_left + n as i32) >> (shift + 1),
+            (true, false) => (sum_top + (n as i32 >> 1)) >> shift,
+            (false, true) => (sum_left + (n as i32 >> 1)) >> shift,
+            (false, false) => 128,
+        };
+        out[..n * n].fill(dc);
+        return out;
+    }
+
+    for y in 0..size {
+        for x in 0..size {
+            out[(y * size + x) as usize] = match mode {
+                0 => t(x),
+                1 => l(y),
+                3 if x == size - 1 && y == size - 1 => (t(2 * size - 2) + 3 * t(2 * size - 1) + 2) >> 2,
+                3 => (t(x + y) + 2 * t(x + y + 1) + t(x + y + 2) + 2) >> 2,
+                4 if x > y => (t(x - y - 2) + 2 * t(x - y - 1) + t(x - y) + 2) >> 2,
+                4 if x < y => (l(y - x - 2) + 2 * l(y - x - 1) + l(y - x) + 2) >> 2,
+                4 => (t(0) + 2 * corner + l(0) + 2) >> 2,
+                5 => {
+                    let z: isize = 2 * x - y;
+                    if z >= 0 && z % 2 == 0 {
+                        (t(x - (y >> 1) - 1) + t(x - (y >> 1)) + 1) >> 1
+                    } else if z > 0 {
+                        (t(x - (y >> 1) - 2) + 2 * t(x - (y >> 1) - 1) + t(x - (y >> 1)) + 2) >> 2
+                    } else if z == -1 {
+                        (l(0) + 2 * corner + t(0) + 2) >> 2
+                    } else {
+                        (l(y - 2 * x - 1) + 2 * l(y - 2 * x - 2) + l(y - 2 * x - 3) + 2) >> 2
+                    }
+                },
+                6 => {
+                    let z: isize = 2 * y - x;
+                    if z >= 0 && z % 2 == 0 {
+                        (l(y - (x >> 1) - 1) + l(y - (x >> 1)) + 1) >> 1
+                    } else if z > 0 {
+                        (l(y - (x >> 1) - 2) + 2 * l(y - (x >> 1) - 1) + l(y - (x >> 1)) + 2) >> 2
+                    } else if z == -1 {
+                        (l(0) + 2 * corner + t(0) + 2) >> 2
+                    } else {
+                        (t(x - 2 * y - 1) + 2 * t(x - 2 * y - 2) + t(x - 2 * y - 3) + 2) >> 2
+                    }
+                },
+                7 if y % 2 == 0 => (t(x + (y >> 1)) + t(x + (y >> 1) + 1) + 1) >> 1,
+                7 => (t(x + (y >> 1)) + 2 * t(x + (y >> 1) + 1) + t(x + (y >> 1) + 2) + 2) >> 2,
+                _ => {
+                    let z: isize = x + 2 * y;
+                    if z < 2 * size - 3 && z % 2 == 0 {
+                        (l(y + (x >> 1)) + l(y + (x >> 1) + 1) + 1) >> 1
+                    } else if z < 2 * size - 3 {
+                        (l(y + (x >> 1)) + 2 * l(y + (x >> 1) + 1) + l(y + (x >> 1) + 2) + 2) >> 2
+                    } else if z == 2 * size - 3 {
+                        (l(size - 2) + 3 * l(size - 1) + 2) >> 2
+                    } else {
+                        l(size - 1)
+                    }
+                },
+            };
+        }
+    }
+    out
+}
+
+/// Inverse 4x4 transform of a raster block (8.5.12.2), without the final rounding.
+fn idct_4x4(block: &mut [i64; 16]) {
+    for i in 0..8 {
+        // rows first, then columns
+        let (at, step): (usize, usize) = if i < 4 { (i * 4, 1) } else { (i - 4, 4) };
+        let d: [i64; 4] = std::array::from_fn(|k| block[at + k * step]);
+        let e: [i64; 4] = [d[0] + d[2], d[0] - d[2], (d[1] >> 1) - d[3], d[1] + (d[3] >> 1)];
+        let f: [i64; 4] = [e[0] + e[3], e[1] + e[2], e[1] - e[2], e[0] - e[3]];
+        for k in 0..4 {
+            block[at + k * step] = f[k];
+        }
+    }
+}
+
+/// Inverse 8x8 transform of a raster block (8.5.13.2), without the final rounding.
+fn idct_8x8(block: &mut [i64; 64]) {
+    for i in 0..16 {
+        let (at, step): (usize, usize) = if i < 8 { (i * 8, 1) } else { (i - 8, 8) };
+        let d: [i64; 8] = std::array::from_fn(|k| block[at + k * step]);
+        let a0: i64 = d[0] + d[4];
+        let a4: i64 = d[0] - d[4];
+        let a2: i64 = (d[2] >> 1) - d[6];
+        let a6: i64 = d[2] + (d[6] >> 1);
+        let b0: i64 = a0 + a6;
+        let b2: i64 = a4 + a2;
+        let b4: i64 = a4 - a2;
+        let b6: i64 = a0 - a6;
+        let a1: i64 = -d[3] + d[5] - d[7] - (d[7] >> 1);
+        let a3: i64 = d[1] + d[7] - d[3] - (d[3] >> 1);
+        let a5: i64 = -d[1] + d[7] + d[5] + (d[5] >> 1);
+        let a7: i64 = d[3] + d[5] + d[1] + (d[1] >> 1);
+        let b1: i64 = a1 + (a7 >> 2);
+        let b7: i64 = a7 - (a1 >> 2);
+        let b3: i64 = a3 + (a5 >> 2);
+        let b5: i64 = (a3 >> 2) - a5;
+        let f: [i64; 8] = [b0 + b7, b2 + b5, b4 + b3, b6 + b1, b6 - b1, b4 - b3, b2 - b5, b0 - b7];
+        for k in 0..8 {
+            block[at + k * step] = f[k];
+        }
+    }
+}
+
+/// Scales a coefficient of a 4x4 block by its LevelScale4x4 (8.5.12.1).
+fn scale_4x4(level: i32, scale: i64, qp: i32) -> i64 {
+    if qp >= 24 {
+        (level as i64 * scale) << (qp / 6 - 4)
+    } else {
+        (level as i64 * scale + (1 << (3 - qp / 6))) >> (4 - qp / 6)
+    }
+}
+
+/// Scales a coefficient of an 8x8 block, or a luma DC, by its LevelScale (8.5.13.1, 8.5.10).
+fn scale_8x8(level: i64, scale: i64, qp: i32) -> i64 {
+    if qp >= 36 {
+        (level * scale) << (qp / 6 - 6)
+    } else {
+        (level * scale + (1 << (5 - qp / 6))) >> (6 - qp / 6)
+    }
+}
+
+impl SliceDecoder<'_> {
+    /// The macroblock `dx`, `dy` away from `current` when it is decoded and in the same slice.
+    fn neighbour(&self, current: usize, dx: isize, dy: isize) -> Option<usize> {
+        let width: usize = self.picture.width;
+        let x: isize = (current % width) as isize + dx;
+        let y: isize = (current / width) as isize + dy;
+        if x < 0 || y < 0 || x >= width as isize {
+            return None;
+        }
+        let address: usize = y as usize * width + x as usize;
+        if address < current && self.picture.macroblocks[address].slice == self.slice + 1 {
+            return Some(address);
+        }
+        None
+    }
+
+    /// The macroblock and 4x4 block at (`x`, `y`) in 4x4 units relative to `current`,
+    /// one block outside it at most.
+    fn block(&self, current: usize, x: isize, y: isize, size: isize) -> Option<(usize, usize)> {
+        let dx: isize = if x < 0 { -1 } else if x >= size { 1 } else { 0 };
+        let dy: isize = if y < 0 { -1 } else { 0 };
+        let address: usize = if dx == 0 && dy == 0 { current } else { self.neighbour(current, dx, dy)? };
+        Some((address, ((y - dy * size) * size + x - dx * size) as usize))
+    }
+
+    /// Non-zero coefficients of the 4x4 luma block at (`x`, `y`), `None` when unavailable.
+    fn luma_coefficients(&self, current: usize, x: isize, y: isize) -> Option<u8> {
+        let (address, at) = self.block(current, x, y, 4)?;
+        Some(self.picture.macroblocks[address].coefficients[BLOCK_INDEX[at / 4][at % 4]])
+    }
+
+    /// Non-zero AC coefficients of the 4x4 chroma block at (`x`, `y`), `None` when unavailable.
+    fn chroma_coefficients(&self, current: usize, component: usize, x: isize, y: isize) -> Option<u8> {
+        let (address, at) = self.block(current, x, y, 2)?;
+        Some(self.picture.macroblocks[address].chroma[component][at])
+    }
+
+    /// nC of CAVLC from the coefficient counts of the left and upper blocks (9.2.1).
+    fn nc(a: Option<u8>, b: Option<u8>) -> i32 {
+        match (a, b) {
+            (Some(a), Some(b)) => (a as i32 + b as i32 + 1) >> 1,
+            (Some(n), None) | (None, Some(n)) => n as i32,
+            (None, None) => 0,
+        }
+    }
+
+    /// Decodes the macroblocks of a slice.
+    fn decode(&mut self, bits: Bits, header: &Header) -> Option<()> {
+        let total: usize = self.picture.width * self.picture.height;
+        let mut address: usize = header.first_mb;
+        self.qp = header.qp;
+        self.last_qp_delta = 0;
+
+        if self.pps.cabac {
+            let mut bits: Bits = bits;
+            bits.align();
+            let mut cabac: Cabac = Cabac::new(bits, header.qp)?;
+            loop {
+                if address >= total {
+                    return None;
+                }
+                self.macroblock(address, &mut Entropy::Cabac(&mut cabac))?;
+                if cabac.terminate()? == 1 {
+                    return Some(());
+                }
+                address += 1;
+            }
+        }
+
+        let mut bits: Bits = bits;
+        loop {
+            if address >= total {
+                return None;
+            }
+            self.macroblock(address, &mut Entropy::Cavlc(&mut bits))?;
+            if !bits.more_data() {
+                return Some(());
+            }
+            address += 1;
+        }
+    }
+
+    /// Parses and reconstructs one macroblock.
+    fn macroblock(&mut self, address: usize, entropy: &mut Entropy) -> Option<()> {
+        self.picture.macroblocks[address] = Macroblock { slice: self.slice + 1, ..Macroblock::default() };
+        let parsed: Parsed = match entropy {
+            Entropy::Cavlc(bits) => self.parse_cavlc(address, bits)?,
+            Entropy::Cabac(cabac) => self.parse_cabac(address, cabac)?,
+        };
+        self.reconstruct(address, &parsed)
+    }
+
+    /// Reads the 256 luma and the chroma samples of an I_PCM macroblock.
+    fn pcm(&mut self, address: usize, bits: &mut Bits) -> Option<Parsed> {
+        bits.align();
+        let chroma: usize = if self.sps.chroma_format == 0 { 0 } else { 128 };
+        let start: usize = bits.position / 8;
+        let pcm: Vec<u8> = bits.data.get(start..start + 256)?.to_vec();
+        bits.data.get(start + 256 + chroma - 1)?;
+        bits.position += (256 + chroma) * 8;
+
+        let macroblock: &mut Macroblock = &mut self.picture.macroblocks[address];
+        macroblock.kind = Kind::Pcm;
+        macroblock.cbp = 0x2F;
+        macroblock.modes = [2; 16];
+        macroblock.coefficients = [16; 16];
+        macroblock.chroma = [[16; 4]; 2];
+        macroblock.dc = 7;
+        self.last_qp_delta = 0;
+        Some(Parsed { modes: [None; 16], intra_16x16_mode: 0, pcm, residual: Residual::empty() })
+    }
+
+    fn parse_cavlc(&mut self, address: usize, bits: &mut Bits) -> Option<Parsed> {
+        let mb_type: u32 = bits.ue()?;
+        if mb_type == 25 {
+            return self.pcm(address, bits);
+        }
+        if mb_type > 25 {
+            return None;
+        }
+
+        let mut parsed: Parsed = Parsed { modes: [None; 16], intra_16x16_mode: 0, pcm: Vec::new(), residual: Residual::empty() };
+        let mut macroblock: Macroblock = self.picture.macroblocks[address];
+        if mb_type == 0 {
+            macroblock.transform_8x8 = self.pps.transform_8x8 && bits.flag()?;
+            for mode in parsed.modes.iter_mut().take(if macroblock.transform_8x8 { 4 } else { 16 }) {
+                if !bits.flag()? {
+                    *mode = Some(bits.bits(3)? as u8);
+                }
+            }
+        }
+        else {
+            macroblock.kind = Kind::Intra16x16;
+            parsed.intra_16x16_mode = ((mb_type - 1) % 4) as u8;
+            macroblock.cbp = (((mb_type - 1) / 4 % 3) << 4) as u8 | if mb_type >= 13 { 15 } else { 0 };
+            macroblock.modes = [2; 16];
+            if macroblock.cbp > 15 && self.sps.chroma_format == 0 {
+                return None;
+            }
+        }
+        if self.sps.chroma_format != 0 {
+            macroblock.chroma_mode = bits.ue()?.min(4) as u8;
+            if macroblock.chroma_mode > 3 {
+                return None;
+            }
+        }
+        if mb_type == 0 {
+            let code: usize = bits.ue()? as usize;
+            macroblock.cbp = if self.sps.chroma_format == 0 { *CBP_INTRA_MONO.get(code)? } else { *CBP_INTRA.get(code)? };
+        }
+        self.picture.macroblocks[address] = macroblock;
+
+        let luma: u8 = macroblock.cbp & 15;
+        let chroma: u8 = macroblock.cbp >> 4;
+        let mut qp_delta: i32 = 0;
+        if luma > 0 || chroma > 0 || macroblock.kind == Kind::Intra16x16 {
+            qp_delta = bits.se()?;
+        }
+        self.update_qp(qp_delta)?;
+
+        let residual: &mut Residual = &mut parsed.residual;
+        if macroblock.kind == Kind::Intra16x16 {
+            let nc: i32 = Self::nc(self.luma_coefficients(address, -1, 0), self.luma_coefficients(address, 0, -1));
+            Self::block_cavlc(bits, nc, &mut residual.dc)?;
+        }
+        for block8 in 0..4 {
+            for sub in 0..4 {
+                let index: usize = block8 * 4 + sub;
+                if luma & (1 << block8) == 0 {
+                    continue;
+                }
+                let (x, y) = BLOCK_XY[index];
+                let nc: i32 = Self::nc(
+                    self.luma_coefficients(address, x as isize - 1, y as isize),
+                    self.luma_coefficients(address, x as isize, y as isize - 1));
+                let count: usize = if macroblock.kind == Kind::Intra16x16 {
+                    Self::block_cavlc(bits, nc, &mut residual.blocks[index][1..])?
+                } else {
+                    let mut levels: [i32; 16] = [0; 16];
+                    let count: usize = Self::block_cavlc(bits, nc, &mut levels)?;
+                    if macroblock.transform_8x8 {
+                        for (k, level) in levels.iter().enumerate() {
+                            residual.blocks_8x8[block8][4 * k + sub] = *level;
+                        }
+                    } else {
+                        residual.blocks[index] = levels;
+                    }
+                    count
+                };
+                self.picture.macroblocks[address].coefficients[index] = count as u8;
+            }
+        }
+
+        if chroma > 0 {
+            for _ in 0..2 {
+                let mut levels: [i32; 4] = [0; 4];
+                Self::block_cavlc(bits, -1, &mut levels)?;
+            }
+        }
+        if chroma == 2 {
+            for component in 0..2 {
+                for index in 0..4 {
+                    let (x, y) = ((index % 2) as isize, (index / 2) as isize);
+                    let nc: i32 = Self::nc(
+                        self.chroma_coefficients(address, component, x - 1, y),
+                        self.chroma_coefficients(address, component, x, y - 1));
+                    let mut levels: [i32; 15] = [0; 15];
+                    let count: usize = Self::block_cavlc(bits, nc, &mut levels)?;
+                    self.picture.macroblocks[address].chroma[component][index] = count as u8;
+                }
+            }
+        }
+        Some(parsed)
+    }
+
+    /// residual_block_cavlc() into `levels`, returning TotalCoeff (9.2).
+    fn block_cavlc(bits: &mut Bits, nc: i32, levels: &mut [i32]) -> Option<usize> {
+        let max: usize = levels.len();
+        let token: usize = match nc {
+            -1 => bits.vlc(&CHROMA_DC_TOKEN_LENGTH, &CHROMA_DC_TOKEN_CODE)?,
+            0..=1 => bits.vlc(&COEFF_TOKEN_LENGTH[0], &COEFF_TOKEN_CODE[0])?,
+            2..=3 => bits.vlc(&COEFF_TOKEN_LENGTH[1], &COEFF_TOKEN_CODE[1])?,
+            4..=7 => bits.vlc(&COEFF_TOKEN_LENGTH[2], &COEFF_TOKEN_CODE[2])?,
+            _ => match bits.bits(6)? as usize {
+                3 => 0,
+                code => ((code >> 2) + 1) * 4 + (code & 3),
+            },
+        };
+        let (total, trailing): (usize, usize) = (token / 4, token % 4);
+        if total == 0 {
+            return Some(0);
+        }
+        if total > max || trailing > total {
+            return None;
+        }
+
+        let mut values: [i32; 16] = [0; 16];
+        let mut suffix_length: u32 = if total > 10 && trailing < 3 { 1 } else { 0 };
+        for (i, value) in values.iter_mut().enumerate().take(total) {
+            if i < trailing {
+                *value = 1 - 2 * bits.bit()? as i32;
+                continue;
+            }
+            let mut prefix: u32 = 0;
+            while bits.bit()? == 0 {
+                prefix += 1;
+                if prefix > 25 {
+                    return None;
+                }
+            }
+            let mut code: i32 = (prefix.min(15) << suffix_length) as i32;
+            if suffix_length > 0 || prefix >= 14 {
+                let size: u32 = if prefix == 14 && suffix_length == 0 { 4 } else if prefix >= 15 { prefix - 3 } else { suffix_length };
+                code += bits.bits(size)? as i32;
+            }
+            if prefix >= 15 && suffix_length == 0 {
+                code += 15;
+            }
+            if prefix >= 16 {
+                code += (1 << (prefix - 3)) - 4096;
+            }
+            if i == trailing && trailing < 3 {
+                code += 2;
+            }
+            *value = if code % 2 == 0 { (code + 2) >> 1 } else { (-code - 1) >> 1 };
+            if suffix_length == 0 {
+                suffix_length = 1;
+            }
+            if value.abs() > (3 << (suffix_length - 1)) && suffix_length < 6 {
+                suffix_length += 1;
+            }
+        }
+
+        let mut zeros: usize = 0;
+        if total < max {
+            zeros = if max == 4 {
+                bits.vlc(&CHROMA_DC_ZEROS_LENGTH[total - 1], &CHROMA_DC_ZEROS_CODE[total - 1])?
+            } else {
+                bits.vlc(&TOTAL_ZEROS_LENGTH[total - 1], &TOTAL_ZEROS_CODE[total - 1])?
+            };
+        }
+        if total + zeros > max {
+            return None;
+        }
+
+        let mut runs: [usize; 16] = [0; 16];
+        for run in runs.iter_mut().take(total - 1) {
+            if zeros == 0 {
+                break;
+            }
+            *run = bits.vlc(&RUN_BEFORE_LENGTH[zeros.min(7) - 1], &RUN_BEFORE_CODE[zeros.min(7) - 1])?;
+            zeros = zeros.checked_sub(*run)?;
+        }
+        runs[total - 1] = zeros;
+
+        let mut position: usize = 0;
+        for i in (0..total).rev() {
+            position += runs[i];
+            *levels.get_mut(position)? = values[i].clamp(-32768, 32767);
+            position += 1;
+        }
+        Some(total)
+    }
+
+    /// Applies mb_qp_delta to QPY (7.4.5).
+    fn update_qp(&mut self, qp_delta: i32) -> Option<()> {
+        if !(-26..=25).contains(&qp_delta) {
+            return None;
+        }
+        self.qp = (self.qp + qp_delta + 52) % 52;
+        self.last_qp_delta = qp_delta;
+        Some(())
+    }
+
+    fn parse_cabac(&mut self, address: usize, cabac: &mut Cabac) -> Option<Parsed> {
+        let left: Option<usize> = self.neighbour(address, -1, 0);
+        let up: Option<usize> = self.neighbour(address, 0, -1);
+        let mbs: &[Macroblock] = &self.picture.macroblocks;
+        let not_nxn = |n: Option<usize>| n.map_or(0, |n| (mbs[n].kind != Kind::NxN) as usize);
+
+        // mb_type of I slices (9.3.2.5, 9.3.3.1.1.3)
+        let mut macroblock: Macroblock = mbs[address];
+        let mut parsed: Parsed = Parsed { modes: [None; 16], intra_16x16_mode: 0, pcm: Vec::new(), residual: Residual::empty() };
+        if cabac.decision(3 + not_nxn(left) + not_nxn(up))? == 1 {
+            if cabac.terminate()? == 1 {
+                let parsed: Parsed = self.pcm(address, &mut cabac.bits)?;
+                cabac.start()?;
+                return Some(parsed);
+            }
+            macroblock.kind = Kind::Intra16x16;
+            macroblock.modes = [2; 16];
+            let luma: u8 = if cabac.decision(3 + 3)? == 1 { 15 } else { 0 };
+            let chroma: u8 = if cabac.decision(3 + 4)? == 0 { 0 } else { 1 + cabac.decision(3 + 5)? as u8 };
+            if chroma > 0 && self.sps.chroma_format == 0 {
+                return None;
+            }
+            macroblock.cbp = (chroma << 4) | luma;
+            parsed.intra_16x16_mode = (cabac.decision(3 + 6)? * 2 + cabac.decision(3 + 7)?) as u8;
+        }
+        else {
+            if self.pps.transform_8x8 {
+                let inc: usize = [left, up].iter().flatten().filter(|n| mbs[**n].transform_8x8).count();
+                macroblock.transform_8x8 = cabac.decision(399 + inc)? == 1;
+            }
+            for mode in parsed.modes.iter_mut().take(if macroblock.transform_8x8 { 4 } else { 16 }) {
+                if cabac.decision(68)? == 0 {
+                    let rem: u32 = cabac.decision(69)? | (cabac.decision(69)? << 1) | (cabac.decision(69)? << 2);
+                    *mode = Some(rem as u8);
+                }
+            }
+        }
+
+        if self.sps.chroma_format != 0 {
+            let inc: usize = [left, up].iter()
+                .flatten()
+                .filter(|n| mbs[**n].kind != Kind::Pcm && mbs[**n].chroma_mode != 0)
+                .count();
+            macroblock.chroma_mode = 0;
+            if cabac.decision(64 + inc)? == 1 {
+                macroblock.chroma_mode = 1;
+                while macroblock.chroma_mode < 3 && cabac.decision(64 + 3)? == 1 {
+                    macroblock.chroma_mode += 1;
+                }
+            }
+        }
+
+        if macroblock.kind == Kind::NxN {
+            // unavailable and I_PCM neighbours count as coded (9.3.3.1.1.4)
+            let cbp_of = |n: Option<usize>| n.map_or(0x2F, |n| mbs[n].cbp);
+            let (cbp_left, cbp_up) = (cbp_of(left), cbp_of(up));
+            let mut luma: u8 = 0;
+            for block8 in 0..4 {
+                let a: u8 = if block8 % 2 == 1 { luma >> (block8 - 1) } else { cbp_left >> (block8 + 1) };
+                let b: u8 = if block8 >= 2 { luma >> (block8 - 2) } else { cbp_up >> (block8 + 2) };
+                let inc: usize = ((a & 1) == 0) as usize + 2 * ((b & 1) == 0) as usize;
+                luma |= (cabac.decision(73 + inc)? as u8) << block8;
+            }
+            let mut chroma: u8 = 0;
+            if self.sps.chroma_format != 0 {
+                let (a, b): (u8, u8) = (left.map_or(0, |n| mbs[n].cbp >> 4), up.map_or(0, |n| mbs[n].cbp >> 4));
+                if cabac.decision(77 + (a > 0) as usize + 2 * (b > 0) as usize)? == 1 {
+                    chroma = 1 + cabac.decision(77 + 4 + (a == 2) as usize + 2 * (b == 2) as usize)? as u8;
+                }
+            }
+            macroblock.cbp = (chroma << 4) | luma;
+        }
+        self.picture.macroblocks[address] = macroblock;
+
+        let luma: u8 = macroblock.cbp & 15;
+        let chroma: u8 = macroblock.cbp >> 4;
+        let mut qp_delta: i32 = 0;
+        if luma > 0 || chroma > 0 || macroblock.kind == Kind::Intra16x16 {
+            let mut context: usize = 60 + (self.last_qp_delta != 0) as usize;
+            let mut count: i32 = 0;
+            while cabac.decision(context)? == 1 {
+                count += 1;
+                if count > 52 {
+                    return None;
+                }
+                context = if count == 1 { 62 } else { 63 };
+            }
+            qp_delta = if count % 2 == 1 { (count + 1) / 2 } else { -(count / 2) };
+        }
+        self.update_qp(qp_delta)?;
+
+        if macroblock.kind == Kind::Intra16x16 {
+            let mbs: &[Macroblock] = &self.picture.macroblocks;
+            let dc = |n: Option<usize>| n.map_or(1, |n| (mbs[n].dc & 1) as usize);
+            let inc: usize = dc(left) + 2 * dc(up);
+            if Self::block_cabac(cabac, LUMA_DC, Some(inc), &mut parsed.residual.dc)? > 0 {
+                self.picture.macroblocks[address].dc |= 1;
+            }
+        }
+        for (index, &(x, y)) in BLOCK_XY.iter().enumerate() {
+            let block8: usize = index / 4;
+            if luma & (1 << block8) == 0 {
+                continue;
+            }
+            if macroblock.transform_8x8 {
+                if index % 4 == 0 {
+                    let count: usize = Self::block_cabac(cabac, LUMA_8X8, None, &mut parsed.residual.blocks_8x8[block8])?;
+                    self.picture.macroblocks[address].coefficients[index..index + 4].fill(count as u8);
+                }
+                continue;
+            }
+            let coded = |n: Option<u8>| n.map_or(1, |n| (n != 0) as usize);
+            let inc: usize = coded(self.luma_coefficients(address, x as isize - 1, y as isize))
+                + 2 * coded(self.luma_coefficients(address, x as isize, y as isize - 1));
+            let count: usize = if macroblock.kind == Kind::Intra16x16 {
+                Self::block_cabac(cabac, LUMA_AC, Some(inc), &mut parsed.residual.blocks[index][1..])?
+            } else {
+                Self::block_cabac(cabac, LUMA_4X4, Some(inc), &mut parsed.residual.blocks[index])?
+            };
+            self.picture.macroblocks[address].coefficients[index] = count as u8;
+        }
+
+        if chroma > 0 {
+            for component in 0..2 {
+                let mbs: &[Macroblock] = &self.picture.macroblocks;
+                let dc = |n: Option<usize>| n.map_or(1, |n| ((mbs[n].dc >> (component + 1)) & 1) as usize);
+                let inc: usize = dc(left) + 2 * dc(up);
+                let mut levels: [i32; 4] = [0; 4];
+                if Self::block_cabac(cabac, CHROMA_DC, Some(inc), &mut levels)? > 0 {
+                    self.picture.macroblocks[address].dc |= 2 << component;
+                }
+            }
+        }
+        if chroma == 2 {
+            for component in 0..2 {
+                for index in 0..4 {
+                    let (x, y) = ((index % 2) as isize, (index / 2) as isize);
+                    let coded = |n: Option<u8>| n.map_or(1, |n| (n != 0) as usize);
+                    let inc: usize = coded(self.chroma_coefficients(address, component, x - 1, y))
+                        + 2 * coded(self.chroma_coefficients(address, component, x, y - 1));
+                    let mut levels: [i32; 15] = [0; 15];
+                    let count: usize = Self::block_cabac(cabac, CHROMA_AC, Some(inc), &mut levels)?;
+                    self.picture.macroblocks[address].chroma[component][index] = count as u8;
+                }
+            }
+        }
+        Some(parsed)
+    }
+
+    /// residual_block_cabac() of ctxBlockCat `category` into `levels`, returning the
+    /// number of non-zero coefficients. `coded` is the coded_block_flag ctxIdxInc,
+    /// `None` for 8x8 blocks which have no such flag in 4:2:0 (9.3.3.1.1.9, 9.3.3.1.3).
+    fn block_cabac(cabac: &mut Cabac, category: usize, coded: Option<usize>, levels: &mut [i32]) -> Option<usize> {
+        if let Some(inc) = coded {
+            if cabac.decision(CODED_BLOCK_FLAG + CODED_BLOCK_OFFSET[category] + inc)? == 0 {
+                return Some(0);
+            }
+        }
+
+        let count: usize = levels.len();
+        let (significant, last, abs): (usize, usize, usize) = if category == LUMA_8X8 {
+            (SIGNIFICANT_8X8, LAST_SIGNIFICANT_8X8, ABS_LEVEL_8X8)
+        } else {
+            (SIGNIFICANT + SIGNIFICANT_OFFSET[category], LAST_SIGNIFICANT + SIGNIFICANT_OFFSET[category], ABS_LEVEL + ABS_LEVEL_OFFSET[category])
+        };
+
+        let mut map: [bool; 64] = [false; 64];
+        let mut end: usize = count - 1;
+        for i in 0..count - 1 {
+            let (inc, last_inc): (usize, usize) = match category {
+                LUMA_8X8 => (SIGNIFICANT_8X8_INC[i] as usize, LAST_SIGNIFICANT_8X8_INC[i] as usize),
+                CHROMA_DC => (i.min(2), i.min(2)),
+                _ => (i, i),
+            };
+            if cabac.decision(significant + inc)? == 1 {
+                map[i] = true;
+                if cabac.decision(last + last_inc)? == 1 {
+                    end = i;
+                    break;
+                }
+            }
+        }
+        if end == count - 1 {
+            map[end] = true;
+        }
+
+        let (mut equal_one, mut greater_one): (usize, usize) = (0, 0);
+        let mut nonzero: usize = 0;
+        for i in (0..=end).rev().filter(|i| map[*i]) {
+            let first: usize = if greater_one != 0 { 0 } else { (1 + equal_one).min(4) };
+            let mut level: u32 = 0;
+            if cabac.decision(abs + first)? == 1 {
+                let inc: usize = 5 + greater_one.min(4 - (category == CHROMA_DC) as usize);
+                level = 1;
+                while level < 14 && cabac.decision(abs + inc)? == 1 {
+                    level += 1;
+                }
+                if level == 14 {
+                    let mut k: u32 = 0;
+                    while cabac.bypass()? == 1 {
+                        level += 1 << k;
+                        k += 1;
+                        if k > 20 {
+                            return None;
+                        }
+                    }
+                    while k > 0 {
+                        k -= 1;
+                        level += cabac.bypass()? << k;
+                    }
+                }
+            }
+            if level == 0 { equal_one += 1 } else { greater_one += 1 }
+            let value: i32 = (level as i32 + 1).min(32768);
+            levels[i] = if cabac.bypass()? == 1 { -value } else { value };
+            nonzero += 1;
+        }
+        Some(nonzero)
+    }
+
+    /// Predicted intra mode of the 4x4 block at (`x`, `y`), or of the 8x8 block there (8.3.1.1, 8.3.2.1).
+    fn predicted_mode(&self, address: usize, x: usize, y: usize, is_8x8: bool) -> u8 {
+        let mode = |dx: isize, dy: isize, sub: usize| {
+            let (neighbour, at) = self.block(address, x as isize + dx, y as isize + dy, 4)?;
+            let mut index: usize = BLOCK_INDEX[at / 4][at % 4];
+            if is_8x8 && neighbour != address {
+                index = index / 4 * 4 + sub;
+            }
+            Some(self.picture.macroblocks[neighbour].modes[index])
+        };
+        match (mode(-1, 0, 1), mode(0, -1, 2)) {
+            (Some(a), Some(b)) => a.min(b),
+            _ => 2,
+        }
+    }
+
+    /// Availability of the samples above-right of an `n`x`n` block at `px`, `py` in the macroblock.
+    fn has_top_right(&self, address: usize, px: usize, py: usize, n: usize) -> bool {
+        if py == 0 {
+            return if px + n < 16 { self.neighbour(address, 0, -1).is_some() } else { self.neighbour(address, 1, -1).is_some() };
+        }
+        if px + n >= 16 {
+            return false;
+        }
+        if n == 4 {
+            BLOCK_INDEX[(py - 1) / 4][(px + 4) / 4] < BLOCK_INDEX[py / 4][px / 4]
+        } else {
+            ((py - 1) / 8) * 2 + (px + 8) / 8 < (py / 8) * 2 + px / 8
+        }
+    }
+
+    /// The neighbouring samples of an `n`x`n` block: `2n` above, `n` left, the corner and
+    /// the availability of each.
+    fn references(&self, address: usize, px: usize, py: usize, n: usize, top_right: bool) -> ([i32; 32], [i32; 16], i32, bool, bool, bool) {
+        let stride: usize = self.picture.width * 16;
+        let x0: usize = (address % self.picture.width) * 16 + px;
+        let y0: usize = (address / self.picture.width) * 16 + py;
+        let luma: &[u8] = &self.picture.luma;
+
+        let has_left: bool = px > 0 || self.neighbour(address, -1, 0).is_some();
+        let has_top: bool = py > 0 || self.neighbour(address, 0, -1).is_some();
+        let has_corner: bool = match (px > 0, py > 0) {
+            (true, true) => true,
+            (false, true) => has_left,
+            (true, false) => has_top,
+            (false, false) => self.neighbour(address, -1, -1).is_some(),
+        };
+
+        let mut top: [i32; 32] = [128; 32];
+        let mut left: [i32; 16] = [128; 16];
+        let mut corner: i32 = 128;
+        if has_top {
+            for (i, sample) in top.iter_mut().enumerate().take(2 * n) {
+                let x: usize = if i < n || top_right { x0 + i } else { x0 + n - 1 };
+                *sample = luma[(y0 - 1) * stride + x] as i32;
+            }
+        }
+        if has_left {
+            for (i, sample) in left.iter_mut().enumerate().take(n) {
+                *sample = luma[(y0 + i) * stride + x0 - 1] as i32;
+            }
+        }
+        if has_corner {
+            corner = luma[(y0 - 1) * stride + x0 - 1] as i32;
+        }
+        (top, left, corner, has_top, has_left, has_corner)
+    }
+
+    /// Adds `residual` (raster, before rounding) to `prediction` at `px`, `py` of the macroblock.
+    fn store(&mut self, address: usize, px: usize, py: usize, n: usize, prediction: &[i32], residual: &[i64]) {
+        let stride: usize = self.picture.width * 16;
+        let x0: usize = (address % self.picture.width) * 16 + px;
+        let y0: usize = (address / self.picture.width) * 16 + py;
+        for y in 0..n {
+            for x in 0..n {
+                let value: i64 = prediction[y * n + x] as i64 + ((residual[y * n + x] + 32) >> 6);
+                self.picture.luma[(y0 + y) * stride + x0 + x] = value.clamp(0, 255) as u8;
+            }
+        }
+    }
+
+    /// Scaled and inverse transformed 4x4 block, `dc` replacing the first coefficient when given.
+    fn residual_4x4(&self, levels: &[i32; 16], dc: Option<i64>) -> [i64; 16] {
+        let mut block: [i64; 16] = [0; 16];
+        let scale: &[i64; 16] = &self.pps.level_4x4[(self.qp % 6) as usize];
+        for (k, position) in ZIGZAG_4X4.iter().enumerate() {
+            if levels[k] != 0 {
+                block[*position] = scale_4x4(levels[k], scale[*position], self.qp);
+            }
+        }
+        if let Some(dc) = dc {
+            block[0] = dc;
+        }
+        idct_4x4(&mut block);
+        block
+    }
+
+    /// Intra prediction and residual of a parsed macroblock into the luma plane.
+    fn reconstruct(&mut self, address: usize, parsed: &Parsed) -> Option<()> {
+        let macroblock: Macroblock = self.picture.macroblocks[address];
+        match macroblock.kind {
+            Kind::Pcm => {
+                let stride: usize = self.picture.width * 16;
+                let x0: usize = (address % self.picture.width) * 16;
+                let y0: usize = (address / self.picture.width) * 16;
+                for (y, row) in parsed.pcm.chunks(16).enumerate() {
+                    self.picture.luma[(y0 + y) * stride + x0..][..16].copy_from_slice(row);
+                }
+            },
+            Kind::Intra16x16 => {
+                let (top, left, corner, has_top, has_left, _) = self.references(address, 0, 0, 16, false);
+                let mode: u8 = parsed.intra_16x16_mode;
+                if (mode == 0 && !has_top) || (mode == 1 && !has_left) || (mode == 3 && !(has_top && has_left)) {
+                    return None;
+                }
+                let prediction: [i32; 256] = std::array::from_fn(|i| {
+                    let (x, y) = ((i % 16) as i32, (i / 16) as i32);
+                    match mode {
+                        0 => top[x as usize],
+                        1 => left[y as usize],
+                        2 => {
+                            let sum_top: i32 = top[..16].iter().sum();
+                            let sum_left: i32 = left.iter().sum();
+                            match (has_top, has_left) {
+                                (true, true) => (sum_top + sum_left + 16) >> 5,
+                                (true, false) => (sum_top + 8) >> 4,
+                                (false, true) => (sum_left + 8) >> 4,
+                                (false, false) => 128,
+                            }
+                        },
+                        _ => {
+                            let t = |i: i32| if i < 0 { corner } else { top[i as usize] };
+                            let l = |i: i32| if i < 0 { corner } else { left[i as usize] };
+                            let h: i32 = (0..8).map(|k| (k + 1) * (t(8 + k) - t(6 - k))).sum();
+                            let v: i32 = (0..8).map(|k| (k + 1) * (l(8 + k) - l(6 - k))).sum();
+                            let a: i32 = 16 * (l(15) + t(15));
+                            let b: i32 = (5 * h + 32) >> 6;
+                            let c: i32 = (5 * v + 32) >> 6;
+                            ((a + b * (x - 7) + c * (y - 7) + 16) >> 5).clamp(0, 255)
+                        },
+                    }
+                });
+
+                // luma DC: inverse Hadamard then scaling (8.5.10)
+                let mut c: [i64; 16] = [0; 16];
+                for (k, position) in ZIGZAG_4X4.iter().enumerate() {
+                    c[*position] = parsed.residual.dc[k] as i64;
+                }
+                for i in 0..8 {
+                    let (at, step): (usize, usize) = if i < 4 { (i * 4, 1) } else { (i - 4, 4) };
+                    let d: [i64; 4] = std::array::from_fn(|k| c[at + k * step]);
+                    let f: [i64; 4] = [d[0] + d[1] + d[2] + d[3], d[0] + d[1] - d[2] - d[3], d[0] - d[1] - d[2] + d[3], d[0] - d[1] + d[2] - d[3]];
+                    for k in 0..4 {
+                        c[at + k * step] = f[k];
+                    }
+                }
+                let scale: i64 = self.pps.level_4x4[(self.qp % 6) as usize][0];
+
+                for (index, (bx, by)) in BLOCK_XY.iter().enumerate() {
+                    let dc: i64 = scale_8x8(c[by * 4 + bx], scale, self.qp);
+                    let block: [i64; 16] = self.residual_4x4(&parsed.residual.blocks[index], Some(dc));
+                    let part: [i32; 16] = std::array::from_fn(|i| prediction[(by * 4 + i / 4) * 16 + bx * 4 + i % 4]);
+                    self.store(address, bx * 4, by * 4, 4, &part, &block);
+                }
+            },
+            Kind::NxN => {
+                let is_8x8: bool = macroblock.transform_8x8;
+                let (n, count): (usize, usize) = if is_8x8 { (8, 4) } else { (4, 16) };
+                for (index, &(x, y)) in BLOCK_XY.iter().enumerate().take(count) {
+                    let (px, py): (usize, usize) = if is_8x8 { (index % 2 * 8, index / 2 * 8) } else { (x * 4, y * 4) };
+                    let predicted: u8 = self.predicted_mode(address, px / 4, py / 4, is_8x8);
+                    let mode: u8 = match parsed.modes[index] {
+                        None => predicted,
+                        Some(rem) if rem < predicted => rem,
+                        Some(rem) => rem + 1,
+                    };
+                    let macroblock: &mut Macroblock = &mut self.picture.macroblocks[address];
+                    if is_8x8 {
+                        macroblock.modes[index * 4..index * 4 + 4].fill(mode);
+                    } else {
+                        macroblock.modes[index] = mode;
+                    }
+
+                    let top_right: bool = self.has_top_right(address, px, py, n);
+                    let (mut top, mut left, mut corner, has_top, has_left, has_corner) = self.references(address, px, py, n, top_right);
+                    let needs_top: bool = matches!(mode, 0 | 3 | 4 | 5 | 6 | 7);
+                    let needs_left: bool = matches!(mode, 1 | 4 | 5 | 6 | 8);
+                    if (needs_top && !has_top) || (needs_left && !has_left) || (matches!(mode, 4..=6) && !has_corner) {
+                        return None;
+                    }
+
+                    let residual: Vec<i64> = if is_8x8 {
+                        // reference sample filtering (8.3.2.2.1)
+                        let (t, l, c): ([i32; 32], [i32; 16], i32) = (top, left, corner);
+                        if has_top {
+                            top[0] = if has_corner { (c + 2 * t[0] + t[1] + 2) >> 2 } else { (3 * t[0] + t[1] + 2) >> 2 };
+                            for i in 1..15 {
+                                top[i] = (t[i - 1] + 2 * t[i] + t[i + 1] + 2) >> 2;
+                            }
+                            top[15] = (t[14] + 3 * t[15] + 2) >> 2;
+                        }
+                        if has_corner {
+                            corner = match (has_top, has_left) {
+                                (true, true) => (t[0] + 2 * c + l[0] + 2) >> 2,
+                                (true, false) => (3 * c + t[0] + 2) >> 2,
+                                (false, true) => (3 * c + l[0] + 2) >> 2,
+                                (false, false) => c,
+                            };
+                        }
+                        if has_left {
+                            left[0] = if has_corner { (c + 2 * l[0] + l[1] + 2) >> 2 } else { (3 * l[0] + l[1] + 2) >> 2 };
+                            for i in 1..7 {
+                                left[i] = (l[i - 1] + 2 * l[i] + l[i + 1] + 2) >> 2;
+                            }
+                            left[7] = (l[6] + 3 * l[7] + 2) >> 2;
+                        }
+
+                        let mut block: [i64; 64] = [0; 64];
+                        let scale: &[i64; 64] = &self.pps.level_8x8[(self.qp % 6) as usize];
+                        for (k, position) in ZIGZAG_8X8.iter().enumerate() {
+                            let level: i32 = parsed.residual.blocks_8x8[index][k];
+                            if level != 0 {
+                                block[*position] = scale_8x8(level as i64, scale[*position], self.qp);
+                            }
+                        }
+                        idct_8x8(&mut block);
+                        block.to_vec()
+                    } else {
+                        self.residual_4x4(&parsed.residual.blocks[index], None).to_vec()
+                    };
+
+                    let prediction: [i32; 64] = predict(mode, n, &top, &left, corner, has_top, has_left);
+                    self.store(address, px, py, n, &prediction, &residual);
+                }
+            },
+        }
+        Some(())
+    }
+}
+
+impl Residual {
+    fn empty() -> Self {
+        Self { dc: [0; 16], blocks: [[0; 16]; 16], blocks_8x8: [[0; 64]; 4] }
+    }
+}
+
+/// The entropy decoder of a slice.
+enum Entropy<'a, 'b> {
+    Cavlc(&'a mut Bits<'b>),
+    Cabac(&'a mut Cabac<'b>),
+}
+
+/// Intra-only H.264 decoder for the keyframes of a video track: I slices of
+/// 8-bit progressive 4:2:0 or monochrome pictures, CAVLC or CABAC, without
+/// slice groups. Only the luma plane is reconstructed and the deblocking
+/// filter is skipped, which is plenty for a perceptual hash.
+pub struct Decoder {
+    /// Size of the NAL unit length prefix of samples.
+    length_size: usize,
+    sps: HashMap<u32, Sps>,
+    pps: HashMap<u32, Pps>,
+}
+
+impl Decoder {
+    pub fn new(length_size: usize) -> Self {
+        Self { length_size, sps: HashMap::new(), pps: HashMap::new() }
+    }
+
+    /// Reads a sequence or picture parameter set NAL unit, e.g. from an `avcC` box.
+    pub fn parameter_set(&mut self, nal: &[u8]) {
+        let data: Vec<u8> = match nal.first() {
+            Some(_) => unescape(&nal[1..]),
+            None => return
+        };
+        match nal[0] & 0x1F {
+            7 => {
+                if let Some((id, sps)) = Sps::parse(&data) {
+                    self.sps.insert(id, sps);
+                }
+            },
+            8 => {
+                if let Some((id, pps)) = Pps::parse(&data, &self.sps) {
+                    self.pps.insert(id, pps);
+                }
+            },
+            _ => {}
+        }
+    }
+
+    /// Decodes a sample of length-prefixed NAL units to its luma plane.
+    /// `None` unless it is a whole intra picture this decoder supports.
+    pub fn decode(&mut self, sample: &[u8]) -> Option<GrayImage> {
+        let mut picture: Option<(Picture, u32)> = None;
+        let mut slices: u32 = 0;
+        let mut at: usize = 0;
+
+        while at < sample.len() {
+            let length: usize = sample.get(at..at.checked_add(self.length_size)?)?
+                .iter()
+                .fold(0, |n, b| (n << 8) | *b as usize);
+            at += self.length_size;
+            let nal: &[u8] = sample.get(at..at.checked_add(length)?)?;
+            at += length;
+
+            let nal_type: u8 = match nal.first() {
+                Some(header) => header & 0x1F,
+                None => continue
+            };
+            if nal_type == 7 || nal_type == 8 {
+                self.parameter_set(nal);
+                continue;
+            }
+            if nal_type != 1 && nal_type != 5 {
+                continue;
+            }
+
+            let data: Vec<u8> = unescape(&nal[1..]);
+            let mut bits: Bits = Bits::new(&data);
+            let header: Header = self.header(&mut bits, nal[0])?;
+            if !header.intra {
+                return None;
+            }
+            if header.redundant {
+                continue;
+            }
+
+            let pps: &Pps = self.pps.get(&header.pps)?;
+            let sps: &Sps = self.sps.get(&pps.sps)?;
+            let (current, _) = picture.get_or_insert_with(|| (Picture {
+                width: sps.width,
+                height: sps.height,
+                luma: vec![0; sps.width * sps.height * 256],
+                macroblocks: vec![Macroblock::default(); sps.width * sps.height],
+            }, pps.sps));
+            if current.width != sps.width || current.height != sps.height {
+                return None;
+            }
+
+            let mut decoder: SliceDecoder = SliceDecoder { sps, pps, picture: current, slice: slices, qp: header.qp, last_qp_delta: 0 };
+            decoder.decode(bits, &header)?;
+            slices += 1;
+        }
+
+        let (picture, sps_id) = picture?;
+        let sps: &Sps = self.sps.get(&sps_id)?;
+        let stride: usize = picture.width * 16;
+        let [left, right, top, bottom] = sps.crop;
+        let width: usize = stride - left - right;
+        let height: usize = picture.height * 16 - top - bottom;
+        Some(GrayImage::from_fn(width as u32, height as u32, |x, y| {
+            image::Luma([picture.luma[(y as usize + top) * stride + x as usize + left]])
+        }))
+    }
+
+    /// slice_header() up to the slice data (7.3.3).
+    fn header(&self, bits: &mut Bits, nal_header: u8) -> Option<Header> {
+        let first_mb: usize = bits.ue()? as usize;
+        let slice_type: u32 = bits.ue()?;
+        let pps_id: u32 = bits.ue()?;
+        let pps: &Pps = self.pps.get(&pps_id)?;
+        let sps: &Sps = self.sps.get(&pps.sps)?;
+        let intra: bool = slice_type % 5 == 2;
+        if !intra {
+            return Some(Header { first_mb, intra, pps: pps_id, redundant: false, qp: 0 });
+        }
+
+        bits.bits(sps.log2_max_frame_num)?;
+        if nal_header & 0x1F == 5 {
+            bits.ue()?;
+        }
+        if sps.poc_type == 0 {
+            bits.bits(sps.log2_max_poc_lsb)?;
+            if pps.bottom_field_pic_order {
+                bits.se()?;
+            }
+        }
+        else if sps.poc_type == 1 && !sps.delta_pic_order_always_zero {
+            bits.se()?;
+            if pps.bottom_field_pic_order {
+                bits.se()?;
+            }
+        }
+        let redundant: bool = pps.redundant_pic_cnt && bits.ue()? > 0;
+
+        // dec_ref_pic_marking()
+        if nal_header & 0x60 != 0 {
+            if nal_header & 0x1F == 5 {
+                bits.bits(2)?;
+            }
+            else if bits.flag()? {
+                loop {
+                    match bits.ue()? {
+                        0 => break,
+                        1 | 2 | 4 => { bits.ue()?; },
+                        3 => { bits.ue()?; bits.ue()?; },
+                        5 => {},
+                        6 => { bits.ue()?; },
+                        _ => return None
+                    }
+                }
+            }
+        }
+
+        let qp: i32 = pps.init_qp + bits.se()?;
+        if !(0..=51).contains(&qp) {
+            return None;
+        }
+        if pps.deblocking_control && bits.ue()? != 1 {
+            bits.se()?;
+            bits.se()?;
+        }
+        Some(Header { first_mb, intra, pps: pps_id, redundant, qp })
+    }
+}
+
+
+
+#[cfg(test)]
+pub(crate) mod test {
+    use image::{GrayImage, Luma};
+
+    use super::{
+        Decoder, CHROMA_DC_TOKEN_CODE, CHROMA_DC_TOKEN_LENGTH, CHROMA_DC_ZEROS_CODE, CHROMA_DC_ZEROS_LENGTH,
+        COEFF_TOKEN_CODE, COEFF_TOKEN_LENGTH, RUN_BEFORE_CODE, RUN_BEFORE_LENGTH, TOTAL_ZEROS_CODE, TOTAL_ZEROS_LENGTH};
+
+    /// Writes an RBSP bit by bit.
+    struct BitWriter {
+        bits: Vec<bool>,
+    }
+
+    impl BitWriter {
+        fn new() -> Self {
+            Self { bits: Vec::new() }
+        }
+
+        fn put(&mut self, value: u32, count: u32) -> &mut Self {
+            (0..count).rev().for_each(|i| self.bits.push((value >> i) & 1 == 1));
+            self
+        }
+
+        fn ue(&mut self, value: u32) -> &mut Self {
+            let length: u32 = 32 - (value + 1).leading_zeros();
+            self.put(0, length - 1).put(value + 1, length)
+        }
+
+        fn align(&mut self) -> &mut Self {
+            self.bits.resize(self.bits.len().div_ceil(8) * 8, false);
+            self
+        }
+
+        fn bytes(&mut self, data: &[u8]) -> &mut Self {
+            data.iter().for_each(|b| { self.put(*b as u32, 8); });
+            self
+        }
+
+        /// The NAL unit with `header`, trailing bits and emulation prevention.
+        fn nal(&mut self, header: u8) -> Vec<u8> {
+            self.put(1, 1).align();
+            let mut data: Vec<u8> = vec![header];
+            let mut zeros: usize = 0;
+            for byte in self.bits.chunks(8).map(|c| c.iter().fold(0u8, |b, bit| (b << 1) | *bit as u8)) {
+                if zeros >= 2 && byte <= 3 {
+                    data.push(3);
+                    zeros = 0;
+                }
+                zeros = if byte == 0 { zeros + 1 } else { 0 };
+                data.push(byte);
+            }
+            data
+        }
+    }
+
+    /// Baseline SPS and PPS NAL units of a `width`x`height` picture.
+    pub fn parameter_sets(width: u32, height: u32) -> (Vec<u8>, Vec<u8>) {
+        let (columns, rows): (u32, u32) = (width.div_ceil(16), height.div_ceil(16));
+        let mut sps: BitWriter = BitWriter::new();
+        sps.put(66, 8).put(0, 8).put(30, 8).ue(0).ue(0).ue(2).ue(1).put(0, 1)
+            .ue(columns - 1).ue(rows - 1).put(1, 1).put(1, 1);
+        if columns * 16 != width || rows * 16 != height {
+            sps.put(1, 1).ue(0).ue((columns * 16 - width) / 2).ue(0).ue((rows * 16 - height) / 2);
+        } else {
+            sps.put(0, 1);
+        }
+        sps.put(0, 1);
+
+        let mut pps: BitWriter = BitWriter::new();
+        pps.ue(0).ue(0).put(0, 1).put(0, 1).ue(0).ue(0).ue(0).put(0, 3).ue(0).ue(0).ue(0).put(1, 1).put(0, 1).put(0, 1);
+        (sps.nal(0x67), pps.nal(0x68))
+    }
+
+    /// A sample holding an IDR picture of `img` as I_PCM macroblocks, with 4 byte NAL lengths.
+    pub fn pcm_sample(img: &GrayImage) -> Vec<u8> {
+        let (columns, rows): (u32, u32) = (img.width().div_ceil(16), img.height().div_ceil(16));
+        let mut slice: BitWriter = BitWriter::new();
+        slice.ue(0).ue(7).ue(0).put(0, 4).ue(0).put(0, 2).ue(0).ue(1);
+        for mb in 0..columns * rows {
+            slice.ue(25).align();
+            for y in 0..16 {
+                for x in 0..16 {
+                    let (x, y) = ((mb % columns) * 16 + x, (mb / columns) * 16 + y);
+                    slice.put(img.get_pixel(x.min(img.width() - 1), y.min(img.height() - 1))[0] as u32, 8);
+                }
+            }
+            slice.bytes(&[128; 128]);
+        }
+
+        let nal: Vec<u8> = slice.nal(0x65);
+        let mut sample: Vec<u8> = (nal.len() as u32).to_be_bytes().to_vec();
+        sample.extend_from_slice(&nal);
+        sample
+    }
+
+    fn decoder(width: u32, height: u32) -> Decoder {
+        let (sps, pps) = parameter_sets(width, height);
+        let mut decoder: Decoder = Decoder::new(4);
+        decoder.parameter_set(&sps);
+        decoder.parameter_set(&pps);
+        decoder
+    }
+
+    #[test]
+    fn test_vlc_tables_are_prefix_free() {
+        let mut tables: Vec<(&[u8], &[u8])> = vec![(&CHROMA_DC_TOKEN_LENGTH, &CHROMA_DC_TOKEN_CODE)];
+        tables.extend(COEFF_TOKEN_LENGTH.iter().zip(COEFF_TOKEN_CODE.iter()).map(|(l, c)| (&l[..], &c[..])));
+        tables.extend(TOTAL_ZEROS_LENGTH.iter().zip(TOTAL_ZEROS_CODE.iter()).map(|(l, c)| (&l[..], &c[..])));
+        tables.extend(CHROMA_DC_ZEROS_LENGTH.iter().zip(CHROMA_DC_ZEROS_CODE.iter()).map(|(l, c)| (&l[..], &c[..])));
+        tables.extend(RUN_BEFORE_LENGTH.iter().zip(RUN_BEFORE_CODE.iter()).map(|(l, c)| (&l[..], &c[..])));
+
+        for (lengths, codes) in tables {
+            let entries: Vec<(u8, u32)> = lengths.iter()
+                .zip(codes.iter())
+                .filter(|(l, _)| **l > 0)
+                .map(|(l, c)| (*l, *c as u32))
+                .collect();
+            let kraft: f64 = entries.iter().map(|(l, _)| 0.5f64.powi(*l as i32)).sum();
+            assert!(kraft <= 1.0, "{:?}", lengths);
+            for (i, (length, code)) in entries.iter().enumerate() {
+                for (other_length, other) in entries.iter().skip(i + 1) {
+                    let shortest: u8 = *length.min(other_length);
+                    assert_ne!(code >> (length - shortest), other >> (other_length - shortest), "{:?}", lengths);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_pcm_picture() {
+        let img: GrayImage = GrayImage::from_fn(40, 24, |x, y| Luma([(x * 6 + y) as u8]));
+        let decoded: GrayImage = decoder(40, 24).decode(&pcm_sample(&img)).unwrap();
+
+        assert_eq!(decoded, img);
+    }
+
+    #[test]
+    fn test_intra_16x16_dc() {
+        // I_16x16_2_0_0 predicts 128 without neighbours, a DC level of 10 at QP 26 adds 8
+        let mut slice: BitWriter = BitWriter::new();
+        slice.ue(0).ue(7).ue(0).put(0, 4).ue(0).put(0, 2).ue(0).ue(1);
+        slice.ue(3).ue(0).ue(0);
+        slice.put(0b000101, 6).put(1, 15).put(2, 4).put(1, 1);
+        let nal: Vec<u8> = slice.nal(0x65);
+        let mut sample: Vec<u8> = (nal.len() as u32).to_be_bytes().to_vec();
+        sample.extend_from_slice(&nal);
+
+        let decoded: GrayImage = decoder(16, 16).decode(&sample).unwrap();
+        assert!(decoded.pixels().all(|p| p[0] == 136));
+    }
+
+    #[test]
+    fn test_broken_samples() {
+        let img: GrayImage = GrayImage::from_pixel(32, 32, Luma([90]));
+        let mut sample: Vec<u8> = pcm_sample(&img);
+        let mut decoder: Decoder = decoder(32, 32);
+
+        // a P slice
+        assert!(decoder.decode(&[0, 0, 0, 3, 0x41, 0x9A, 0x80]).is_none());
+        // a slice of a picture that was never described
+        assert!(Decoder::new(4).decode(&sample).is_none());
+
+        sample.truncate(sample.len() / 2);
+        assert!(decoder.decode(&sample).is_none());
+        sample[3] = 4;
+        assert!(decoder.decode(&sample).is_none());
+    }
+}
@@ -1,15 +1,77 @@
-use std::{fs::File, io::Read};
+use std::{fs::File, io::{Read, Seek, SeekFrom}, sync::Arc};
 
-use image::DynamicImage;
+use image::{DynamicImage, RgbImage};
+use md5::Md5;
+use sha2::{Digest, Sha256};
 
 use crate::{
+    image::upright,
     meta::{MetaAttribute, MetaSource, MetaType, MetaValue}, 
-    FromFile, Extractor, Meta};
+    FromFile, Extractor, HashAlgorithm, Meta, MetaError};
 
+/// Read buffer for streaming hashes. Large enough for BLAKE3 to use its SIMD paths.
+pub(crate) const BUFFER_SIZE: usize = 1024 * 1024;
+
+/// Progress callback: (bytes hashed, total bytes)
+pub type Progress = Arc<dyn Fn(u64, u64) + Send + Sync>;
+
+/// BLAKE3 over width and height (u32 LE) followed by the RGB8 rows.
+pub(crate) fn pixel_digest(img: &RgbImage) -> String {
+    let mut hasher: blake3::Hasher = blake3::Hasher::new();
+    hasher.update(&img.width().to_le_bytes());
+    hasher.update(&img.height().to_le_bytes());
+    hasher.update(img.as_raw());
+    hasher.finalize().to_string()
+}
+
+/// Hashers for every requested algorithm, fed from the same buffer.
+struct Digests {
+    blake3: blake3::Hasher,
+    sha256: Option<Sha256>,
+    md5: Option<Md5>,
+}
+
+impl Digests {
+    fn new(algorithms: &[HashAlgorithm]) -> Self {
+        Self {
+            blake3: blake3::Hasher::new(),
+            sha256: algorithms.contains(&HashAlgorithm::Sha256).then(Sha256::new),
+            md5: algorithms.contains(&HashAlgorithm::Md5).then(Md5::new),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        self.blake3.update(data);
+        if let Some(sha256) = self.sha256.as_mut() {
+            sha256.update(data);
+        }
+        if let Some(md5) = self.md5.as_mut() {
+            md5.update(data);
+        }
+    }
+
+    fn finalize(self) -> Vec<(HashAlgorithm, String)> {
+        let mut digests: Vec<(HashAlgorithm, String)> = vec![
+            (HashAlgorithm::Blake3, self.blake3.finalize().to_string())
+        ];
+        if let Some(sha256) = self.sha256 {
+            digests.push((HashAlgorithm::Sha256, format!("{:x}", sha256.finalize())));
+        }
+        if let Some(md5) = self.md5 {
+            digests.push((HashAlgorithm::Md5, format!("{:x}", md5.finalize())));
+        }
+        return digests;
+    }
+}
 
 pub struct MetaHash {
     path: String,
-    pixel_only: bool
+    pixel_only: bool,
+    primary: bool,
+    mmap: bool,
+    threads: bool,
+    algorithms: Vec<HashAlgorithm>,
+    progress: Option<Progress>,
 }
 
 impl MetaHash { 
@@ -17,11 +79,116 @@ impl MetaHash {
         self.pixel_only = true;
         return self;
     }
+
+    /// Only compute the additional digests, for formats whose `hash`
+    /// comes from another extractor (HEIF hashes its decoded image).
+    pub fn skip_primary(mut self) -> Self {
+        self.primary = false;
+        return self;
+    }
+
+    /// Memory map the file instead of streaming it through a read buffer.
+    /// Requires the `hash-mmap` feature, otherwise the file is streamed.
+    pub fn use_mmap(mut self) -> Self {
+        self.mmap = true;
+        return self;
+    }
+
+    /// Hash a memory mapped file on multiple threads.
+    /// Requires the `hash-rayon` feature, otherwise the file is hashed on one thread.
+    pub fn use_threads(mut self) -> Self {
+        self.mmap = true;
+        self.threads = true;
+        return self;
+    }
+
+    /// Additional digests computed alongside BLAKE3 in the same pass.
+    pub fn algorithms(mut self, algorithms: &[HashAlgorithm]) -> Self {
+        for algorithm in algorithms {
+            if !self.algorithms.contains(algorithm) {
+                self.algorithms.push(*algorithm);
+            }
+        }
+        return self;
+    }
+
+    /// Report progress while hashing large files.
+    pub fn on_progress(mut self, progress: Progress) -> Self {
+        self.progress = Some(progress);
+        return self;
+    }
+
+    fn report(&self, done: u64, total: u64) {
+        if let Some(progress) = &self.progress {
+            progress(done, total);
+        }
+    }
+
+    /// The pixels are decoded once, `hash`, `hash.pixel` and the perceptual hashes all come from them.
+    fn decode_pixels(&self) -> Result<DynamicImage, MetaError> {
+        println!("Using pixel only algorithm for calculating hash...");
+
+        Ok(image::open(&self.path)?)
+    }
+
+    /// Memory mapping only pays off for BLAKE3, which can hash the mapping directly.
+    #[cfg(feature = "hash-mmap")]
+    fn hash_mmap(&self, total: u64) -> Result<Vec<(HashAlgorithm, String)>, MetaError> {
+        let mut hasher: blake3::Hasher = blake3::Hasher::new();
+
+        #[cfg(feature = "hash-rayon")]
+        {
+            if self.threads {
+                hasher.update_mmap_rayon(&self.path)?;
+            } else {
+                hasher.update_mmap(&self.path)?;
+            }
+        }
+
+        #[cfg(not(feature = "hash-rayon"))]
+        {
+            hasher.update_mmap(&self.path)?;
+        }
+
+        self.report(total, total);
+        Ok(vec![(HashAlgorithm::Blake3, hasher.finalize().to_string())])
+    }
+
+    /// Stream the file through a fixed size buffer.
+    /// Produces the same digests as hashing the whole file at once.
+    fn hash_stream(&self, total: u64) -> Result<Vec<(HashAlgorithm, String)>, MetaError> {
+        let mut file: File = File::open(&self.path)?;
+        let mut digests: Digests = Digests::new(&self.algorithms);
+        let mut buffer: Vec<u8> = vec![0; BUFFER_SIZE];
+        let mut done: u64 = 0;
+
+        loop {
+            let read: usize = match file.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            };
+            digests.update(&buffer[..read]);
+            done += read as u64;
+            self.report(done, total);
+        }
+
+        Ok(digests.finalize())
+    }
 }
 
 impl FromFile for MetaHash {
     fn file(path: &str) -> Self {
-        Self { path: path.to_string(), pixel_only: false }
+        Self { 
+            path: path.to_string(), 
+            pixel_only: false,
+            primary: true,
+            mmap: false,
+            threads: false,
+            algorithms: vec![HashAlgorithm::Blake3],
+            progress: None,
+        }
     }
 }
 
@@ -30,26 +197,137 @@ impl Extractor for MetaHash {
     fn name(&self) -> String {
         return "HASH".to_string();
     }
-    fn extract(&self, meta: &mut Meta) -> Result<(), crate::MetaError> {
-        let mut content: Vec<u8> = Vec::new();
+    fn extract(&self, meta: &mut Meta) -> Result<(), MetaError> {
+        let total: u64 = std::fs::metadata(&self.path)?.len();
 
-        if self.pixel_only {
-            println!("Using pixel only algorithm for calculating hash...");
+        let pixels: Option<DynamicImage> = match self.pixel_only {
+            true => Some(self.decode_pixels()?),
+            false => None
+        };
 
-            let img: DynamicImage = image::open(&self.path)?;
-            content.append(&mut img.as_bytes().to_vec().to_owned());
+        let digests: Vec<(HashAlgorithm, String)> = if self.pixel_only || !self.primary {
+            let mut digests: Vec<(HashAlgorithm, String)> = Vec::new();
+            if let Some(img) = &pixels {
+                digests.push((HashAlgorithm::Blake3, blake3::hash(img.as_bytes()).to_string()));
+            }
 
+            // checksums from Takeout or a cloud provider are over the file, whatever `hash` is
+            if self.algorithms.iter().any(|a| *a != HashAlgorithm::Blake3) {
+                let file: Vec<(HashAlgorithm, String)> = self.hash_stream(total)?;
+                digests.extend(file.into_iter().filter(|(a, _)| *a != HashAlgorithm::Blake3));
+            }
+            digests
         } else {
-            let mut file: File = File::open(&self.path)?;
-            file.read_to_end(&mut content)?;
+
+            #[cfg(feature = "hash-mmap")]
+            {
+                if self.mmap && self.algorithms == [HashAlgorithm::Blake3] {
+                    self.hash_mmap(total)?
+                } else {
+                    self.hash_stream(total)?
+                }
+            }
+
+            #[cfg(not(feature = "hash-mmap"))]
+            {
+                self.hash_stream(total)?
+            }
+        };
+
+        for (algorithm, digest) in digests {
+            meta.add(MetaAttribute{
+                source: MetaSource::Hash,
+                tag: algorithm.tag(),
+                value: MetaType::String(MetaValue::from(digest)),
+            });
+        }
+
+        // `hash` stays as it always was so existing asset hashes remain valid.
+        if let Some(img) = pixels {
+            let rgb: RgbImage = upright(img.to_rgb8(), &self.path);
+            meta.add(MetaAttribute{
+                source: MetaSource::Hash,
+                tag: "hash.pixel".to_string(),
+                value: MetaType::String(MetaValue::from(pixel_digest(&rgb))),
+            });
+
+            #[cfg(feature = "phash")]
+            crate::phash::add_hashes(&DynamicImage::ImageRgb8(rgb).to_luma8(), meta);
+        }
+
+        Ok(())
+    }
+}
+
+/// Bytes read for each sample of a [`SampleHash`].
+pub(crate) const SAMPLE_SIZE: u64 = 64 * 1024;
+
+/// Cheap fingerprint for triage: file size plus BLAKE3 over the head, the tail
+/// and `chunks` evenly spaced samples in between. Equal values only make a
+/// candidate duplicate, the full hash still has to confirm it.
+pub struct SampleHash {
+    path: String,
+    chunks: usize,
+}
+
+impl SampleHash {
+    pub fn chunks(mut self, chunks: usize) -> Self {
+        self.chunks = chunks;
+        return self;
+    }
+
+    /// Offsets of the head, evenly spaced middle samples and the tail.
+    fn offsets(size: u64, chunks: usize) -> Vec<u64> {
+        let mut offsets: Vec<u64> = vec![0];
+        let last: u64 = size - SAMPLE_SIZE;
+        for n in 1..=chunks as u64 {
+            offsets.push(last * n / (chunks as u64 + 1));
+        }
+        offsets.push(last);
+        return offsets;
+    }
+
+    fn digest(&self) -> Result<String, MetaError> {
+        let mut file: File = File::open(&self.path)?;
+        let size: u64 = file.metadata()?.len();
+
+        let mut hasher: blake3::Hasher = blake3::Hasher::new();
+        hasher.update(&size.to_le_bytes());
+
+        // small files are read completely, so the sample is exact
+        if size <= SAMPLE_SIZE * (self.chunks as u64 + 2) {
+            hasher.update_reader(&mut file)?;
+        } else {
+            let mut buffer: Vec<u8> = vec![0; SAMPLE_SIZE as usize];
+            for offset in Self::offsets(size, self.chunks) {
+                file.seek(SeekFrom::Start(offset))?;
+                file.read_exact(&mut buffer)?;
+                hasher.update(&buffer);
+            }
         }
-        
-        let hash: blake3::Hash = blake3::hash(&content);
 
+        Ok(format!("{}:{}", size, hasher.finalize()))
+    }
+}
+
+impl FromFile for SampleHash {
+    fn file(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            chunks: 8,
+        }
+    }
+}
+
+impl Extractor for SampleHash {
+    fn name(&self) -> String {
+        return "SAMPLE".to_string();
+    }
+    fn extract(&self, meta: &mut Meta) -> Result<(), MetaError> {
         meta.add(MetaAttribute{
             source: MetaSource::Hash,
-            tag: "hash".to_string(),
-            value: MetaType::String(MetaValue::from(hash.to_string())),
+            tag: "hash.sample".to_string(),
+            value: MetaType::String(MetaValue::from(self.digest()?)),
         });
 
         Ok(())
@@ -58,7 +336,8 @@ impl Extractor for MetaHash {
 
 #[cfg(test)]
 mod test {
-    use crate::{FromFile, Extractor, Meta};
+    use std::{fs, sync::{Arc, atomic::{AtomicU64, Ordering}}};
+    use crate::{FromFile, Extractor, HashAlgorithm, Meta};
     use super::MetaHash;
 
     fn get_hash(file: &str, use_pixel: bool) -> String {
@@ -96,4 +375,198 @@ mod test {
 
         assert_ne!(hash_one, hash_two)
     }
-}    
\ No newline at end of file
+
+    #[test]
+    fn test_blake3_hash_streaming_matches_whole_file() {
+        let path = std::env::temp_dir().join(format!("informant_hash_{}.bin", std::process::id()));
+        let content: Vec<u8> = (0..(3 * super::BUFFER_SIZE + 17)).map(|x| (x % 251) as u8).collect();
+        fs::write(&path, &content).unwrap();
+
+        let seen: Arc<AtomicU64> = Arc::new(AtomicU64::new(0));
+        let progress = seen.clone();
+        let mh: MetaHash = MetaHash::file(path.to_str().unwrap())
+            .on_progress(Arc::new(move |done, _total| progress.store(done, Ordering::SeqCst)));
+
+        let mut meta: Meta = Meta::new();
+        mh.extract(&mut meta).unwrap();
+        let hash: String = String::from(meta.find("hash").first().unwrap().value.clone());
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(hash, blake3::hash(&content).to_string());
+        assert_eq!(seen.load(Ordering::SeqCst), content.len() as u64);
+    }
+
+    #[test]
+    fn test_multiple_algorithms_single_pass() {
+        let path = std::env::temp_dir().join(format!("informant_digests_{}.txt", std::process::id()));
+        fs::write(&path, b"The quick brown fox jumps over the lazy dog").unwrap();
+
+        let mh: MetaHash = MetaHash::file(path.to_str().unwrap())
+            .algorithms(&[HashAlgorithm::Sha256, HashAlgorithm::Md5]);
+
+        let mut meta: Meta = Meta::new();
+        mh.extract(&mut meta).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let sha256: String = String::from(meta.find("hash.sha256").first().unwrap().value.clone());
+        let md5: String = String::from(meta.find("hash.md5").first().unwrap().value.clone());
+
+        assert_eq!(meta.find("hash").len(), 1);
+        assert_eq!(sha256, "d7a8fbb307d7809469ca9abcb0082e4f8d5651e46d3cdb762d02d0bf37c9e592");
+        assert_eq!(md5, "9e107d9d372bb6826bd81d3542a419d6");
+    }
+
+    #[test]
+    fn test_additional_digests_cover_the_file() {
+        use image::{DynamicImage, Rgb, RgbImage};
+        use sha2::{Digest, Sha256};
+
+        let path = std::env::temp_dir().join(format!("informant_digests_{}.png", std::process::id()));
+        DynamicImage::ImageRgb8(RgbImage::from_fn(8, 8, |x, y| Rgb([x as u8, y as u8, 0]))).save(&path).unwrap();
+        let content: Vec<u8> = fs::read(&path).unwrap();
+        let sha256: String = format!("{:x}", Sha256::digest(&content));
+
+        let mut pixel: Meta = Meta::new();
+        MetaHash::file(path.to_str().unwrap()).algorithms(&[HashAlgorithm::Sha256]).use_pixel().extract(&mut pixel).unwrap();
+        let mut secondary: Meta = Meta::new();
+        MetaHash::file(path.to_str().unwrap()).algorithms(&[HashAlgorithm::Sha256]).skip_primary().extract(&mut secondary).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(String::from(pixel.find("hash.sha256").first().unwrap().value.clone()), sha256);
+        assert_ne!(String::from(pixel.find("hash").first().unwrap().value.clone()), blake3::hash(&content).to_string());
+        assert_eq!(String::from(secondary.find("hash.sha256").first().unwrap().value.clone()), sha256);
+        assert!(secondary.find("hash").is_empty());
+    }
+
+    #[test]
+    fn test_canonical_pixel_hash_ignores_format() {
+        use image::{DynamicImage, ImageBuffer, Rgb, RgbImage};
+
+        let rgb: RgbImage = RgbImage::from_fn(31, 17, |x, y| Rgb([(x * 8) as u8, (y * 15) as u8, ((x + y) * 3) as u8]));
+        let rgb16: ImageBuffer<Rgb<u16>, Vec<u16>> = ImageBuffer::from_fn(31, 17, |x, y| {
+            let p: &Rgb<u8> = rgb.get_pixel(x, y);
+            Rgb([u16::from(p.0[0]) * 257, u16::from(p.0[1]) * 257, u16::from(p.0[2]) * 257])
+        });
+
+        let dir = std::env::temp_dir();
+        let id: u32 = std::process::id();
+        let files: Vec<(std::path::PathBuf, DynamicImage)> = vec![
+            (dir.join(format!("informant_rgb8_{}.png", id)), DynamicImage::ImageRgb8(rgb.clone())),
+            (dir.join(format!("informant_rgba8_{}.png", id)), DynamicImage::ImageRgba8(DynamicImage::ImageRgb8(rgb.clone()).to_rgba8())),
+            (dir.join(format!("informant_rgb16_{}.png", id)), DynamicImage::ImageRgb16(rgb16)),
+        ];
+
+        let hashes: Vec<String> = files.iter().map(|(path, img)| {
+            img.save(path).unwrap();
+            let mut meta: Meta = Meta::new();
+            MetaHash::file(path.to_str().unwrap()).use_pixel().extract(&mut meta).unwrap();
+            fs::remove_file(path).unwrap();
+            String::from(meta.find("hash.pixel").first().unwrap().value.clone())
+        }).collect();
+
+        assert_eq!(hashes[0], super::pixel_digest(&rgb));
+        assert_eq!(hashes[0], hashes[1]);
+        assert_eq!(hashes[0], hashes[2]);
+    }
+
+    #[cfg(feature = "phash")]
+    #[test]
+    fn test_pixel_hashes_share_one_decode() {
+        use image::{DynamicImage, Rgb, RgbImage};
+        use crate::phash::PerceptualHash;
+
+        let path = std::env::temp_dir().join(format!("informant_phash_{}.png", std::process::id()));
+        DynamicImage::ImageRgb8(RgbImage::from_fn(48, 32, |x, y| Rgb([(x * 5) as u8, (y * 7) as u8, 90]))).save(&path).unwrap();
+
+        let mut pixel: Meta = Meta::new();
+        MetaHash::file(path.to_str().unwrap()).use_pixel().extract(&mut pixel).unwrap();
+        let mut perceptual: Meta = Meta::new();
+        PerceptualHash::file(path.to_str().unwrap()).extract(&mut perceptual).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        for tag in ["hash.ahash", "hash.dhash", "hash.phash"] {
+            assert_eq!(String::from(pixel.find(tag).first().unwrap().value.clone()), String::from(perceptual.find(tag).first().unwrap().value.clone()));
+        }
+        assert_eq!(pixel.find("hash.pixel").len(), 1);
+    }
+
+    #[cfg(all(feature = "exif", feature = "phash"))]
+    #[test]
+    fn test_rotated_photo_is_hashed_upright() {
+        use image::{imageops, DynamicImage, GrayImage, ImageOutputFormat, Luma};
+        use crate::phash::{hamming_distance, PerceptualHash};
+
+        let jpeg = |img: &GrayImage, orientation: Option<u16>| {
+            let mut data: Vec<u8> = Vec::new();
+            DynamicImage::ImageLuma8(img.clone()).write_to(&mut data, ImageOutputFormat::Jpeg(95)).unwrap();
+            if let Some(orientation) = orientation {
+                // APP1 with a little-endian TIFF holding a single Orientation entry
+                let mut app1: Vec<u8> = vec![0xFF, 0xE1, 0x00, 0x22];
+                app1.extend_from_slice(b"Exif\0\0II\x2A\0\x08\0\0\0\x01\0\x12\x01\x03\0\x01\0\0\0");
+                app1.extend_from_slice(&orientation.to_le_bytes());
+                app1.extend_from_slice(&[0; 6]);
+                data.splice(2..2, app1);
+            }
+            data
+        };
+        let hashes = |data: &[u8], name: &str| {
+            let path = std::env::temp_dir().join(format!("informant_upright_{}_{}.jpg", name, std::process::id()));
+            fs::write(&path, data).unwrap();
+            let mut pixel: Meta = Meta::new();
+            MetaHash::file(path.to_str().unwrap()).use_pixel().extract(&mut pixel).unwrap();
+            let mut perceptual: Meta = Meta::new();
+            PerceptualHash::file(path.to_str().unwrap()).extract(&mut perceptual).unwrap();
+            fs::remove_file(&path).unwrap();
+
+            ["hash.ahash", "hash.dhash", "hash.phash"].map(|tag| {
+                let value: String = String::from(pixel.find(tag).first().unwrap().value.clone());
+                assert_eq!(value, String::from(perceptual.find(tag).first().unwrap().value.clone()));
+                value
+            })
+        };
+
+        let upright: GrayImage = GrayImage::from_fn(96, 64, |x, y| Luma([(x * 2 + (y / 16) * 40) as u8]));
+        // the camera stored the sensor rows, orientation 6 turns them clockwise
+        let stored: GrayImage = imageops::rotate270(&upright);
+
+        let expected: [String; 3] = hashes(&jpeg(&upright, None), "expected");
+        let rotated: [String; 3] = hashes(&jpeg(&stored, Some(6)), "rotated");
+        let ignored: [String; 3] = hashes(&jpeg(&stored, None), "ignored");
+        for ((expected, rotated), ignored) in expected.iter().zip(&rotated).zip(&ignored) {
+            assert!(hamming_distance(expected, rotated).unwrap() <= 4, "{} vs {}", expected, rotated);
+            assert!(hamming_distance(expected, ignored).unwrap() > 4, "{} vs {}", expected, ignored);
+        }
+    }
+
+    #[test]
+    fn test_sample_hash() {
+        use super::{SampleHash, SAMPLE_SIZE};
+
+        let path = std::env::temp_dir().join(format!("informant_sample_{}.bin", std::process::id()));
+        let sample = |content: &[u8]| {
+            fs::write(&path, content).unwrap();
+            let mut meta: Meta = Meta::new();
+            SampleHash::file(path.to_str().unwrap()).chunks(4).extract(&mut meta).unwrap();
+            String::from(meta.find("hash.sample").first().unwrap().value.clone())
+        };
+
+        let mut content: Vec<u8> = (0..(SAMPLE_SIZE as usize * 20)).map(|x| (x % 241) as u8).collect();
+        let original: String = sample(&content);
+        assert!(original.starts_with(&format!("{}:", content.len())));
+
+        // a byte between the samples is not seen
+        content[SAMPLE_SIZE as usize * 2] ^= 0xFF;
+        assert_eq!(original, sample(&content));
+
+        // a byte in the tail is
+        let last: usize = content.len() - 1;
+        content[last] ^= 0xFF;
+        assert_ne!(original, sample(&content));
+
+        // small files are hashed completely
+        let small: String = sample(b"tiny");
+        assert_eq!(small, format!("4:{}", blake3::hash(&[&4u64.to_le_bytes()[..], b"tiny"].concat())));
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -6,6 +6,40 @@ pub struct Heic{ path: String }
 
 impl Heic {}
 
+/// Copy an interleaved RGB plane to tightly packed RGB8.
+/// libheif pads each row up to `stride` bytes, so rows are copied without the padding.
+#[cfg(feature = "image")]
+fn pack_rgb8(plane: &Plane<&[u8]>) -> Result<image::RgbImage, crate::MetaError> {
+    let row: usize = plane.width as usize * 3;
+    let mut pixels: Vec<u8> = Vec::with_capacity(row * plane.height as usize);
+    for y in 0..plane.height as usize {
+        let start: usize = y * plane.stride;
+        pixels.extend_from_slice(&plane.data[start..start + row]);
+    }
+
+    image::RgbImage::from_raw(plane.width, plane.height, pixels)
+        .ok_or_else(|| "invalid HEIF plane dimensions".into())
+}
+
+/// Decode the primary image to tightly packed RGB8.
+#[cfg(feature = "image")]
+pub(crate) fn decode_rgb8(path: &str) -> Result<image::RgbImage, crate::MetaError> {
+    let ctx: HeifContext = HeifContext::read_from_file(path)?;
+    let handle: ImageHandle = ctx.primary_image_handle()?;
+
+    let lib_heif = LibHeif::new();
+    let image: libheif_rs::Image = lib_heif.decode(
+        &handle,
+        ColorSpace::Rgb(RgbChroma::Rgb), 
+        None
+    )?;
+    let planes: Planes<&[u8]> = image.planes();
+    let plane: Plane<&[u8]> = planes.interleaved
+        .ok_or("missing interleaved RGB plane")?;
+
+    pack_rgb8(&plane)
+}
+
 impl FromFile for Heic {
     fn file(path: &str) -> Self {
         Self { path: path.to_string() }
@@ -44,12 +78,30 @@ impl Extractor for Heic {
         let interleaved_plane: Plane<&[u8]> = planes.interleaved.unwrap();
         let hash: blake3::Hash = blake3::hash(interleaved_plane.data);
 
+        // libheif applies irot/imir while decoding, so the plane is already upright.
+        #[cfg(any(all(feature = "hash", feature = "image"), feature = "phash"))]
+        let rgb: image::RgbImage = pack_rgb8(&interleaved_plane)?;
+
+        #[cfg(all(feature = "hash", feature = "image"))]
+        let pixel_hash: String = crate::hash::pixel_digest(&rgb);
+
         meta.add(MetaAttribute{
             source: MetaSource::Hash,
             tag: "hash".to_string(),
             value: MetaType::String(MetaValue::from(hash.to_string())),
         });
 
+        #[cfg(all(feature = "hash", feature = "image"))]
+        meta.add(MetaAttribute{
+            source: MetaSource::Hash,
+            tag: "hash.pixel".to_string(),
+            value: MetaType::String(MetaValue::from(pixel_hash)),
+        });
+
+        // perceptual hashes from the same decode
+        #[cfg(feature = "phash")]
+        crate::phash::add_hashes(&image::DynamicImage::ImageRgb8(rgb).to_luma8(), meta);
+
         Ok(())
     }
 }
@@ -1,6 +1,39 @@
-use image::{GenericImageView, DynamicImage};
+use image::{GenericImageView, DynamicImage, RgbImage};
 use crate::{meta::{MetaAttribute, MetaSource, MetaValue, MetaType}, FromFile, Extractor, Meta};
 
+/// Rotate/flip decoded pixels so they are upright according to EXIF orientation.
+#[cfg(feature = "exif")]
+fn apply_orientation(img: RgbImage, orientation: u32) -> RgbImage {
+    use image::imageops;
+
+    match orientation {
+        2 => imageops::flip_horizontal(&img),
+        3 => imageops::rotate180(&img),
+        4 => imageops::flip_vertical(&img),
+        5 => imageops::flip_horizontal(&imageops::rotate90(&img)),
+        6 => imageops::rotate90(&img),
+        7 => imageops::flip_horizontal(&imageops::rotate270(&img)),
+        8 => imageops::rotate270(&img),
+        _ => img,
+    }
+}
+
+/// Rotate/flip decoded pixels upright according to the EXIF orientation of `path`.
+/// HEIF is decoded by libheif, which already applies the irot/imir transforms,
+/// so it does not come through here.
+#[cfg(feature = "exif")]
+pub(crate) fn upright(img: RgbImage, path: &str) -> RgbImage {
+    match crate::exif::orientation(path) {
+        Some(orientation) => apply_orientation(img, orientation),
+        None => img
+    }
+}
+
+#[cfg(not(feature = "exif"))]
+pub(crate) fn upright(img: RgbImage, _path: &str) -> RgbImage {
+    img
+}
+
 pub struct CommonImageMeta {
     path: String
 }
@@ -88,4 +121,21 @@ mod test {
         let meta: Meta = get_file_meta("../testdata/original/fails_common_image_extractor.heic").unwrap();
 
     }
-}
\ No newline at end of file
+
+    #[cfg(feature = "exif")]
+    #[test]
+    fn test_apply_orientation() {
+        use image::{imageops, Rgb, RgbImage};
+
+        let upright: RgbImage = RgbImage::from_fn(4, 3, |x, y| Rgb([x as u8, y as u8, 0]));
+
+        // camera stored the pixels rotated, EXIF says how to undo it
+        assert_eq!(super::apply_orientation(imageops::rotate270(&upright), 6), upright);
+        assert_eq!(super::apply_orientation(imageops::rotate90(&upright), 8), upright);
+        assert_eq!(super::apply_orientation(imageops::rotate180(&upright), 3), upright);
+        assert_eq!(super::apply_orientation(imageops::flip_horizontal(&upright), 2), upright);
+
+        let transposed: RgbImage = RgbImage::from_fn(3, 4, |x, y| *upright.get_pixel(y, x));
+        assert_eq!(super::apply_orientation(transposed, 5), upright);
+    }
+}
@@ -3,12 +3,23 @@ use std::path::Path;
 // Common meta objects
 mod meta;
 mod image;
+mod options;
+mod capture;
+mod datetime;
+mod location;
+mod filename;
 
 use general::General;
+use filename::Filename;
 use image::CommonImageMeta;
 pub use meta::Meta;
 
 pub use crate::meta::{MetaClass, MetaAttribute, MetaValue, MetaSource, MetaType};
+pub use options::{HashAlgorithm, Options};
+pub use capture::{CaptureResolver, CaptureSource, CaptureTime, Confidence, Zone, XMP_DATES};
+pub use filename::{FilenameDate, FilenamePattern};
+pub use datetime::parse_datetime;
+pub use location::{Location, LocationSource};
 
 type MetaError = Box<dyn std::error::Error + 'static>;
 
@@ -44,9 +55,14 @@ impl Extractors {
     }
 
     pub fn extract(&self) -> Result<Meta, MetaError> {
+        return self.extract_skipping(&[]);
+    }
+
+    /// Like [`Extractors::extract`], leaving out the named extractors, e.g. ones already run.
+    pub fn extract_skipping(&self, names: &[&str]) -> Result<Meta, MetaError> {
         let mut s:Meta = Meta::new();
         
-        for e in self.0.iter() {
+        for e in self.0.iter().filter(|x| !names.contains(&x.name().as_str())) {
             match e.extract(&mut s) {
                 Ok(_) => (),
                 // todo - convert to debug error
@@ -83,12 +99,96 @@ mod matroska;
 #[cfg(feature = "mp4")]
 mod mp4;
 
+#[cfg(any(feature = "avi", feature = "wave", feature = "fingerprint"))]
+mod riff;
+
+#[cfg(feature = "avi")]
+mod avi;
+
+#[cfg(feature = "mts")]
+mod mts;
+
+#[cfg(feature = "vorbis")]
+mod vorbis;
+
+#[cfg(feature = "wave")]
+mod wave;
+
+#[cfg(feature = "codec")]
+mod codec;
+
 #[cfg(feature = "hash")]
 mod hash;
+#[cfg(feature = "hash")]
+pub use hash::{MetaHash, Progress, SampleHash};
+
+#[cfg(feature = "hash")]
+mod essence;
+
+#[cfg(feature = "phash")]
+mod phash;
+#[cfg(feature = "phash")]
+pub use phash::{hamming_distance, similarity};
+
+#[cfg(feature = "fingerprint")]
+mod fingerprint;
+#[cfg(all(feature = "fingerprint", feature = "mp4"))]
+mod h264;
+
+#[cfg(feature = "acoustic")]
+mod acoustic;
+#[cfg(feature = "acoustic")]
+pub use acoustic::acoustic_similarity;
+
+#[cfg(any(feature = "pdf", feature = "office", feature = "text", feature = "svg", feature = "sidecar"))]
+mod xml;
+
+#[cfg(any(feature = "office", feature = "container"))]
+mod zip;
+
+#[cfg(feature = "office")]
+mod ole;
+
+#[cfg(feature = "pdf")]
+mod pdf;
+
+#[cfg(feature = "office")]
+mod office;
+
+#[cfg(feature = "text")]
+mod text;
+
+#[cfg(feature = "svg")]
+mod svg;
+
+#[cfg(feature = "container")]
+mod container;
+#[cfg(feature = "container")]
+pub use container::{Container, Member};
+
+#[cfg(feature = "sidecar")]
+mod sidecar;
+#[cfg(feature = "sidecar")]
+pub use sidecar::{find_sidecars, Sidecar, SidecarKind, SidecarMeta};
+
+#[cfg(feature = "takeout")]
+mod takeout;
+#[cfg(feature = "takeout")]
+pub use takeout::{find_takeout_json, Takeout};
+
+#[cfg(feature = "live")]
+mod live;
+#[cfg(feature = "live")]
+pub use live::{content_identifier, find_live_pair, Live, LiveKind, LivePhoto};
 
 
 /// Search and collect extractors by file extension.
 pub fn get_extractors(file_path: &str) -> Result<Extractors, MetaError> {
+    get_extractors_with(file_path, &Options::default())
+}
+
+/// Search and collect extractors by file extension, configured by `options`.
+pub fn get_extractors_with(file_path: &str, options: &Options) -> Result<Extractors, MetaError> {
     let extension: String = match Path::new(file_path).extension() {
         Some(x) => { x.to_str().unwrap().trim().to_ascii_lowercase() },
         _ => { panic!("missing file extension") }
@@ -97,8 +197,33 @@ pub fn get_extractors(file_path: &str) -> Result<Extractors, MetaError> {
     let mut extractors: Vec<Box<dyn Extractor>> = vec![
         // universal extractors
         Box::new(General::file(file_path)),
+        Box::new(Filename::file(file_path).patterns(&options.filename_patterns)),
     ];
 
+    #[cfg(feature = "sidecar")]
+    {
+        use crate::sidecar::SidecarMeta;
+        extractors.push(Box::new(SidecarMeta::file(file_path)))
+    }
+
+    #[cfg(feature = "takeout")]
+    {
+        use crate::takeout::Takeout;
+        extractors.push(Box::new(Takeout::file(file_path)))
+    }
+
+    #[cfg(feature = "live")]
+    {
+        use crate::live::Live;
+        extractors.push(Box::new(Live::file(file_path)))
+    }
+
+    #[cfg(feature = "hash")]
+    if let Some(chunks) = options.sample_chunks {
+        use crate::hash::SampleHash;
+        extractors.push(Box::new(SampleHash::file(file_path).chunks(chunks)))
+    }
+
     match extension.as_str() {
         "mkv" => {
             #[cfg(feature = "matroska")]
@@ -110,7 +235,39 @@ pub fn get_extractors(file_path: &str) -> Result<Extractors, MetaError> {
             #[cfg(feature = "hash")]
             {
                 use crate::hash::MetaHash;
-                extractors.push(Box::new(MetaHash::file(file_path)))
+                extractors.push(Box::new(MetaHash::file(file_path).algorithms(&options.hashes)))
+            }
+        },
+        "avi" => {
+            #[cfg(feature = "avi")]
+            {
+                use crate::avi::AVI;
+                extractors.push(Box::new(AVI::file(file_path)))
+            }
+
+            #[cfg(feature = "hash")]
+            {
+                use crate::hash::MetaHash;
+                extractors.push(Box::new(MetaHash::file(file_path).algorithms(&options.hashes)))
+            }
+
+            #[cfg(feature = "fingerprint")]
+            {
+                use crate::fingerprint::VideoFingerprint;
+                extractors.push(Box::new(VideoFingerprint::file(file_path)))
+            }
+        },
+        "mts" | "m2ts" | "m2t" => {
+            #[cfg(feature = "mts")]
+            {
+                use crate::mts::MTS;
+                extractors.push(Box::new(MTS::file(file_path)))
+            }
+
+            #[cfg(feature = "hash")]
+            {
+                use crate::hash::MetaHash;
+                extractors.push(Box::new(MetaHash::file(file_path).algorithms(&options.hashes)))
             }
         },
         "m4a" => {
@@ -135,7 +292,19 @@ pub fn get_extractors(file_path: &str) -> Result<Extractors, MetaError> {
             #[cfg(feature = "hash")]
             {
                 use crate::hash::MetaHash;
-                extractors.push(Box::new(MetaHash::file(file_path)))
+                extractors.push(Box::new(MetaHash::file(file_path).algorithms(&options.hashes)))
+            }
+
+            #[cfg(feature = "acoustic")]
+            {
+                use crate::acoustic::AcousticFingerprint;
+                extractors.push(Box::new(AcousticFingerprint::file(file_path)))
+            }
+
+            #[cfg(feature = "hash")]
+            {
+                use crate::essence::Essence;
+                extractors.push(Box::new(Essence::file(file_path)))
             }
         }
         "mp4" | "mov" | "m4v" => {
@@ -148,10 +317,140 @@ pub fn get_extractors(file_path: &str) -> Result<Extractors, MetaError> {
             #[cfg(feature = "hash")]
             {
                 use crate::hash::MetaHash;
-                extractors.push(Box::new(MetaHash::file(file_path)))
+                extractors.push(Box::new(MetaHash::file(file_path).algorithms(&options.hashes)))
+            }
+
+            #[cfg(feature = "fingerprint")]
+            {
+                use crate::fingerprint::VideoFingerprint;
+                extractors.push(Box::new(VideoFingerprint::file(file_path)))
+            }
+
+            #[cfg(feature = "hash")]
+            {
+                use crate::essence::Essence;
+                extractors.push(Box::new(Essence::file(file_path)))
+            }
+        },
+        "flac" => {
+            #[cfg(feature = "vorbis")]
+            {
+                use crate::vorbis::Vorbis;
+                extractors.push(Box::new(Vorbis::file(file_path)))
+            }
+
+            #[cfg(feature = "ape")]
+            {
+                use crate::ape::Ape;
+                extractors.push(Box::new(Ape::file(file_path)))
+            }
+
+            #[cfg(feature = "id3")]
+            {
+                use crate::id3::ID3;
+                extractors.push(Box::new(ID3::file(file_path)))
+            }
+
+            #[cfg(feature = "hash")]
+            {
+                use crate::hash::MetaHash;
+                extractors.push(Box::new(MetaHash::file(file_path).algorithms(&options.hashes)))
+            }
+
+            #[cfg(feature = "acoustic")]
+            {
+                use crate::acoustic::AcousticFingerprint;
+                extractors.push(Box::new(AcousticFingerprint::file(file_path)))
+            }
+
+            #[cfg(feature = "hash")]
+            {
+                use crate::essence::Essence;
+                extractors.push(Box::new(Essence::file(file_path)))
+            }
+        },
+        "ogg" | "oga" | "opus" => {
+            #[cfg(feature = "vorbis")]
+            {
+                use crate::vorbis::Vorbis;
+                extractors.push(Box::new(Vorbis::file(file_path)))
+            }
+
+            #[cfg(feature = "hash")]
+            {
+                use crate::hash::MetaHash;
+                extractors.push(Box::new(MetaHash::file(file_path).algorithms(&options.hashes)))
+            }
+
+            #[cfg(feature = "acoustic")]
+            {
+                use crate::acoustic::AcousticFingerprint;
+                extractors.push(Box::new(AcousticFingerprint::file(file_path)))
+            }
+        },
+        "wav" | "bwf" | "aif" | "aiff" | "aifc" => {
+            #[cfg(feature = "wave")]
+            {
+                use crate::wave::Wave;
+                extractors.push(Box::new(Wave::file(file_path)))
+            }
+
+            #[cfg(feature = "id3")]
+            {
+                use crate::id3::ID3;
+                extractors.push(Box::new(ID3::file(file_path)))
+            }
+
+            #[cfg(feature = "hash")]
+            {
+                use crate::hash::MetaHash;
+                extractors.push(Box::new(MetaHash::file(file_path).algorithms(&options.hashes)))
+            }
+
+            #[cfg(feature = "acoustic")]
+            {
+                use crate::acoustic::AcousticFingerprint;
+                extractors.push(Box::new(AcousticFingerprint::file(file_path)))
+            }
+        },
+        "amr" | "mp3" | "aac" => {
+            #[cfg(feature = "codec")]
+            {
+                use crate::codec::AudioCodec;
+                extractors.push(Box::new(AudioCodec::file(file_path)))
+            }
+
+            #[cfg(feature = "ape")]
+            {
+                use crate::ape::Ape;
+                extractors.push(Box::new(Ape::file(file_path)))
+            }
+
+            #[cfg(feature = "id3")]
+            {
+                use crate::id3::ID3;
+                extractors.push(Box::new(ID3::file(file_path)))
+            }
+
+            #[cfg(feature = "hash")]
+            {
+                use crate::hash::MetaHash;
+                extractors.push(Box::new(MetaHash::file(file_path).algorithms(&options.hashes)))
+            }
+
+            #[cfg(feature = "acoustic")]
+            {
+                use crate::acoustic::AcousticFingerprint;
+                extractors.push(Box::new(AcousticFingerprint::file(file_path)))
+            }
+
+            #[cfg(feature = "hash")]
+            {
+                use crate::essence::Essence;
+                extractors.push(Box::new(Essence::file(file_path)))
             }
         },
-        "amr" | "mp3" | "wav" | "flac"  | "wma" | "m4r" => {
+        "wma" | "m4r" => {
             #[cfg(feature = "ape")]
             {
                 use crate::ape::Ape;
@@ -167,7 +466,79 @@ pub fn get_extractors(file_path: &str) -> Result<Extractors, MetaError> {
             #[cfg(feature = "hash")]
             {
                 use crate::hash::MetaHash;
-                extractors.push(Box::new(MetaHash::file(file_path)))
+                extractors.push(Box::new(MetaHash::file(file_path).algorithms(&options.hashes)))
+            }
+
+            #[cfg(feature = "acoustic")]
+            {
+                use crate::acoustic::AcousticFingerprint;
+                extractors.push(Box::new(AcousticFingerprint::file(file_path)))
+            }
+        },
+        "pdf" => {
+            #[cfg(feature = "pdf")]
+            {
+                use crate::pdf::PDF;
+                extractors.push(Box::new(PDF::file(file_path)))
+            }
+
+            #[cfg(feature = "hash")]
+            {
+                use crate::hash::MetaHash;
+                extractors.push(Box::new(MetaHash::file(file_path).algorithms(&options.hashes)))
+            }
+        },
+        "docx" | "docm" | "dotx" | "xlsx" | "xlsm" | "pptx" | "pptm" | "ppsx" | "odt" | "ods" | "odp" | "odg"
+        | "doc" | "dot" | "xls" | "ppt" | "pps" => {
+            #[cfg(feature = "office")]
+            {
+                use crate::office::Office;
+                extractors.push(Box::new(Office::file(file_path)))
+            }
+
+            #[cfg(feature = "hash")]
+            {
+                use crate::hash::MetaHash;
+                extractors.push(Box::new(MetaHash::file(file_path).algorithms(&options.hashes)))
+            }
+        },
+        "txt" | "text" | "md" | "log" | "csv" | "tsv" | "xml" | "json" | "vcf" | "vcard" | "ics" | "ical" => {
+            #[cfg(feature = "text")]
+            {
+                use crate::text::Text;
+                extractors.push(Box::new(Text::file(file_path)))
+            }
+
+            #[cfg(feature = "hash")]
+            {
+                use crate::hash::MetaHash;
+                extractors.push(Box::new(MetaHash::file(file_path).algorithms(&options.hashes)))
+            }
+        },
+        "svg" | "svgz" => {
+            #[cfg(feature = "svg")]
+            {
+                use crate::svg::Svg;
+                extractors.push(Box::new(Svg::file(file_path)))
+            }
+
+            #[cfg(feature = "hash")]
+            {
+                use crate::hash::MetaHash;
+                extractors.push(Box::new(MetaHash::file(file_path).algorithms(&options.hashes)))
+            }
+        },
+        "zip" | "tar" | "tgz" | "gz" => {
+            #[cfg(feature = "container")]
+            {
+                use crate::container::Container;
+                extractors.push(Box::new(Container::file(file_path).options(options)))
+            }
+
+            #[cfg(feature = "hash")]
+            {
+                use crate::hash::MetaHash;
+                extractors.push(Box::new(MetaHash::file(file_path).algorithms(&options.hashes)))
             }
         },
         "heic" | "heif" => {
@@ -187,6 +558,13 @@ pub fn get_extractors(file_path: &str) -> Result<Extractors, MetaError> {
                 use crate::heic::Heic;
                 extractors.push(Box::new(Heic::file(file_path)))
             }
+
+            // HEIF hashes its decoded image, the additional digests are over the file
+            #[cfg(feature = "hash")]
+            {
+                use crate::hash::MetaHash;
+                extractors.push(Box::new(MetaHash::file(file_path).algorithms(&options.hashes).skip_primary()))
+            }
         },
         "png" => {
             extractors.push(Box::new(CommonImageMeta::file(file_path)));
@@ -194,7 +572,27 @@ pub fn get_extractors(file_path: &str) -> Result<Extractors, MetaError> {
             #[cfg(feature = "hash")]
             {
                 use crate::hash::MetaHash;
-                extractors.push(Box::new(MetaHash::file(file_path).use_pixel()))
+                extractors.push(Box::new(MetaHash::file(file_path).algorithms(&options.hashes).use_pixel()))
+            }
+
+            // with hashing on, MetaHash derives the perceptual hashes from its decoded pixels
+            #[cfg(all(feature = "phash", not(feature = "hash")))]
+            {
+                use crate::phash::PerceptualHash;
+                extractors.push(Box::new(PerceptualHash::file(file_path)))
+            }
+        },
+        "gif" | "bmp" | "webp" | "hdr" | "pcx" | "mp" | "dng" | "raf" => {
+            #[cfg(feature = "hash")]
+            {
+                use crate::hash::MetaHash;
+                extractors.push(Box::new(MetaHash::file(file_path).algorithms(&options.hashes)))
+            }
+
+            #[cfg(feature = "phash")]
+            {
+                use crate::phash::PerceptualHash;
+                extractors.push(Box::new(PerceptualHash::file(file_path)))
             }
         },
         "jpeg" | "jpg" | "tif" | "tiff" | "cr2" | "jfif" => {
@@ -211,7 +609,19 @@ pub fn get_extractors(file_path: &str) -> Result<Extractors, MetaError> {
             #[cfg(feature = "hash")]
             {
                 use crate::hash::MetaHash;
-                extractors.push(Box::new(MetaHash::file(file_path).use_pixel()))
+                extractors.push(Box::new(MetaHash::file(file_path).algorithms(&options.hashes).use_pixel()))
+            }
+
+            #[cfg(feature = "hash")]
+            {
+                use crate::essence::Essence;
+                extractors.push(Box::new(Essence::file(file_path)))
+            }
+
+            #[cfg(all(feature = "phash", not(feature = "hash")))]
+            {
+                use crate::phash::PerceptualHash;
+                extractors.push(Box::new(PerceptualHash::file(file_path)))
             }
         },
         _ => {
@@ -220,7 +630,7 @@ pub fn get_extractors(file_path: &str) -> Result<Extractors, MetaError> {
             #[cfg(feature = "hash")]
             {
                 use crate::hash::MetaHash;
-                extractors.push(Box::new(MetaHash::file(file_path)))
+                extractors.push(Box::new(MetaHash::file(file_path).algorithms(&options.hashes)))
             }
         }
     }
@@ -0,0 +1,417 @@
+//! Apple Live Photos: a HEIC or JPEG still and a short QuickTime movie
+//! sharing a ContentIdentifier.
+
+use std::{fs::{self, File}, io::{Read, Seek, SeekFrom}, path::Path};
+
+use crate::{
+    meta::{MetaAttribute, MetaSource, MetaType, MetaValue},
+    Extractor, FromFile, Meta, MetaError};
+
+const STILL_EXTENSIONS: [&str; 4] = ["heic", "heif", "jpg", "jpeg"];
+const VIDEO_EXTENSIONS: [&str; 1] = ["mov"];
+
+/// Key of the identifier in the QuickTime `keys` atom.
+const QUICKTIME_KEY: &str = "com.apple.quicktime.content.identifier";
+
+/// Tag of the identifier in the Apple MakerNote.
+#[cfg(feature = "exif")]
+const MAKERNOTE_CONTENT_ID: u16 = 0x0011;
+
+/// Larger movie headers are not read, Live Photo clips are a few seconds long.
+const MAX_MOOV: u64 = 16 * 1024 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LiveKind {
+    Still,
+    Video
+}
+
+impl From<LiveKind> for String {
+    fn from(value: LiveKind) -> Self {
+        String::from(match value {
+            LiveKind::Still => "still",
+            LiveKind::Video => "video",
+        })
+    }
+}
+
+impl LiveKind {
+    /// The half a file could be, by its extension.
+    pub fn of(path: &str) -> Option<LiveKind> {
+        let extension: String = Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
+        if STILL_EXTENSIONS.contains(&extension.as_str()) {
+            return Some(LiveKind::Still);
+        }
+        if VIDEO_EXTENSIONS.contains(&extension.as_str()) {
+            return Some(LiveKind::Video);
+        }
+        None
+    }
+}
+
+/// One half of a Live Photo.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LivePhoto {
+    pub path: String,
+    pub kind: LiveKind,
+    pub content_id: String
+}
+
+/// The ContentIdentifier of a Live Photo still or video.
+pub fn content_identifier(path: &str) -> Option<String> {
+    match LiveKind::of(path)? {
+        LiveKind::Still => still_identifier(path),
+        LiveKind::Video => video_identifier(path)
+    }
+}
+
+#[cfg(feature = "exif")]
+fn still_identifier(path: &str) -> Option<String> {
+    let file: File = File::open(path).ok()?;
+    let exif = exif::Reader::new().read_from_container(&mut std::io::BufReader::new(file)).ok()?;
+    match &exif.get_field(exif::Tag::MakerNote, exif::In::PRIMARY)?.value {
+        exif::Value::Undefined(data, _) => makernote_identifier(data),
+        _ => None
+    }
+}
+
+#[cfg(not(feature = "exif"))]
+fn still_identifier(_path: &str) -> Option<String> {
+    None
+}
+
+fn text(value: &[u8]) -> Option<String> {
+    let value: String = String::from_utf8_lossy(value).trim_end_matches('\0').trim().to_string();
+    (!value.is_empty()).then_some(value)
+}
+
+#[cfg(feature = "exif")]
+fn u16_at(data: &[u8], at: usize) -> Option<u16> {
+    data.get(at..at + 2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+}
+
+fn u32_at(data: &[u8], at: usize) -> Option<u32> {
+    data.get(at..at + 4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+/// The Apple MakerNote is "Apple iOS\0", a version, "MM" and a big endian
+/// IFD whose offsets count from the start of the note.
+#[cfg(feature = "exif")]
+fn makernote_identifier(data: &[u8]) -> Option<String> {
+    if !data.starts_with(b"Apple iOS\0") || data.get(12..14)? != b"MM" {
+        return None;
+    }
+    let count: usize = u16_at(data, 14)? as usize;
+    for i in 0..count {
+        let entry: usize = 16 + i * 12;
+        if u16_at(data, entry)? != MAKERNOTE_CONTENT_ID {
+            continue;
+        }
+        // ASCII, inline up to four bytes
+        if u16_at(data, entry + 2)? != 2 {
+            return None;
+        }
+        let length: usize = u32_at(data, entry + 4)? as usize;
+        let value: &[u8] = match length {
+            0..=4 => data.get(entry + 8..entry + 8 + length)?,
+            _ => {
+                let offset: usize = u32_at(data, entry + 8)? as usize;
+                data.get(offset..offset.checked_add(length)?)?
+            }
+        };
+        return text(value);
+    }
+    None
+}
+
+/// Child atoms of `data` as (type, body).
+fn atoms(data: &[u8]) -> Vec<(&[u8], &[u8])> {
+    let mut children: Vec<(&[u8], &[u8])> = Vec::new();
+    let mut at: usize = 0;
+    while let (Some(size), Some(kind)) = (u32_at(data, at), data.get(at + 4..at + 8)) {
+        let (header, size): (usize, usize) = match size {
+            0 => (8, data.len() - at),
+            1 => match data.get(at + 8..at + 16) {
+                Some(b) => (16, u64::from_be_bytes(b.try_into().unwrap()) as usize),
+                None => break
+            },
+            s => (8, s as usize)
+        };
+        match data.get(at + header..at.saturating_add(size)) {
+            Some(body) if size >= header => children.push((kind, body)),
+            _ => break
+        }
+        at += size;
+    }
+    children
+}
+
+/// The `moov` atom of a QuickTime file, skipping over the media data.
+fn read_moov(path: &str) -> Option<Vec<u8>> {
+    let mut file: File = File::open(path).ok()?;
+    let length: u64 = file.metadata().ok()?.len();
+    let mut at: u64 = 0;
+    while at.saturating_add(8) <= length {
+        let mut header: [u8; 16] = [0; 16];
+        file.seek(SeekFrom::Start(at)).ok()?;
+        file.read_exact(&mut header[..8]).ok()?;
+        let (size, offset): (u64, u64) = match u32::from_be_bytes(header[..4].try_into().unwrap()) {
+            0 => (length - at, 8),
+            1 => {
+                file.read_exact(&mut header[8..]).ok()?;
+                (u64::from_be_bytes(header[8..].try_into().unwrap()), 16)
+            },
+            s => (s as u64, 8)
+        };
+        if size < offset {
+            return None;
+        }
+        if &header[4..8] == b"moov" {
+            if size > MAX_MOOV {
+                return None;
+            }
+            let mut moov: Vec<u8> = vec![0; (size - offset) as usize];
+            file.read_exact(&mut moov).ok()?;
+            return Some(moov);
+        }
+        at = at.checked_add(size)?;
+    }
+    None
+}
+
+/// The identifier in `moov/meta`, where `keys` names the entries of `ilst` by index.
+fn quicktime_identifier(moov: &[u8]) -> Option<String> {
+    let (_, meta) = atoms(moov).into_iter().find(|(kind, _)| *kind == b"meta")?;
+    // QuickTime meta atoms have no version and flags, ISO ones do
+    let meta: &[u8] = match meta.get(4..8) {
+        Some(b"hdlr") => meta,
+        _ => meta.get(4..)?
+    };
+    let children: Vec<(&[u8], &[u8])> = atoms(meta);
+
+    let (_, keys) = children.iter().find(|(kind, _)| *kind == b"keys")?;
+    let count: usize = u32_at(keys, 4)? as usize;
+    let mut at: usize = 8;
+    let mut index: Option<u32> = None;
+    for i in 0..count {
+        let size: usize = u32_at(keys, at)? as usize;
+        if size < 8 {
+            return None;
+        }
+        if keys.get(at + 8..at + size)? == QUICKTIME_KEY.as_bytes() {
+            index = Some(i as u32 + 1);
+            break;
+        }
+        at += size;
+    }
+
+    let index: [u8; 4] = index?.to_be_bytes();
+    let (_, ilst) = children.iter().find(|(kind, _)| *kind == b"ilst")?;
+    let (_, item) = atoms(ilst).into_iter().find(|(kind, _)| *kind == index)?;
+    // data: type, locale, value
+    let (_, data) = atoms(item).into_iter().find(|(kind, _)| *kind == b"data")?;
+    text(data.get(8..)?)
+}
+
+fn video_identifier(path: &str) -> Option<String> {
+    quicktime_identifier(&read_moov(path)?)
+}
+
+/// The other half of a Live Photo: a still or video next to the file, with
+/// the same name apart from the extension and the same ContentIdentifier.
+pub fn find_live_pair(path: &str) -> Option<LivePhoto> {
+    let kind: LiveKind = LiveKind::of(path)?;
+    let content_id: String = content_identifier(path)?;
+
+    let path: &Path = Path::new(path);
+    let stem: &str = path.file_stem()?.to_str()?;
+    let directory: &Path = match path.parent() {
+        Some(d) if !d.as_os_str().is_empty() => d,
+        _ => Path::new(".")
+    };
+
+    let mut files: Vec<String> = fs::read_dir(directory)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .map(|e| directory.join(e.file_name()).display().to_string())
+        .filter(|p| {
+            let other: &Path = Path::new(p);
+            other.file_stem().and_then(|s| s.to_str()).is_some_and(|s| s.eq_ignore_ascii_case(stem))
+                && LiveKind::of(p).is_some_and(|k| k != kind)
+        })
+        .collect();
+    files.sort();
+
+    files.into_iter().find_map(|p| {
+        let other: LiveKind = LiveKind::of(&p)?;
+        (content_identifier(&p)? == content_id).then_some(LivePhoto { path: p, kind: other, content_id: content_id.clone() })
+    })
+}
+
+/// Live Photo details of a still or video: `content_id`, `kind` and the
+/// file name of the other half as `pair` when it sits next to the file.
+pub struct Live {
+    path: String
+}
+
+impl FromFile for Live {
+    fn file(path: &str) -> Self {
+        Self { path: path.to_string() }
+    }
+}
+
+impl Live {
+    fn add_str(meta: &mut Meta, tag: &str, value: String) {
+        meta.add(MetaAttribute{
+            source: MetaSource::Live,
+            tag: tag.to_string(),
+            value: MetaType::String(MetaValue::from(value)),
+        });
+    }
+}
+
+impl Extractor for Live {
+    fn name(&self) -> String {
+        return "LIVE".to_string();
+    }
+
+    fn extract(&self, meta: &mut Meta) -> Result<(), MetaError> {
+        let kind: LiveKind = match LiveKind::of(&self.path) {
+            Some(k) => k,
+            None => return Ok(())
+        };
+        let content_id: String = match content_identifier(&self.path) {
+            Some(c) => c,
+            None => return Ok(())
+        };
+        Self::add_str(meta, "content_id", content_id);
+        Self::add_str(meta, "kind", String::from(kind));
+
+        if let Some(pair) = find_live_pair(&self.path) {
+            let name: String = Path::new(&pair.path).file_name().map_or(String::new(), |n| n.to_string_lossy().to_string());
+            Self::add_str(meta, "pair", name);
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::{fs, path::PathBuf};
+
+    use super::{content_identifier, quicktime_identifier, LiveKind, QUICKTIME_KEY};
+
+    const ID: &str = "5F6C6E8A-2C1B-4A39-9D5E-0E1D3B7C4A21";
+
+    fn atom(kind: &[u8], body: &[u8]) -> Vec<u8> {
+        let mut data: Vec<u8> = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+        data.extend_from_slice(kind);
+        data.extend_from_slice(body);
+        data
+    }
+
+    /// The body of a moov atom carrying the identifier.
+    fn moov(content_id: &str) -> Vec<u8> {
+        let mut keys: Vec<u8> = vec![0, 0, 0, 0, 0, 0, 0, 2];
+        for key in ["com.apple.quicktime.make", QUICKTIME_KEY] {
+            keys.extend(atom(b"mdta", key.as_bytes()));
+        }
+        let mut ilst: Vec<u8> = atom(&1u32.to_be_bytes(), &atom(b"data", b"\0\0\0\x01\0\0\0\0Apple"));
+        ilst.extend(atom(&2u32.to_be_bytes(), &atom(b"data", &[b"\0\0\0\x01\0\0\0\0", content_id.as_bytes()].concat())));
+
+        let mut meta: Vec<u8> = atom(b"hdlr", &[0; 24]);
+        meta.extend(atom(b"keys", &keys));
+        meta.extend(atom(b"ilst", &ilst));
+
+        let mut moov: Vec<u8> = atom(b"mvhd", &[0; 100]);
+        moov.extend(atom(b"meta", &meta));
+        moov
+    }
+
+    /// A QuickTime file with the media data ahead of the moov.
+    fn movie(content_id: &str) -> Vec<u8> {
+        let mut file: Vec<u8> = atom(b"ftyp", b"qt  \0\0\0\0qt  ");
+        file.extend(atom(b"mdat", &[0; 64]));
+        file.extend(atom(b"moov", &moov(content_id)));
+        file
+    }
+
+    /// A JPEG whose EXIF holds an Apple MakerNote with the identifier.
+    #[cfg(feature = "exif")]
+    fn still(content_id: &str) -> Vec<u8> {
+        let mut note: Vec<u8> = b"Apple iOS\0\0\x01MM".to_vec();
+        note.extend_from_slice(&1u16.to_be_bytes());
+        note.extend_from_slice(&0x0011u16.to_be_bytes());
+        note.extend_from_slice(&2u16.to_be_bytes());
+        note.extend_from_slice(&(content_id.len() as u32 + 1).to_be_bytes());
+        note.extend_from_slice(&32u32.to_be_bytes());
+        note.extend_from_slice(&[0; 4]);
+        note.extend_from_slice(content_id.as_bytes());
+        note.push(0);
+
+        // TIFF header, IFD0 pointing at the Exif IFD at 26, the MakerNote at 44
+        let mut tiff: Vec<u8> = b"MM\0\x2a\0\0\0\x08".to_vec();
+        tiff.extend_from_slice(&[0, 1, 0x87, 0x69, 0, 4, 0, 0, 0, 1, 0, 0, 0, 26, 0, 0, 0, 0]);
+        tiff.extend_from_slice(&[0, 1, 0x92, 0x7c, 0, 7]);
+        tiff.extend_from_slice(&(note.len() as u32).to_be_bytes());
+        tiff.extend_from_slice(&[0, 0, 0, 44, 0, 0, 0, 0]);
+        tiff.extend(note);
+
+        let mut app1: Vec<u8> = b"Exif\0\0".to_vec();
+        app1.extend(tiff);
+        let mut file: Vec<u8> = vec![0xff, 0xd8, 0xff, 0xe1];
+        file.extend_from_slice(&(app1.len() as u16 + 2).to_be_bytes());
+        file.extend(app1);
+        file.extend_from_slice(&[0xff, 0xd9]);
+        file
+    }
+
+    fn directory(name: &str) -> PathBuf {
+        let dir: PathBuf = std::env::temp_dir().join(format!("informant-live-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_quicktime_identifier() {
+        assert_eq!(quicktime_identifier(&moov(ID)).as_deref(), Some(ID));
+        assert_eq!(quicktime_identifier(&moov("")), None);
+
+        let dir: PathBuf = directory("video");
+        let path: PathBuf = dir.join("IMG_0001.MOV");
+        fs::write(&path, movie(ID)).unwrap();
+        assert_eq!(content_identifier(path.to_str().unwrap()).as_deref(), Some(ID));
+
+        // a 64-bit atom size running past the end of the address space
+        let mut broken: Vec<u8> = atom(b"ftyp", b"qt  \0\0\0\0qt  ");
+        broken.extend_from_slice(b"\0\0\0\x01mdat");
+        broken.extend_from_slice(&(u64::MAX - 8).to_be_bytes());
+        fs::write(&path, broken).unwrap();
+        assert_eq!(content_identifier(path.to_str().unwrap()), None);
+
+        assert_eq!(LiveKind::of("IMG_0001.HEIC"), Some(LiveKind::Still));
+        assert_eq!(LiveKind::of("IMG_0001.mp4"), None);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "exif")]
+    fn test_find_live_pair() {
+        use super::find_live_pair;
+
+        let dir: PathBuf = directory("pair");
+        fs::write(dir.join("IMG_0001.JPG"), still(ID)).unwrap();
+        fs::write(dir.join("IMG_0001.MOV"), movie(ID)).unwrap();
+        fs::write(dir.join("IMG_0002.JPG"), still(ID)).unwrap();
+        fs::write(dir.join("IMG_0002.MOV"), movie("another")).unwrap();
+        let path = |name: &str| dir.join(name).display().to_string();
+
+        assert_eq!(content_identifier(&path("IMG_0001.JPG")).as_deref(), Some(ID));
+        let pair = find_live_pair(&path("IMG_0001.JPG")).unwrap();
+        assert_eq!((pair.path, pair.kind), (path("IMG_0001.MOV"), LiveKind::Video));
+        assert_eq!(find_live_pair(&path("IMG_0001.MOV")).unwrap().kind, LiveKind::Still);
+        assert_eq!(find_live_pair(&path("IMG_0002.JPG")), None);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
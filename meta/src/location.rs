@@ -0,0 +1,153 @@
+//! Where an asset was captured, from EXIF GPS, XMP sidecars or a Takeout export.
+
+use crate::{Meta, MetaSource};
+
+/// Where a location was found, in the order they are tried.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LocationSource {
+    Exif,
+    Xmp,
+    /// `geoData` of a Google Takeout JSON.
+    Takeout,
+}
+
+impl From<LocationSource> for String {
+    fn from(value: LocationSource) -> Self {
+        String::from(match value {
+            LocationSource::Exif => "exif",
+            LocationSource::Xmp => "xmp",
+            LocationSource::Takeout => "takeout",
+        })
+    }
+}
+
+/// Coordinates in decimal degrees, altitude in meters.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Location {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub altitude: Option<f64>,
+    pub source: LocationSource,
+}
+
+impl Location {
+    /// The first valid location of the meta. (0, 0) is taken for unset.
+    pub fn resolve(meta: &Meta) -> Option<Location> {
+        Self::exif(meta)
+            .or_else(|| Self::decimal(meta, MetaSource::Sidecar, ["Xmp.exif.GPSLatitude", "Xmp.exif.GPSLongitude", "Xmp.exif.GPSAltitude"], LocationSource::Xmp))
+            .or_else(|| Self::decimal(meta, MetaSource::Takeout, ["latitude", "longitude", "altitude"], LocationSource::Takeout))
+    }
+
+    fn new(latitude: f64, longitude: f64, altitude: Option<f64>, source: LocationSource) -> Option<Location> {
+        let valid: bool = latitude.abs() <= 90.0 && longitude.abs() <= 180.0 && (latitude, longitude) != (0.0, 0.0);
+        valid.then_some(Location { latitude, longitude, altitude: altitude.filter(|a| a.is_finite()), source })
+    }
+
+    /// GPS tags as displayed by the EXIF reader, with their hemisphere references.
+    fn exif(meta: &Meta) -> Option<Location> {
+        let hemisphere = |tag: &str, negative: &str| -> f64 {
+            match value(meta, MetaSource::Exif, tag) {
+                Some(r) if r.trim().eq_ignore_ascii_case(negative) => -1.0,
+                _ => 1.0
+            }
+        };
+        let latitude: f64 = parse_dms(&value(meta, MetaSource::Exif, "GPSLatitude")?)? * hemisphere("GPSLatitudeRef", "S");
+        let longitude: f64 = parse_dms(&value(meta, MetaSource::Exif, "GPSLongitude")?)? * hemisphere("GPSLongitudeRef", "W");
+        let altitude: Option<f64> = value(meta, MetaSource::Exif, "GPSAltitude")
+            .and_then(|a| a.trim().parse::<f64>().ok())
+            .map(|a| a * hemisphere("GPSAltitudeRef", "below sea level"));
+        Self::new(latitude, longitude, altitude, LocationSource::Exif)
+    }
+
+    fn decimal(meta: &Meta, source: MetaSource, tags: [&str; 3], location: LocationSource) -> Option<Location> {
+        let number = |tag: &str| value(meta, source.clone(), tag).and_then(|v| v.trim().parse::<f64>().ok());
+        Self::new(number(tags[0])?, number(tags[1])?, number(tags[2]), location)
+    }
+}
+
+fn value(meta: &Meta, source: MetaSource, tag: &str) -> Option<String> {
+    meta.find_one(source, tag).ok().map(|a| String::from(a.value))
+}
+
+/// Degrees, minutes and seconds as displayed by the EXIF reader
+/// ("37 deg 46 min 29.64 sec"), or plain decimal degrees.
+pub fn parse_dms(value: &str) -> Option<f64> {
+    let parts: Vec<f64> = value
+        .split(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-'))
+        .filter(|p| !p.is_empty())
+        .map(|p| p.parse::<f64>())
+        .collect::<Result<_, _>>()
+        .ok()?;
+    match parts.as_slice() {
+        [degrees] => Some(*degrees),
+        [degrees, minutes] => Some(degrees + minutes / 60.0),
+        [degrees, minutes, seconds] => Some(degrees + minutes / 60.0 + seconds / 3600.0),
+        _ => None
+    }
+}
+
+/// An XMP GPSCoordinate, "37,46.494N" or "122,25,9.6W", as signed decimal degrees.
+pub fn parse_xmp_coordinate(value: &str) -> Option<f64> {
+    let value: &str = value.trim();
+    let sign: f64 = match value.chars().last()?.to_ascii_uppercase() {
+        'N' | 'E' => 1.0,
+        'S' | 'W' => -1.0,
+        _ => return value.parse().ok()
+    };
+    Some(sign * parse_dms(&value[..value.len() - 1].replace(',', " "))?)
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{parse_dms, parse_xmp_coordinate, Location, LocationSource};
+    use crate::{Meta, MetaAttribute, MetaSource, MetaType, MetaValue};
+
+    fn tagged(source: MetaSource, tags: &[(&str, &str)]) -> Meta {
+        let mut meta: Meta = Meta::new();
+        for (tag, value) in tags {
+            meta.add(MetaAttribute {
+                source: source.clone(),
+                tag: tag.to_string(),
+                value: MetaType::String(MetaValue::from(value.to_string())),
+            });
+        }
+        meta
+    }
+
+    fn close(value: Option<f64>, expected: f64) -> bool {
+        value.is_some_and(|v| (v - expected).abs() < 1e-9)
+    }
+
+    #[test]
+    fn test_parse_coordinates() {
+        assert!(close(parse_dms("37 deg 46 min 30 sec"), 37.775));
+        assert!(close(parse_dms("46.2044"), 46.2044));
+        assert_eq!(parse_dms("north"), None);
+        assert!(close(parse_xmp_coordinate("37,46.5N"), 37.775));
+        assert!(close(parse_xmp_coordinate("122,25,12W"), -122.42));
+        assert!(close(parse_xmp_coordinate("-33.5"), -33.5));
+    }
+
+    #[test]
+    fn test_resolve_location() {
+        let exif: Meta = tagged(MetaSource::Exif, &[
+            ("GPSLatitude", "33 deg 51 min 36 sec"), ("GPSLatitudeRef", "\"S\""),
+            ("GPSLongitude", "151 deg 12 min 36 sec"), ("GPSLongitudeRef", "\"E\""),
+            ("GPSAltitude", "12"), ("GPSAltitudeRef", "below sea level"),
+        ]);
+        let location: Location = Location::resolve(&exif).unwrap();
+        assert!(close(Some(location.latitude), -33.86) && close(Some(location.longitude), 151.21));
+        assert_eq!(location.altitude, Some(-12.0));
+        assert_eq!(location.source, LocationSource::Exif);
+
+        let mut meta: Meta = tagged(MetaSource::Exif, &[("GPSLatitude", "0 deg 0 min 0 sec"), ("GPSLongitude", "0 deg 0 min 0 sec")]);
+        meta.add(MetaAttribute { source: MetaSource::Takeout, tag: "latitude".to_string(), value: MetaType::Rational(MetaValue::from(46.2044)) });
+        meta.add(MetaAttribute { source: MetaSource::Takeout, tag: "longitude".to_string(), value: MetaType::Rational(MetaValue::from(6.1432)) });
+        let location: Location = Location::resolve(&meta).unwrap();
+        assert_eq!((location.latitude, location.altitude, location.source), (46.2044, None, LocationSource::Takeout));
+
+        assert!(Location::resolve(&tagged(MetaSource::Sidecar, &[("Xmp.exif.GPSLatitude", "95"), ("Xmp.exif.GPSLongitude", "10")])).is_none());
+        assert_eq!(Location::resolve(&tagged(MetaSource::Sidecar, &[("Xmp.exif.GPSLatitude", "-33.86"), ("Xmp.exif.GPSLongitude", "151.21")])).unwrap().source, LocationSource::Xmp);
+    }
+}
@@ -10,7 +10,8 @@ pub enum MetaClass {
     Audio,
     Image,
     Video,
-    Document
+    Document,
+    Archive
 }
 impl Default for MetaClass {
     fn default() -> Self {
@@ -25,6 +26,7 @@ impl From<MetaClass> for String {
             MetaClass::Image => "Image",
             MetaClass::Video => "Video",
             MetaClass::Document => "Document",
+            MetaClass::Archive => "Archive",
         })
     }
 }
@@ -35,6 +37,7 @@ impl From<String> for MetaClass {
             "Image" => MetaClass::Image,
             "Video" => MetaClass::Video,
             "Document" => MetaClass::Document,
+            "Archive" => MetaClass::Archive,
             _ => MetaClass::Unknown,
         }
     }
@@ -57,12 +60,28 @@ impl Eq for MetaClass { }
 pub enum MetaSource {
     Basic,
     Exif,
+    Xmp,
     Matroska,
     MP4,
+    AVI,
+    MTS,
     ID3,
     Ape,
+    Vorbis,
+    Wave,
+    Codec,
     Heic,
     Hash,
+    Fingerprint,
+    PDF,
+    Office,
+    Text,
+    Svg,
+    Container,
+    Filename,
+    Sidecar,
+    Takeout,
+    Live,
 }
 
 impl Default for MetaSource {
@@ -76,18 +95,40 @@ impl From<MetaSource> for String {
         String::from(match value {
             MetaSource::Basic => "Basic",
             MetaSource::Hash => "Hash",
+            MetaSource::Fingerprint => "Fingerprint",
             
             // Image Meta 
             MetaSource::Exif => "Exif",
+            MetaSource::Xmp => "Xmp",
             MetaSource::Heic => "Heic",
+            MetaSource::Svg => "Svg",
 
             // Video Meta
             MetaSource::Matroska => "Matroska",
             MetaSource::MP4 => "MP4",
+            MetaSource::AVI => "AVI",
+            MetaSource::MTS => "MTS",
 
             // Audio Meta
             MetaSource::ID3 => "ID3",
             MetaSource::Ape => "Ape",
+            MetaSource::Vorbis => "Vorbis",
+            MetaSource::Wave => "Wave",
+            MetaSource::Codec => "Codec",
+
+            // Document Meta
+            MetaSource::PDF => "PDF",
+            MetaSource::Office => "Office",
+            MetaSource::Text => "Text",
+
+            // Archive Meta
+            MetaSource::Container => "Container",
+
+            // File Meta
+            MetaSource::Filename => "Filename",
+            MetaSource::Sidecar => "Sidecar",
+            MetaSource::Takeout => "Takeout",
+            MetaSource::Live => "Live",
         })
     }
 }
@@ -119,6 +160,7 @@ impl Hash for MetaAttribute {
             MetaType::Rational(x) => f64::from(x.value).to_string().hash(state),
             MetaType::Int64(x) => i64::from(x.value).to_string().hash(state),
             MetaType::UInt64(x) => u64::from(x.value).to_string().hash(state),
+            MetaType::Meta(x) => x.value.0.iter().for_each(|a| a.hash(state)),
         }
     }
 }
@@ -232,6 +274,9 @@ pub enum MetaType {
     Rational(MetaValue<f64>),
     Int64(MetaValue<i64>),
     UInt64(MetaValue<u64>),
+
+    /// Meta of something inside the file, e.g. a member of an archive.
+    Meta(MetaValue<Meta>),
 }
 
 impl Default for MetaType {
@@ -270,6 +315,7 @@ impl From<MetaType> for String {
             MetaType::String(x) => x.value,
             MetaType::Int64(x) => x.value.to_string(),
             MetaType::UInt64(x) => x.value.to_string(),
+            MetaType::Meta(x) => serde_json::to_string(&x.value).unwrap_or_default(),
             MetaType::Rational(x) => {
                 if x.value.is_nan(){
                     return "NaN".to_owned();
@@ -358,6 +404,12 @@ impl From<u64> for MetaValue<u64> {
     }
 }
 
+impl From<Meta> for MetaValue<Meta> {
+    fn from(value: Meta) -> Self {
+        return Self{value};
+    }
+}
+
 impl From<MetaType> for u64 {
     fn from(value: MetaType) -> Self {
         match value {
@@ -0,0 +1,532 @@
+use std::{fs::{self, File}, io::{BufReader, Read, Seek, SeekFrom}, path::{Path, PathBuf}};
+
+use crate::{
+    meta::{MetaAttribute, MetaSource, MetaType, MetaValue},
+    Extractor, FromFile, Meta, MetaError};
+
+const SYNC_BYTE: u8 = 0x47;
+
+/// How much of the head of the stream is scanned for tables, the first PCR, and MDPM.
+const HEAD_WINDOW: u64 = 8 * 1024 * 1024;
+
+/// How much of the tail of the stream is scanned for the last PCR.
+const TAIL_WINDOW: u64 = 4 * 1024 * 1024;
+
+/// PCR runs at 27MHz and wraps at 2^33 * 300.
+const PCR_HZ: f64 = 27_000_000.0;
+const PCR_WRAP: u64 = (1 << 33) * 300;
+
+/// H.264 SEI user_data_unregistered UUID used by AVCHD cameras, followed by "MDPM".
+const MDPM_UUID: [u8; 16] = [
+    0x17, 0xee, 0x8c, 0x60, 0xf8, 0x4d, 0x11, 0xd9,
+    0x8c, 0xd6, 0x08, 0x00, 0x20, 0x0c, 0x9a, 0x66
+];
+
+#[derive(Debug, Clone)]
+struct Stream {
+    pid: u16,
+    stream_type: u8,
+}
+
+/// Modified Digital Video Pack Metadata written by AVCHD camcorders.
+#[derive(Debug, Default, PartialEq)]
+struct Mdpm {
+    date_time_original: Option<String>,
+    dst: bool,
+    make: Option<String>,
+    model: Option<String>,
+}
+
+pub struct MTS { path: String }
+
+impl MTS {
+    fn add_str(meta: &mut Meta, tag: &str, value: String) {
+        if value.is_empty() {
+            return;
+        }
+        meta.add(MetaAttribute{
+            source: MetaSource::MTS,
+            tag: tag.to_string(),
+            value: MetaType::String(MetaValue::from(value)),
+        });
+    }
+
+    fn add_u64(meta: &mut Meta, tag: &str, value: u64) {
+        meta.add(MetaAttribute{
+            source: MetaSource::MTS,
+            tag: tag.to_string(),
+            value: MetaType::UInt64(MetaValue::from(value)),
+        });
+    }
+
+    /// Plain transport streams use 188 byte packets,
+    /// BDAV/AVCHD (.m2ts, .mts) prefix each packet with a 4 byte timecode.
+    fn packet_size(data: &[u8]) -> Option<(usize, usize)> {
+        for (size, offset) in [(188, 0), (192, 4)] {
+            let synced: bool = (0..3).all(|i| data.get(offset + i * size) == Some(&SYNC_BYTE));
+            if synced {
+                return Some((size, offset));
+            }
+        }
+        None
+    }
+
+    /// Iterate the 188 byte TS packets within a buffer.
+    fn packets(data: &[u8], size: usize, offset: usize) -> impl Iterator<Item = &[u8]> {
+        data[offset.min(data.len())..]
+            .chunks(size)
+            .filter(|p| p.len() >= 188 && p[0] == SYNC_BYTE)
+            .map(|p| &p[..188])
+    }
+
+    fn pid(packet: &[u8]) -> u16 {
+        (((packet[1] & 0x1f) as u16) << 8) | packet[2] as u16
+    }
+
+    fn payload_unit_start(packet: &[u8]) -> bool {
+        packet[1] & 0x40 != 0
+    }
+
+    /// Offset of the payload within a packet, skipping the adaptation field.
+    fn payload(packet: &[u8]) -> Option<&[u8]> {
+        let control: u8 = (packet[3] >> 4) & 0x03;
+        match control {
+            0x01 => Some(&packet[4..]),
+            0x03 => {
+                let start: usize = 5 + packet[4] as usize;
+                packet.get(start..)
+            },
+            _ => None
+        }
+    }
+
+    fn pcr(packet: &[u8]) -> Option<u64> {
+        let control: u8 = (packet[3] >> 4) & 0x03;
+        if control & 0x02 == 0 || packet[4] < 7 || packet[5] & 0x10 == 0 {
+            return None;
+        }
+        let p: &[u8] = &packet[6..12];
+        let base: u64 = ((p[0] as u64) << 25) | ((p[1] as u64) << 17) | ((p[2] as u64) << 9) | ((p[3] as u64) << 1) | ((p[4] as u64) >> 7);
+        let ext: u64 = (((p[4] & 0x01) as u64) << 8) | p[5] as u64;
+        Some(base * 300 + ext)
+    }
+
+    /// Strip the pointer field and return the section body (table id onwards).
+    fn section(packet: &[u8]) -> Option<&[u8]> {
+        if !Self::payload_unit_start(packet) {
+            return None;
+        }
+        let payload: &[u8] = Self::payload(packet)?;
+        let pointer: usize = *payload.first()? as usize;
+        payload.get(1 + pointer..)
+    }
+
+    /// Program Association Table: program_number -> PMT pid
+    fn parse_pat(section: &[u8]) -> Vec<(u16, u16)> {
+        let mut programs: Vec<(u16, u16)> = Vec::new();
+        if section.len() < 8 || section[0] != 0x00 {
+            return programs;
+        }
+        let length: usize = ((((section[1] & 0x0f) as usize) << 8) | section[2] as usize).min(section.len() - 3);
+        // skip header (8 bytes), exclude CRC (4 bytes)
+        let entries: &[u8] = &section[8..(3 + length).saturating_sub(4).max(8)];
+        for e in entries.chunks_exact(4) {
+            let program: u16 = ((e[0] as u16) << 8) | e[1] as u16;
+            let pid: u16 = (((e[2] & 0x1f) as u16) << 8) | e[3] as u16;
+            if program != 0 {
+                programs.push((program, pid));
+            }
+        }
+        programs
+    }
+
+    /// Program Map Table: (PCR pid, elementary streams)
+    fn parse_pmt(section: &[u8]) -> Option<(u16, Vec<Stream>)> {
+        if section.len() < 12 || section[0] != 0x02 {
+            return None;
+        }
+        let length: usize = ((((section[1] & 0x0f) as usize) << 8) | section[2] as usize).min(section.len() - 3);
+        let end: usize = (3 + length).saturating_sub(4);
+        let pcr_pid: u16 = (((section[8] & 0x1f) as u16) << 8) | section[9] as u16;
+        let info_length: usize = (((section[10] & 0x0f) as usize) << 8) | section[11] as usize;
+
+        let mut streams: Vec<Stream> = Vec::new();
+        let mut i: usize = 12 + info_length;
+        while i + 5 <= end {
+            let stream_type: u8 = section[i];
+            let pid: u16 = (((section[i + 1] & 0x1f) as u16) << 8) | section[i + 2] as u16;
+            let es_info_length: usize = (((section[i + 3] & 0x0f) as usize) << 8) | section[i + 4] as usize;
+            streams.push(Stream { pid, stream_type });
+            i += 5 + es_info_length;
+        }
+        Some((pcr_pid, streams))
+    }
+
+    fn codec(stream_type: u8) -> &'static str {
+        match stream_type {
+            0x01 => "MPEG-1 Video",
+            0x02 => "MPEG-2 Video",
+            0x03 => "MPEG-1 Audio",
+            0x04 => "MPEG-2 Audio",
+            0x06 => "Private Data",
+            0x0f => "AAC",
+            0x10 => "MPEG-4 Visual",
+            0x11 => "AAC LATM",
+            0x1b => "H.264",
+            0x20 => "H.264 MVC",
+            0x24 => "HEVC",
+            0x80 => "LPCM",
+            0x81 => "AC-3",
+            0x82 => "DTS",
+            0x83 => "TrueHD",
+            0x84 | 0xa1 => "E-AC-3",
+            0x85 | 0x86 => "DTS-HD",
+            0x90 => "PGS",
+            0x92 => "Text Subtitle",
+            _ => "Unknown"
+        }
+    }
+
+    fn bcd(b: u8) -> u32 {
+        ((b >> 4) as u32) * 10 + (b & 0x0f) as u32
+    }
+
+    fn make(code: u16) -> Option<&'static str> {
+        match code {
+            0x0103 => Some("Panasonic"),
+            0x0108 => Some("Sony"),
+            0x1011 => Some("Canon"),
+            0x1104 => Some("JVC"),
+            _ => None
+        }
+    }
+
+    /// Remove H.264 emulation prevention bytes (00 00 03 -> 00 00).
+    fn unescape(data: &[u8]) -> Vec<u8> {
+        let mut out: Vec<u8> = Vec::with_capacity(data.len());
+        let mut zeros: usize = 0;
+        for &b in data {
+            if zeros >= 2 && b == 0x03 {
+                zeros = 0;
+                continue;
+            }
+            zeros = if b == 0 { zeros + 1 } else { 0 };
+            out.push(b);
+        }
+        out
+    }
+
+    /// Parse an MDPM block. `data` starts right after the "MDPM" marker:
+    /// one count byte followed by 5 byte (tag, value[4]) entries.
+    fn parse_mdpm(data: &[u8]) -> Option<Mdpm> {
+        let data: Vec<u8> = Self::unescape(&data[..data.len().min(1024)]);
+        let count: usize = *data.first()? as usize;
+        let mut mdpm: Mdpm = Mdpm::default();
+        let mut date: Option<[u8; 4]> = None;
+        let mut time: Option<[u8; 4]> = None;
+        let mut model: Vec<u8> = Vec::new();
+
+        for entry in data[1..].chunks_exact(5).take(count) {
+            let value: [u8; 4] = [entry[1], entry[2], entry[3], entry[4]];
+            match entry[0] {
+                0x18 => date = Some(value),
+                0x19 => time = Some(value),
+                0xe0 => {
+                    let code: u16 = ((value[0] as u16) << 8) | value[1] as u16;
+                    mdpm.make = Self::make(code).map(|x| x.to_string());
+                },
+                0xe4..=0xef => model.extend_from_slice(&value),
+                _ => ()
+            }
+        }
+
+        if let (Some(d), Some(t)) = (date, time) {
+            let tz: u8 = d[0];
+            let mut value: String = format!("{:02}{:02}:{:02}:{:02} {:02}:{:02}:{:02}",
+                Self::bcd(d[1]), Self::bcd(d[2]), Self::bcd(d[3]),
+                Self::bcd(t[0]), Self::bcd(t[1]), Self::bcd(t[2]), Self::bcd(t[3]));
+
+            // bit 7 set means no time zone recorded
+            if tz & 0x80 == 0 {
+                let sign: char = if tz & 0x20 != 0 { '-' } else { '+' };
+                let hours: u8 = (tz >> 1) & 0x0f;
+                let minutes: u8 = if tz & 0x01 != 0 { 30 } else { 0 };
+                value = format!("{}{}{:02}:{:02}", value, sign, hours, minutes);
+            }
+            mdpm.dst = tz & 0x80 == 0 && tz & 0x40 != 0;
+            mdpm.date_time_original = Some(value);
+        }
+
+        let model: String = String::from_utf8_lossy(&model)
+            .trim_matches(|c: char| c == '\0' || c.is_whitespace())
+            .to_string();
+        if !model.is_empty() {
+            mdpm.model = Some(model);
+        }
+
+        (mdpm != Mdpm::default()).then_some(mdpm)
+    }
+
+    /// Locate "MDPM" in a buffer, preferring the SEI UUID prefixed occurrence.
+    fn find_mdpm(data: &[u8]) -> Option<Mdpm> {
+        let mut uuid_mdpm: Vec<u8> = MDPM_UUID.to_vec();
+        uuid_mdpm.extend_from_slice(b"MDPM");
+
+        let position: usize = match find(data, &uuid_mdpm) {
+            Some(p) => p + uuid_mdpm.len(),
+            None => find(data, b"MDPM")? + 4,
+        };
+        Self::parse_mdpm(&data[position..])
+    }
+
+    fn read_window<R: Read + Seek>(reader: &mut R, offset: u64, len: u64) -> Result<Vec<u8>, MetaError> {
+        let mut data: Vec<u8> = Vec::new();
+        reader.seek(SeekFrom::Start(offset))?;
+        reader.take(len).read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    fn add_mdpm(mdpm: &Mdpm, meta: &mut Meta) {
+        if let Some(x) = &mdpm.date_time_original {
+            Self::add_str(meta, "mdpm.date_time_original", x.to_owned());
+            Self::add_u64(meta, "mdpm.dst", mdpm.dst as u64);
+        }
+        if let Some(x) = &mdpm.make {
+            Self::add_str(meta, "mdpm.make", x.to_owned());
+        }
+        if let Some(x) = &mdpm.model {
+            Self::add_str(meta, "mdpm.model", x.to_owned());
+        }
+    }
+
+    fn from_reader<R: Read + Seek>(&self, reader: &mut R, file_size: u64, meta: &mut Meta) -> Result<Option<Mdpm>, MetaError> {
+        let head: Vec<u8> = Self::read_window(reader, 0, HEAD_WINDOW)?;
+
+        let (size, offset) = match Self::packet_size(&head) {
+            Some(x) => x,
+            None => {
+                return Err(MetaError::from("not an MPEG transport stream"));
+            }
+        };
+        Self::add_u64(meta, "packet_size", size as u64);
+
+        // PSI: PAT -> PMT
+        let mut pmt_pid: Option<u16> = None;
+        let mut pmt: Option<(u16, Vec<Stream>)> = None;
+        for packet in Self::packets(&head, size, offset) {
+            let pid: u16 = Self::pid(packet);
+            if pid == 0 && pmt_pid.is_none() {
+                if let Some((program, pid)) = Self::section(packet).map(Self::parse_pat).and_then(|p| p.first().cloned()) {
+                    Self::add_u64(meta, "program_number", program as u64);
+                    pmt_pid = Some(pid);
+                }
+            }
+            else if Some(pid) == pmt_pid {
+                pmt = Self::section(packet).and_then(Self::parse_pmt);
+                if pmt.is_some() {
+                    break;
+                }
+            }
+        }
+
+        let (pcr_pid, streams) = pmt.unwrap_or((0x1fff, Vec::new()));
+        Self::add_u64(meta, "stream_count", streams.len() as u64);
+
+        for (i, stream) in streams.iter().enumerate() {
+            Self::add_u64(meta, &format!("stream_{}.pid", i), stream.pid as u64);
+            Self::add_u64(meta, &format!("stream_{}.stream_type", i), stream.stream_type as u64);
+            Self::add_str(meta, &format!("stream_{}.codec", i), Self::codec(stream.stream_type).to_string());
+        }
+
+        // Duration: last PCR - first PCR
+        let first_pcr: Option<u64> = Self::packets(&head, size, offset)
+            .filter(|p| Self::pid(p) == pcr_pid)
+            .find_map(Self::pcr);
+
+        let tail_start: u64 = file_size.saturating_sub(TAIL_WINDOW);
+        // realign the tail window on a packet boundary
+        let tail_start: u64 = tail_start - (tail_start % size as u64);
+        let tail: Vec<u8> = Self::read_window(reader, tail_start, TAIL_WINDOW)?;
+        let last_pcr: Option<u64> = Self::packets(&tail, size, offset)
+            .filter(|p| Self::pid(p) == pcr_pid)
+            .filter_map(Self::pcr)
+            .last();
+
+        if let (Some(first), Some(last)) = (first_pcr, last_pcr) {
+            let ticks: u64 = if last >= first { last - first } else { last + PCR_WRAP - first };
+            meta.add(MetaAttribute{
+                source: MetaSource::MTS,
+                tag: "duration".to_string(),
+                value: MetaType::Rational(MetaValue::from(ticks as f64 / PCR_HZ)),
+            });
+        }
+
+        // MDPM lives in the SEI of the video stream.
+        let video_pid: Option<u16> = streams.iter()
+            .find(|s| s.stream_type == 0x1b || s.stream_type == 0x20)
+            .map(|s| s.pid);
+
+        let mut video: Vec<u8> = Vec::new();
+        if let Some(video_pid) = video_pid {
+            Self::packets(&head, size, offset)
+                .filter(|p| Self::pid(p) == video_pid)
+                .filter_map(Self::payload)
+                .for_each(|p| video.extend_from_slice(p));
+        }
+
+        let mdpm: Option<Mdpm> = Self::find_mdpm(&video);
+        if let Some(x) = &mdpm {
+            Self::add_mdpm(x, meta);
+        }
+
+        Ok(mdpm)
+    }
+
+    /// When the clip lives in an AVCHD tree (BDMV/STREAM/00001.MTS), look up the
+    /// matching clip info (BDMV/CLIPINF/00001.CPI) and the playlists referencing it.
+    fn avchd(&self, has_mdpm: bool, meta: &mut Meta) -> Result<(), MetaError> {
+        let path: &Path = Path::new(&self.path);
+
+        let stream_dir: &Path = match path.parent() {
+            Some(p) if is_named(p, "STREAM") => p,
+            _ => return Ok(())
+        };
+        let bdmv: &Path = match stream_dir.parent() {
+            Some(p) if is_named(p, "BDMV") => p,
+            _ => return Ok(())
+        };
+        let clip: String = match path.file_stem().and_then(|x| x.to_str()) {
+            Some(x) => x.to_string(),
+            None => return Ok(())
+        };
+
+        Self::add_str(meta, "avchd.clip", clip.clone());
+
+        let mut sidecars: Vec<PathBuf> = Vec::new();
+
+        for dir in ["CLIPINF", "clipinf"] {
+            for ext in ["CPI", "cpi", "CLPI", "clpi"] {
+                let cpi: PathBuf = bdmv.join(dir).join(format!("{}.{}", clip, ext));
+                if cpi.is_file() {
+                    Self::add_str(meta, "avchd.clip_info", cpi.file_name().unwrap().to_string_lossy().to_string());
+                    sidecars.push(cpi);
+                }
+            }
+        }
+
+        for dir in ["PLAYLIST", "playlist"] {
+            let entries = match fs::read_dir(bdmv.join(dir)) {
+                Ok(x) => x,
+                Err(_) => continue
+            };
+            for entry in entries.flatten() {
+                let playlist: PathBuf = entry.path();
+                let data: Vec<u8> = match fs::read(&playlist) {
+                    Ok(x) => x,
+                    Err(_) => continue
+                };
+                // playlists reference clips as "00001M2TS"
+                let reference: String = format!("{}M2TS", clip);
+                if find(&data, reference.as_bytes()).is_some() {
+                    Self::add_str(meta, "avchd.playlist", playlist.file_name().unwrap().to_string_lossy().to_string());
+                    sidecars.push(playlist);
+                }
+            }
+        }
+
+        if has_mdpm {
+            return Ok(());
+        }
+
+        // Some cameras only record the MDPM pack in the maker data of the sidecars.
+        for sidecar in sidecars {
+            let data: Vec<u8> = fs::read(&sidecar)?;
+            if let Some(mdpm) = Self::find_mdpm(&data) {
+                Self::add_mdpm(&mdpm, meta);
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn is_named(path: &Path, name: &str) -> bool {
+    path.file_name()
+        .and_then(|x| x.to_str())
+        .is_some_and(|x| x.eq_ignore_ascii_case(name))
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.is_empty() || haystack.len() < needle.len() {
+        return None;
+    }
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+impl FromFile for MTS {
+    fn file(path: &str) -> Self {
+        Self { path: path.to_string() }
+    }
+}
+
+impl Extractor for MTS {
+    fn name(&self) -> String {
+        return "VIDEO".to_string();
+    }
+
+    fn extract(&self, meta: &mut Meta) -> Result<(), MetaError> {
+        let file: File = File::open(&self.path)?;
+        let size: u64 = file.metadata()?.len();
+        let mut reader: BufReader<File> = BufReader::new(file);
+
+        let mdpm: Option<Mdpm> = self.from_reader(&mut reader, size, meta)?;
+        self.avchd(mdpm.is_some(), meta)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{MTS, MDPM_UUID};
+    use crate::{MetaError, FromFile, Extractor, Meta};
+
+    const TEST_VIDEO: &str = "../testdata/Video/test.mts";
+
+    #[test]
+    fn test_parse_mdpm() {
+        let mut data: Vec<u8> = MDPM_UUID.to_vec();
+        data.extend_from_slice(b"MDPM");
+        data.push(4);
+        // UTC+1, no DST, 2010-05-12 14:33:10
+        data.extend_from_slice(&[0x18, 0x02, 0x20, 0x10, 0x05]);
+        data.extend_from_slice(&[0x19, 0x12, 0x14, 0x33, 0x10]);
+        data.extend_from_slice(&[0xe0, 0x01, 0x08, 0x00, 0x00]);
+        data.extend_from_slice(&[0xe4, b'H', b'D', b'R', 0x00]);
+
+        let mdpm = MTS::find_mdpm(&data).unwrap();
+        assert_eq!(mdpm.date_time_original.as_deref(), Some("2010:05:12 14:33:10+01:00"));
+        assert_eq!(mdpm.make.as_deref(), Some("Sony"));
+        assert_eq!(mdpm.model.as_deref(), Some("HDR"));
+        assert!(!mdpm.dst);
+    }
+
+    #[test]
+    fn test_unescape() {
+        assert_eq!(MTS::unescape(&[0x00, 0x00, 0x03, 0x01, 0x03]), vec![0x00, 0x00, 0x01, 0x03]);
+    }
+
+    #[test]
+    fn test_parse() {
+        let mut meta: Meta = Meta::new();
+        let extractor: MTS = MTS::file(TEST_VIDEO);
+        let result: Result<(), MetaError> = extractor.extract(&mut meta);
+        match result {
+            Ok(_) => {
+                println!("{:#?}", meta);
+            },
+            Err(e) => {
+                println!("test error {:#?}", e);
+            }
+        }
+    }
+}
@@ -0,0 +1,490 @@
+use std::{fs::File, io::{BufReader, Read, Seek, SeekFrom}};
+
+use chrono::DateTime;
+
+use crate::{
+    meta::{MetaAttribute, MetaSource, MetaType, MetaValue},
+    ole::{self, CompoundFile}, xml, zip,
+    Extractor, FromFile, Meta, MetaError};
+
+/// Property parts larger than this are not read.
+const MAX_PART: usize = 16 * 1024 * 1024;
+
+/// Seconds between the FILETIME epoch (1601) and the Unix epoch.
+const FILETIME_EPOCH: i64 = 11_644_473_600;
+
+/// OOXML `docProps/core.xml` elements and the tags they are stored under.
+const CORE_KEYS: [(&str, &str); 9] = [
+    ("dc:title", "title"),
+    ("dc:subject", "subject"),
+    ("dc:creator", "creator"),
+    ("cp:keywords", "keywords"),
+    ("dc:description", "description"),
+    ("cp:category", "category"),
+    ("cp:lastModifiedBy", "last_modified_by"),
+    ("cp:revision", "revision"),
+    ("cp:contentStatus", "status"),
+];
+
+/// OOXML `docProps/app.xml` elements holding text.
+const APP_TEXT_KEYS: [(&str, &str); 5] = [
+    ("Application", "application"),
+    ("AppVersion", "app_version"),
+    ("Company", "company"),
+    ("Manager", "manager"),
+    ("Template", "template"),
+];
+
+/// OOXML `docProps/app.xml` elements holding counts.
+const APP_COUNT_KEYS: [(&str, &str); 7] = [
+    ("Pages", "pages"),
+    ("Words", "words"),
+    ("Characters", "characters"),
+    ("Lines", "lines"),
+    ("Paragraphs", "paragraphs"),
+    ("Slides", "slides"),
+    ("TotalTime", "editing_minutes"),
+];
+
+/// ODF `meta.xml` elements holding text.
+const ODF_KEYS: [(&str, &str); 7] = [
+    ("dc:title", "title"),
+    ("dc:subject", "subject"),
+    ("dc:description", "description"),
+    ("meta:keyword", "keywords"),
+    ("meta:initial-creator", "creator"),
+    ("dc:creator", "last_modified_by"),
+    ("meta:generator", "application"),
+];
+
+/// ODF `meta:document-statistic` attributes.
+const ODF_COUNT_KEYS: [(&str, &str); 5] = [
+    ("meta:page-count", "pages"),
+    ("meta:word-count", "words"),
+    ("meta:character-count", "characters"),
+    ("meta:paragraph-count", "paragraphs"),
+    ("meta:table-count", "tables"),
+];
+
+/// Office document properties: OOXML (docx, xlsx, pptx) and ODF (odt, ods, odp)
+/// packages are read straight from the zip container, legacy Word, Excel and
+/// PowerPoint files from the OLE2 SummaryInformation stream.
+pub struct Office { path: String }
+
+impl FromFile for Office {
+    fn file(path: &str) -> Self {
+        Self { path: path.to_string() }
+    }
+}
+
+/// A W3CDTF / ISO 8601 date as an EXIF style local time and, when present,
+/// its UTC offset in minutes. Fractional seconds are dropped.
+fn parse_iso_date(value: &str) -> Option<(String, Option<i64>)> {
+    let value: &str = value.trim();
+    let numbers: Vec<u32> = value
+        .get(..19.min(value.len()))?
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse().ok())
+        .collect::<Option<Vec<u32>>>()?;
+    if numbers.len() < 3 || numbers[0] < 1000 {
+        return None;
+    }
+    let field = |i: usize| numbers.get(i).copied().unwrap_or(0);
+    let (month, day) = (field(1), field(2));
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || field(3) > 23 || field(4) > 59 || field(5) > 60 {
+        return None;
+    }
+
+    let zone: &str = value.get(19..).unwrap_or("").trim_start_matches(|c: char| c == '.' || c.is_ascii_digit());
+    let offset: Option<i64> = match zone.chars().next() {
+        Some('Z') => Some(0),
+        Some(sign @ ('+' | '-')) => {
+            let digits: String = zone[1..].chars().filter(char::is_ascii_digit).collect();
+            let hours: i64 = digits.get(0..2)?.parse().ok()?;
+            let minutes: i64 = digits.get(2..4).and_then(|m| m.parse().ok()).unwrap_or(0);
+            Some(if sign == '-' { -(hours * 60 + minutes) } else { hours * 60 + minutes })
+        },
+        _ => None
+    };
+
+    let local: String = format!("{:04}:{:02}:{:02} {:02}:{:02}:{:02}", field(0), month, day, field(3), field(4), field(5));
+    Some((local, offset))
+}
+
+/// A FILETIME (100ns ticks since 1601, UTC) as "YYYY:MM:DD HH:MM:SS".
+fn filetime(ticks: u64) -> Option<String> {
+    if ticks == 0 {
+        return None;
+    }
+    DateTime::from_timestamp((ticks / 10_000_000) as i64 - FILETIME_EPOCH, 0).map(|t| t.format("%Y:%m:%d %H:%M:%S").to_string())
+}
+
+/// A property from an OLE property set.
+enum Property {
+    Int(i64),
+    Text(String),
+    Time(u64),
+}
+
+/// Properties of the first section of a property set stream (MS-OLEPS).
+fn property_set(data: &[u8]) -> Vec<(u32, Property)> {
+    let u16_at = |at: usize| data.get(at..at + 2).map(|b| u16::from_le_bytes([b[0], b[1]]));
+    let u32_at = |at: usize| data.get(at..at + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+
+    if u16_at(0) != Some(0xFFFE) || u32_at(24).unwrap_or(0) == 0 {
+        return Vec::new();
+    }
+    let section: usize = u32_at(44).unwrap_or(0) as usize;
+    let count: usize = (u32_at(section + 4).unwrap_or(0) as usize).min(1024);
+
+    let entries: Vec<(u32, usize)> = (0..count)
+        .filter_map(|i| Some((u32_at(section + 8 + i * 8)?, section + u32_at(section + 12 + i * 8)? as usize)))
+        .collect();
+
+    // strings are in the codepage of property 1
+    let codepage: u16 = entries.iter()
+        .find(|(id, _)| *id == 1)
+        .and_then(|(_, at)| u16_at(at + 4))
+        .unwrap_or(1252);
+
+    let mut properties: Vec<(u32, Property)> = Vec::new();
+    for (id, at) in entries {
+        let value: Option<Property> = match u32_at(at).map(|t| t & 0xFFFF) {
+            // VT_I2
+            Some(2) => u16_at(at + 4).map(|v| Property::Int(v as i16 as i64)),
+            // VT_I4
+            Some(3) => u32_at(at + 4).map(|v| Property::Int(v as i32 as i64)),
+            // VT_LPSTR
+            Some(0x1E) => u32_at(at + 4).and_then(|len| data.get(at + 8..at + 8 + len as usize)).map(|bytes| {
+                Property::Text(match codepage {
+                    65001 => String::from_utf8_lossy(bytes).into_owned(),
+                    1200 => String::from_utf16_lossy(&bytes.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect::<Vec<u16>>()),
+                    // single byte codepages, Windows-1252 in practice
+                    _ => bytes.iter().map(|b| *b as char).collect(),
+                })
+            }),
+            // VT_LPWSTR, length in characters
+            Some(0x1F) => u32_at(at + 4).and_then(|len| data.get(at + 8..at + 8 + len as usize * 2)).map(|bytes| {
+                Property::Text(String::from_utf16_lossy(&bytes.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect::<Vec<u16>>()))
+            }),
+            // VT_FILETIME
+            Some(0x40) => data.get(at + 4..at + 12).map(|b| Property::Time(u64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]))),
+            _ => None
+        };
+        if let Some(value) = value {
+            properties.push((id, value));
+        }
+    }
+    properties
+}
+
+impl Office {
+    fn add_str(meta: &mut Meta, tag: &str, value: String) {
+        let value: &str = value.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+        if value.is_empty() {
+            return;
+        }
+        meta.add(MetaAttribute{
+            source: MetaSource::Office,
+            tag: tag.to_string(),
+            value: MetaType::String(MetaValue::from(value.to_string())),
+        });
+    }
+
+    fn add_u64(meta: &mut Meta, tag: &str, value: u64) {
+        meta.add(MetaAttribute{
+            source: MetaSource::Office,
+            tag: tag.to_string(),
+            value: MetaType::UInt64(MetaValue::from(value)),
+        });
+    }
+
+    fn add_date(meta: &mut Meta, tag: &str, value: &str) {
+        let (local, offset) = match parse_iso_date(value) {
+            Some(d) => d,
+            None => return
+        };
+        Self::add_str(meta, tag, local);
+        if let Some(offset) = offset {
+            meta.add(MetaAttribute{
+                source: MetaSource::Office,
+                tag: format!("{}.offset", tag),
+                value: MetaType::Int64(MetaValue::from(offset)),
+            });
+        }
+    }
+
+    /// FILETIMEs are UTC.
+    fn add_filetime(meta: &mut Meta, tag: &str, ticks: u64) {
+        if let Some(local) = filetime(ticks) {
+            Self::add_str(meta, tag, local);
+            meta.add(MetaAttribute{
+                source: MetaSource::Office,
+                tag: format!("{}.offset", tag),
+                value: MetaType::Int64(MetaValue::from(0i64)),
+            });
+        }
+    }
+
+    fn add_count(meta: &mut Meta, tag: &str, value: Option<String>) {
+        if let Some(count) = value.and_then(|v| v.trim().parse::<u64>().ok()) {
+            Self::add_u64(meta, tag, count);
+        }
+    }
+
+    fn read_part<R: Read + Seek>(reader: &mut R, entries: &[zip::Entry], name: &str) -> Option<String> {
+        let entry: &zip::Entry = zip::find(entries, name)?;
+        let data: Vec<u8> = zip::read(reader, entry, MAX_PART).ok()?;
+        Some(String::from_utf8_lossy(&data).into_owned())
+    }
+
+    fn from_package<R: Read + Seek>(reader: &mut R, meta: &mut Meta) -> Result<(), MetaError> {
+        let entries: Vec<zip::Entry> = zip::entries(reader)?;
+
+        // ODF declares itself in an uncompressed `mimetype` member
+        if let Some(mime) = Self::read_part(reader, &entries, "mimetype") {
+            Self::add_str(meta, "format", "odf".to_string());
+            Self::add_str(meta, "mime", mime);
+
+            if let Some(xml) = Self::read_part(reader, &entries, "meta.xml") {
+                for (element, tag) in ODF_KEYS {
+                    if let Some(value) = xml::text(&xml, element) {
+                        Self::add_str(meta, tag, value);
+                    }
+                }
+                if let Some(value) = xml::text(&xml, "meta:creation-date") {
+                    Self::add_date(meta, "created", &value);
+                }
+                if let Some(value) = xml::text(&xml, "dc:date") {
+                    Self::add_date(meta, "modified", &value);
+                }
+                Self::add_count(meta, "revision", xml::text(&xml, "meta:editing-cycles"));
+                for (attribute, tag) in ODF_COUNT_KEYS {
+                    Self::add_count(meta, tag, xml::attribute(&xml, attribute));
+                }
+            }
+            return Ok(());
+        }
+
+        if zip::find(&entries, "[Content_Types].xml").is_none() {
+            return Ok(());
+        }
+        Self::add_str(meta, "format", "ooxml".to_string());
+
+        if let Some(xml) = Self::read_part(reader, &entries, "docProps/core.xml") {
+            for (element, tag) in CORE_KEYS {
+                if let Some(value) = xml::text(&xml, element) {
+                    Self::add_str(meta, tag, value);
+                }
+            }
+            for (element, tag) in [("dcterms:created", "created"), ("dcterms:modified", "modified"), ("cp:lastPrinted", "printed")] {
+                if let Some(value) = xml::text(&xml, element) {
+                    Self::add_date(meta, tag, &value);
+                }
+            }
+        }
+
+        if let Some(xml) = Self::read_part(reader, &entries, "docProps/app.xml") {
+            for (element, tag) in APP_TEXT_KEYS {
+                if let Some(value) = xml::text(&xml, element) {
+                    Self::add_str(meta, tag, value);
+                }
+            }
+            for (element, tag) in APP_COUNT_KEYS {
+                Self::add_count(meta, tag, xml::text(&xml, element));
+            }
+        }
+        Ok(())
+    }
+
+    fn from_compound<R: Read + Seek>(reader: R, meta: &mut Meta) -> Result<(), MetaError> {
+        let mut file: CompoundFile<R> = CompoundFile::open(reader)?;
+
+        // password protected OOXML is an encrypted package inside a compound file
+        if file.find("EncryptedPackage").is_some() {
+            Self::add_str(meta, "format", "ooxml".to_string());
+            Self::add_u64(meta, "encrypted", 1);
+            return Ok(());
+        }
+        Self::add_str(meta, "format", "ole2".to_string());
+
+        let data: Vec<u8> = match file.read_stream("\u{5}SummaryInformation")? {
+            Some(d) => d,
+            None => return Ok(())
+        };
+
+        for (id, value) in property_set(&data) {
+            match (id, value) {
+                (2, Property::Text(v)) => Self::add_str(meta, "title", v),
+                (3, Property::Text(v)) => Self::add_str(meta, "subject", v),
+                (4, Property::Text(v)) => Self::add_str(meta, "creator", v),
+                (5, Property::Text(v)) => Self::add_str(meta, "keywords", v),
+                (6, Property::Text(v)) => Self::add_str(meta, "description", v),
+                (7, Property::Text(v)) => Self::add_str(meta, "template", v),
+                (8, Property::Text(v)) => Self::add_str(meta, "last_modified_by", v),
+                (9, Property::Text(v)) => Self::add_str(meta, "revision", v),
+                (18, Property::Text(v)) => Self::add_str(meta, "application", v),
+                (11, Property::Time(t)) => Self::add_filetime(meta, "printed", t),
+                (12, Property::Time(t)) => Self::add_filetime(meta, "created", t),
+                (13, Property::Time(t)) => Self::add_filetime(meta, "modified", t),
+                (14, Property::Int(v)) if v >= 0 => Self::add_u64(meta, "pages", v as u64),
+                (15, Property::Int(v)) if v >= 0 => Self::add_u64(meta, "words", v as u64),
+                (16, Property::Int(v)) if v >= 0 => Self::add_u64(meta, "characters", v as u64),
+                // bit 0 flags a password protected document
+                (19, Property::Int(v)) => Self::add_u64(meta, "encrypted", (v & 1) as u64),
+                _ => ()
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Extractor for Office {
+    fn name(&self) -> String {
+        return "OFFICE".to_string();
+    }
+
+    fn extract(&self, meta: &mut Meta) -> Result<(), MetaError> {
+        let mut reader: BufReader<File> = BufReader::new(File::open(&self.path)?);
+
+        if zip::is_zip(&mut reader)? {
+            return Self::from_package(&mut reader, meta);
+        }
+
+        let mut magic: [u8; 8] = [0; 8];
+        reader.seek(SeekFrom::Start(0))?;
+        if reader.read_exact(&mut magic).is_ok() && magic == ole::MAGIC {
+            return Self::from_compound(reader, meta);
+        }
+
+        // e.g. HTML or CSV saved with an .xls extension, nothing to report
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{filetime, parse_iso_date, Office};
+    use crate::{ole::test::compound_file, zip::test::archive, Extractor, FromFile, Meta, MetaSource, MetaType};
+
+    fn extract(data: &[u8], extension: &str) -> Meta {
+        let path = std::env::temp_dir().join(format!("informant_office_{}_{}.{}", std::process::id(), data.len(), extension));
+        std::fs::write(&path, data).unwrap();
+        let mut meta: Meta = Meta::new();
+        Office::file(path.to_str().unwrap()).extract(&mut meta).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        meta
+    }
+
+    fn value(meta: &Meta, tag: &str) -> String {
+        match meta.find_one(MetaSource::Office, tag).unwrap().value {
+            MetaType::String(v) => v.value,
+            MetaType::UInt64(v) => v.value.to_string(),
+            MetaType::Int64(v) => v.value.to_string(),
+            MetaType::Rational(v) => v.value.to_string(),
+            MetaType::Meta(_) => panic!("unexpected nested meta"),
+        }
+    }
+
+    #[test]
+    fn test_ooxml() {
+        let core: &[u8] = br#"<?xml version="1.0"?><cp:coreProperties xmlns:cp="x" xmlns:dc="y" xmlns:dcterms="z">
+            <dc:title>Budget 2021</dc:title><dc:creator>Ann Smith</dc:creator><cp:lastModifiedBy>Bob</cp:lastModifiedBy>
+            <cp:revision>7</cp:revision><dcterms:created xsi:type="dcterms:W3CDTF">2021-03-04T05:06:07Z</dcterms:created>
+            <dcterms:modified xsi:type="dcterms:W3CDTF">2021-03-05T10:00:00.123+01:00</dcterms:modified></cp:coreProperties>"#;
+        let app: &[u8] = br#"<Properties><Application>Microsoft Office Word</Application><Pages>3</Pages><Words>812</Words>
+            <Company>ACME</Company><AppVersion>16.0000</AppVersion></Properties>"#;
+
+        let meta: Meta = extract(&archive(&[("[Content_Types].xml", b"<Types/>"), ("docProps/core.xml", core), ("docProps/app.xml", app)], true), "docx");
+
+        assert_eq!(value(&meta, "format"), "ooxml");
+        assert_eq!(value(&meta, "title"), "Budget 2021");
+        assert_eq!(value(&meta, "creator"), "Ann Smith");
+        assert_eq!(value(&meta, "last_modified_by"), "Bob");
+        assert_eq!(value(&meta, "revision"), "7");
+        assert_eq!(value(&meta, "created"), "2021:03:04 05:06:07");
+        assert_eq!(value(&meta, "created.offset"), "0");
+        assert_eq!(value(&meta, "modified.offset"), "60");
+        assert_eq!(value(&meta, "application"), "Microsoft Office Word");
+        assert_eq!(value(&meta, "pages"), "3");
+        assert_eq!(value(&meta, "words"), "812");
+        assert_eq!(value(&meta, "company"), "ACME");
+    }
+
+    #[test]
+    fn test_odf() {
+        let xml: &[u8] = br#"<office:document-meta><office:meta><meta:generator>LibreOffice/7.3</meta:generator>
+            <dc:title>Minutes</dc:title><meta:initial-creator>Ann</meta:initial-creator>
+            <meta:creation-date>2022-01-02T03:04:05.123456789</meta:creation-date><meta:editing-cycles>4</meta:editing-cycles>
+            <meta:document-statistic meta:page-count="2" meta:word-count="345"/></office:meta></office:document-meta>"#;
+
+        let meta: Meta = extract(&archive(&[("mimetype", b"application/vnd.oasis.opendocument.text"), ("meta.xml", xml)], false), "odt");
+
+        assert_eq!(value(&meta, "format"), "odf");
+        assert_eq!(value(&meta, "mime"), "application/vnd.oasis.opendocument.text");
+        assert_eq!(value(&meta, "title"), "Minutes");
+        assert_eq!(value(&meta, "creator"), "Ann");
+        assert_eq!(value(&meta, "application"), "LibreOffice/7.3");
+        assert_eq!(value(&meta, "created"), "2022:01:02 03:04:05");
+        assert!(meta.find_one(MetaSource::Office, "created.offset").is_err());
+        assert_eq!(value(&meta, "revision"), "4");
+        assert_eq!(value(&meta, "pages"), "2");
+        assert_eq!(value(&meta, "words"), "345");
+    }
+
+    #[test]
+    fn test_summary_information() {
+        // property set with a single section: codepage, title, author, created, pages
+        let properties: [(u32, Vec<u8>); 5] = [
+            (1, [2u32.to_le_bytes().to_vec(), 1252u16.to_le_bytes().to_vec(), vec![0, 0]].concat()),
+            (2, [0x1Eu32.to_le_bytes().to_vec(), 8u32.to_le_bytes().to_vec(), b"Letter\0\0".to_vec()].concat()),
+            (4, [0x1Eu32.to_le_bytes().to_vec(), 8u32.to_le_bytes().to_vec(), b"J\xF6rg\0\0\0\0".to_vec()].concat()),
+            // 2003-04-05 06:07:08 UTC
+            (12, [0x40u32.to_le_bytes().to_vec(), ((1_049_522_828u64 + 11_644_473_600) * 10_000_000).to_le_bytes().to_vec()].concat()),
+            (14, [3u32.to_le_bytes().to_vec(), 5u32.to_le_bytes().to_vec()].concat()),
+        ];
+
+        let mut section: Vec<u8> = Vec::new();
+        let mut values: Vec<u8> = Vec::new();
+        let header_len: usize = 8 + properties.len() * 8;
+        for (id, value) in &properties {
+            section.extend_from_slice(&id.to_le_bytes());
+            section.extend_from_slice(&((header_len + values.len()) as u32).to_le_bytes());
+            values.extend_from_slice(value);
+        }
+        let section: Vec<u8> = [((header_len + values.len()) as u32).to_le_bytes().to_vec(),
+            (properties.len() as u32).to_le_bytes().to_vec(), section, values].concat();
+
+        let mut stream: Vec<u8> = vec![0xFE, 0xFF, 0, 0, 0, 0, 0, 0];
+        stream.extend_from_slice(&[0; 16]);
+        stream.extend_from_slice(&1u32.to_le_bytes());
+        stream.extend_from_slice(&[0; 16]);
+        stream.extend_from_slice(&48u32.to_le_bytes());
+        stream.extend_from_slice(&section);
+
+        let meta: Meta = extract(&compound_file("\u{5}SummaryInformation", &stream), "doc");
+
+        assert_eq!(value(&meta, "format"), "ole2");
+        assert_eq!(value(&meta, "title"), "Letter");
+        assert_eq!(value(&meta, "creator"), "Jörg");
+        assert_eq!(value(&meta, "created"), "2003:04:05 06:07:08");
+        assert_eq!(value(&meta, "pages"), "5");
+    }
+
+    #[test]
+    fn test_encrypted_package() {
+        let meta: Meta = extract(&compound_file("EncryptedPackage", &[0; 100]), "docx");
+        assert_eq!(value(&meta, "format"), "ooxml");
+        assert_eq!(value(&meta, "encrypted"), "1");
+    }
+
+    #[test]
+    fn test_dates() {
+        assert_eq!(filetime(0), None);
+        assert_eq!(parse_iso_date("2020-02-03T04:05:06-05:30"), Some(("2020:02:03 04:05:06".to_string(), Some(-330))));
+        assert_eq!(parse_iso_date("2020-02-03"), Some(("2020:02:03 00:00:00".to_string(), None)));
+        assert_eq!(parse_iso_date("not a date"), None);
+    }
+}
@@ -0,0 +1,268 @@
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::MetaError;
+
+pub const MAGIC: [u8; 8] = [0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1];
+
+const END_OF_CHAIN: u32 = 0xFFFF_FFFE;
+const MAX_SECTOR: u32 = 0xFFFF_FFFA;
+
+/// Streams larger than this are not read.
+const MAX_STREAM: u64 = 64 * 1024 * 1024;
+
+/// Directory entry types.
+const STREAM: u8 = 2;
+const ROOT: u8 = 5;
+
+/// An entry of the compound file directory.
+#[derive(Debug, Clone)]
+pub struct DirEntry {
+    pub name: String,
+    pub kind: u8,
+    pub start: u32,
+    pub size: u64,
+}
+
+/// OLE2 compound file (legacy .doc, .xls, .ppt, and encrypted OOXML packages).
+/// Streams are looked up by name; the red-black tree is not needed for that.
+pub struct CompoundFile<R> {
+    reader: R,
+    sector_size: u64,
+    mini_sector_size: u64,
+    mini_cutoff: u64,
+    fat: Vec<u32>,
+    mini_fat: Vec<u32>,
+    pub entries: Vec<DirEntry>,
+}
+
+fn read_u16(data: &[u8], at: usize) -> u16 {
+    data.get(at..at + 2).map_or(0, |b| u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn read_u32(data: &[u8], at: usize) -> u32 {
+    data.get(at..at + 4).map_or(0, |b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn to_u32s(data: &[u8]) -> impl Iterator<Item = u32> + '_ {
+    data.chunks_exact(4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+impl<R: Read + Seek> CompoundFile<R> {
+    pub fn open(mut reader: R) -> Result<Self, MetaError> {
+        let mut header: [u8; 512] = [0; 512];
+        reader.seek(SeekFrom::Start(0))?;
+        reader.read_exact(&mut header)?;
+        if header[0..8] != MAGIC {
+            return Err(MetaError::from("not an OLE2 compound file"));
+        }
+
+        let sector_shift: u16 = read_u16(&header, 30);
+        let mini_shift: u16 = read_u16(&header, 32);
+        if !(7..=16).contains(&sector_shift) || mini_shift >= sector_shift {
+            return Err(MetaError::from("invalid OLE2 sector size"));
+        }
+
+        let mut file: CompoundFile<R> = CompoundFile {
+            reader,
+            sector_size: 1 << sector_shift,
+            mini_sector_size: 1 << mini_shift,
+            mini_cutoff: read_u32(&header, 56) as u64,
+            fat: Vec::new(),
+            mini_fat: Vec::new(),
+            entries: Vec::new(),
+        };
+
+        // the first 109 FAT sectors are listed in the header, the rest in a DIFAT chain
+        let fat_sectors: usize = read_u32(&header, 44) as usize;
+        let mut difat: Vec<u32> = to_u32s(&header[76..512]).collect();
+        let mut next: u32 = read_u32(&header, 68);
+        let mut guard: usize = 0;
+        while next <= MAX_SECTOR && difat.len() < fat_sectors && guard < fat_sectors {
+            let sector: Vec<u8> = file.sector(next)?;
+            let (entries, pointer) = sector.split_at(sector.len() - 4);
+            difat.extend(to_u32s(entries));
+            next = read_u32(pointer, 0);
+            guard += 1;
+        }
+
+        for id in difat.into_iter().take(fat_sectors).filter(|id| *id <= MAX_SECTOR) {
+            let sector: Vec<u8> = file.sector(id)?;
+            file.fat.extend(to_u32s(&sector));
+        }
+
+        let mini_fat: Vec<u8> = file.chain(read_u32(&header, 60), u64::MAX)?;
+        file.mini_fat = to_u32s(&mini_fat).collect();
+
+        let directory: Vec<u8> = file.chain(read_u32(&header, 48), u64::MAX)?;
+        file.entries = directory
+            .chunks_exact(128)
+            .map(|e| {
+                let name_len: usize = (read_u16(e, 64) as usize).clamp(2, 64) - 2;
+                let name: Vec<u16> = e[..name_len].chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+                DirEntry {
+                    name: String::from_utf16_lossy(&name),
+                    kind: e[66],
+                    start: read_u32(e, 116),
+                    // the high half is garbage in version 3 files
+                    size: read_u32(e, 120) as u64,
+                }
+            })
+            .collect();
+
+        Ok(file)
+    }
+
+    fn sector(&mut self, id: u32) -> Result<Vec<u8>, MetaError> {
+        let mut data: Vec<u8> = vec![0; self.sector_size as usize];
+        self.reader.seek(SeekFrom::Start((id as u64 + 1) * self.sector_size))?;
+        self.reader.read_exact(&mut data)?;
+        Ok(data)
+    }
+
+    /// Follow a FAT chain, reading at most `limit` bytes.
+    fn chain(&mut self, start: u32, limit: u64) -> Result<Vec<u8>, MetaError> {
+        let mut data: Vec<u8> = Vec::new();
+        let mut id: u32 = start;
+        let mut hops: usize = 0;
+        while id <= MAX_SECTOR && (data.len() as u64) < limit {
+            // cycles in a damaged FAT would never end
+            if hops > self.fat.len() {
+                return Err(MetaError::from("OLE2 sector chain loops"));
+            }
+            data.extend(self.sector(id)?);
+            id = self.fat.get(id as usize).copied().unwrap_or(END_OF_CHAIN);
+            hops += 1;
+        }
+        Ok(data)
+    }
+
+    pub fn find(&self, name: &str) -> Option<&DirEntry> {
+        self.entries.iter().find(|e| e.kind == STREAM && e.name == name)
+    }
+
+    /// Content of the named stream, `None` when there is no such stream.
+    pub fn read_stream(&mut self, name: &str) -> Result<Option<Vec<u8>>, MetaError> {
+        let entry: DirEntry = match self.find(name) {
+            Some(e) => e.clone(),
+            None => return Ok(None)
+        };
+        if entry.size > MAX_STREAM {
+            return Err(MetaError::from(format!("OLE2 stream {} too large", name)));
+        }
+
+        let mut data: Vec<u8> = match entry.size < self.mini_cutoff {
+            true => self.mini_chain(entry.start, entry.size)?,
+            false => self.chain(entry.start, entry.size)?,
+        };
+        data.truncate(entry.size as usize);
+        Ok(Some(data))
+    }
+
+    /// Small streams live in 64 byte sectors inside the root entry's stream.
+    fn mini_chain(&mut self, start: u32, size: u64) -> Result<Vec<u8>, MetaError> {
+        let root: DirEntry = match self.entries.iter().find(|e| e.kind == ROOT) {
+            Some(r) => r.clone(),
+            None => return Err(MetaError::from("OLE2 root entry missing"))
+        };
+        let container: Vec<u8> = self.chain(root.start, root.size.min(MAX_STREAM))?;
+
+        let mut data: Vec<u8> = Vec::new();
+        let mut id: u32 = start;
+        let mut hops: usize = 0;
+        while id <= MAX_SECTOR && (data.len() as u64) < size {
+            if hops > self.mini_fat.len() {
+                return Err(MetaError::from("OLE2 mini sector chain loops"));
+            }
+            let at: usize = (id as u64 * self.mini_sector_size) as usize;
+            match container.get(at..at + self.mini_sector_size as usize) {
+                Some(sector) => data.extend_from_slice(sector),
+                None => break
+            }
+            id = self.mini_fat.get(id as usize).copied().unwrap_or(END_OF_CHAIN);
+            hops += 1;
+        }
+        Ok(data)
+    }
+}
+
+
+#[cfg(test)]
+pub mod test {
+    use std::io::Cursor;
+
+    use super::{CompoundFile, MAGIC};
+
+    const FREE: u32 = 0xFFFF_FFFF;
+    const END: u32 = 0xFFFF_FFFE;
+
+    fn dir_entry(name: &str, kind: u8, child: u32, start: u32, size: u32) -> Vec<u8> {
+        let mut entry: Vec<u8> = vec![0; 128];
+        let units: Vec<u16> = name.encode_utf16().chain(std::iter::once(0)).collect();
+        for (i, u) in units.iter().enumerate() {
+            entry[i * 2..i * 2 + 2].copy_from_slice(&u.to_le_bytes());
+        }
+        entry[64..66].copy_from_slice(&((units.len() * 2) as u16).to_le_bytes());
+        entry[66] = kind;
+        entry[68..72].copy_from_slice(&FREE.to_le_bytes());
+        entry[72..76].copy_from_slice(&FREE.to_le_bytes());
+        entry[76..80].copy_from_slice(&child.to_le_bytes());
+        entry[116..120].copy_from_slice(&start.to_le_bytes());
+        entry[120..124].copy_from_slice(&size.to_le_bytes());
+        entry
+    }
+
+    fn sector(words: &[u32]) -> Vec<u8> {
+        let mut data: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+        data.resize(512, 0xFF);
+        data
+    }
+
+    /// A version 3 compound file holding one small stream, stored in the mini stream.
+    pub fn compound_file(name: &str, content: &[u8]) -> Vec<u8> {
+        let minis: u32 = content.len().div_ceil(64) as u32;
+        let containers: u32 = content.len().div_ceil(512).max(1) as u32;
+
+        let mut header: Vec<u8> = vec![0; 512];
+        header[0..8].copy_from_slice(&MAGIC);
+        header[24..26].copy_from_slice(&0x3Eu16.to_le_bytes());
+        header[26..28].copy_from_slice(&3u16.to_le_bytes());
+        header[28..30].copy_from_slice(&0xFFFEu16.to_le_bytes());
+        header[30..32].copy_from_slice(&9u16.to_le_bytes());
+        header[32..34].copy_from_slice(&6u16.to_le_bytes());
+        header[44..48].copy_from_slice(&1u32.to_le_bytes());
+        header[48..52].copy_from_slice(&1u32.to_le_bytes());
+        header[56..60].copy_from_slice(&4096u32.to_le_bytes());
+        header[60..64].copy_from_slice(&2u32.to_le_bytes());
+        header[64..68].copy_from_slice(&1u32.to_le_bytes());
+        header[68..72].copy_from_slice(&END.to_le_bytes());
+        header[76..512].copy_from_slice(&sector(&[0])[..436]);
+
+        // sectors: 0 FAT, 1 directory, 2 mini FAT, 3.. mini stream container
+        let mut fat: Vec<u32> = vec![0xFFFF_FFFD, END, END];
+        fat.extend((4..3 + containers).chain(std::iter::once(END)));
+        let mini_fat: Vec<u32> = (1..minis).chain(std::iter::once(END)).collect();
+
+        let mut directory: Vec<u8> = dir_entry("Root Entry", 5, 1, 3, minis * 64);
+        directory.extend(dir_entry(name, 2, FREE, 0, content.len() as u32));
+        directory.resize(512, 0);
+
+        let mut container: Vec<u8> = content.to_vec();
+        container.resize(containers as usize * 512, 0);
+
+        [header, sector(&fat), directory, sector(&mini_fat), container].concat()
+    }
+
+    #[test]
+    fn test_read_mini_stream() {
+        let content: Vec<u8> = (0..300u32).map(|i| i as u8).collect();
+        let mut file = CompoundFile::open(Cursor::new(compound_file("\u{5}SummaryInformation", &content))).unwrap();
+
+        assert_eq!(file.read_stream("\u{5}SummaryInformation").unwrap(), Some(content));
+        assert_eq!(file.read_stream("WordDocument").unwrap(), None);
+    }
+
+    #[test]
+    fn test_not_a_compound_file() {
+        assert!(CompoundFile::open(Cursor::new(vec![0u8; 1024])).is_err());
+    }
+}
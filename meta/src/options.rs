@@ -0,0 +1,121 @@
+use crate::{FilenamePattern, Zone};
+
+/// Digest algorithms supported by the hash extractor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum HashAlgorithm {
+    Blake3,
+    Sha256,
+    Md5,
+}
+
+impl HashAlgorithm {
+    /// Meta tag the digest is emitted under.
+    /// BLAKE3 keeps the original `hash` tag since it is the primary key for assets.
+    pub fn tag(&self) -> String {
+        String::from(match self {
+            HashAlgorithm::Blake3 => "hash",
+            HashAlgorithm::Sha256 => "hash.sha256",
+            HashAlgorithm::Md5 => "hash.md5",
+        })
+    }
+}
+
+impl From<HashAlgorithm> for String {
+    fn from(value: HashAlgorithm) -> Self {
+        String::from(match value {
+            HashAlgorithm::Blake3 => "blake3",
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Md5 => "md5",
+        })
+    }
+}
+
+impl TryFrom<&str> for HashAlgorithm {
+    type Error = crate::MetaError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.to_lowercase().replace('-', "").as_str() {
+            "blake3" => Ok(HashAlgorithm::Blake3),
+            "sha256" => Ok(HashAlgorithm::Sha256),
+            "md5" => Ok(HashAlgorithm::Md5),
+            _ => Err(format!("unsupported hash algorithm: {}", value).into()),
+        }
+    }
+}
+
+/// Settings applied when collecting extractors for a file.
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// Digests computed by the hash extractor. BLAKE3 is always computed.
+    pub hashes: Vec<HashAlgorithm>,
+
+    /// Number of middle samples for the `hash.sample` pre-hash, disabled when `None`.
+    pub sample_chunks: Option<usize>,
+
+    /// Run the extractors on each member of zip and tar archives.
+    pub inspect_members: bool,
+
+    /// Zone assumed for capture times recorded without an offset.
+    pub default_zone: Option<Zone>,
+
+    /// File name patterns tried before the built-in ones.
+    pub filename_patterns: Vec<FilenamePattern>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            hashes: vec![HashAlgorithm::Blake3],
+            sample_chunks: None,
+            inspect_members: false,
+            default_zone: None,
+            filename_patterns: Vec::new(),
+        }
+    }
+}
+
+impl Options {
+    pub fn with_hash(mut self, algorithm: HashAlgorithm) -> Self {
+        if !self.hashes.contains(&algorithm) {
+            self.hashes.push(algorithm);
+        }
+        return self;
+    }
+
+    pub fn with_sample(mut self, chunks: usize) -> Self {
+        self.sample_chunks = Some(chunks);
+        return self;
+    }
+
+    pub fn with_members(mut self) -> Self {
+        self.inspect_members = true;
+        return self;
+    }
+
+    pub fn with_zone(mut self, zone: Zone) -> Self {
+        self.default_zone = Some(zone);
+        return self;
+    }
+
+    pub fn with_filename_pattern(mut self, pattern: FilenamePattern) -> Self {
+        self.filename_patterns.push(pattern);
+        return self;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{HashAlgorithm, Options};
+
+    #[test]
+    fn test_hash_algorithm_names() {
+        assert_eq!(HashAlgorithm::try_from("SHA-256").unwrap(), HashAlgorithm::Sha256);
+        assert_eq!(HashAlgorithm::try_from("md5").unwrap(), HashAlgorithm::Md5);
+        assert!(HashAlgorithm::try_from("crc32").is_err());
+
+        let options: Options = Options::default()
+            .with_hash(HashAlgorithm::Md5)
+            .with_hash(HashAlgorithm::Md5);
+        assert_eq!(options.hashes, vec![HashAlgorithm::Blake3, HashAlgorithm::Md5]);
+    }
+}
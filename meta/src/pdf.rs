@@ -0,0 +1,924 @@
+use std::{collections::{HashMap, HashSet}, fs, io::Read};
+
+use flate2::read::ZlibDecoder;
+use regex::bytes::Regex;
+
+use crate::{
+    meta::{MetaAttribute, MetaSource, MetaType, MetaValue},
+    xml, Extractor, FromFile, Meta, MetaError};
+
+/// Decoded streams larger than this are truncated.
+const MAX_STREAM: usize = 64 * 1024 * 1024;
+
+/// Nesting limit for arrays, dictionaries and reference chains.
+const MAX_DEPTH: usize = 32;
+
+/// Pages whose content streams are searched for text operators.
+const TEXT_PAGES: usize = 64;
+
+/// Document information dictionary entries and the tags they are stored under.
+const INFO_KEYS: [(&str, &str); 6] = [
+    ("Title", "info.title"),
+    ("Author", "info.author"),
+    ("Subject", "info.subject"),
+    ("Keywords", "info.keywords"),
+    ("Creator", "info.creator"),
+    ("Producer", "info.producer"),
+];
+
+/// XMP properties and the tags they are stored under.
+const XMP_KEYS: [(&str, &str); 8] = [
+    ("dc:title", "xmp.title"),
+    ("dc:creator", "xmp.creator"),
+    ("dc:description", "xmp.description"),
+    ("xmp:CreateDate", "xmp.create_date"),
+    ("xmp:ModifyDate", "xmp.modify_date"),
+    ("xmp:CreatorTool", "xmp.creator_tool"),
+    ("pdf:Producer", "xmp.producer"),
+    ("pdf:Keywords", "xmp.keywords"),
+];
+
+/// PDF document metadata: the Info dictionary, embedded XMP, page count and
+/// size of the first page, version, encryption, and whether any text is drawn.
+///
+/// Objects are located by scanning the file rather than trusting the xref
+/// table, so damaged and incrementally updated files still yield what they can.
+pub struct PDF { path: String }
+
+impl FromFile for PDF {
+    fn file(path: &str) -> Self {
+        Self { path: path.to_string() }
+    }
+}
+
+type Dict = HashMap<String, Object>;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Object {
+    Null,
+    Bool(bool),
+    Number(f64),
+    Name(String),
+    String(Vec<u8>),
+    Array(Vec<Object>),
+    Dict(Dict),
+    Ref(u32),
+    Stream(Dict, Vec<u8>),
+}
+
+impl Object {
+    fn as_dict(&self) -> Option<&Dict> {
+        match self {
+            Object::Dict(d) | Object::Stream(d, _) => Some(d),
+            _ => None
+        }
+    }
+
+    fn as_number(&self) -> Option<f64> {
+        match self {
+            Object::Number(n) => Some(*n),
+            _ => None
+        }
+    }
+
+    fn as_name(&self) -> Option<&str> {
+        match self {
+            Object::Name(n) => Some(n),
+            _ => None
+        }
+    }
+}
+
+fn is_whitespace(b: u8) -> bool {
+    matches!(b, 0 | 9 | 10 | 12 | 13 | 32)
+}
+
+fn is_delimiter(b: u8) -> bool {
+    matches!(b, b'(' | b')' | b'<' | b'>' | b'[' | b']' | b'{' | b'}' | b'/' | b'%')
+}
+
+fn is_regular(b: u8) -> bool {
+    !is_whitespace(b) && !is_delimiter(b)
+}
+
+/// Reads PDF objects from a byte slice.
+struct Lexer<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(data: &'a [u8], pos: usize) -> Self {
+        Self { data, pos }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.data.get(self.pos).copied()
+    }
+
+    fn starts_with(&self, s: &[u8]) -> bool {
+        self.data[self.pos.min(self.data.len())..].starts_with(s)
+    }
+
+    fn skip_space(&mut self) {
+        while let Some(b) = self.peek() {
+            if is_whitespace(b) {
+                self.pos += 1;
+            }
+            else if b == b'%' {
+                while !matches!(self.peek(), None | Some(b'\n') | Some(b'\r')) {
+                    self.pos += 1;
+                }
+            }
+            else {
+                break;
+            }
+        }
+    }
+
+    fn token(&mut self) -> &'a [u8] {
+        let start: usize = self.pos;
+        while self.peek().is_some_and(is_regular) {
+            self.pos += 1;
+        }
+        &self.data[start..self.pos]
+    }
+
+    fn object(&mut self, depth: usize) -> Option<Object> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+        self.skip_space();
+
+        match self.peek()? {
+            b'<' if self.starts_with(b"<<") => {
+                self.pos += 2;
+                self.dict(depth).map(Object::Dict)
+            },
+            b'<' => self.hex_string(),
+            b'(' => self.literal_string(),
+            b'[' => {
+                self.pos += 1;
+                let mut items: Vec<Object> = Vec::new();
+                loop {
+                    self.skip_space();
+                    if self.peek()? == b']' {
+                        self.pos += 1;
+                        return Some(Object::Array(items));
+                    }
+                    items.push(self.object(depth + 1)?);
+                }
+            },
+            b'/' => {
+                self.pos += 1;
+                Some(Object::Name(decode_name(self.token())))
+            },
+            b'+' | b'-' | b'.' | b'0'..=b'9' => self.number(),
+            _ => match self.token() {
+                b"true" => Some(Object::Bool(true)),
+                b"false" => Some(Object::Bool(false)),
+                b"null" => Some(Object::Null),
+                _ => None
+            }
+        }
+    }
+
+    fn dict(&mut self, depth: usize) -> Option<Dict> {
+        let mut dict: Dict = HashMap::new();
+        loop {
+            self.skip_space();
+            if self.starts_with(b">>") {
+                self.pos += 2;
+                return Some(dict);
+            }
+            let key: String = match self.object(depth + 1)? {
+                Object::Name(n) => n,
+                _ => return None
+            };
+            let value: Object = self.object(depth + 1)?;
+            dict.insert(key, value);
+        }
+    }
+
+    /// A number, or an indirect reference `num gen R`.
+    fn number(&mut self) -> Option<Object> {
+        let token: &[u8] = self.token();
+        let value: f64 = std::str::from_utf8(token).ok()?.parse().ok()?;
+
+        if token.iter().all(u8::is_ascii_digit) {
+            let save: usize = self.pos;
+            self.skip_space();
+            let generation: &[u8] = self.token();
+            self.skip_space();
+            if !generation.is_empty() && generation.iter().all(u8::is_ascii_digit) && self.peek() == Some(b'R') {
+                self.pos += 1;
+                if self.peek().map_or(true, |b| !is_regular(b)) {
+                    return Some(Object::Ref(value as u32));
+                }
+            }
+            self.pos = save;
+        }
+        Some(Object::Number(value))
+    }
+
+    fn hex_string(&mut self) -> Option<Object> {
+        self.pos += 1;
+        let mut digits: Vec<u8> = Vec::new();
+        loop {
+            let b: u8 = self.peek()?;
+            self.pos += 1;
+            match b {
+                b'>' => break,
+                _ if b.is_ascii_hexdigit() => digits.push((b as char).to_digit(16)? as u8),
+                _ => ()
+            }
+        }
+        if digits.len() % 2 == 1 {
+            digits.push(0);
+        }
+        Some(Object::String(digits.chunks(2).map(|p| (p[0] << 4) | p[1]).collect()))
+    }
+
+    fn literal_string(&mut self) -> Option<Object> {
+        self.pos += 1;
+        let mut value: Vec<u8> = Vec::new();
+        let mut nesting: usize = 0;
+        loop {
+            let b: u8 = self.peek()?;
+            self.pos += 1;
+            match b {
+                b'(' => {
+                    nesting += 1;
+                    value.push(b);
+                },
+                b')' if nesting == 0 => break,
+                b')' => {
+                    nesting -= 1;
+                    value.push(b);
+                },
+                b'\\' => {
+                    let e: u8 = self.peek()?;
+                    self.pos += 1;
+                    match e {
+                        b'n' => value.push(b'\n'),
+                        b'r' => value.push(b'\r'),
+                        b't' => value.push(b'\t'),
+                        b'b' => value.push(8),
+                        b'f' => value.push(12),
+                        b'0'..=b'7' => {
+                            let mut code: u32 = (e - b'0') as u32;
+                            for _ in 0..2 {
+                                match self.peek() {
+                                    Some(d @ b'0'..=b'7') => {
+                                        code = code * 8 + (d - b'0') as u32;
+                                        self.pos += 1;
+                                    },
+                                    _ => break
+                                }
+                            }
+                            value.push(code as u8);
+                        },
+                        // line continuation
+                        b'\r' => {
+                            if self.peek() == Some(b'\n') {
+                                self.pos += 1;
+                            }
+                        },
+                        b'\n' => (),
+                        _ => value.push(e),
+                    }
+                },
+                _ => value.push(b),
+            }
+        }
+        Some(Object::String(value))
+    }
+
+    /// An indirect object body, including stream data when present.
+    fn indirect(&mut self) -> Option<Object> {
+        let object: Object = self.object(0)?;
+        let dict: Dict = match object {
+            Object::Dict(d) => d,
+            other => return Some(other)
+        };
+
+        self.skip_space();
+        if !self.starts_with(b"stream") {
+            return Some(Object::Dict(dict));
+        }
+        self.pos += 6;
+        if self.peek() == Some(b'\r') {
+            self.pos += 1;
+        }
+        if self.peek() == Some(b'\n') {
+            self.pos += 1;
+        }
+        let start: usize = self.pos;
+
+        // trust /Length only when it lands on endstream
+        let direct: Option<usize> = dict.get("Length")
+            .and_then(Object::as_number)
+            .and_then(|n| start.checked_add(n as usize))
+            .filter(|end| *end <= self.data.len() && {
+                let mut lexer: Lexer = Lexer::new(self.data, *end);
+                lexer.skip_space();
+                lexer.starts_with(b"endstream")
+            });
+
+        let end: usize = match direct {
+            Some(end) => end,
+            None => {
+                let found: usize = find(self.data, b"endstream", start)?;
+                let mut end: usize = found;
+                while end > start && matches!(self.data[end - 1], b'\r' | b'\n') {
+                    end -= 1;
+                }
+                end
+            }
+        };
+
+        self.pos = end;
+        self.skip_space();
+        self.pos += b"endstream".len();
+        Some(Object::Stream(dict, self.data[start..end].to_vec()))
+    }
+}
+
+fn find(data: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    data.get(from..)?
+        .windows(needle.len())
+        .position(|w| w == needle)
+        .map(|p| p + from)
+}
+
+/// Names may escape any byte as `#xx`.
+fn decode_name(token: &[u8]) -> String {
+    let mut name: Vec<u8> = Vec::with_capacity(token.len());
+    let mut i: usize = 0;
+    while i < token.len() {
+        if token[i] == b'#' && i + 2 < token.len() {
+            if let Some(b) = std::str::from_utf8(&token[i + 1..i + 3])
+                .ok()
+                .and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                name.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        name.push(token[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&name).into_owned()
+}
+
+/// Text strings are UTF-16BE or UTF-8 with a byte order mark, otherwise PDFDocEncoding,
+/// which matches Latin-1 for everything that shows up in practice.
+fn decode_text(bytes: &[u8]) -> String {
+    if bytes.starts_with(&[0xFE, 0xFF]) {
+        let units: Vec<u16> = bytes[2..]
+            .chunks_exact(2)
+            .map(|c| u16::from_be_bytes([c[0], c[1]]))
+            .collect();
+        return String::from_utf16_lossy(&units);
+    }
+    if bytes.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        return String::from_utf8_lossy(&bytes[3..]).into_owned();
+    }
+    bytes.iter().map(|b| *b as char).collect()
+}
+
+/// A PDF date `D:YYYYMMDDHHmmSSOHH'mm'` as an EXIF style local time and,
+/// when the producer recorded one, its UTC offset in minutes.
+fn parse_date(value: &str) -> Option<(String, Option<i64>)> {
+    let value: &str = value.trim();
+    let value: &str = value.strip_prefix("D:").unwrap_or(value);
+    let digits: usize = value.bytes().take_while(u8::is_ascii_digit).count();
+    if digits < 4 {
+        return None;
+    }
+
+    let field = |start: usize, default: u32| -> u32 {
+        value.get(start..start + 2)
+            .filter(|_| start + 2 <= digits)
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(default)
+    };
+    let year: u32 = value[0..4].parse().ok()?;
+    let (month, day) = (field(4, 1), field(6, 1));
+    let (hour, minute, second) = (field(8, 0), field(10, 0), field(12, 0));
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    let rest: &str = &value[digits..];
+    let offset: Option<i64> = match rest.chars().next() {
+        Some('Z') => Some(0),
+        Some(sign @ ('+' | '-')) => {
+            let numbers: Vec<i64> = rest[1..]
+                .split('\'')
+                .filter_map(|s| s.trim().parse().ok())
+                .collect();
+            let minutes: i64 = numbers.first()? * 60 + numbers.get(1).copied().unwrap_or(0);
+            Some(if sign == '-' { -minutes } else { minutes })
+        },
+        _ => None
+    };
+
+    let local: String = format!("{:04}:{:02}:{:02} {:02}:{:02}:{:02}", year, month, day, hour, minute, second);
+    Some((local, offset))
+}
+
+/// Whether a content stream shows text, i.e. has a text object with a show operator.
+fn shows_text(content: &[u8]) -> bool {
+    let mut lexer: Lexer = Lexer::new(content, 0);
+    let mut in_text: bool = false;
+
+    while lexer.pos < content.len() {
+        lexer.skip_space();
+        match lexer.peek() {
+            None => break,
+            Some(b'(') => { lexer.literal_string(); },
+            Some(b'<') if !lexer.starts_with(b"<<") => { lexer.hex_string(); },
+            Some(b) if is_regular(b) => match lexer.token() {
+                b"BT" => in_text = true,
+                b"ET" => in_text = false,
+                b"Tj" | b"TJ" | b"'" | b"\"" if in_text => return true,
+                _ => ()
+            },
+            Some(_) => lexer.pos += 1,
+        }
+    }
+    false
+}
+
+/// Objects of a PDF file, gathered in file order so later revisions win.
+struct Document {
+    version: Option<String>,
+    objects: HashMap<u32, Object>,
+    trailer: Dict,
+}
+
+impl Document {
+    fn parse(data: &[u8]) -> Result<Self, MetaError> {
+        let header: &[u8] = &data[..data.len().min(1024)];
+        let start: usize = match find(header, b"%PDF-", 0) {
+            Some(s) => s,
+            None => return Err(MetaError::from("not a PDF file"))
+        };
+        let version: Option<String> = Some(Lexer::new(data, start + 5).token())
+            .filter(|v| !v.is_empty())
+            .map(|v| String::from_utf8_lossy(v).into_owned());
+
+        let mut doc: Document = Document { version, objects: HashMap::new(), trailer: HashMap::new() };
+        let re: Regex = Regex::new(r"(?-u)(\d+)\s+\d+\s+obj\b|trailer").unwrap();
+
+        let mut pos: usize = start;
+        while let Some(caps) = re.captures(&data[pos..]) {
+            let m = caps.get(0).unwrap();
+            let mut lexer: Lexer = Lexer::new(data, pos + m.end());
+
+            match caps.get(1) {
+                Some(number) => {
+                    let number: u32 = match std::str::from_utf8(number.as_bytes()).ok().and_then(|n| n.parse().ok()) {
+                        Some(n) => n,
+                        None => {
+                            pos += m.end();
+                            continue;
+                        }
+                    };
+                    if let Some(object) = lexer.indirect() {
+                        doc.insert(number, object);
+                    }
+                },
+                None => {
+                    if let Some(Object::Dict(dict)) = lexer.object(0) {
+                        doc.trailer.extend(dict);
+                    }
+                }
+            }
+            // stream data is skipped, so binary content cannot pose as objects
+            pos = lexer.pos.max(pos + m.end());
+        }
+        Ok(doc)
+    }
+
+    fn insert(&mut self, number: u32, object: Object) {
+        if let Object::Stream(dict, _) = &object {
+            match dict.get("Type").and_then(Object::as_name) {
+                // cross-reference streams double as the trailer
+                Some("XRef") => {
+                    for key in ["Root", "Info", "Encrypt", "ID"] {
+                        if let Some(value) = dict.get(key) {
+                            self.trailer.insert(key.to_string(), value.clone());
+                        }
+                    }
+                },
+                Some("ObjStm") => self.unpack(&object),
+                _ => ()
+            }
+        }
+        self.objects.insert(number, object);
+    }
+
+    /// Objects compressed into an object stream.
+    fn unpack(&mut self, stream: &Object) {
+        let (dict, data) = match (stream.as_dict(), self.decode(stream)) {
+            (Some(d), Some(data)) => (d, data),
+            _ => return
+        };
+        let count: usize = dict.get("N").and_then(Object::as_number).unwrap_or(0.0) as usize;
+        let first: usize = dict.get("First").and_then(Object::as_number).unwrap_or(0.0) as usize;
+
+        let mut lexer: Lexer = Lexer::new(&data, 0);
+        let mut entries: Vec<(u32, usize)> = Vec::new();
+        for _ in 0..count {
+            match (lexer.object(0), lexer.object(0)) {
+                (Some(Object::Number(n)), Some(Object::Number(o))) => entries.push((n as u32, o as usize)),
+                _ => break
+            }
+        }
+        for (number, offset) in entries {
+            if let Some(object) = Lexer::new(&data, first + offset).object(0) {
+                self.objects.insert(number, object);
+            }
+        }
+    }
+
+    fn resolve<'a>(&'a self, object: &'a Object) -> &'a Object {
+        let mut object: &Object = object;
+        for _ in 0..MAX_DEPTH {
+            match object {
+                Object::Ref(n) => object = self.objects.get(n).unwrap_or(&Object::Null),
+                _ => break
+            }
+        }
+        object
+    }
+
+    fn get<'a>(&'a self, dict: &'a Dict, key: &str) -> Option<&'a Object> {
+        dict.get(key).map(|o| self.resolve(o))
+    }
+
+    fn encrypted(&self) -> bool {
+        self.trailer.contains_key("Encrypt")
+    }
+
+    fn catalog(&self) -> Option<&Dict> {
+        self.get(&self.trailer, "Root").and_then(Object::as_dict)
+    }
+
+    /// Stream data with its filters applied. Only Flate is supported.
+    fn decode(&self, stream: &Object) -> Option<Vec<u8>> {
+        let (dict, raw) = match stream {
+            Object::Stream(d, r) => (d, r),
+            _ => return None
+        };
+        let filters: Vec<&str> = match self.get(dict, "Filter") {
+            None | Some(Object::Null) => Vec::new(),
+            Some(Object::Name(n)) => vec![n.as_str()],
+            Some(Object::Array(a)) => a.iter().filter_map(|f| self.resolve(f).as_name()).collect(),
+            _ => return None
+        };
+
+        let mut data: Vec<u8> = raw.clone();
+        for filter in filters {
+            match filter {
+                "FlateDecode" | "Fl" => {
+                    let mut decoded: Vec<u8> = Vec::new();
+                    // damaged streams still give up what decoded before the error
+                    let _ = ZlibDecoder::new(&data[..]).take(MAX_STREAM as u64).read_to_end(&mut decoded);
+                    if decoded.is_empty() {
+                        return None;
+                    }
+                    data = decoded;
+                },
+                _ => return None
+            }
+        }
+        Some(data)
+    }
+
+    /// Leaf pages in order, with the inherited MediaBox and Rotate of each.
+    /// A node reached twice, e.g. through cyclic /Kids, is visited once.
+    fn pages(&self, limit: usize) -> Vec<(&Dict, Option<&Object>, Option<f64>)> {
+        let root: &Dict = match self.catalog().and_then(|c| self.get(c, "Pages")).and_then(Object::as_dict) {
+            Some(r) => r,
+            None => return Vec::new()
+        };
+
+        let mut pages: Vec<(&Dict, Option<&Object>, Option<f64>)> = Vec::new();
+        let mut stack: Vec<(&Dict, Option<&Object>, Option<f64>, usize)> = vec![(root, None, None, 0)];
+        let mut visited: HashSet<*const Dict> = HashSet::new();
+        while let Some((node, media_box, rotate, depth)) = stack.pop() {
+            if pages.len() >= limit || depth > MAX_DEPTH {
+                break;
+            }
+            if !visited.insert(node) {
+                continue;
+            }
+            let media_box: Option<&Object> = self.get(node, "MediaBox").or(media_box);
+            let rotate: Option<f64> = self.get(node, "Rotate").and_then(Object::as_number).or(rotate);
+
+            match self.get(node, "Kids") {
+                Some(Object::Array(kids)) => {
+                    for kid in kids.iter().rev() {
+                        if let Some(kid) = self.resolve(kid).as_dict() {
+                            stack.push((kid, media_box, rotate, depth + 1));
+                        }
+                    }
+                },
+                _ => pages.push((node, media_box, rotate)),
+            }
+        }
+        pages
+    }
+
+    fn has_text(&self, page: &Dict) -> bool {
+        let streams: Vec<&Object> = match self.get(page, "Contents") {
+            Some(Object::Array(items)) => items.iter().map(|i| self.resolve(i)).collect(),
+            Some(stream) => vec![stream],
+            None => Vec::new()
+        };
+        streams
+            .into_iter()
+            .filter_map(|s| self.decode(s))
+            .any(|content| shows_text(&content))
+    }
+}
+
+impl PDF {
+    fn add_str(meta: &mut Meta, tag: &str, value: String) {
+        if value.trim().is_empty() {
+            return;
+        }
+        meta.add(MetaAttribute{
+            source: MetaSource::PDF,
+            tag: tag.to_string(),
+            value: MetaType::String(MetaValue::from(value)),
+        });
+    }
+
+    fn add_u64(meta: &mut Meta, tag: &str, value: u64) {
+        meta.add(MetaAttribute{
+            source: MetaSource::PDF,
+            tag: tag.to_string(),
+            value: MetaType::UInt64(MetaValue::from(value)),
+        });
+    }
+
+    fn add_f64(meta: &mut Meta, tag: &str, value: f64) {
+        meta.add(MetaAttribute{
+            source: MetaSource::PDF,
+            tag: tag.to_string(),
+            value: MetaType::Rational(MetaValue::from(value)),
+        });
+    }
+
+    fn add_info(doc: &Document, meta: &mut Meta) {
+        let info: &Dict = match doc.get(&doc.trailer, "Info").and_then(Object::as_dict) {
+            Some(i) => i,
+            None => return
+        };
+
+        for (key, tag) in INFO_KEYS {
+            if let Some(Object::String(value)) = doc.get(info, key) {
+                Self::add_str(meta, tag, decode_text(value));
+            }
+        }
+
+        for (key, tag) in [("CreationDate", "info.creation_date"), ("ModDate", "info.mod_date")] {
+            let value: String = match doc.get(info, key) {
+                Some(Object::String(v)) => decode_text(v),
+                _ => continue
+            };
+            if let Some((local, offset)) = parse_date(&value) {
+                Self::add_str(meta, tag, local);
+                if let Some(offset) = offset {
+                    meta.add(MetaAttribute{
+                        source: MetaSource::PDF,
+                        tag: format!("{}.offset", tag),
+                        value: MetaType::Int64(MetaValue::from(offset)),
+                    });
+                }
+            }
+        }
+    }
+
+    fn add_xmp(doc: &Document, catalog: &Dict, meta: &mut Meta) {
+        let xml: String = match doc.get(catalog, "Metadata").and_then(|m| doc.decode(m)) {
+            Some(data) => String::from_utf8_lossy(&data).into_owned(),
+            None => return
+        };
+        for (property, tag) in XMP_KEYS {
+            if let Some(value) = xml::attribute(&xml, property).or_else(|| xml::text(&xml, property)) {
+                Self::add_str(meta, tag, value);
+            }
+        }
+    }
+
+    fn add_pages(doc: &Document, catalog: &Dict, meta: &mut Meta) {
+        if let Some(count) = doc.get(catalog, "Pages")
+            .and_then(Object::as_dict)
+            .and_then(|p| doc.get(p, "Count"))
+            .and_then(Object::as_number) {
+            Self::add_u64(meta, "pages", count.max(0.0) as u64);
+        }
+
+        let pages = doc.pages(TEXT_PAGES);
+        if let Some((_, Some(Object::Array(media_box)), rotate)) = pages.first() {
+            let corners: Vec<f64> = media_box.iter().filter_map(|v| doc.resolve(v).as_number()).collect();
+            if corners.len() == 4 {
+                let (mut width, mut height) = ((corners[2] - corners[0]).abs(), (corners[3] - corners[1]).abs());
+                if rotate.is_some_and(|r| (r as i64).rem_euclid(180) == 90) {
+                    std::mem::swap(&mut width, &mut height);
+                }
+                Self::add_f64(meta, "page_width", width);
+                Self::add_f64(meta, "page_height", height);
+            }
+        }
+
+        // content streams of encrypted files cannot be read
+        if !doc.encrypted() && !pages.is_empty() {
+            let text: bool = pages.iter().any(|(page, _, _)| doc.has_text(page));
+            Self::add_u64(meta, "has_text", text as u64);
+        }
+    }
+}
+
+impl Extractor for PDF {
+    fn name(&self) -> String {
+        return "PDF".to_string();
+    }
+
+    fn extract(&self, meta: &mut Meta) -> Result<(), MetaError> {
+        let data: Vec<u8> = fs::read(&self.path)?;
+        let doc: Document = Document::parse(&data)?;
+
+        // the catalog may override the header version after an update
+        let version: Option<String> = doc.catalog()
+            .and_then(|c| doc.get(c, "Version"))
+            .and_then(Object::as_name)
+            .map(str::to_string)
+            .or(doc.version.clone());
+        if let Some(version) = version {
+            Self::add_str(meta, "version", version);
+        }
+
+        Self::add_u64(meta, "encrypted", doc.encrypted() as u64);
+
+        // strings of encrypted files are ciphertext
+        if !doc.encrypted() {
+            Self::add_info(&doc, meta);
+        }
+
+        if let Some(catalog) = doc.catalog() {
+            if !doc.encrypted() {
+                Self::add_xmp(&doc, catalog, meta);
+            }
+            Self::add_pages(&doc, catalog, meta);
+        }
+
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::io::Write;
+
+    use flate2::{write::ZlibEncoder, Compression};
+
+    use super::{decode_text, parse_date, shows_text, Document, Object, PDF};
+    use crate::{Extractor, FromFile, Meta, MetaSource, MetaType};
+
+    fn deflate(data: &[u8]) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn stream(number: u32, dict: &str, data: &[u8]) -> Vec<u8> {
+        let mut object: Vec<u8> = format!("{} 0 obj\n<< {} /Length {} >>\nstream\n", number, dict, data.len()).into_bytes();
+        object.extend_from_slice(data);
+        object.extend_from_slice(b"\nendstream\nendobj\n");
+        object
+    }
+
+    fn sample(text: &[u8]) -> Vec<u8> {
+        let xmp: &[u8] = br#"<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF><rdf:Description xmp:CreateDate="2019-05-04T10:20:30+02:00">
+            <dc:title><rdf:Alt><rdf:li xml:lang="x-default">Tax return 2018</rdf:li></rdf:Alt></dc:title>
+            </rdf:Description></rdf:RDF></x:xmpmeta>"#;
+
+        let mut pdf: Vec<u8> = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+        pdf.extend_from_slice(b"1 0 obj\n<< /Type /Catalog /Pages 2 0 R /Metadata 5 0 R >>\nendobj\n");
+        pdf.extend_from_slice(b"2 0 obj\n<< /Type /Pages /Kids [3 0 R] /Count 1 /MediaBox [0 0 595.28 841.89] >>\nendobj\n");
+        pdf.extend_from_slice(b"3 0 obj\n<< /Type /Page /Parent 2 0 R /Contents 4 0 R >>\nendobj\n");
+        pdf.extend(stream(4, "/Filter /FlateDecode", &deflate(text)));
+        pdf.extend(stream(5, "/Type /Metadata /Subtype /XML", xmp));
+        pdf.extend_from_slice(b"6 0 obj\n<< /Title <FEFF005300630061006E> /Author (Jane \\(J\\) Doe) /CreationDate (D:20190504102030+02'00') >>\nendobj\n");
+        pdf.extend_from_slice(b"trailer\n<< /Root 1 0 R /Info 6 0 R /Size 7 >>\n%%EOF\n");
+        pdf
+    }
+
+    fn extract(data: &[u8]) -> Meta {
+        let path = std::env::temp_dir().join(format!("informant_pdf_{}_{}.pdf", std::process::id(), data.len()));
+        std::fs::write(&path, data).unwrap();
+        let mut meta: Meta = Meta::new();
+        PDF::file(path.to_str().unwrap()).extract(&mut meta).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        meta
+    }
+
+    fn value(meta: &Meta, tag: &str) -> String {
+        match meta.find_one(MetaSource::PDF, tag).unwrap().value {
+            MetaType::String(v) => v.value,
+            MetaType::UInt64(v) => v.value.to_string(),
+            MetaType::Int64(v) => v.value.to_string(),
+            MetaType::Rational(v) => v.value.to_string(),
+            MetaType::Meta(_) => panic!("unexpected nested meta"),
+        }
+    }
+
+    #[test]
+    fn test_document_metadata() {
+        let meta: Meta = extract(&sample(b"BT /F1 12 Tf 72 712 Td (Hello) Tj ET"));
+
+        assert_eq!(value(&meta, "version"), "1.4");
+        assert_eq!(value(&meta, "encrypted"), "0");
+        assert_eq!(value(&meta, "pages"), "1");
+        assert_eq!(value(&meta, "page_width"), "595.28");
+        assert_eq!(value(&meta, "page_height"), "841.89");
+        assert_eq!(value(&meta, "has_text"), "1");
+        assert_eq!(value(&meta, "info.title"), "Scan");
+        assert_eq!(value(&meta, "info.author"), "Jane (J) Doe");
+        assert_eq!(value(&meta, "info.creation_date"), "2019:05:04 10:20:30");
+        assert_eq!(value(&meta, "info.creation_date.offset"), "120");
+        assert_eq!(value(&meta, "xmp.create_date"), "2019-05-04T10:20:30+02:00");
+        assert_eq!(value(&meta, "xmp.title"), "Tax return 2018");
+    }
+
+    #[test]
+    fn test_scanned_document_has_no_text() {
+        let meta: Meta = extract(&sample(b"q 595 0 0 842 0 0 cm /Im0 Do Q"));
+        assert_eq!(value(&meta, "has_text"), "0");
+    }
+
+    #[test]
+    fn test_object_stream_and_encryption() {
+        let objects: &[u8] = b"1 0 2 34 << /Type /Catalog /Pages 2 0 R >> << /Type /Pages /Kids [] /Count 12 >>";
+        let mut pdf: Vec<u8> = b"%PDF-1.5\n".to_vec();
+        pdf.extend(stream(3, "/Type /ObjStm /N 2 /First 9 /Filter /FlateDecode", &deflate(objects)));
+        pdf.extend(stream(4, "/Type /XRef /Root 1 0 R /Encrypt 5 0 R /Size 6", b"\x00\x01"));
+        pdf.extend_from_slice(b"5 0 obj\n<< /Filter /Standard /V 2 >>\nendobj\n%%EOF\n");
+
+        let doc: Document = Document::parse(&pdf).unwrap();
+        assert!(doc.encrypted());
+        assert_eq!(doc.objects.get(&2).and_then(|p| p.as_dict()).and_then(|p| p.get("Count")), Some(&Object::Number(12.0)));
+
+        let meta: Meta = extract(&pdf);
+        assert_eq!(value(&meta, "encrypted"), "1");
+        assert_eq!(value(&meta, "pages"), "12");
+    }
+
+    #[test]
+    fn test_stream_length_out_of_range() {
+        let mut pdf: Vec<u8> = b"%PDF-1.4\n".to_vec();
+        pdf.extend_from_slice(b"1 0 obj\n<< /Length 99999999999999999999999 >>\nstream\nabc\nendstream\nendobj\n");
+        pdf.extend_from_slice(b"trailer\n<< /Size 2 >>\n%%EOF\n");
+
+        let doc: Document = Document::parse(&pdf).unwrap();
+        assert!(doc.objects.contains_key(&1));
+    }
+
+    #[test]
+    fn test_cyclic_page_tree() {
+        let mut pdf: Vec<u8> = b"%PDF-1.4\n".to_vec();
+        pdf.extend_from_slice(b"1 0 obj\n<< /Type /Catalog /Pages 2 0 R >>\nendobj\n");
+        pdf.extend_from_slice(b"2 0 obj\n<< /Type /Pages /Kids [3 0 R 2 0 R 4 0 R] /Count 2 >>\nendobj\n");
+        pdf.extend_from_slice(b"3 0 obj\n<< /Type /Page /Parent 2 0 R >>\nendobj\n");
+        pdf.extend_from_slice(b"4 0 obj\n<< /Type /Pages /Kids [2 0 R 3 0 R] /Count 1 >>\nendobj\n");
+        pdf.extend_from_slice(b"trailer\n<< /Root 1 0 R /Size 5 >>\n%%EOF\n");
+
+        let doc: Document = Document::parse(&pdf).unwrap();
+        assert_eq!(doc.pages(1000).len(), 1);
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(parse_date("D:20200102030405Z"), Some(("2020:01:02 03:04:05".to_string(), Some(0))));
+        assert_eq!(parse_date("D:20200102030405-05'30'"), Some(("2020:01:02 03:04:05".to_string(), Some(-330))));
+        assert_eq!(parse_date("D:2020"), Some(("2020:01:01 00:00:00".to_string(), None)));
+        assert_eq!(parse_date("D:20201399"), None);
+        assert_eq!(parse_date("yesterday"), None);
+    }
+
+    #[test]
+    fn test_text_helpers() {
+        assert_eq!(decode_text(&[0xFE, 0xFF, 0x00, 0x48, 0x00, 0xE9]), "Hé");
+        assert_eq!(decode_text(b"caf\xE9"), "café");
+        assert!(shows_text(b"BT [(A) -20 (B)] TJ ET"));
+        assert!(!shows_text(b"(BT) Tj"));
+        assert!(!shows_text(b"BT <54 6a> ET"));
+    }
+}
@@ -0,0 +1,261 @@
+use std::{fs, path::Path};
+
+use image::{imageops::{self, FilterType}, DynamicImage, GrayImage, ImageFormat};
+
+use crate::{
+    image::upright,
+    meta::{MetaAttribute, MetaSource, MetaType, MetaValue},
+    FromFile, Extractor, Meta, MetaError};
+
+/// Side of the DCT input for pHash. Only the top-left 8x8 frequencies are kept.
+const DCT_SIZE: usize = 32;
+
+/// Perceptual hashes (aHash, dHash, pHash) of the decoded image.
+/// Resized, recompressed or re-exported copies of the same photo land within
+/// a small Hamming distance of each other, see [`hamming_distance`].
+pub struct PerceptualHash {
+    path: String,
+}
+
+impl FromFile for PerceptualHash {
+    fn file(path: &str) -> Self {
+        Self { path: path.to_string() }
+    }
+}
+
+impl PerceptualHash {
+    /// The decoded image, or the JPEG preview embedded in raw files, turned
+    /// upright by the EXIF orientation. `None` when neither can be decoded.
+    fn load(&self) -> Result<Option<GrayImage>, MetaError> {
+        let extension: String = Path::new(&self.path)
+            .extension()
+            .map_or(String::new(), |x| x.to_string_lossy().to_ascii_lowercase());
+
+        match extension.as_str() {
+            #[cfg(feature = "heic")]
+            "heic" | "heif" => {
+                let rgb: image::RgbImage = crate::heic::decode_rgb8(&self.path)?;
+                Ok(Some(DynamicImage::ImageRgb8(rgb).to_luma8()))
+            },
+            _ => {
+                let img: Option<DynamicImage> = match image::open(&self.path) {
+                    Ok(img) => Some(img),
+                    Err(_) => embedded_preview(&fs::read(&self.path)?)
+                };
+                // same pixels as `hash.pixel`, so both agree on rotated photos
+                Ok(img.map(|img| DynamicImage::ImageRgb8(upright(img.to_rgb8(), &self.path)).to_luma8()))
+            }
+        }
+    }
+}
+
+/// The first JPEG embedded in `data` that decodes, e.g. the preview of a
+/// DNG, CR2 or RAF. Perceptual hashes shrink to 32x32, a preview is plenty.
+fn embedded_preview(data: &[u8]) -> Option<DynamicImage> {
+    data.windows(3)
+        .enumerate()
+        .filter(|(_, w)| *w == [0xFF, 0xD8, 0xFF])
+        .find_map(|(at, _)| image::load_from_memory_with_format(&data[at..], ImageFormat::Jpeg).ok())
+}
+
+impl Extractor for PerceptualHash {
+    fn name(&self) -> String {
+        return "PHASH".to_string();
+    }
+
+    fn extract(&self, meta: &mut Meta) -> Result<(), MetaError> {
+        // formats the decoder does not know get no perceptual hash
+        let img: GrayImage = match self.load() {
+            Ok(Some(img)) => img,
+            _ => return Ok(())
+        };
+
+        add_hashes(&img, meta);
+        Ok(())
+    }
+}
+
+/// Add aHash, dHash and pHash of a decoded image as Hash attributes.
+pub(crate) fn add_hashes(img: &GrayImage, meta: &mut Meta) {
+    let hashes: [(&str, u64); 3] = [
+        ("hash.ahash", ahash(img)),
+        ("hash.dhash", dhash(img)),
+        ("hash.phash", phash(img)),
+    ];
+
+    for (tag, hash) in hashes {
+        meta.add(MetaAttribute {
+            source: MetaSource::Hash,
+            tag: tag.to_string(),
+            value: MetaType::String(MetaValue::from(to_hex(hash))),
+        });
+    }
+}
+
+fn to_hex(hash: u64) -> String {
+    format!("{:016x}", hash)
+}
+
+fn shrink(img: &GrayImage, width: u32, height: u32) -> GrayImage {
+    imageops::resize(img, width, height, FilterType::Triangle)
+}
+
+/// Average hash: 8x8 thumbnail, one bit per pixel brighter than the mean.
+pub(crate) fn ahash(img: &GrayImage) -> u64 {
+    let small: GrayImage = shrink(img, 8, 8);
+    let pixels: Vec<u64> = small.pixels().map(|p| u64::from(p.0[0])).collect();
+    let mean: u64 = pixels.iter().sum::<u64>() / pixels.len() as u64;
+
+    pixels
+        .iter()
+        .fold(0u64, |hash, &p| (hash << 1) | u64::from(p > mean))
+}
+
+/// Difference hash: 9x8 thumbnail, one bit per horizontal gradient.
+pub(crate) fn dhash(img: &GrayImage) -> u64 {
+    let small: GrayImage = shrink(img, 9, 8);
+    let mut hash: u64 = 0;
+
+    for y in 0..8 {
+        for x in 0..8 {
+            let left: u8 = small.get_pixel(x, y).0[0];
+            let right: u8 = small.get_pixel(x + 1, y).0[0];
+            hash = (hash << 1) | u64::from(left < right);
+        }
+    }
+    hash
+}
+
+/// DCT hash: 32x32 thumbnail, one bit per low frequency above the median.
+/// The DC term is left out of the median since it only carries overall brightness.
+pub(crate) fn phash(img: &GrayImage) -> u64 {
+    let small: GrayImage = shrink(img, DCT_SIZE as u32, DCT_SIZE as u32);
+    let input: Vec<f64> = small.pixels().map(|p| f64::from(p.0[0])).collect();
+    let dct: Vec<f64> = dct_2d(&input, DCT_SIZE);
+
+    let mut low: Vec<f64> = Vec::with_capacity(64);
+    for y in 0..8 {
+        for x in 0..8 {
+            low.push(dct[y * DCT_SIZE + x]);
+        }
+    }
+
+    let mut sorted: Vec<f64> = low[1..].to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let median: f64 = sorted[sorted.len() / 2];
+
+    low
+        .iter()
+        .fold(0u64, |hash, &c| (hash << 1) | u64::from(c > median))
+}
+
+/// Separable, unnormalized DCT-II over an n x n block.
+fn dct_2d(input: &[f64], n: usize) -> Vec<f64> {
+    let mut table: Vec<f64> = vec![0.0; n * n];
+    for k in 0..n {
+        for i in 0..n {
+            table[k * n + i] = (std::f64::consts::PI / n as f64 * (i as f64 + 0.5) * k as f64).cos();
+        }
+    }
+
+    let mut rows: Vec<f64> = vec![0.0; n * n];
+    for y in 0..n {
+        for k in 0..n {
+            rows[y * n + k] = (0..n).map(|i| input[y * n + i] * table[k * n + i]).sum();
+        }
+    }
+
+    let mut output: Vec<f64> = vec![0.0; n * n];
+    for x in 0..n {
+        for k in 0..n {
+            output[k * n + x] = (0..n).map(|i| rows[i * n + x] * table[k * n + i]).sum();
+        }
+    }
+    output
+}
+
+/// Split a hash into its 64-bit words. Video fingerprints hold one word per frame.
+fn parse(hash: &str) -> Result<Vec<u64>, MetaError> {
+    let hash: &str = hash.trim();
+    if hash.is_empty() || hash.len() % 16 != 0 || !hash.is_ascii() {
+        return Err(format!("invalid perceptual hash {:?}", hash).into());
+    }
+
+    (0..hash.len())
+        .step_by(16)
+        .map(|i| u64::from_str_radix(&hash[i..i + 16], 16)
+            .map_err(|e| format!("invalid perceptual hash {:?}: {}", hash, e).into()))
+        .collect()
+}
+
+/// Number of differing bits between two perceptual hashes of the same kind.
+pub fn hamming_distance(a: &str, b: &str) -> Result<u32, MetaError> {
+    let (a, b) = (parse(a)?, parse(b)?);
+    if a.len() != b.len() {
+        return Err("perceptual hashes differ in length".into());
+    }
+    Ok(a.iter().zip(b.iter()).map(|(x, y)| (x ^ y).count_ones()).sum())
+}
+
+/// Similarity between two perceptual hashes from 0.0 (unrelated) to 1.0 (identical).
+pub fn similarity(a: &str, b: &str) -> Result<f64, MetaError> {
+    let bits: usize = a.trim().len() * 4;
+    Ok(1.0 - f64::from(hamming_distance(a, b)?) / bits as f64)
+}
+
+
+#[cfg(test)]
+mod test {
+    use image::{imageops::{self, FilterType}, GrayImage, Luma};
+
+    use super::{ahash, dhash, embedded_preview, hamming_distance, phash, similarity, to_hex};
+
+    fn gradient(width: u32, height: u32) -> GrayImage {
+        GrayImage::from_fn(width, height, |x, y| {
+            let v: u32 = (x * 255 / width + (y * 97 / height)) % 256;
+            Luma([v as u8])
+        })
+    }
+
+    #[test]
+    fn test_resized_copy_is_similar() {
+        let original: GrayImage = gradient(640, 480);
+        let resized: GrayImage = imageops::resize(&original, 213, 160, FilterType::Lanczos3);
+        let other: GrayImage = GrayImage::from_fn(640, 480, |x, y| Luma([((x ^ y) % 7 * 36) as u8]));
+
+        for hash in [ahash, dhash, phash] {
+            let a: String = to_hex(hash(&original));
+            let b: String = to_hex(hash(&resized));
+            let c: String = to_hex(hash(&other));
+
+            assert!(hamming_distance(&a, &b).unwrap() <= 6, "{} vs {}", a, b);
+            assert!(similarity(&a, &c).unwrap() < similarity(&a, &b).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_hamming_distance() {
+        assert_eq!(hamming_distance("0000000000000000", "000000000000000f").unwrap(), 4);
+        assert_eq!(similarity("ffffffffffffffff", "ffffffffffffffff").unwrap(), 1.0);
+        assert!(hamming_distance("zz", "00").is_err());
+        assert!(hamming_distance("0000000000000000", "00000000000000000000000000000000").is_err());
+        assert_eq!(hamming_distance("00000000000000010000000000000001", "00000000000000000000000000000000").unwrap(), 2);
+    }
+
+    #[test]
+    fn test_embedded_preview() {
+        let preview: GrayImage = gradient(64, 48);
+        let mut jpeg: Vec<u8> = Vec::new();
+        image::DynamicImage::ImageLuma8(preview.clone()).write_to(&mut jpeg, image::ImageOutputFormat::Jpeg(90)).unwrap();
+
+        // a raw container: header, the preview, sensor data
+        let mut raw: Vec<u8> = b"FUJIFILMCCD-RAW \xFF\xD8\xFF\x00".to_vec();
+        raw.extend_from_slice(&jpeg);
+        raw.extend_from_slice(&[0x55; 256]);
+
+        let found: GrayImage = embedded_preview(&raw).unwrap().to_luma8();
+        assert_eq!(found.dimensions(), (64, 48));
+        assert!(hamming_distance(&to_hex(phash(&found)), &to_hex(phash(&preview))).unwrap() <= 4);
+        assert!(embedded_preview(&[0x55; 256]).is_none());
+    }
+}
@@ -0,0 +1,213 @@
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::MetaError;
+
+/// A single RIFF chunk header.
+/// `offset` points at the first byte of the chunk payload.
+#[derive(Debug, Clone)]
+pub struct Chunk {
+    pub id: [u8; 4],
+    pub size: u32,
+    pub offset: u64,
+
+    /// The form type for `RIFF`, `RF64`, and `LIST` chunks (e.g. `AVI `, `WAVE`, `INFO`).
+    pub list_type: Option<[u8; 4]>,
+}
+
+impl Chunk {
+    pub fn is(&self, id: &[u8; 4]) -> bool {
+        &self.id == id
+    }
+
+    pub fn is_list(&self, list_type: &[u8; 4]) -> bool {
+        self.list_type.as_ref() == Some(list_type)
+    }
+
+    /// Offset of the first child chunk for `RIFF`/`RF64`/`LIST` chunks.
+    pub fn children(&self) -> u64 {
+        match self.list_type {
+            Some(_) => self.offset + 4,
+            None => self.offset,
+        }
+    }
+
+    /// Offset of the first byte after this chunk, including the pad byte.
+    pub fn end(&self) -> u64 {
+        self.offset + self.size as u64 + (self.size as u64 & 1)
+    }
+}
+
+pub fn fourcc(id: &[u8]) -> String {
+    String::from_utf8_lossy(id)
+        .trim_end_matches(['\0', ' '])
+        .to_string()
+}
+
+pub fn read_u16(data: &[u8], at: usize) -> Option<u16> {
+    data.get(at..at + 2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+}
+
+pub fn read_u32(data: &[u8], at: usize) -> Option<u32> {
+    data.get(at..at + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+pub fn read_i32(data: &[u8], at: usize) -> Option<i32> {
+    read_u32(data, at).map(|x| x as i32)
+}
+
+/// Decode a zero terminated text payload (INFO values, IDIT, etc).
+pub fn read_text(data: &[u8]) -> String {
+    let end: usize = data.iter().position(|b| *b == 0).unwrap_or(data.len());
+    String::from_utf8_lossy(&data[..end]).trim().to_string()
+}
+
+/// Read the chunk header at `offset`.
+/// Returns `None` once the end of the parent (or file) is reached,
+/// or when the header is cut off.
+pub fn read_chunk<R: Read + Seek>(reader: &mut R, offset: u64, limit: u64) -> Result<Option<Chunk>, MetaError> {
+    if offset.saturating_add(8) > limit {
+        return Ok(None);
+    }
+
+    let mut header: [u8; 8] = [0; 8];
+    if reader.seek(SeekFrom::Start(offset)).is_err() || reader.read_exact(&mut header).is_err() {
+        return Ok(None);
+    }
+
+    let mut id: [u8; 4] = [0; 4];
+    id.copy_from_slice(&header[0..4]);
+    let size: u32 = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+
+    let list_type: Option<[u8; 4]> = match &id {
+        b"RIFF" | b"RF64" | b"LIST" => {
+            let mut form: [u8; 4] = [0; 4];
+            if reader.read_exact(&mut form).is_err() {
+                return Ok(None);
+            }
+            Some(form)
+        },
+        _ => None
+    };
+
+    Ok(Some(Chunk { id, size, offset: offset + 8, list_type }))
+}
+
+/// Collect the direct children of a `RIFF`/`LIST` chunk.
+/// Truncated files are tolerated; the walk stops at the first chunk that
+/// extends past the end of its parent.
+pub fn children<R: Read + Seek>(reader: &mut R, parent: &Chunk, file_size: u64) -> Result<Vec<Chunk>, MetaError> {
+    let limit: u64 = parent.end().min(file_size);
+    let mut offset: u64 = parent.children();
+    let mut chunks: Vec<Chunk> = Vec::new();
+
+    while let Some(chunk) = read_chunk(reader, offset, limit)? {
+        offset = chunk.end();
+        chunks.push(chunk);
+    }
+
+    Ok(chunks)
+}
+
+/// Read the payload of a (small) chunk. Payloads are capped at `max` bytes.
+pub fn read_payload<R: Read + Seek>(reader: &mut R, chunk: &Chunk, max: usize) -> Result<Vec<u8>, MetaError> {
+    let len: usize = (chunk.size as usize).min(max);
+    let mut data: Vec<u8> = vec![0; len];
+
+    // a truncated payload reads as far as the file goes
+    let read: usize = match reader.seek(SeekFrom::Start(chunk.offset)) {
+        Ok(_) => reader.read(&mut data).unwrap_or(0),
+        Err(_) => 0
+    };
+    data.truncate(read);
+
+    Ok(data)
+}
+
+/// Common `LIST/INFO` ids, mapped to friendly tag names.
+pub fn info_tag(id: &[u8; 4]) -> String {
+    String::from(match id {
+        b"INAM" => "title",
+        b"IART" => "artist",
+        b"ICMT" => "comment",
+        b"ICOP" => "copyright",
+        b"ICRD" => "creation_date",
+        b"IENG" => "engineer",
+        b"IGNR" => "genre",
+        b"IKEY" => "keywords",
+        b"IPRD" => "product",
+        b"ISBJ" => "subject",
+        b"ISFT" => "software",
+        b"ISRC" => "source",
+        b"ITCH" => "technician",
+        b"IPRT" | b"ITRK" => "track",
+        x => return fourcc(x).to_lowercase(),
+    })
+}
+
+/// Common WAVE format tags (WAVEFORMATEX `wFormatTag`).
+pub fn audio_format(format_tag: u16) -> &'static str {
+    match format_tag {
+        0x0001 => "PCM",
+        0x0002 => "MS ADPCM",
+        0x0003 => "IEEE Float",
+        0x0006 => "A-Law",
+        0x0007 => "u-Law",
+        0x0011 => "IMA ADPCM",
+        0x0031 | 0x0032 => "GSM 6.10",
+        0x0050 => "MPEG",
+        0x0055 => "MP3",
+        0x00FF | 0x1600 | 0x1601 => "AAC",
+        0x0161..=0x0163 => "WMA",
+        0x2000 => "AC3",
+        0x2001 => "DTS",
+        0xFFFE => "Extensible",
+        _ => "Unknown",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+    use super::{children, read_chunk, info_tag};
+
+    #[test]
+    fn test_walk_list() {
+        let mut data: Vec<u8> = Vec::new();
+        data.extend_from_slice(b"RIFF");
+        data.extend_from_slice(&24u32.to_le_bytes());
+        data.extend_from_slice(b"TEST");
+        data.extend_from_slice(b"abcd");
+        data.extend_from_slice(&3u32.to_le_bytes());
+        data.extend_from_slice(b"xyz\0");
+        data.extend_from_slice(b"efgh");
+        data.extend_from_slice(&0u32.to_le_bytes());
+
+        let size: u64 = data.len() as u64;
+        let mut reader = Cursor::new(data);
+        let riff = read_chunk(&mut reader, 0, size).unwrap().unwrap();
+        assert!(riff.is_list(b"TEST"));
+
+        let chunks = children(&mut reader, &riff, size).unwrap();
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks[0].is(b"abcd"));
+        assert_eq!(chunks[0].size, 3);
+        assert!(chunks[1].is(b"efgh"));
+
+        assert_eq!(info_tag(b"INAM"), "title");
+        assert_eq!(info_tag(b"IXYZ"), "ixyz");
+    }
+
+    #[test]
+    fn test_truncated() {
+        // RIFF header cut before its form type
+        let data: Vec<u8> = [&b"RIFF"[..], &64u32.to_le_bytes(), b"AV"].concat();
+        let size: u64 = data.len() as u64;
+        assert!(read_chunk(&mut Cursor::new(data), 0, size).unwrap().is_none());
+
+        // the limit claims more bytes than the reader holds
+        let data: Vec<u8> = [&b"RIFF"[..], &64u32.to_le_bytes(), b"TEST", b"abcd"].concat();
+        let mut reader = Cursor::new(data);
+        let riff = read_chunk(&mut reader, 0, 72).unwrap().unwrap();
+        assert!(children(&mut reader, &riff, 72).unwrap().is_empty());
+    }
+}
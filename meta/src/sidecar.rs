@@ -0,0 +1,361 @@
+use std::{fs, path::Path};
+
+use regex::Regex;
+use serde_json::Value;
+
+use crate::{
+    meta::{MetaAttribute, MetaSource, MetaType, MetaValue},
+    location::parse_xmp_coordinate,
+    xml, Extractor, FromFile, Meta, MetaError};
+
+/// XMP properties read from sidecars, stored under their exiv2 key
+/// (`exif:DateTimeOriginal` as `Xmp.exif.DateTimeOriginal`).
+const XMP_KEYS: [&str; 14] = [
+    "exif:DateTimeOriginal",
+    "photoshop:DateCreated",
+    "xmp:CreateDate",
+    "xmp:ModifyDate",
+    "xmp:CreatorTool",
+    "xmp:Rating",
+    "xmp:Label",
+    "dc:title",
+    "dc:description",
+    "dc:creator",
+    "tiff:Make",
+    "tiff:Model",
+    "exif:GPSLatitude",
+    "exif:GPSLongitude",
+];
+
+/// Leaves taken from a JSON sidecar, they can be long lists of people or albums.
+const MAX_JSON_TAGS: usize = 200;
+
+/// Files that describe another file next to them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SidecarKind {
+    /// XMP written by Lightroom, darktable or digiKam, e.g. DSC001.xmp or DSC001.NEF.xmp.
+    Xmp,
+    /// Photos edits of an iPhone picture, IMG_1234.AAE.
+    Aae,
+    /// JSON written by exports such as Google Takeout, photo.jpg.json.
+    Json,
+    /// JPEG thumbnail of a camera video, MVI_0001.THM.
+    Thm,
+}
+
+impl From<SidecarKind> for String {
+    fn from(value: SidecarKind) -> Self {
+        String::from(match value {
+            SidecarKind::Xmp => "xmp",
+            SidecarKind::Aae => "aae",
+            SidecarKind::Json => "json",
+            SidecarKind::Thm => "thm",
+        })
+    }
+}
+
+impl TryFrom<&str> for SidecarKind {
+    type Error = MetaError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.to_ascii_lowercase().as_str() {
+            "xmp" => Ok(SidecarKind::Xmp),
+            "aae" => Ok(SidecarKind::Aae),
+            "json" => Ok(SidecarKind::Json),
+            "thm" => Ok(SidecarKind::Thm),
+            _ => Err(format!("not a sidecar extension: {}", value).into()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sidecar {
+    pub path: String,
+    pub kind: SidecarKind,
+}
+
+impl Sidecar {
+    /// The name of the sidecar after the one of its file, e.g. for
+    /// `photo.jpg.json` archived with `<uuid>.jpg` this is `<uuid>.jpg.json`.
+    pub fn rename(&self, source: &str, name: &str) -> String {
+        let own: &str = Path::new(&self.path).file_name().and_then(|n| n.to_str()).unwrap_or_default();
+        let source: &str = Path::new(source).file_name().and_then(|n| n.to_str()).unwrap_or_default();
+        let extension: String = String::from(self.kind);
+
+        if own.len() > source.len() && own[..source.len()].eq_ignore_ascii_case(source) {
+            return format!("{}.{}", name, extension);
+        }
+        let stem: &str = name.rsplit_once('.').map_or(name, |(stem, _)| stem);
+        return format!("{}.{}", stem, extension);
+    }
+}
+
+/// Sidecars next to `path`: files with a sidecar extension named after its stem
+/// (IMG_1234.AAE for IMG_1234.HEIC) or its full name (photo.jpg.json), and the
+/// Google Takeout JSON, in name order. A sidecar has no sidecars itself.
+pub fn find_sidecars(path: &str) -> Vec<Sidecar> {
+    let path: &Path = Path::new(path);
+    let name: &str = match path.file_name().and_then(|n| n.to_str()) {
+        Some(n) => n,
+        None => return Vec::new()
+    };
+    let (stem, extension) = name.rsplit_once('.').unwrap_or((name, ""));
+    if SidecarKind::try_from(extension).is_ok() {
+        return Vec::new();
+    }
+
+    let directory: &Path = match path.parent() {
+        Some(d) if !d.as_os_str().is_empty() => d,
+        _ => Path::new(".")
+    };
+    let entries = match fs::read_dir(directory) {
+        Ok(e) => e,
+        Err(_) => return Vec::new()
+    };
+
+    let mut sidecars: Vec<Sidecar> = entries
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_ok_and(|t| t.is_file()))
+        .filter_map(|e| {
+            let other: String = e.file_name().to_str()?.to_string();
+            let (other_stem, other_extension) = other.rsplit_once('.')?;
+            let kind: SidecarKind = SidecarKind::try_from(other_extension).ok()?;
+            let matches: bool = other_stem.eq_ignore_ascii_case(stem) || other_stem.eq_ignore_ascii_case(name);
+            matches.then(|| Sidecar { path: e.path().display().to_string(), kind })
+        })
+        .collect();
+
+    // Takeout names its JSON by rules of its own
+    #[cfg(feature = "takeout")]
+    if let Some(json) = crate::takeout::find_takeout_json(&path.display().to_string()) {
+        if !sidecars.iter().any(|s| s.path == json) {
+            sidecars.push(Sidecar { path: json, kind: SidecarKind::Json });
+        }
+    }
+
+    sidecars.sort_by(|a, b| a.path.cmp(&b.path));
+    sidecars
+}
+
+/// Metadata of the sidecars of a file. Each sidecar is listed as
+/// `sidecar.{i}.name` and `sidecar.{i}.kind`. XMP properties keep their exiv2
+/// key (`Xmp.dc.title`), the others are prefixed with the kind:
+/// `aae.adjustmentEditorBundleID`, `json.photoTakenTime.timestamp` or `thm.Model`.
+pub struct SidecarMeta {
+    path: String
+}
+
+impl FromFile for SidecarMeta {
+    fn file(path: &str) -> Self {
+        Self { path: path.to_string() }
+    }
+}
+
+impl SidecarMeta {
+    fn add_str(meta: &mut Meta, tag: &str, value: String) {
+        if value.trim().is_empty() {
+            return;
+        }
+        meta.add(MetaAttribute{
+            source: MetaSource::Sidecar,
+            tag: tag.to_string(),
+            value: MetaType::String(MetaValue::from(value)),
+        });
+    }
+
+    fn add_xmp(meta: &mut Meta, content: &str) {
+        for property in XMP_KEYS {
+            if let Some(value) = xml::attribute(content, property).or_else(|| xml::text(content, property)) {
+                // "37,46.494N" would not survive the sanitizer, decimal degrees do
+                let value: Option<String> = match property.starts_with("exif:GPS") {
+                    true => parse_xmp_coordinate(&value).map(|v| v.to_string()),
+                    false => Some(value)
+                };
+                if let Some(value) = value {
+                    Self::add_str(meta, &format!("Xmp.{}", property.replace(':', ".")), value);
+                }
+            }
+        }
+
+        // keywords are a bag, the sanitizer cuts values at commas
+        if let Some(bag) = xml::element(content, "dc:subject") {
+            let re: Regex = Regex::new(r"(?s)<rdf:li[^>]*>(.*?)</rdf:li>").unwrap();
+            let keywords: Vec<String> = re
+                .captures_iter(bag)
+                .map(|c| xml::unescape(c[1].trim()))
+                .filter(|k| !k.is_empty())
+                .collect();
+            Self::add_str(meta, "Xmp.dc.subject", keywords.join("; "));
+        }
+    }
+
+    /// The top level keys of the adjustments property list.
+    fn add_aae(meta: &mut Meta, content: &str) {
+        let re: Regex = Regex::new(r"<key>(\w+)</key>\s*<(string|date|integer|real)>([^<]*)</").unwrap();
+        for c in re.captures_iter(content) {
+            if c[1].starts_with("adjustment") {
+                Self::add_str(meta, &format!("aae.{}", &c[1]), xml::unescape(&c[3]));
+            }
+        }
+    }
+
+    fn add_json(meta: &mut Meta, tag: &str, value: &Value, count: &mut usize) {
+        if *count >= MAX_JSON_TAGS {
+            return;
+        }
+        match value {
+            Value::Object(map) => {
+                for (key, v) in map {
+                    Self::add_json(meta, &format!("{}.{}", tag, key), v, count);
+                }
+            },
+            Value::Array(items) => {
+                for (i, v) in items.iter().enumerate() {
+                    Self::add_json(meta, &format!("{}.{}", tag, i), v, count);
+                }
+            },
+            Value::Null => (),
+            Value::String(s) => {
+                *count += 1;
+                Self::add_str(meta, tag, s.clone());
+            },
+            v => {
+                *count += 1;
+                Self::add_str(meta, tag, v.to_string());
+            }
+        }
+    }
+
+    /// A THM is a small JPEG, its EXIF names the camera of the video.
+    #[cfg(feature = "exif")]
+    fn add_thm(meta: &mut Meta, path: &str) -> Result<(), MetaError> {
+        use crate::exif::ExifExtractor;
+
+        let mut exif: Meta = Meta::new();
+        ExifExtractor::file(path).extract(&mut exif)?;
+        for attribute in exif.0 {
+            meta.add(MetaAttribute {
+                source: MetaSource::Sidecar,
+                tag: format!("thm.{}", attribute.tag),
+                value: attribute.value,
+            });
+        }
+        Ok(())
+    }
+
+    fn add_sidecar(meta: &mut Meta, sidecar: &Sidecar) -> Result<(), MetaError> {
+        match sidecar.kind {
+            SidecarKind::Xmp => Self::add_xmp(meta, &String::from_utf8_lossy(&fs::read(&sidecar.path)?)),
+            SidecarKind::Aae => Self::add_aae(meta, &String::from_utf8_lossy(&fs::read(&sidecar.path)?)),
+            SidecarKind::Json => {
+                let value: Value = serde_json::from_slice(&fs::read(&sidecar.path)?)?;
+                Self::add_json(meta, "json", &value, &mut 0);
+            },
+            #[cfg(feature = "exif")]
+            SidecarKind::Thm => Self::add_thm(meta, &sidecar.path)?,
+            #[cfg(not(feature = "exif"))]
+            SidecarKind::Thm => (),
+        }
+        Ok(())
+    }
+}
+
+impl Extractor for SidecarMeta {
+    fn name(&self) -> String {
+        return "SIDECAR".to_string();
+    }
+
+    fn extract(&self, meta: &mut Meta) -> Result<(), MetaError> {
+        for (i, sidecar) in find_sidecars(&self.path).iter().enumerate() {
+            let name: String = Path::new(&sidecar.path).file_name().map_or(String::new(), |n| n.to_string_lossy().to_string());
+            Self::add_str(meta, &format!("sidecar.{}.name", i), name.clone());
+            Self::add_str(meta, &format!("sidecar.{}.kind", i), String::from(sidecar.kind));
+
+            // a broken sidecar must not keep its file from being archived
+            if let Err(e) = Self::add_sidecar(meta, sidecar) {
+                eprintln!("sidecar error ({}): {}", name, e);
+            }
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::{fs, path::PathBuf};
+
+    use super::{find_sidecars, SidecarKind, SidecarMeta};
+    use crate::{Extractor, FromFile, Meta, MetaSource};
+
+    fn directory(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir: PathBuf = std::env::temp_dir().join(format!("informant-sidecar-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for (file, content) in files {
+            fs::write(dir.join(file), content).unwrap();
+        }
+        dir
+    }
+
+    fn value(meta: &Meta, tag: &str) -> String {
+        String::from(meta.find_one(MetaSource::Sidecar, tag).unwrap().value)
+    }
+
+    #[test]
+    fn test_find_sidecars() {
+        let dir: PathBuf = directory("find", &[
+            ("IMG_1234.HEIC", ""), ("IMG_1234.AAE", ""), ("IMG_12345.AAE", ""),
+            ("photo.jpg", ""), ("photo.jpg.json", ""), ("photo.txt", ""),
+            ("DSC001.NEF", ""), ("DSC001.xmp", ""), ("DSC001.NEF.xmp", ""),
+        ]);
+        let found = |name: &str| -> Vec<(String, SidecarKind)> {
+            find_sidecars(dir.join(name).to_str().unwrap())
+                .into_iter()
+                .map(|s| (PathBuf::from(s.path).file_name().unwrap().to_string_lossy().to_string(), s.kind))
+                .collect()
+        };
+
+        assert_eq!(found("IMG_1234.HEIC"), vec![("IMG_1234.AAE".to_string(), SidecarKind::Aae)]);
+        assert_eq!(found("photo.jpg"), vec![("photo.jpg.json".to_string(), SidecarKind::Json)]);
+        assert_eq!(found("DSC001.NEF").len(), 2);
+        assert!(found("IMG_1234.AAE").is_empty());
+        assert!(found("missing/IMG_1234.HEIC").is_empty());
+
+        let sidecars = find_sidecars(dir.join("DSC001.NEF").to_str().unwrap());
+        assert_eq!(sidecars[0].rename("DSC001.NEF", "0b7e.nef"), "0b7e.nef.xmp");
+        assert_eq!(sidecars[1].rename("DSC001.NEF", "0b7e.nef"), "0b7e.xmp");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_sidecar_meta() {
+        let dir: PathBuf = directory("meta", &[
+            ("IMG_0001.HEIC", ""),
+            ("IMG_0001.xmp", r#"<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF>
+                <rdf:Description xmp:Rating="4" exif:DateTimeOriginal="2021-06-15T13:45:30+02:00" exif:GPSLatitude="46,12.264N">
+                <dc:subject><rdf:Bag><rdf:li>beach</rdf:li><rdf:li>sun &amp; sea</rdf:li></rdf:Bag></dc:subject>
+                </rdf:Description></rdf:RDF></x:xmpmeta>"#),
+            ("IMG_0001.AAE", r#"<plist version="1.0"><dict>
+                <key>adjustmentEditorBundleID</key><string>com.apple.mobileslideshow</string>
+                <key>adjustmentTimestamp</key><date>2021-06-16T08:00:00Z</date>
+                <key>adjustmentData</key><data>AAAA</data></dict></plist>"#),
+            ("IMG_0001.HEIC.json", r#"{"title": "IMG_0001.HEIC", "photoTakenTime": {"timestamp": "1623757530"}, "people": [{"name": "Ann"}], "url": null}"#),
+            ("IMG_0001.THM", "not a jpeg"),
+        ]);
+
+        let mut meta: Meta = Meta::new();
+        SidecarMeta::file(dir.join("IMG_0001.HEIC").to_str().unwrap()).extract(&mut meta).unwrap();
+        fs::remove_dir_all(dir).unwrap();
+
+        assert_eq!(value(&meta, "sidecar.0.kind"), "aae");
+        assert_eq!(value(&meta, "Xmp.xmp.Rating"), "4");
+        assert_eq!(value(&meta, "Xmp.exif.DateTimeOriginal"), "2021-06-15T13:45:30+02:00");
+        assert_eq!(value(&meta, "Xmp.dc.subject"), "beach; sun & sea");
+        assert!((value(&meta, "Xmp.exif.GPSLatitude").parse::<f64>().unwrap() - 46.2044).abs() < 1e-9);
+        assert_eq!(value(&meta, "aae.adjustmentEditorBundleID"), "com.apple.mobileslideshow");
+        assert_eq!(value(&meta, "json.photoTakenTime.timestamp"), "1623757530");
+        assert_eq!(value(&meta, "json.people.0.name"), "Ann");
+        assert!(meta.find_one(MetaSource::Sidecar, "aae.adjustmentData").is_err());
+        assert!(meta.find_one(MetaSource::Sidecar, "sidecar.3.name").is_ok());
+    }
+}
@@ -0,0 +1,337 @@
+use std::{fs::File, io::Read};
+
+use flate2::read::GzDecoder;
+
+use crate::{
+    meta::{MetaAttribute, MetaSource, MetaType, MetaValue},
+    xml, Extractor, FromFile, Meta, MetaError};
+
+/// Documents larger than this (after decompression) are not read.
+const MAX_SVG: u64 = 64 * 1024 * 1024;
+
+/// Dublin Core properties of the RDF metadata block and their tags.
+const RDF_KEYS: [(&str, &str); 5] = [
+    ("dc:title", "metadata.title"),
+    ("dc:creator", "metadata.creator"),
+    ("dc:date", "metadata.date"),
+    ("dc:description", "metadata.description"),
+    ("dc:rights", "metadata.rights"),
+];
+
+/// SVG documents (and gzipped .svgz): intrinsic size and viewBox, the title,
+/// description and RDF metadata, and the application that wrote the file.
+/// Raster decoders cannot read SVG, so this also provides its identity hash.
+pub struct Svg { path: String }
+
+impl FromFile for Svg {
+    fn file(path: &str) -> Self {
+        Self { path: path.to_string() }
+    }
+}
+
+/// A length in CSS pixels. Relative units (%, em) have no absolute size.
+fn to_pixels(length: &str) -> Option<f64> {
+    let length: &str = length.trim();
+    let split: usize = length
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-' || c == '+' || c == 'e' || c == 'E'))
+        .unwrap_or(length.len());
+    let (number, unit) = length.split_at(split);
+    let value: f64 = number.parse().ok()?;
+
+    let scale: f64 = match unit.trim() {
+        "" | "px" => 1.0,
+        "pt" => 96.0 / 72.0,
+        "pc" => 16.0,
+        "mm" => 96.0 / 25.4,
+        "cm" => 96.0 / 2.54,
+        "in" => 96.0,
+        _ => return None
+    };
+    Some(value * scale).filter(|v| v.is_finite() && *v > 0.0)
+}
+
+/// The viewBox as `min-x min-y width height`, accepting comma separators.
+fn parse_view_box(view_box: &str) -> Option<[f64; 4]> {
+    let values: Vec<f64> = view_box
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse().ok())
+        .collect::<Option<Vec<f64>>>()?;
+    match values[..] {
+        [x, y, w, h] => Some([x, y, w, h]),
+        _ => None
+    }
+}
+
+/// The start tag of the root `svg` element, namespace prefix allowed.
+fn root_tag(document: &str) -> Option<&str> {
+    let mut rest: &str = document;
+    loop {
+        let start: usize = rest.find('<')?;
+        rest = &rest[start + 1..];
+        if rest.starts_with("!--") {
+            rest = &rest[rest.find("-->")? + 3..];
+            continue;
+        }
+        if rest.starts_with(['?', '!']) {
+            continue;
+        }
+        let tag: &str = &rest[..rest.find('>')?];
+        let name: &str = tag.split(|c: char| c.is_whitespace() || c == '/').next()?;
+        return match name.rsplit(':').next() == Some("svg") {
+            true => Some(tag),
+            false => None
+        };
+    }
+}
+
+/// Application named in a `Generator:` comment (Illustrator, Sketch, ...)
+/// or in Inkscape's version attribute.
+fn generator(document: &str, root: &str) -> Option<String> {
+    let mut rest: &str = document;
+    while let Some(start) = rest.find("<!--") {
+        let comment: &str = &rest[start + 4..];
+        let end: usize = comment.find("-->")?;
+        if let Some(at) = comment[..end].find("Generator:") {
+            return Some(comment[at + 10..end].trim().to_string());
+        }
+        rest = &comment[end..];
+    }
+    xml::attribute(root, "inkscape:version").map(|v| format!("Inkscape {}", v))
+}
+
+/// The document without its XML declaration, comments and insignificant
+/// whitespace, so re-saving or re-indenting does not change the identity.
+#[cfg(feature = "hash")]
+fn canonical(document: &str) -> String {
+    let mut out: String = String::with_capacity(document.len());
+    let mut rest: &str = document.trim_start_matches('\u{feff}');
+
+    while !rest.is_empty() {
+        if rest.starts_with("<?xml") {
+            rest = rest.find("?>").map_or("", |e| &rest[e + 2..]);
+        }
+        else if rest.starts_with("<!--") {
+            rest = rest.find("-->").map_or("", |e| &rest[e + 3..]);
+        }
+        else {
+            let first: usize = rest.chars().next().map_or(1, char::len_utf8);
+            let next: usize = rest[first..].find('<').map_or(rest.len(), |n| n + first);
+            out.push_str(&rest[..next]);
+            rest = &rest[next..];
+        }
+    }
+
+    // collapse whitespace runs, and drop them entirely between tags
+    let mut collapsed: String = String::with_capacity(out.len());
+    for word in out.split_whitespace() {
+        let between_tags: bool = collapsed.ends_with('>') && word.starts_with('<');
+        if !collapsed.is_empty() && !between_tags {
+            collapsed.push(' ');
+        }
+        collapsed.push_str(word);
+    }
+    collapsed
+}
+
+impl Svg {
+    fn add_str(meta: &mut Meta, tag: &str, value: String) {
+        if value.trim().is_empty() {
+            return;
+        }
+        meta.add(MetaAttribute{
+            source: MetaSource::Svg,
+            tag: tag.to_string(),
+            value: MetaType::String(MetaValue::from(value)),
+        });
+    }
+
+    fn add_f64(meta: &mut Meta, tag: &str, value: f64) {
+        meta.add(MetaAttribute{
+            source: MetaSource::Svg,
+            tag: tag.to_string(),
+            value: MetaType::Rational(MetaValue::from(value)),
+        });
+    }
+
+    fn read(&self) -> Result<String, MetaError> {
+        let mut raw: Vec<u8> = Vec::new();
+        File::open(&self.path)?.take(MAX_SVG).read_to_end(&mut raw)?;
+
+        // .svgz, or a gzipped file that kept the .svg name
+        if raw.starts_with(&[0x1F, 0x8B]) {
+            let mut data: Vec<u8> = Vec::new();
+            GzDecoder::new(&raw[..]).take(MAX_SVG).read_to_end(&mut data)?;
+            raw = data;
+        }
+        Ok(String::from_utf8_lossy(&raw).into_owned())
+    }
+}
+
+impl Extractor for Svg {
+    fn name(&self) -> String {
+        return "SVG".to_string();
+    }
+
+    fn extract(&self, meta: &mut Meta) -> Result<(), MetaError> {
+        let document: String = self.read()?;
+        // not SVG after all (e.g. an HTML page saved as .svg), nothing to report
+        let root: &str = match root_tag(&document) {
+            Some(r) => r,
+            None => return Ok(())
+        };
+
+        let view_box: Option<[f64; 4]> = xml::attribute(root, "viewBox").and_then(|v| parse_view_box(&v));
+        if let Some([x, y, w, h]) = view_box {
+            Self::add_str(meta, "view_box", format!("{} {} {} {}", x, y, w, h));
+        }
+
+        // without explicit dimensions the viewBox is the intrinsic size
+        for (attribute, index) in [("width", 2), ("height", 3)] {
+            let length: Option<String> = xml::attribute(root, attribute);
+            if let Some(length) = &length {
+                Self::add_str(meta, attribute, length.clone());
+            }
+            let pixels: Option<f64> = match &length {
+                Some(l) => to_pixels(l),
+                None => view_box.map(|v| v[index]).filter(|v| *v > 0.0),
+            };
+            if let Some(pixels) = pixels {
+                Self::add_f64(meta, &format!("{}_px", attribute), pixels);
+            }
+        }
+
+        if let Some(version) = xml::attribute(root, "version") {
+            Self::add_str(meta, "version", version);
+        }
+        if let Some(generator) = generator(&document, root) {
+            Self::add_str(meta, "generator", generator);
+        }
+
+        // only the document's own title and desc, not those of nested shapes
+        let body: &str = &document[document.find(root).unwrap_or(0)..];
+        let first: &str = &body[..body.find("<g").unwrap_or(body.len())];
+        for (element, tag) in [("title", "title"), ("desc", "description")] {
+            if let Some(value) = xml::text(first, element).or_else(|| xml::text(first, &format!("svg:{}", element))) {
+                Self::add_str(meta, tag, value);
+            }
+        }
+
+        if let Some(metadata) = xml::element(&document, "metadata") {
+            for (property, tag) in RDF_KEYS {
+                if let Some(value) = xml::text(metadata, property) {
+                    Self::add_str(meta, tag, value);
+                }
+            }
+            let license: Option<&str> = metadata.find("<cc:license").map(|at| &metadata[at..]).and_then(|l| l.get(..l.find('>')?));
+            if let Some(license) = license.and_then(|l| xml::attribute(l, "rdf:resource")) {
+                Self::add_str(meta, "metadata.license", license);
+            }
+        }
+
+        #[cfg(feature = "hash")]
+        meta.add(MetaAttribute{
+            source: MetaSource::Hash,
+            tag: "hash.canonical".to_string(),
+            value: MetaType::String(MetaValue::from(blake3::hash(canonical(&document).as_bytes()).to_string())),
+        });
+
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{parse_view_box, root_tag, to_pixels, Svg};
+    use crate::{Extractor, FromFile, Meta, MetaSource, MetaType};
+
+    const DRAWING: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="no"?>
+<!-- Created with Inkscape (http://www.inkscape.org/) -->
+<svg width="210mm" height="297mm" viewBox="0,0,210,297" version="1.1"
+   xmlns="http://www.w3.org/2000/svg" xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape"
+   inkscape:version="1.2.2 (b0a8486541)">
+  <title>Floor plan</title>
+  <metadata>
+    <rdf:RDF>
+      <cc:Work rdf:about="">
+        <dc:creator><cc:Agent><dc:title>Jane Doe</dc:title></cc:Agent></dc:creator>
+        <dc:date>2022-11-05</dc:date>
+        <cc:license rdf:resource="http://creativecommons.org/licenses/by/4.0/" />
+      </cc:Work>
+    </rdf:RDF>
+  </metadata>
+  <g><title>Kitchen</title><rect x="10" y="10" width="50" height="40"/></g>
+</svg>
+"#;
+
+    fn extract(data: &[u8], extension: &str) -> Meta {
+        let path = std::env::temp_dir().join(format!("informant_svg_{}_{}.{}", std::process::id(), data.len(), extension));
+        std::fs::write(&path, data).unwrap();
+        let mut meta: Meta = Meta::new();
+        Svg::file(path.to_str().unwrap()).extract(&mut meta).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        meta
+    }
+
+    fn value(meta: &Meta, source: MetaSource, tag: &str) -> String {
+        match meta.find_one(source, tag).unwrap().value {
+            MetaType::String(v) => v.value,
+            MetaType::UInt64(v) => v.value.to_string(),
+            MetaType::Int64(v) => v.value.to_string(),
+            MetaType::Rational(v) => format!("{:.1}", v.value),
+            MetaType::Meta(_) => panic!("unexpected nested meta"),
+        }
+    }
+
+    #[test]
+    fn test_svg_metadata() {
+        let meta: Meta = extract(DRAWING.as_bytes(), "svg");
+
+        assert_eq!(value(&meta, MetaSource::Svg, "width"), "210mm");
+        assert_eq!(value(&meta, MetaSource::Svg, "width_px"), "793.7");
+        assert_eq!(value(&meta, MetaSource::Svg, "height_px"), "1122.5");
+        assert_eq!(value(&meta, MetaSource::Svg, "view_box"), "0 0 210 297");
+        assert_eq!(value(&meta, MetaSource::Svg, "version"), "1.1");
+        assert_eq!(value(&meta, MetaSource::Svg, "generator"), "Inkscape 1.2.2 (b0a8486541)");
+        assert_eq!(value(&meta, MetaSource::Svg, "title"), "Floor plan");
+        assert_eq!(value(&meta, MetaSource::Svg, "metadata.creator"), "Jane Doe");
+        assert_eq!(value(&meta, MetaSource::Svg, "metadata.date"), "2022-11-05");
+        assert_eq!(value(&meta, MetaSource::Svg, "metadata.license"), "http://creativecommons.org/licenses/by/4.0");
+    }
+
+    #[test]
+    #[cfg(feature = "hash")]
+    fn test_canonical_hash() {
+        use std::io::Write;
+
+        use flate2::{write::GzEncoder, Compression};
+
+        use super::canonical;
+
+        assert_eq!(canonical("<?xml version=\"1.0\"?>\n<svg>\n  <!-- x --><g/>\n</svg>"), "<svg><g/></svg>");
+        assert_eq!(canonical("<svg><text><!-- c -->é</text>\n<text>日本語<!-- c -->ü</text></svg>"), "<svg><text>é</text><text>日本語ü</text></svg>");
+
+        let reformatted: String = DRAWING
+            .replace("<!-- Created with Inkscape (http://www.inkscape.org/) -->\n", "")
+            .replace("\n  ", "\r\n\t");
+        let mut gzipped = GzEncoder::new(Vec::new(), Compression::default());
+        gzipped.write_all(DRAWING.as_bytes()).unwrap();
+        let changed: String = DRAWING.replace("width=\"50\"", "width=\"51\"");
+
+        let original: String = value(&extract(DRAWING.as_bytes(), "svg"), MetaSource::Hash, "hash.canonical");
+        assert_eq!(value(&extract(reformatted.as_bytes(), "svg"), MetaSource::Hash, "hash.canonical"), original);
+        assert_eq!(value(&extract(&gzipped.finish().unwrap(), "svgz"), MetaSource::Hash, "hash.canonical"), original);
+        assert_ne!(value(&extract(changed.as_bytes(), "svg"), MetaSource::Hash, "hash.canonical"), original);
+    }
+
+    #[test]
+    fn test_helpers() {
+        assert_eq!(to_pixels("1in"), Some(96.0));
+        assert_eq!(to_pixels("12pt"), Some(16.0));
+        assert_eq!(to_pixels("100%"), None);
+        assert_eq!(parse_view_box("0 0 10"), None);
+        assert!(root_tag("<?xml version=\"1.0\"?><!DOCTYPE svg><svg:svg xmlns:svg=\"x\"/>").is_some());
+        assert!(root_tag("<html><svg/></html>").is_none());
+    }
+}
@@ -0,0 +1,286 @@
+use std::{fs, path::Path, sync::LazyLock};
+
+use regex::Regex;
+use serde_json::Value;
+
+use crate::{
+    meta::{MetaAttribute, MetaSource, MetaType, MetaValue},
+    Extractor, FromFile, Meta, MetaError};
+
+/// Takeout cuts the JSON name in front of `.json` at 46 characters.
+const MAX_NAME: usize = 46;
+
+/// Newer exports name the JSON `photo.jpg.supplemental-metadata.json`.
+const SUPPLEMENTAL: &str = ".supplemental-metadata";
+
+/// Edited copies use the JSON of the original, the suffix depends on the export language.
+const EDITED_SUFFIXES: [&str; 5] = ["-edited", "-bearbeitet", "-modifié", "-editado", "-modificato"];
+
+/// A duplicate counter at the end of a stem, e.g. photo(1).
+static COUNTER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(.+?)(\(\d+\))$").unwrap());
+
+/// The first `max` characters of `value`.
+fn truncate(value: &str, max: usize) -> &str {
+    value.char_indices().nth(max).map_or(value, |(at, _)| &value[..at])
+}
+
+/// The name Takeout describes and the duplicate counter moved out of it:
+/// photo(1).jpg is ("photo.jpg", "photo", "(1)"), photo-edited.jpg is ("photo.jpg", "photo", "").
+fn original_name(name: &str) -> (String, String, String) {
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) => (stem, Some(extension)),
+        None => (name, None)
+    };
+    let stem: &str = EDITED_SUFFIXES.iter().find_map(|s| stem.strip_suffix(s)).unwrap_or(stem);
+
+    let (stem, counter) = match COUNTER.captures(stem) {
+        Some(c) => (c.get(1).unwrap().as_str(), c.get(2).unwrap().as_str()),
+        None => (stem, "")
+    };
+    let original: String = match extension {
+        Some(e) => format!("{}.{}", stem, e),
+        None => stem.to_string()
+    };
+    (original, stem.to_string(), counter.to_string())
+}
+
+/// JSON names Takeout may have written for `name`, most likely first.
+fn candidates(name: &str) -> Vec<String> {
+    let (original, stem, counter) = original_name(name);
+
+    let mut names: Vec<String> = Vec::new();
+    for base in [original.clone(), format!("{}{}", original, SUPPLEMENTAL), stem] {
+        let candidate: String = format!("{}{}.json", truncate(&base, MAX_NAME), counter);
+        if !names.contains(&candidate) {
+            names.push(candidate);
+        }
+    }
+    names
+}
+
+/// The Takeout JSON of a photo or video next to it: photo.jpg.json, the
+/// supplemental-metadata variants cut at any length, photo.jpg(1).json for
+/// photo(1).jpg, the JSON of the original for photo-edited.jpg and names
+/// truncated at 46 characters. Names are matched case-insensitively.
+pub fn find_takeout_json(path: &str) -> Option<String> {
+    let path: &Path = Path::new(path);
+    let name: &str = path.file_name()?.to_str()?;
+    if name.to_ascii_lowercase().ends_with(".json") {
+        return None;
+    }
+    let directory: &Path = match path.parent() {
+        Some(d) if !d.as_os_str().is_empty() => d,
+        _ => Path::new(".")
+    };
+
+    let files: Vec<String> = fs::read_dir(directory)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.file_name().to_str().map(|n| n.to_string()))
+        .filter(|n| n.to_ascii_lowercase().ends_with(".json"))
+        .collect();
+
+    let found: Option<&String> = candidates(name)
+        .iter()
+        .find_map(|c| files.iter().find(|f| f.eq_ignore_ascii_case(c)))
+        .or_else(|| {
+            // photo.jpg.supplemental-metad.json, photo.jpg.suppl(1).json
+            let (original, _, counter) = original_name(name);
+            let full: String = format!("{}{}", original, SUPPLEMENTAL).to_ascii_lowercase();
+            let suffix: String = format!("{}.json", counter).to_ascii_lowercase();
+            files.iter().find(|f| {
+                let f: String = f.to_ascii_lowercase();
+                match f.strip_suffix(&suffix) {
+                    Some(cut) => cut.len() > original.len() + 1 && full.starts_with(cut),
+                    None => false
+                }
+            })
+        });
+    found.map(|f| directory.join(f).display().to_string())
+}
+
+/// Metadata of a Google Takeout export, read from the JSON next to the file.
+/// Takeout strips the EXIF dates and GPS of many files, the JSON keeps them:
+/// `photo_taken_time` and `creation_time` in Unix seconds, `latitude`,
+/// `longitude` and `altitude` when known, `title`, `description`, `people` and `url`.
+pub struct Takeout {
+    path: String
+}
+
+impl FromFile for Takeout {
+    fn file(path: &str) -> Self {
+        Self { path: path.to_string() }
+    }
+}
+
+impl Takeout {
+    fn add_str(meta: &mut Meta, tag: &str, value: String) {
+        if value.trim().is_empty() {
+            return;
+        }
+        meta.add(MetaAttribute{
+            source: MetaSource::Takeout,
+            tag: tag.to_string(),
+            value: MetaType::String(MetaValue::from(value)),
+        });
+    }
+
+    fn add_i64(meta: &mut Meta, tag: &str, value: i64) {
+        meta.add(MetaAttribute{
+            source: MetaSource::Takeout,
+            tag: tag.to_string(),
+            value: MetaType::Int64(MetaValue::from(value)),
+        });
+    }
+
+    fn add_f64(meta: &mut Meta, tag: &str, value: f64) {
+        meta.add(MetaAttribute{
+            source: MetaSource::Takeout,
+            tag: tag.to_string(),
+            value: MetaType::Rational(MetaValue::from(value)),
+        });
+    }
+
+    /// Timestamps are strings of Unix seconds, `{"timestamp": "1623757530", "formatted": ...}`.
+    fn timestamp(value: &Value) -> Option<i64> {
+        match &value["timestamp"] {
+            Value::String(s) => s.trim().parse().ok(),
+            Value::Number(n) => n.as_i64(),
+            _ => None
+        }.filter(|t| *t > 0)
+    }
+
+    /// Takeout writes zeros for an unknown location.
+    fn location(value: &Value) -> Option<(f64, f64, f64)> {
+        let latitude: f64 = value["latitude"].as_f64()?;
+        let longitude: f64 = value["longitude"].as_f64()?;
+        if latitude == 0.0 && longitude == 0.0 {
+            return None;
+        }
+        Some((latitude, longitude, value["altitude"].as_f64().unwrap_or(0.0)))
+    }
+}
+
+impl Extractor for Takeout {
+    fn name(&self) -> String {
+        return "TAKEOUT".to_string();
+    }
+
+    fn extract(&self, meta: &mut Meta) -> Result<(), MetaError> {
+        let json: String = match find_takeout_json(&self.path) {
+            Some(j) => j,
+            None => return Ok(())
+        };
+        let value: Value = serde_json::from_slice(&fs::read(&json)?)?;
+
+        // other exports write JSON next to files too
+        if !["photoTakenTime", "creationTime", "geoData"].iter().any(|k| value.get(k).is_some()) {
+            return Ok(());
+        }
+
+        let name: String = Path::new(&json).file_name().map_or(String::new(), |n| n.to_string_lossy().to_string());
+        Self::add_str(meta, "json", name);
+
+        for key in ["title", "description", "url"] {
+            if let Some(s) = value[key].as_str() {
+                Self::add_str(meta, key, s.to_string());
+            }
+        }
+        if let Some(t) = Self::timestamp(&value["photoTakenTime"]) {
+            Self::add_i64(meta, "photo_taken_time", t);
+        }
+        if let Some(t) = Self::timestamp(&value["creationTime"]) {
+            Self::add_i64(meta, "creation_time", t);
+        }
+        if let Some((latitude, longitude, altitude)) = Self::location(&value["geoData"]).or_else(|| Self::location(&value["geoDataExif"])) {
+            Self::add_f64(meta, "latitude", latitude);
+            Self::add_f64(meta, "longitude", longitude);
+            Self::add_f64(meta, "altitude", altitude);
+        }
+
+        // the sanitizer cuts values at commas
+        let people: Vec<&str> = value["people"]
+            .as_array()
+            .map(|p| p.iter().filter_map(|x| x["name"].as_str()).collect())
+            .unwrap_or_default();
+        Self::add_str(meta, "people", people.join("; "));
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::{fs, path::PathBuf};
+
+    use super::{candidates, find_takeout_json, Takeout};
+    use crate::{Extractor, FromFile, Meta, MetaSource};
+
+    fn directory(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir: PathBuf = std::env::temp_dir().join(format!("informant-takeout-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for (file, content) in files {
+            fs::write(dir.join(file), content).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_candidates() {
+        assert_eq!(candidates("IMG_1234.jpg"), vec!["IMG_1234.jpg.json", "IMG_1234.jpg.supplemental-metadata.json", "IMG_1234.json"]);
+        assert_eq!(candidates("IMG_1234(1).jpg")[0], "IMG_1234.jpg(1).json");
+        assert_eq!(candidates("IMG_1234-edited.jpg")[0], "IMG_1234.jpg.json");
+        assert_eq!(candidates("IMG_1234(2)-edited.jpg")[1], "IMG_1234.jpg.supplemental-metadata(2).json");
+
+        let long: &str = "Screenshot_20191121-194005_Google-Maps-Navigation.jpg";
+        assert_eq!(candidates(long)[0], "Screenshot_20191121-194005_Google-Maps-Navigat.json");
+    }
+
+    #[test]
+    fn test_find_json() {
+        let dir: PathBuf = directory("find", &[
+            ("a.jpg", ""), ("a.jpg.json", ""),
+            ("b(1).jpg", ""), ("b.jpg(1).json", ""), ("b.jpg.json", ""),
+            ("c.heic", ""), ("c.heic.supplemental-metad.json", ""),
+            ("d-edited.jpg", ""), ("D.JPG.JSON", ""),
+            ("e.jpg", ""), ("e.jpg.x.json", ""),
+        ]);
+        let found = |name: &str| find_takeout_json(dir.join(name).to_str().unwrap()).map(|p| PathBuf::from(p).file_name().unwrap().to_string_lossy().to_string());
+
+        assert_eq!(found("a.jpg").as_deref(), Some("a.jpg.json"));
+        assert_eq!(found("b(1).jpg").as_deref(), Some("b.jpg(1).json"));
+        assert_eq!(found("c.heic").as_deref(), Some("c.heic.supplemental-metad.json"));
+        assert_eq!(found("d-edited.jpg").as_deref(), Some("D.JPG.JSON"));
+        assert_eq!(found("e.jpg"), None);
+        assert_eq!(found("a.jpg.json"), None);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_takeout_meta() {
+        let dir: PathBuf = directory("meta", &[
+            ("IMG_0001.jpg", ""),
+            ("IMG_0001.jpg.supplemental-metadata.json", r#"{
+                "title": "IMG_0001.jpg", "description": "Lake day",
+                "photoTakenTime": {"timestamp": "1623757530", "formatted": "Jun 15, 2021, 11:45:30 AM UTC"},
+                "creationTime": {"timestamp": "1623800000"},
+                "geoData": {"latitude": 0.0, "longitude": 0.0, "altitude": 0.0},
+                "geoDataExif": {"latitude": 46.2044, "longitude": 6.1432, "altitude": 375.0},
+                "people": [{"name": "Ann"}, {"name": "Bob"}]}"#),
+            ("notes.txt", ""), ("notes.txt.json", r#"{"tags": []}"#),
+        ]);
+
+        let mut meta: Meta = Meta::new();
+        Takeout::file(dir.join("IMG_0001.jpg").to_str().unwrap()).extract(&mut meta).unwrap();
+        let value = |tag: &str| String::from(meta.find_one(MetaSource::Takeout, tag).unwrap().value);
+        assert_eq!(value("photo_taken_time"), "1623757530");
+        assert_eq!(value("latitude"), "46.2044");
+        assert_eq!(value("people"), "Ann; Bob");
+        assert_eq!(value("description"), "Lake day");
+
+        let mut other: Meta = Meta::new();
+        Takeout::file(dir.join("notes.txt").to_str().unwrap()).extract(&mut other).unwrap();
+        assert!(other.0.is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
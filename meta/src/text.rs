@@ -0,0 +1,608 @@
+use std::{fs::File, io::Read, path::Path};
+
+use serde_json::Value;
+
+use crate::{
+    meta::{MetaAttribute, MetaSource, MetaType, MetaValue},
+    Extractor, FromFile, Meta, MetaError};
+
+/// Only this much of a file is read, counts then cover the prefix.
+const MAX_TEXT: u64 = 64 * 1024 * 1024;
+
+/// Bytes inspected when guessing the encoding.
+const SNIFF_SIZE: usize = 64 * 1024;
+
+/// JSON object keys listed in `json.keys`.
+const MAX_KEYS: usize = 32;
+
+/// Windows-1252 characters for 0x80..=0x9F, where Latin-1 has control codes.
+const CP1252: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8D}', 'Ž', '\u{8F}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9D}', 'ž', 'Ÿ',
+];
+
+/// vCard properties and the tags they are stored under.
+const VCARD_KEYS: [(&str, &str); 8] = [
+    ("FN", "vcard.name"),
+    ("ORG", "vcard.organization"),
+    ("TITLE", "vcard.title"),
+    ("EMAIL", "vcard.email"),
+    ("TEL", "vcard.phone"),
+    ("BDAY", "vcard.birthday"),
+    ("REV", "vcard.revision"),
+    ("VERSION", "vcard.version"),
+];
+
+/// iCalendar properties of the first event and the tags they are stored under.
+const EVENT_KEYS: [(&str, &str); 3] = [
+    ("SUMMARY", "ical.summary"),
+    ("LOCATION", "ical.location"),
+    ("UID", "ical.uid"),
+];
+
+/// Plain and structured text: encoding, line/word/character counts, and the
+/// key fields of XML, JSON, CSV, vCard and iCalendar content.
+pub struct Text { path: String }
+
+impl FromFile for Text {
+    fn file(path: &str) -> Self {
+        Self { path: path.to_string() }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Encoding {
+    Ascii,
+    Utf8,
+    Utf16LE,
+    Utf16BE,
+    Latin1,
+    Windows1252,
+    Binary,
+}
+
+impl From<&Encoding> for String {
+    fn from(value: &Encoding) -> Self {
+        String::from(match value {
+            Encoding::Ascii => "ASCII",
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf16LE => "UTF-16LE",
+            Encoding::Utf16BE => "UTF-16BE",
+            Encoding::Latin1 => "ISO-8859-1",
+            Encoding::Windows1252 => "Windows-1252",
+            Encoding::Binary => "binary",
+        })
+    }
+}
+
+/// The encoding of `data` and the length of its byte order mark.
+fn detect_encoding(data: &[u8]) -> (Encoding, usize) {
+    if data.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        return (Encoding::Utf8, 3);
+    }
+    if data.starts_with(&[0xFF, 0xFE]) {
+        return (Encoding::Utf16LE, 2);
+    }
+    if data.starts_with(&[0xFE, 0xFF]) {
+        return (Encoding::Utf16BE, 2);
+    }
+
+    let sample: &[u8] = &data[..data.len().min(SNIFF_SIZE)];
+
+    // BOM-less UTF-16 shows up as a zero in every other byte of ASCII text
+    let pairs: usize = sample.len() / 2;
+    if pairs > 0 {
+        let even: usize = sample.iter().step_by(2).filter(|b| **b == 0).count();
+        let odd: usize = sample.iter().skip(1).step_by(2).filter(|b| **b == 0).count();
+        if odd * 10 > pairs * 3 && even * 10 < pairs {
+            return (Encoding::Utf16LE, 0);
+        }
+        if even * 10 > pairs * 3 && odd * 10 < pairs {
+            return (Encoding::Utf16BE, 0);
+        }
+    }
+
+    let controls: usize = sample.iter().filter(|b| matches!(**b, 0..=8 | 14..=26 | 28..=31)).count();
+    if controls * 100 > sample.len() {
+        return (Encoding::Binary, 0);
+    }
+
+    if data.is_ascii() {
+        return (Encoding::Ascii, 0);
+    }
+    // a multi-byte sequence cut off by the read limit is still UTF-8
+    match std::str::from_utf8(data) {
+        Ok(_) => return (Encoding::Utf8, 0),
+        Err(e) if e.error_len().is_none() && data.len() as u64 == MAX_TEXT => return (Encoding::Utf8, 0),
+        Err(_) => ()
+    }
+    if data.iter().any(|b| (0x80..=0x9F).contains(b)) {
+        return (Encoding::Windows1252, 0);
+    }
+    (Encoding::Latin1, 0)
+}
+
+fn decode(data: &[u8], encoding: &Encoding) -> String {
+    match encoding {
+        Encoding::Utf16LE | Encoding::Utf16BE => {
+            let units: Vec<u16> = data
+                .chunks_exact(2)
+                .map(|c| match encoding {
+                    Encoding::Utf16LE => u16::from_le_bytes([c[0], c[1]]),
+                    _ => u16::from_be_bytes([c[0], c[1]]),
+                })
+                .collect();
+            String::from_utf16_lossy(&units)
+        },
+        Encoding::Latin1 => data.iter().map(|b| *b as char).collect(),
+        Encoding::Windows1252 => data
+            .iter()
+            .map(|b| match b {
+                0x80..=0x9F => CP1252[(b - 0x80) as usize],
+                _ => *b as char,
+            })
+            .collect(),
+        _ => String::from_utf8_lossy(data).into_owned(),
+    }
+}
+
+/// Content lines of vCard and iCalendar files with folded lines joined.
+fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+/// Name, parameters and value of a vCard or iCalendar content line.
+type ContentLine = (String, Vec<(String, String)>, String);
+
+/// Split a content line into its upper-cased name (group dropped), parameters and value.
+fn content_line(line: &str) -> Option<ContentLine> {
+    let (head, value) = line.split_once(':')?;
+    let mut parts = head.split(';');
+    let name: &str = parts.next()?;
+    let name: &str = name.rsplit('.').next().unwrap_or(name);
+
+    let params: Vec<(String, String)> = parts
+        .filter_map(|p| p.split_once('='))
+        .map(|(k, v)| (k.to_ascii_uppercase(), v.trim_matches('"').to_string()))
+        .collect();
+
+    let value: String = value
+        .replace("\\n", " ")
+        .replace("\\N", " ")
+        .replace("\\,", ",")
+        .replace("\\;", ";")
+        .replace("\\\\", "\\");
+    Some((name.to_ascii_uppercase(), params, value))
+}
+
+/// An iCalendar DATE or DATE-TIME as an EXIF style time, and whether it is UTC.
+fn parse_ical_date(value: &str) -> Option<(String, bool)> {
+    let value: &str = value.trim();
+    let (date, time) = match value.split_once('T') {
+        Some((d, t)) => (d, t),
+        None => (value, "000000")
+    };
+    let utc: bool = time.ends_with('Z');
+    let time: &str = time.trim_end_matches('Z');
+    if date.len() != 8 || time.len() != 6 || !date.bytes().chain(time.bytes()).all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let (month, day): (u32, u32) = (date[4..6].parse().ok()?, date[6..8].parse().ok()?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    Some((format!("{}:{}:{} {}:{}:{}", &date[0..4], &date[4..6], &date[6..8], &time[0..2], &time[2..4], &time[4..6]), utc))
+}
+
+/// Fields of a delimited line, honouring double quotes.
+fn split_delimited(line: &str, delimiter: char) -> Vec<String> {
+    let mut fields: Vec<String> = Vec::new();
+    let mut field: String = String::new();
+    let mut quoted: bool = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            },
+            '"' => quoted = !quoted,
+            _ if c == delimiter && !quoted => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// The delimiter splitting the first lines into the same number of fields.
+/// Without a csv/tsv extension at least three lines must agree.
+fn detect_delimiter(lines: &[&str], expected: bool) -> Option<char> {
+    let sample: Vec<&str> = lines.iter().take(5).copied().collect();
+    if sample.len() < if expected { 1 } else { 3 } {
+        return None;
+    }
+    ['\t', ',', ';', '|']
+        .into_iter()
+        .find(|d| {
+            let columns: usize = split_delimited(sample[0], *d).len();
+            columns > 1 && sample.iter().all(|l| split_delimited(l, *d).len() == columns)
+        })
+}
+
+fn delimiter_name(delimiter: char) -> &'static str {
+    match delimiter {
+        '\t' => "tab",
+        ',' => "comma",
+        ';' => "semicolon",
+        _ => "pipe",
+    }
+}
+
+impl Text {
+    fn add_str(meta: &mut Meta, tag: &str, value: String) {
+        if value.trim().is_empty() {
+            return;
+        }
+        meta.add(MetaAttribute{
+            source: MetaSource::Text,
+            tag: tag.to_string(),
+            value: MetaType::String(MetaValue::from(value)),
+        });
+    }
+
+    fn add_u64(meta: &mut Meta, tag: &str, value: u64) {
+        meta.add(MetaAttribute{
+            source: MetaSource::Text,
+            tag: tag.to_string(),
+            value: MetaType::UInt64(MetaValue::from(value)),
+        });
+    }
+
+    fn add_counts(text: &str, meta: &mut Meta) {
+        let crlf: usize = text.matches("\r\n").count();
+        let lf: usize = text.matches('\n').count() - crlf;
+        let cr: usize = text.matches('\r').count() - crlf;
+        let line_ending: Option<&str> = match (crlf > 0, lf > 0, cr > 0) {
+            (false, false, false) => None,
+            (true, false, false) => Some("crlf"),
+            (false, true, false) => Some("lf"),
+            (false, false, true) => Some("cr"),
+            _ => Some("mixed"),
+        };
+
+        let breaks: usize = crlf + lf + cr;
+        let unterminated: bool = !text.is_empty() && !text.ends_with(['\n', '\r']);
+        Self::add_u64(meta, "lines", (breaks + usize::from(unterminated)) as u64);
+        Self::add_u64(meta, "words", text.split_whitespace().count() as u64);
+        Self::add_u64(meta, "characters", text.chars().count() as u64);
+        if let Some(line_ending) = line_ending {
+            Self::add_str(meta, "line_ending", line_ending.to_string());
+        }
+    }
+
+    fn parse_xml(text: &str, meta: &mut Meta) -> bool {
+        // skip the declaration, comments, processing instructions and doctype
+        let mut rest: &str = text;
+        while let Some(start) = rest.find('<') {
+            rest = &rest[start + 1..];
+            if !rest.starts_with(['?', '!']) {
+                break;
+            }
+        }
+        let tag: &str = &rest[..rest.find('>').unwrap_or(rest.len())];
+        let root: &str = tag.split(|c: char| c.is_whitespace() || c == '/').next().unwrap_or("");
+        if root.is_empty() {
+            return false;
+        }
+
+        Self::add_str(meta, "format", "xml".to_string());
+        Self::add_str(meta, "xml.root", root.to_string());
+
+        let prefix: String = match root.split_once(':') {
+            Some((p, _)) => format!("xmlns:{}", p),
+            None => "xmlns".to_string()
+        };
+        if let Some(namespace) = crate::xml::attribute(tag, &prefix) {
+            Self::add_str(meta, "xml.namespace", namespace);
+        }
+        true
+    }
+
+    fn parse_json(text: &str, meta: &mut Meta) -> bool {
+        let value: Value = match serde_json::from_str(text) {
+            Ok(v) => v,
+            Err(_) => return false
+        };
+        Self::add_str(meta, "format", "json".to_string());
+
+        match value {
+            Value::Object(map) => {
+                Self::add_str(meta, "json.type", "object".to_string());
+                Self::add_u64(meta, "json.length", map.len() as u64);
+                let keys: Vec<&str> = map.keys().take(MAX_KEYS).map(String::as_str).collect();
+                Self::add_str(meta, "json.keys", keys.join(" "));
+            },
+            Value::Array(items) => {
+                Self::add_str(meta, "json.type", "array".to_string());
+                Self::add_u64(meta, "json.length", items.len() as u64);
+            },
+            _ => Self::add_str(meta, "json.type", "scalar".to_string()),
+        }
+        true
+    }
+
+    fn parse_vcard(lines: &[String], meta: &mut Meta) -> bool {
+        Self::add_str(meta, "format", "vcard".to_string());
+
+        let mut cards: u64 = 0;
+        let mut seen: Vec<&str> = Vec::new();
+        let mut structured_name: Option<String> = None;
+
+        for line in lines {
+            let (name, _, value) = match content_line(line) {
+                Some(l) => l,
+                None => continue
+            };
+            if name == "BEGIN" && value.trim().eq_ignore_ascii_case("VCARD") {
+                cards += 1;
+            }
+            // key fields come from the first card
+            if cards != 1 {
+                continue;
+            }
+            if name == "N" && structured_name.is_none() {
+                // family;given;additional;prefix;suffix
+                let parts: Vec<&str> = value.split(';').collect();
+                let given: Vec<&str> = [parts.get(3), parts.get(1), parts.get(2), parts.first(), parts.get(4)]
+                    .into_iter()
+                    .flatten()
+                    .map(|p| p.trim())
+                    .filter(|p| !p.is_empty())
+                    .collect();
+                structured_name = Some(given.join(" "));
+            }
+            if let Some((_, tag)) = VCARD_KEYS.iter().find(|(key, _)| *key == name) {
+                if !seen.contains(tag) {
+                    seen.push(tag);
+                    Self::add_str(meta, tag, value);
+                }
+            }
+        }
+
+        if !seen.contains(&"vcard.name") {
+            if let Some(name) = structured_name {
+                Self::add_str(meta, "vcard.name", name);
+            }
+        }
+        Self::add_u64(meta, "vcard.count", cards);
+        true
+    }
+
+    fn parse_ical(lines: &[String], meta: &mut Meta) -> bool {
+        Self::add_str(meta, "format", "icalendar".to_string());
+
+        let (mut events, mut todos) = (0u64, 0u64);
+        let mut seen: Vec<String> = Vec::new();
+
+        for line in lines {
+            let (name, params, value) = match content_line(line) {
+                Some(l) => l,
+                None => continue
+            };
+            match (name.as_str(), value.trim()) {
+                ("BEGIN", "VEVENT") => events += 1,
+                ("BEGIN", "VTODO") => todos += 1,
+                ("PRODID", _) => Self::add_str(meta, "ical.product", value),
+                ("X-WR-CALNAME", _) => Self::add_str(meta, "ical.name", value),
+                ("SUMMARY" | "LOCATION" | "UID", _) if events == 1 && !seen.contains(&name) => {
+                    let tag: &str = EVENT_KEYS.iter().find(|(k, _)| *k == name).map(|(_, t)| *t).unwrap_or("");
+                    Self::add_str(meta, tag, value);
+                    seen.push(name);
+                },
+                ("DTSTART" | "DTEND", _) if events == 1 && !seen.contains(&name) => {
+                    let tag: String = format!("ical.{}", name.to_ascii_lowercase());
+                    if let Some((local, utc)) = parse_ical_date(&value) {
+                        Self::add_str(meta, &tag, local);
+                        if utc {
+                            meta.add(MetaAttribute{
+                                source: MetaSource::Text,
+                                tag: format!("{}.offset", tag),
+                                value: MetaType::Int64(MetaValue::from(0i64)),
+                            });
+                        }
+                        if let Some((_, zone)) = params.iter().find(|(k, _)| k == "TZID") {
+                            Self::add_str(meta, &format!("{}.tzid", tag), zone.clone());
+                        }
+                    }
+                    seen.push(name);
+                },
+                _ => ()
+            }
+        }
+        Self::add_u64(meta, "ical.events", events);
+        Self::add_u64(meta, "ical.todos", todos);
+        true
+    }
+
+    fn parse_delimited(text: &str, expected: bool, meta: &mut Meta) -> bool {
+        let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).collect();
+        let delimiter: char = match detect_delimiter(&lines, expected) {
+            Some(d) => d,
+            None => return false
+        };
+        let header: Vec<String> = split_delimited(lines[0], delimiter)
+            .into_iter()
+            .map(|h| h.trim().to_string())
+            .collect();
+
+        Self::add_str(meta, "format", "csv".to_string());
+        Self::add_str(meta, "csv.delimiter", delimiter_name(delimiter).to_string());
+        Self::add_u64(meta, "csv.columns", header.len() as u64);
+        Self::add_u64(meta, "csv.rows", (lines.len() - 1) as u64);
+        Self::add_str(meta, "csv.header", header.join(" | "));
+        true
+    }
+}
+
+impl Extractor for Text {
+    fn name(&self) -> String {
+        return "TEXT".to_string();
+    }
+
+    fn extract(&self, meta: &mut Meta) -> Result<(), MetaError> {
+        let extension: String = Path::new(&self.path)
+            .extension()
+            .map_or(String::new(), |x| x.to_string_lossy().to_ascii_lowercase());
+
+        let mut data: Vec<u8> = Vec::new();
+        File::open(&self.path)?.take(MAX_TEXT).read_to_end(&mut data)?;
+
+        let (encoding, bom) = detect_encoding(&data);
+        Self::add_str(meta, "encoding", String::from(&encoding));
+        if encoding == Encoding::Binary {
+            return Ok(());
+        }
+        Self::add_u64(meta, "bom", u64::from(bom > 0));
+        if data.len() as u64 == MAX_TEXT {
+            Self::add_u64(meta, "truncated", 1);
+        }
+
+        let text: String = decode(&data[bom..], &encoding);
+        Self::add_counts(&text, meta);
+
+        let body: &str = text.trim_start();
+        let head: String = body.chars().take(16).collect::<String>().to_ascii_uppercase();
+
+        let delimited: bool = matches!(extension.as_str(), "csv" | "tsv");
+
+        let structured: bool = if head.starts_with("BEGIN:VCARD") {
+            Self::parse_vcard(&unfold(body), meta)
+        }
+        else if head.starts_with("BEGIN:VCALENDAR") {
+            Self::parse_ical(&unfold(body), meta)
+        }
+        else if body.starts_with('<') {
+            Self::parse_xml(body, meta)
+        }
+        else {
+            (body.starts_with(['{', '[']) && Self::parse_json(body, meta))
+                || Self::parse_delimited(body, delimited, meta)
+        };
+
+        if !structured {
+            Self::add_str(meta, "format", "plain".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{detect_encoding, parse_ical_date, split_delimited, Encoding, Text};
+    use crate::{Extractor, FromFile, Meta, MetaSource, MetaType};
+
+    fn extract(data: &[u8], extension: &str) -> Meta {
+        let path = std::env::temp_dir().join(format!("informant_text_{}_{}.{}", std::process::id(), data.len(), extension));
+        std::fs::write(&path, data).unwrap();
+        let mut meta: Meta = Meta::new();
+        Text::file(path.to_str().unwrap()).extract(&mut meta).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        meta
+    }
+
+    fn value(meta: &Meta, tag: &str) -> String {
+        match meta.find_one(MetaSource::Text, tag).unwrap().value {
+            MetaType::String(v) => v.value,
+            MetaType::UInt64(v) => v.value.to_string(),
+            MetaType::Int64(v) => v.value.to_string(),
+            MetaType::Rational(v) => v.value.to_string(),
+            MetaType::Meta(_) => panic!("unexpected nested meta"),
+        }
+    }
+
+    #[test]
+    fn test_encoding() {
+        assert_eq!(detect_encoding(b"plain"), (Encoding::Ascii, 0));
+        assert_eq!(detect_encoding("caf\u{e9}".as_bytes()), (Encoding::Utf8, 0));
+        assert_eq!(detect_encoding(b"\xEF\xBB\xBFhi"), (Encoding::Utf8, 3));
+        assert_eq!(detect_encoding(b"\xFF\xFEh\x00i\x00"), (Encoding::Utf16LE, 2));
+        assert_eq!(detect_encoding(b"h\x00e\x00l\x00l\x00o\x00"), (Encoding::Utf16LE, 0));
+        assert_eq!(detect_encoding(b"caf\xE9"), (Encoding::Latin1, 0));
+        assert_eq!(detect_encoding(b"\x93quoted\x94"), (Encoding::Windows1252, 0));
+        assert_eq!(detect_encoding(b"\x00\x01\x02\x03binary\x04\x05"), (Encoding::Binary, 0));
+    }
+
+    #[test]
+    fn test_plain_counts() {
+        let meta: Meta = extract(b"\xFF\xFEo\x00n\x00e\x00 \x00t\x00w\x00o\x00\r\x00\n\x00t\x00h\x00r\x00e\x00e\x00", "txt");
+
+        assert_eq!(value(&meta, "encoding"), "UTF-16LE");
+        assert_eq!(value(&meta, "bom"), "1");
+        assert_eq!(value(&meta, "lines"), "2");
+        assert_eq!(value(&meta, "words"), "3");
+        assert_eq!(value(&meta, "characters"), "14");
+        assert_eq!(value(&meta, "line_ending"), "crlf");
+        assert_eq!(value(&meta, "format"), "plain");
+    }
+
+    #[test]
+    fn test_structured_formats() {
+        let meta: Meta = extract(b"<?xml version=\"1.0\"?>\n<!-- export -->\n<gpx:gpx xmlns:gpx=\"http://www.topografix.com/GPX/1/1\" version=\"1.1\"/>", "xml");
+        assert_eq!(value(&meta, "format"), "xml");
+        assert_eq!(value(&meta, "xml.root"), "gpx:gpx");
+        assert_eq!(value(&meta, "xml.namespace"), "http://www.topografix.com/GPX/1/1");
+
+        let meta: Meta = extract(br#"{"title": "Notes", "created": 1650000000, "tags": []}"#, "json");
+        assert_eq!(value(&meta, "format"), "json");
+        assert_eq!(value(&meta, "json.type"), "object");
+        assert_eq!(value(&meta, "json.keys"), "created tags title");
+
+        let meta: Meta = extract(b"date;amount;\"memo; note\"\n2021-01-01;10;\"rent\"\n2021-01-02;5;x\n", "csv");
+        assert_eq!(value(&meta, "format"), "csv");
+        assert_eq!(value(&meta, "csv.delimiter"), "semicolon");
+        assert_eq!(value(&meta, "csv.columns"), "3");
+        assert_eq!(value(&meta, "csv.rows"), "2");
+        assert_eq!(value(&meta, "csv.header"), "date | amount | memo; note");
+
+        let meta: Meta = extract(b"Dear John, how are you?\nFine, thanks.\n", "txt");
+        assert_eq!(value(&meta, "format"), "plain");
+    }
+
+    #[test]
+    fn test_vcard_and_icalendar() {
+        let vcard: &[u8] = b"BEGIN:VCARD\r\nVERSION:3.0\r\nN:Doe;Jane;;Dr.;\r\nitem1.EMAIL;TYPE=INTERNET:jane@example.com\r\nBDAY:1980-02-0\r\n 3\r\nEND:VCARD\r\nBEGIN:VCARD\r\nFN:Other\r\nEND:VCARD\r\n";
+        let meta: Meta = extract(vcard, "vcf");
+        assert_eq!(value(&meta, "format"), "vcard");
+        assert_eq!(value(&meta, "vcard.name"), "Dr. Jane Doe");
+        assert_eq!(value(&meta, "vcard.email"), "jane@example.com");
+        assert_eq!(value(&meta, "vcard.birthday"), "1980-02-03");
+        assert_eq!(value(&meta, "vcard.count"), "2");
+
+        let ical: &[u8] = b"BEGIN:VCALENDAR\nPRODID:-//Example//Cal//EN\nBEGIN:VEVENT\nSUMMARY:Dentist\nDTSTART;TZID=Europe/Berlin:20230405T093000\nDTEND:20230405T100000Z\nEND:VEVENT\nBEGIN:VEVENT\nSUMMARY:Later\nEND:VEVENT\nEND:VCALENDAR\n";
+        let meta: Meta = extract(ical, "ics");
+        assert_eq!(value(&meta, "format"), "icalendar");
+        assert_eq!(value(&meta, "ical.summary"), "Dentist");
+        assert_eq!(value(&meta, "ical.dtstart"), "2023:04:05 09:30:00");
+        assert_eq!(value(&meta, "ical.dtstart.tzid"), "Europe/Berlin");
+        assert_eq!(value(&meta, "ical.dtend.offset"), "0");
+        assert_eq!(value(&meta, "ical.events"), "2");
+    }
+
+    #[test]
+    fn test_helpers() {
+        assert_eq!(split_delimited("a,\"b,c\",\"d\"\"e\"", ','), vec!["a", "b,c", "d\"e"]);
+        assert_eq!(parse_ical_date("20200101"), Some(("2020:01:01 00:00:00".to_string(), false)));
+        assert_eq!(parse_ical_date("2020-01-01"), None);
+    }
+}
@@ -0,0 +1,559 @@
+use std::{fs::File, io::{BufReader, Read, Seek, SeekFrom}};
+
+use crate::{
+    meta::{MetaAttribute, MetaSource, MetaType, MetaValue},
+    Extractor, FromFile, Meta, MetaError};
+
+/// How much of the tail of an Ogg stream is scanned for the last granule position.
+const OGG_TAIL_WINDOW: u64 = 64 * 1024;
+
+/// Header packets larger than this (e.g. embedded cover art) are truncated.
+const MAX_PACKET: usize = 16 * 1024 * 1024;
+
+/// Opus always runs its granule clock at 48kHz.
+const OPUS_RATE: u64 = 48_000;
+
+/// Native FLAC and Ogg (Vorbis/Opus) metadata.
+pub struct Vorbis { path: String }
+
+impl Vorbis {
+    fn add_str(meta: &mut Meta, tag: &str, value: String) {
+        if value.trim().is_empty() {
+            return;
+        }
+        meta.add(MetaAttribute{
+            source: MetaSource::Vorbis,
+            tag: tag.to_string(),
+            value: MetaType::String(MetaValue::from(value)),
+        });
+    }
+
+    fn add_u64(meta: &mut Meta, tag: &str, value: u64) {
+        meta.add(MetaAttribute{
+            source: MetaSource::Vorbis,
+            tag: tag.to_string(),
+            value: MetaType::UInt64(MetaValue::from(value)),
+        });
+    }
+
+    fn add_duration(meta: &mut Meta, samples: u64, rate: u64) {
+        if rate == 0 {
+            return;
+        }
+        meta.add(MetaAttribute{
+            source: MetaSource::Vorbis,
+            tag: "duration".to_string(),
+            value: MetaType::Rational(MetaValue::from(samples as f64 / rate as f64)),
+        });
+    }
+
+    /// Vorbis comment block (shared by FLAC, Ogg Vorbis, and Opus).
+    /// Little endian: vendor length, vendor, comment count, then `KEY=value` strings.
+    fn parse_comments(data: &[u8], meta: &mut Meta) {
+        let mut reader: ByteReader = ByteReader::new(data);
+
+        if let Some(vendor) = reader.string_le() {
+            Self::add_str(meta, "vendor", vendor);
+        }
+
+        let count: u32 = reader.u32_le().unwrap_or(0);
+        for _ in 0..count {
+            let comment: String = match reader.string_le() {
+                Some(x) => x,
+                None => break
+            };
+            let (key, value) = match comment.split_once('=') {
+                Some(x) => x,
+                None => continue
+            };
+            let key: String = key.trim().to_ascii_lowercase();
+
+            // cover art is exposed via the picture block, not as a comment
+            if key == "metadata_block_picture" || key == "coverart" {
+                Self::add_u64(meta, "comment.has_picture", 1);
+                continue;
+            }
+            Self::add_str(meta, &format!("comment.{}", key), value.to_string());
+        }
+    }
+
+    /// FLAC STREAMINFO
+    fn parse_streaminfo(data: &[u8], meta: &mut Meta) {
+        if data.len() < 18 {
+            return;
+        }
+        let packed: u64 = u64::from_be_bytes([data[10], data[11], data[12], data[13], data[14], data[15], data[16], data[17]]);
+        let sample_rate: u64 = packed >> 44;
+        let channels: u64 = ((packed >> 41) & 0x07) + 1;
+        let bits_per_sample: u64 = ((packed >> 36) & 0x1f) + 1;
+        let total_samples: u64 = packed & 0x0f_ffff_ffff;
+
+        Self::add_u64(meta, "sample_rate", sample_rate);
+        Self::add_u64(meta, "channels", channels);
+        Self::add_u64(meta, "bits_per_sample", bits_per_sample);
+        Self::add_u64(meta, "total_samples", total_samples);
+        Self::add_duration(meta, total_samples, sample_rate);
+
+        if let Some(md5) = data.get(18..34) {
+            Self::add_str(meta, "md5", md5.iter().map(|b| format!("{:02x}", b)).collect());
+        }
+    }
+
+    /// FLAC PICTURE (big endian)
+    fn parse_picture(picture_no: usize, data: &[u8], meta: &mut Meta) {
+        let mut reader: ByteReader = ByteReader::new(data);
+        let picture_type: u32 = match reader.u32_be() {
+            Some(x) => x,
+            None => return
+        };
+        Self::add_u64(meta, &format!("picture_{}.type", picture_no), picture_type as u64);
+
+        if let Some(mime) = reader.string_be() {
+            Self::add_str(meta, &format!("picture_{}.mime", picture_no), mime);
+        }
+        if let Some(description) = reader.string_be() {
+            Self::add_str(meta, &format!("picture_{}.description", picture_no), description);
+        }
+        if let (Some(width), Some(height)) = (reader.u32_be(), reader.u32_be()) {
+            Self::add_u64(meta, &format!("picture_{}.width", picture_no), width as u64);
+            Self::add_u64(meta, &format!("picture_{}.height", picture_no), height as u64);
+        }
+        // depth, colors
+        reader.skip(8);
+        if let Some(size) = reader.u32_be() {
+            Self::add_u64(meta, &format!("picture_{}.size", picture_no), size as u64);
+        }
+    }
+
+    /// FLAC CUESHEET
+    fn parse_cuesheet(data: &[u8], meta: &mut Meta) {
+        if data.len() < 396 {
+            return;
+        }
+        let catalog: String = String::from_utf8_lossy(&data[0..128]).trim_matches('\0').to_string();
+        Self::add_str(meta, "cuesheet.catalog", catalog);
+
+        let lead_in: u64 = u64::from_be_bytes([data[128], data[129], data[130], data[131], data[132], data[133], data[134], data[135]]);
+        Self::add_u64(meta, "cuesheet.lead_in", lead_in);
+        Self::add_u64(meta, "cuesheet.is_cd", (data[136] >> 7) as u64);
+        Self::add_u64(meta, "cuesheet.tracks", data[395] as u64);
+    }
+
+    /// Skip an ID3v2 tag some encoders prepend to FLAC files.
+    fn skip_id3<R: Read + Seek>(reader: &mut R) -> Result<u64, MetaError> {
+        let mut header: [u8; 10] = [0; 10];
+        reader.seek(SeekFrom::Start(0))?;
+        if reader.read_exact(&mut header).is_err() || &header[0..3] != b"ID3" {
+            return Ok(0);
+        }
+        let size: u64 = header[6..10].iter().fold(0u64, |acc, b| (acc << 7) | (*b & 0x7f) as u64);
+        let footer: u64 = if header[5] & 0x10 != 0 { 10 } else { 0 };
+        Ok(10 + size + footer)
+    }
+
+    fn from_flac<R: Read + Seek>(&self, reader: &mut R, meta: &mut Meta) -> Result<(), MetaError> {
+        let start: u64 = Self::skip_id3(reader)?;
+        reader.seek(SeekFrom::Start(start))?;
+
+        let mut magic: [u8; 4] = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != b"fLaC" {
+            return Err(MetaError::from("not a FLAC file"));
+        }
+
+        Self::add_str(meta, "codec", "FLAC".to_string());
+
+        let mut picture_no: usize = 0;
+        loop {
+            let mut header: [u8; 4] = [0; 4];
+            if reader.read_exact(&mut header).is_err() {
+                break;
+            }
+            let last: bool = header[0] & 0x80 != 0;
+            let block_type: u8 = header[0] & 0x7f;
+            let length: usize = u32::from_be_bytes([0, header[1], header[2], header[3]]) as usize;
+            let next: u64 = reader.stream_position()? + length as u64;
+
+            // pictures can be large, their fields are all in front of the image data
+            let wanted: usize = match block_type {
+                6 => length.min(64 * 1024),
+                1..=3 => 0,
+                _ => length.min(MAX_PACKET),
+            };
+            let mut data: Vec<u8> = vec![0; wanted];
+            reader.read_exact(&mut data)?;
+
+            match block_type {
+                0 => Self::parse_streaminfo(&data, meta),
+                4 => Self::parse_comments(&data, meta),
+                5 => Self::parse_cuesheet(&data, meta),
+                6 => {
+                    Self::parse_picture(picture_no, &data, meta);
+                    picture_no += 1;
+                },
+                127 => break,
+                _ => ()
+            }
+
+            if last {
+                break;
+            }
+            reader.seek(SeekFrom::Start(next))?;
+        }
+
+        if picture_no > 0 {
+            Self::add_u64(meta, "picture_count", picture_no as u64);
+        }
+        Ok(())
+    }
+
+    fn from_ogg<R: Read + Seek>(&self, reader: &mut R, file_size: u64, meta: &mut Meta) -> Result<(), MetaError> {
+        let mut pages: OggPackets<R> = OggPackets::new(reader);
+
+        let identification: Vec<u8> = match pages.next_packet()? {
+            Some(x) => x,
+            None => {
+                return Err(MetaError::from("not an Ogg file"));
+            }
+        };
+
+        let (rate, pre_skip) = if identification.starts_with(b"\x01vorbis") && identification.len() >= 28 {
+            let mut r: ByteReader = ByteReader::new(&identification[7..]);
+            let _version: Option<u32> = r.u32_le();
+            let channels: u8 = r.u8().unwrap_or(0);
+            let rate: u32 = r.u32_le().unwrap_or(0);
+            let _maximum: Option<u32> = r.u32_le();
+            let nominal: u32 = r.u32_le().unwrap_or(0);
+
+            Self::add_str(meta, "codec", "Vorbis".to_string());
+            Self::add_u64(meta, "channels", channels as u64);
+            Self::add_u64(meta, "sample_rate", rate as u64);
+            if nominal > 0 && nominal < i32::MAX as u32 {
+                Self::add_u64(meta, "bitrate_nominal", nominal as u64);
+            }
+            (rate as u64, 0u64)
+        }
+        else if identification.starts_with(b"OpusHead") && identification.len() >= 19 {
+            let channels: u8 = identification[9];
+            let pre_skip: u16 = u16::from_le_bytes([identification[10], identification[11]]);
+            let input_rate: u32 = u32::from_le_bytes([identification[12], identification[13], identification[14], identification[15]]);
+
+            Self::add_str(meta, "codec", "Opus".to_string());
+            Self::add_u64(meta, "channels", channels as u64);
+            Self::add_u64(meta, "sample_rate", OPUS_RATE);
+            Self::add_u64(meta, "input_sample_rate", input_rate as u64);
+            Self::add_u64(meta, "pre_skip", pre_skip as u64);
+            (OPUS_RATE, pre_skip as u64)
+        }
+        else {
+            // Theora, Speex, FLAC and others carry nothing we read
+            return Ok(());
+        };
+
+        let serial: u32 = pages.serial.unwrap_or(0);
+
+        match pages.next_packet()? {
+            Some(c) if c.starts_with(b"\x03vorbis") => Self::parse_comments(&c[7..], meta),
+            Some(c) if c.starts_with(b"OpusTags") => Self::parse_comments(&c[8..], meta),
+            _ => ()
+        }
+
+        // Duration from the granule position of the last page of the stream.
+        let tail_start: u64 = file_size.saturating_sub(OGG_TAIL_WINDOW);
+        let reader: &mut R = pages.into_inner();
+        let mut tail: Vec<u8> = Vec::new();
+        reader.seek(SeekFrom::Start(tail_start))?;
+        reader.take(OGG_TAIL_WINDOW).read_to_end(&mut tail)?;
+
+        if let Some(granule) = last_granule(&tail, serial) {
+            Self::add_duration(meta, granule.saturating_sub(pre_skip), rate);
+        }
+
+        Ok(())
+    }
+}
+
+/// Find the granule position of the last page belonging to `serial`.
+fn last_granule(data: &[u8], serial: u32) -> Option<u64> {
+    let mut granule: Option<u64> = None;
+    let mut i: usize = 0;
+    while i + 27 <= data.len() {
+        if &data[i..i + 4] == b"OggS" {
+            let page_serial: u32 = u32::from_le_bytes([data[i + 14], data[i + 15], data[i + 16], data[i + 17]]);
+            let position: i64 = i64::from_le_bytes([
+                data[i + 6], data[i + 7], data[i + 8], data[i + 9],
+                data[i + 10], data[i + 11], data[i + 12], data[i + 13]]);
+            if page_serial == serial && position >= 0 {
+                granule = Some(position as u64);
+            }
+            i += 27;
+        }
+        else {
+            i += 1;
+        }
+    }
+    granule
+}
+
+/// Reassembles packets of the first logical stream of an Ogg file.
+struct OggPackets<'a, R: Read + Seek> {
+    reader: &'a mut R,
+    serial: Option<u32>,
+    segments: Vec<u8>,
+    segment: usize,
+}
+
+impl<'a, R: Read + Seek> OggPackets<'a, R> {
+    fn new(reader: &'a mut R) -> Self {
+        Self { reader, serial: None, segments: Vec::new(), segment: 0 }
+    }
+
+    fn into_inner(self) -> &'a mut R {
+        self.reader
+    }
+
+    /// Read the next page header, returning false at end of file.
+    fn next_page(&mut self) -> Result<bool, MetaError> {
+        loop {
+            let mut header: [u8; 27] = [0; 27];
+            if self.reader.read_exact(&mut header).is_err() {
+                return Ok(false);
+            }
+            if &header[0..4] != b"OggS" {
+                return Err(MetaError::from("invalid Ogg page"));
+            }
+            let serial: u32 = u32::from_le_bytes([header[14], header[15], header[16], header[17]]);
+            let mut lacing: Vec<u8> = vec![0; header[26] as usize];
+            self.reader.read_exact(&mut lacing)?;
+
+            if *self.serial.get_or_insert(serial) != serial {
+                // interleaved page from another logical stream
+                let skip: i64 = lacing.iter().map(|x| *x as i64).sum();
+                self.reader.seek(SeekFrom::Current(skip))?;
+                continue;
+            }
+
+            self.segments = lacing;
+            self.segment = 0;
+            return Ok(true);
+        }
+    }
+
+    fn next_packet(&mut self) -> Result<Option<Vec<u8>>, MetaError> {
+        let mut packet: Vec<u8> = Vec::new();
+        loop {
+            if self.segment >= self.segments.len() && !self.next_page()? {
+                return Ok((!packet.is_empty()).then_some(packet));
+            }
+            while self.segment < self.segments.len() {
+                let length: usize = self.segments[self.segment] as usize;
+                self.segment += 1;
+
+                if packet.len() + length <= MAX_PACKET {
+                    let start: usize = packet.len();
+                    packet.resize(start + length, 0);
+                    self.reader.read_exact(&mut packet[start..])?;
+                }
+                else {
+                    self.reader.seek(SeekFrom::Current(length as i64))?;
+                }
+
+                if length < 255 {
+                    return Ok(Some(packet));
+                }
+            }
+        }
+    }
+}
+
+struct ByteReader<'a> {
+    data: &'a [u8],
+    at: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, at: 0 }
+    }
+
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let slice: &[u8] = self.data.get(self.at..self.at.checked_add(len)?)?;
+        self.at += len;
+        Some(slice)
+    }
+
+    fn skip(&mut self, len: usize) {
+        self.at = self.at.saturating_add(len);
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|x| x[0])
+    }
+
+    fn u32_le(&mut self) -> Option<u32> {
+        self.take(4).map(|x| u32::from_le_bytes([x[0], x[1], x[2], x[3]]))
+    }
+
+    fn u32_be(&mut self) -> Option<u32> {
+        self.take(4).map(|x| u32::from_be_bytes([x[0], x[1], x[2], x[3]]))
+    }
+
+    fn string_le(&mut self) -> Option<String> {
+        let len: usize = self.u32_le()? as usize;
+        self.take(len).map(|x| String::from_utf8_lossy(x).to_string())
+    }
+
+    fn string_be(&mut self) -> Option<String> {
+        let len: usize = self.u32_be()? as usize;
+        self.take(len).map(|x| String::from_utf8_lossy(x).to_string())
+    }
+}
+
+impl FromFile for Vorbis {
+    fn file(path: &str) -> Self {
+        Self { path: path.to_string() }
+    }
+}
+
+impl Extractor for Vorbis {
+    fn name(&self) -> String {
+        return "VORBIS".to_string();
+    }
+
+    fn extract(&self, meta: &mut Meta) -> Result<(), MetaError> {
+        let file: File = File::open(&self.path)?;
+        let size: u64 = file.metadata()?.len();
+        let mut reader: BufReader<File> = BufReader::new(file);
+
+        let start: u64 = Self::skip_id3(&mut reader)?;
+        let mut magic: [u8; 4] = [0; 4];
+        reader.seek(SeekFrom::Start(start))?;
+        reader.read_exact(&mut magic)?;
+        reader.seek(SeekFrom::Start(0))?;
+
+        match &magic {
+            b"fLaC" => self.from_flac(&mut reader, meta),
+            b"OggS" => self.from_ogg(&mut reader, size, meta),
+            _ => Err(MetaError::from("not a FLAC or Ogg file"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+    use super::Vorbis;
+    use crate::{MetaError, FromFile, Extractor, Meta, MetaSource};
+
+    const TEST_AUDIO: &str = "../testdata/Audio/test.flac";
+
+    fn comments() -> Vec<u8> {
+        let mut data: Vec<u8> = Vec::new();
+        let vendor: &[u8] = b"reference libFLAC 1.4.2";
+        data.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+        data.extend_from_slice(vendor);
+        data.extend_from_slice(&2u32.to_le_bytes());
+        for c in [&b"TITLE=Razorblade"[..], &b"DATE=2003-06-01"[..]] {
+            data.extend_from_slice(&(c.len() as u32).to_le_bytes());
+            data.extend_from_slice(c);
+        }
+        data
+    }
+
+    #[test]
+    fn test_parse_flac_synthetic() {
+        let mut data: Vec<u8> = b"fLaC".to_vec();
+
+        // STREAMINFO: 44100Hz, 2 channels, 16 bits, 441000 samples
+        let mut info: Vec<u8> = vec![0; 34];
+        let packed: u64 = (44_100u64 << 44) | (1 << 41) | (15 << 36) | 441_000;
+        info[10..18].copy_from_slice(&packed.to_be_bytes());
+        data.extend_from_slice(&[0x00, 0x00, 0x00, 34]);
+        data.extend_from_slice(&info);
+
+        let c: Vec<u8> = comments();
+        data.push(0x84);
+        data.extend_from_slice(&(c.len() as u32).to_be_bytes()[1..]);
+        data.extend_from_slice(&c);
+
+        let mut meta: Meta = Meta::new();
+        Vorbis::file(TEST_AUDIO).from_flac(&mut Cursor::new(data), &mut meta).unwrap();
+
+        assert_eq!(u64::from(meta.find_one(MetaSource::Vorbis, "sample_rate").unwrap().value), 44_100);
+        assert_eq!(u64::from(meta.find_one(MetaSource::Vorbis, "channels").unwrap().value), 2);
+        assert_eq!(String::from(meta.find_one(MetaSource::Vorbis, "duration").unwrap().value), "10");
+        assert_eq!(String::from(meta.find_one(MetaSource::Vorbis, "comment.title").unwrap().value), "Razorblade");
+        assert_eq!(String::from(meta.find_one(MetaSource::Vorbis, "comment.date").unwrap().value), "2003-06-01");
+    }
+
+    fn page(serial: u32, granule: i64, packets: &[&[u8]]) -> Vec<u8> {
+        let mut lacing: Vec<u8> = Vec::new();
+        let mut body: Vec<u8> = Vec::new();
+        for p in packets {
+            let mut len: usize = p.len();
+            while len >= 255 {
+                lacing.push(255);
+                len -= 255;
+            }
+            lacing.push(len as u8);
+            body.extend_from_slice(p);
+        }
+        let mut data: Vec<u8> = b"OggS".to_vec();
+        data.extend_from_slice(&[0, 0]);
+        data.extend_from_slice(&granule.to_le_bytes());
+        data.extend_from_slice(&serial.to_le_bytes());
+        data.extend_from_slice(&[0; 8]);
+        data.push(lacing.len() as u8);
+        data.extend_from_slice(&lacing);
+        data.extend_from_slice(&body);
+        data
+    }
+
+    #[test]
+    fn test_parse_opus_synthetic() {
+        let mut head: Vec<u8> = b"OpusHead".to_vec();
+        head.extend_from_slice(&[1, 2]);
+        head.extend_from_slice(&312u16.to_le_bytes());
+        head.extend_from_slice(&44_100u32.to_le_bytes());
+        head.extend_from_slice(&[0, 0, 0]);
+
+        let mut tags: Vec<u8> = b"OpusTags".to_vec();
+        tags.extend_from_slice(&comments());
+
+        let mut data: Vec<u8> = page(7, 0, &[&head]);
+        data.extend_from_slice(&page(7, 0, &[&tags]));
+        data.extend_from_slice(&page(7, 48_000 * 3 + 312, &[&[0u8; 10]]));
+
+        let size: u64 = data.len() as u64;
+        let mut meta: Meta = Meta::new();
+        Vorbis::file(TEST_AUDIO).from_ogg(&mut Cursor::new(data), size, &mut meta).unwrap();
+
+        assert_eq!(String::from(meta.find_one(MetaSource::Vorbis, "codec").unwrap().value), "Opus");
+        assert_eq!(String::from(meta.find_one(MetaSource::Vorbis, "duration").unwrap().value), "3");
+        assert_eq!(String::from(meta.find_one(MetaSource::Vorbis, "comment.title").unwrap().value), "Razorblade");
+    }
+
+    #[test]
+    fn test_other_ogg_codec() {
+        let data: Vec<u8> = page(3, 0, &[b"\x80theora\x03\x02\x01"]);
+        let size: u64 = data.len() as u64;
+        let mut meta: Meta = Meta::new();
+        Vorbis::file(TEST_AUDIO).from_ogg(&mut Cursor::new(data), size, &mut meta).unwrap();
+        assert!(meta.0.is_empty());
+    }
+
+    #[test]
+    fn test_parse() {
+        let mut meta: Meta = Meta::new();
+        let extractor: Vorbis = Vorbis::file(TEST_AUDIO);
+        let result: Result<(), MetaError> = extractor.extract(&mut meta);
+        match result {
+            Ok(_) => {
+                println!("{:#?}", meta);
+            },
+            Err(e) => {
+                println!("test error {:#?}", e);
+            }
+        }
+    }
+}
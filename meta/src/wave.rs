@@ -0,0 +1,397 @@
+use std::{fs::File, io::{BufReader, Read, Seek, SeekFrom}, sync::LazyLock};
+
+use regex::Regex;
+
+use crate::{
+    meta::{MetaAttribute, MetaSource, MetaType, MetaValue},
+    riff::{self, Chunk},
+    Extractor, FromFile, Meta, MetaError};
+
+/// iXML elements worth keeping (field recorders).
+const IXML_TAGS: [&str; 10] = [
+    "PROJECT", "SCENE", "TAKE", "TAPE", "NOTE",
+    "CIRCLED", "FILE_UID", "UBITS", "TIMECODE_RATE", "TIMECODE_FLAG"
+];
+
+/// One pattern per iXML element, compiled on first use.
+static IXML_PATTERNS: LazyLock<Vec<(&str, Regex)>> = LazyLock::new(|| {
+    IXML_TAGS
+        .iter()
+        .map(|tag| (*tag, Regex::new(&format!(r"<{0}>\s*([^<]*?)\s*</{0}>", tag)).unwrap()))
+        .collect()
+});
+
+/// WAV/BWF (RIFF) and AIFF/AIFC (IFF) chunk metadata.
+pub struct Wave { path: String }
+
+impl Wave {
+    fn add_str(meta: &mut Meta, tag: &str, value: String) {
+        if value.trim().is_empty() {
+            return;
+        }
+        meta.add(MetaAttribute{
+            source: MetaSource::Wave,
+            tag: tag.to_string(),
+            value: MetaType::String(MetaValue::from(value)),
+        });
+    }
+
+    fn add_u64(meta: &mut Meta, tag: &str, value: u64) {
+        meta.add(MetaAttribute{
+            source: MetaSource::Wave,
+            tag: tag.to_string(),
+            value: MetaType::UInt64(MetaValue::from(value)),
+        });
+    }
+
+    fn add_duration(meta: &mut Meta, seconds: f64) {
+        if !seconds.is_finite() {
+            return;
+        }
+        meta.add(MetaAttribute{
+            source: MetaSource::Wave,
+            tag: "duration".to_string(),
+            value: MetaType::Rational(MetaValue::from(seconds)),
+        });
+    }
+
+    /// Fixed width, zero padded ASCII field.
+    fn text(data: &[u8], from: usize, len: usize) -> String {
+        data.get(from..(from + len).min(data.len()))
+            .map(riff::read_text)
+            .unwrap_or_default()
+    }
+
+    /// WAVEFORMATEX
+    fn get_fmt(data: &[u8], meta: &mut Meta) -> u32 {
+        let format_tag: u16 = riff::read_u16(data, 0).unwrap_or(0);
+        let byte_rate: u32 = riff::read_u32(data, 8).unwrap_or(0);
+
+        Self::add_u64(meta, "format_tag", format_tag as u64);
+        Self::add_str(meta, "audio_format", riff::audio_format(format_tag).to_string());
+
+        if let Some(channels) = riff::read_u16(data, 2) {
+            Self::add_u64(meta, "channels", channels as u64);
+        }
+        if let Some(sample_rate) = riff::read_u32(data, 4) {
+            Self::add_u64(meta, "sample_rate", sample_rate as u64);
+        }
+        if let Some(bits) = riff::read_u16(data, 14) {
+            Self::add_u64(meta, "bits_per_sample", bits as u64);
+        }
+        Self::add_u64(meta, "bitrate", byte_rate as u64 * 8);
+
+        byte_rate
+    }
+
+    /// Broadcast Wave Format extension (EBU Tech 3285)
+    fn get_bext(data: &[u8], meta: &mut Meta) {
+        Self::add_str(meta, "bext.description", Self::text(data, 0, 256));
+        Self::add_str(meta, "bext.originator", Self::text(data, 256, 32));
+        Self::add_str(meta, "bext.originator_reference", Self::text(data, 288, 32));
+
+        let date: String = Self::text(data, 320, 10);
+        let time: String = Self::text(data, 330, 8);
+        Self::add_str(meta, "bext.origination_date", date.clone());
+        Self::add_str(meta, "bext.origination_time", time.clone());
+
+        // "yyyy-mm-dd" + "hh-mm-ss" (some recorders use ':' or '/' as separators)
+        if date.len() == 10 && time.len() == 8 {
+            let date: String = date.replace(['/', ':', '.'], "-");
+            let time: String = time.replace(['-', '.'], ":");
+            Self::add_str(meta, "bext.origination", format!("{} {}", date, time));
+        }
+
+        if let (Some(low), Some(high)) = (riff::read_u32(data, 338), riff::read_u32(data, 342)) {
+            Self::add_u64(meta, "bext.time_reference", ((high as u64) << 32) | low as u64);
+        }
+        if let Some(version) = riff::read_u16(data, 346) {
+            Self::add_u64(meta, "bext.version", version as u64);
+        }
+        if data.len() > 602 {
+            Self::add_str(meta, "bext.coding_history", riff::read_text(&data[602..]));
+        }
+    }
+
+    fn get_ixml(data: &[u8], meta: &mut Meta) {
+        let xml: String = riff::read_text(data);
+        for (tag, re) in IXML_PATTERNS.iter() {
+            if let Some(c) = re.captures(&xml) {
+                Self::add_str(meta, &format!("ixml.{}", tag.to_lowercase()), c[1].to_string());
+            }
+        }
+    }
+
+    fn from_riff<R: Read + Seek>(&self, reader: &mut R, file_size: u64, meta: &mut Meta) -> Result<(), MetaError> {
+        let wave: Chunk = match riff::read_chunk(reader, 0, file_size)? {
+            Some(c) if (c.is(b"RIFF") || c.is(b"RF64")) && c.is_list(b"WAVE") => c,
+            _ => {
+                return Err(MetaError::from("not a WAVE (RIFF) file"));
+            }
+        };
+
+        Self::add_str(meta, "container", riff::fourcc(&wave.id));
+
+        let mut byte_rate: u32 = 0;
+        let mut data_size: Option<u64> = None;
+        let mut ds64_data_size: Option<u64> = None;
+        let mut sample_count: Option<u64> = None;
+        let mut sample_rate: u32 = 0;
+
+        for chunk in riff::children(reader, &wave, file_size)? {
+            if chunk.is(b"ds64") {
+                // RF64: 64 bit riff size, data size, sample count
+                let data: Vec<u8> = riff::read_payload(reader, &chunk, 28)?;
+                if let (Some(low), Some(high)) = (riff::read_u32(&data, 8), riff::read_u32(&data, 12)) {
+                    ds64_data_size = Some(((high as u64) << 32) | low as u64);
+                }
+            }
+            else if chunk.is(b"fmt ") {
+                let data: Vec<u8> = riff::read_payload(reader, &chunk, 64)?;
+                byte_rate = Self::get_fmt(&data, meta);
+                sample_rate = riff::read_u32(&data, 4).unwrap_or(0);
+            }
+            else if chunk.is(b"fact") {
+                let data: Vec<u8> = riff::read_payload(reader, &chunk, 4)?;
+                sample_count = riff::read_u32(&data, 0).map(|x| x as u64);
+            }
+            else if chunk.is(b"data") {
+                data_size = match chunk.size {
+                    u32::MAX => ds64_data_size,
+                    x => Some(x as u64),
+                };
+            }
+            else if chunk.is(b"bext") {
+                let data: Vec<u8> = riff::read_payload(reader, &chunk, 64 * 1024)?;
+                Self::get_bext(&data, meta);
+            }
+            else if chunk.is(b"iXML") {
+                let data: Vec<u8> = riff::read_payload(reader, &chunk, 256 * 1024)?;
+                Self::get_ixml(&data, meta);
+            }
+            else if chunk.is_list(b"INFO") {
+                for item in riff::children(reader, &chunk, file_size)? {
+                    let data: Vec<u8> = riff::read_payload(reader, &item, 4096)?;
+                    Self::add_str(meta, &format!("info.{}", riff::info_tag(&item.id)), riff::read_text(&data));
+                }
+            }
+        }
+
+        // compressed formats carry the real sample count in `fact`
+        match (sample_count, data_size) {
+            (Some(samples), _) if sample_rate > 0 && samples > 0 => {
+                Self::add_duration(meta, samples as f64 / sample_rate as f64);
+            },
+            (_, Some(size)) if byte_rate > 0 => {
+                Self::add_duration(meta, size as f64 / byte_rate as f64);
+            },
+            _ => ()
+        }
+
+        Ok(())
+    }
+
+    /// 80 bit IEEE 754 extended precision (AIFF sample rate)
+    fn extended(data: &[u8]) -> f64 {
+        if data.len() < 10 {
+            return 0.0;
+        }
+        let sign: f64 = if data[0] & 0x80 != 0 { -1.0 } else { 1.0 };
+        let exponent: i32 = (((data[0] & 0x7f) as i32) << 8) | data[1] as i32;
+        let mantissa: u64 = u64::from_be_bytes([data[2], data[3], data[4], data[5], data[6], data[7], data[8], data[9]]);
+        if exponent == 0 && mantissa == 0 {
+            return 0.0;
+        }
+        sign * mantissa as f64 * 2f64.powi(exponent - 16383 - 63)
+    }
+
+    fn from_aiff<R: Read + Seek>(&self, reader: &mut R, file_size: u64, meta: &mut Meta) -> Result<(), MetaError> {
+        let mut header: [u8; 12] = [0; 12];
+        reader.seek(SeekFrom::Start(0))?;
+        reader.read_exact(&mut header)?;
+
+        if &header[0..4] != b"FORM" || (&header[8..12] != b"AIFF" && &header[8..12] != b"AIFC") {
+            return Err(MetaError::from("not an AIFF file"));
+        }
+        Self::add_str(meta, "container", riff::fourcc(&header[8..12]));
+
+        let mut offset: u64 = 12;
+        while offset + 8 <= file_size {
+            let mut chunk: [u8; 8] = [0; 8];
+            reader.seek(SeekFrom::Start(offset))?;
+            if reader.read_exact(&mut chunk).is_err() {
+                break;
+            }
+            let id: &[u8] = &chunk[0..4];
+            let size: u64 = u32::from_be_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]) as u64;
+
+            // sound data is skipped, only the small chunks are read
+            let data: Vec<u8> = match id {
+                b"COMM" | b"NAME" | b"AUTH" | b"(c) " | b"ANNO" => {
+                    let mut data: Vec<u8> = vec![0; size.min(64 * 1024) as usize];
+                    // a chunk cut short ends the file, keep what was read before it
+                    if reader.read_exact(&mut data).is_err() {
+                        break;
+                    }
+                    data
+                },
+                _ => Vec::new()
+            };
+
+            match id {
+                b"COMM" if data.len() >= 18 => {
+                    let channels: u16 = u16::from_be_bytes([data[0], data[1]]);
+                    let frames: u32 = u32::from_be_bytes([data[2], data[3], data[4], data[5]]);
+                    let bits: u16 = u16::from_be_bytes([data[6], data[7]]);
+                    let sample_rate: f64 = Self::extended(&data[8..18]);
+
+                    Self::add_u64(meta, "channels", channels as u64);
+                    Self::add_u64(meta, "sample_frames", frames as u64);
+                    Self::add_u64(meta, "bits_per_sample", bits as u64);
+                    Self::add_u64(meta, "sample_rate", sample_rate.round() as u64);
+                    if sample_rate > 0.0 {
+                        Self::add_duration(meta, frames as f64 / sample_rate);
+                    }
+                    if let Some(compression) = data.get(18..22) {
+                        Self::add_str(meta, "compression", riff::fourcc(compression));
+                    }
+                },
+                b"NAME" => Self::add_str(meta, "info.title", riff::read_text(&data)),
+                b"AUTH" => Self::add_str(meta, "info.artist", riff::read_text(&data)),
+                b"(c) " => Self::add_str(meta, "info.copyright", riff::read_text(&data)),
+                b"ANNO" => Self::add_str(meta, "info.comment", riff::read_text(&data)),
+                _ => ()
+            }
+
+            offset += 8 + size + (size & 1);
+        }
+
+        Ok(())
+    }
+}
+
+impl FromFile for Wave {
+    fn file(path: &str) -> Self {
+        Self { path: path.to_string() }
+    }
+}
+
+impl Extractor for Wave {
+    fn name(&self) -> String {
+        return "WAVE".to_string();
+    }
+
+    fn extract(&self, meta: &mut Meta) -> Result<(), MetaError> {
+        let file: File = File::open(&self.path)?;
+        let size: u64 = file.metadata()?.len();
+        let mut reader: BufReader<File> = BufReader::new(file);
+
+        let mut magic: [u8; 4] = [0; 4];
+        reader.read_exact(&mut magic)?;
+
+        match &magic {
+            b"RIFF" | b"RF64" => self.from_riff(&mut reader, size, meta),
+            b"FORM" => self.from_aiff(&mut reader, size, meta),
+            _ => Err(MetaError::from("not a WAVE or AIFF file"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+    use super::Wave;
+    use crate::{MetaError, FromFile, Extractor, Meta, MetaSource};
+
+    const TEST_AUDIO: &str = "../testdata/Audio/test.wav";
+
+    fn chunk(id: &[u8], payload: &[u8]) -> Vec<u8> {
+        let mut data: Vec<u8> = id.to_vec();
+        data.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        data.extend_from_slice(payload);
+        if payload.len() % 2 == 1 {
+            data.push(0);
+        }
+        data
+    }
+
+    #[test]
+    fn test_parse_bwf_synthetic() {
+        let mut fmt: Vec<u8> = Vec::new();
+        fmt.extend_from_slice(&1u16.to_le_bytes());
+        fmt.extend_from_slice(&2u16.to_le_bytes());
+        fmt.extend_from_slice(&48_000u32.to_le_bytes());
+        fmt.extend_from_slice(&192_000u32.to_le_bytes());
+        fmt.extend_from_slice(&4u16.to_le_bytes());
+        fmt.extend_from_slice(&16u16.to_le_bytes());
+
+        let mut bext: Vec<u8> = vec![0; 602];
+        bext[256..263].copy_from_slice(b"ZOOM H6");
+        bext[320..330].copy_from_slice(b"2021-03-04");
+        bext[330..338].copy_from_slice(b"10:11:12");
+
+        let mut body: Vec<u8> = b"WAVE".to_vec();
+        body.extend_from_slice(&chunk(b"fmt ", &fmt));
+        body.extend_from_slice(&chunk(b"bext", &bext));
+        body.extend_from_slice(&chunk(b"iXML", b"<BWFXML><SCENE>12A</SCENE><TAKE> 3 </TAKE></BWFXML>"));
+        body.extend_from_slice(&chunk(b"data", &vec![0; 384_000]));
+
+        let data: Vec<u8> = chunk(b"RIFF", &body);
+        let size: u64 = data.len() as u64;
+        let mut meta: Meta = Meta::new();
+        Wave::file(TEST_AUDIO).from_riff(&mut Cursor::new(data), size, &mut meta).unwrap();
+
+        assert_eq!(u64::from(meta.find_one(MetaSource::Wave, "sample_rate").unwrap().value), 48_000);
+        assert_eq!(String::from(meta.find_one(MetaSource::Wave, "duration").unwrap().value), "2");
+        assert_eq!(String::from(meta.find_one(MetaSource::Wave, "bext.originator").unwrap().value), "ZOOM H6");
+        assert_eq!(String::from(meta.find_one(MetaSource::Wave, "bext.origination").unwrap().value), "2021-03-04 10:11:12");
+        assert_eq!(String::from(meta.find_one(MetaSource::Wave, "ixml.scene").unwrap().value), "12A");
+        assert_eq!(String::from(meta.find_one(MetaSource::Wave, "ixml.take").unwrap().value), "3");
+    }
+
+    #[test]
+    fn test_truncated_aiff() {
+        let mut comm: Vec<u8> = Vec::new();
+        comm.extend_from_slice(&2u16.to_be_bytes());
+        comm.extend_from_slice(&88_200u32.to_be_bytes());
+        comm.extend_from_slice(&16u16.to_be_bytes());
+        comm.extend_from_slice(&[0x40, 0x0e, 0xac, 0x44, 0, 0, 0, 0, 0, 0]);
+
+        let mut data: Vec<u8> = b"FORM\0\0\0\0AIFF".to_vec();
+        data.extend_from_slice(b"COMM");
+        data.extend_from_slice(&(comm.len() as u32).to_be_bytes());
+        data.extend_from_slice(&comm);
+        // the NAME chunk claims more than the file holds
+        data.extend_from_slice(b"NAME\0\0\0\x40Unfinished");
+
+        let size: u64 = data.len() as u64;
+        let mut meta: Meta = Meta::new();
+        Wave::file(TEST_AUDIO).from_aiff(&mut Cursor::new(data), size, &mut meta).unwrap();
+
+        assert_eq!(u64::from(meta.find_one(MetaSource::Wave, "sample_rate").unwrap().value), 44_100);
+        assert_eq!(String::from(meta.find_one(MetaSource::Wave, "duration").unwrap().value), "2");
+        assert!(meta.find_one(MetaSource::Wave, "info.title").is_err());
+    }
+
+    #[test]
+    fn test_extended() {
+        // 44100.0
+        let data: [u8; 10] = [0x40, 0x0e, 0xac, 0x44, 0, 0, 0, 0, 0, 0];
+        assert_eq!(Wave::extended(&data), 44_100.0);
+    }
+
+    #[test]
+    fn test_parse() {
+        let mut meta: Meta = Meta::new();
+        let extractor: Wave = Wave::file(TEST_AUDIO);
+        let result: Result<(), MetaError> = extractor.extract(&mut meta);
+        match result {
+            Ok(_) => {
+                println!("{:#?}", meta);
+            },
+            Err(e) => {
+                println!("test error {:#?}", e);
+            }
+        }
+    }
+}
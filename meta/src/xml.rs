@@ -0,0 +1,154 @@
+//! Just enough XML to pull properties out of metadata documents (XMP, OOXML
+//! and ODF properties, SVG). Namespaces are matched by their literal prefix.
+
+/// Replace the predefined and numeric character entities.
+pub fn unescape(value: &str) -> String {
+    if !value.contains('&') {
+        return value.to_string();
+    }
+
+    let mut out: String = String::with_capacity(value.len());
+    let mut rest: &str = value;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let end: usize = match rest.find(';') {
+            Some(e) if e <= 10 => e,
+            _ => {
+                out.push('&');
+                rest = &rest[1..];
+                continue;
+            }
+        };
+        let decoded: Option<char> = match &rest[1..end] {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            n if n.starts_with("#x") || n.starts_with("#X") => u32::from_str_radix(&n[2..], 16).ok().and_then(char::from_u32),
+            n if n.starts_with('#') => n[1..].parse().ok().and_then(char::from_u32),
+            _ => None
+        };
+        match decoded {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            },
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Value of the first `name="..."` (or single quoted) attribute.
+pub fn attribute(xml: &str, name: &str) -> Option<String> {
+    let mut from: usize = 0;
+    while let Some(found) = xml[from..].find(name) {
+        let start: usize = from + found;
+        from = start + name.len();
+
+        if !xml[..start].ends_with(|c: char| c.is_whitespace()) {
+            continue;
+        }
+        let rest: &str = xml[from..].trim_start();
+        let rest: &str = match rest.strip_prefix('=') {
+            Some(r) => r.trim_start(),
+            None => continue
+        };
+        let quote: char = rest.chars().next().filter(|q| *q == '"' || *q == '\'')?;
+        let value: &str = &rest[1..];
+        return Some(unescape(&value[..value.find(quote)?]));
+    }
+    None
+}
+
+/// Raw content of the first `<name>...</name>` element, markup included.
+/// Empty elements yield an empty string.
+#[cfg(any(feature = "pdf", feature = "office", feature = "svg", feature = "sidecar"))]
+pub fn element<'a>(xml: &'a str, name: &str) -> Option<&'a str> {
+    let open: String = format!("<{}", name);
+    let close: String = format!("</{}>", name);
+
+    let mut from: usize = 0;
+    while let Some(found) = xml[from..].find(&open) {
+        let start: usize = from + found + open.len();
+        from = start;
+
+        // `<dc:title` must not match `<dc:titles`
+        match xml[start..].chars().next() {
+            Some(c) if c.is_whitespace() || c == '>' || c == '/' => (),
+            _ => continue
+        }
+        let tag_end: usize = start + xml[start..].find('>')?;
+        if xml[..tag_end].ends_with('/') {
+            return Some("");
+        }
+        let body: &str = &xml[tag_end + 1..];
+        return Some(&body[..body.find(&close)?]);
+    }
+    None
+}
+
+/// Text of the first `name` element with markup stripped. Language
+/// alternatives and sequences (`rdf:li`) yield their first entry.
+#[cfg(any(feature = "pdf", feature = "office", feature = "svg", feature = "sidecar"))]
+pub fn text(xml: &str, name: &str) -> Option<String> {
+    let body: &str = element(xml, name)?;
+    let body: &str = element(body, "rdf:li").unwrap_or(body);
+
+    let mut plain: String = String::with_capacity(body.len());
+    let mut in_tag: bool = false;
+    for c in body.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            _ if !in_tag => plain.push(c),
+            _ => ()
+        }
+    }
+
+    let value: String = unescape(plain.trim());
+    if value.is_empty() {
+        return None;
+    }
+    Some(value)
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{attribute, unescape};
+
+    #[test]
+    fn test_attribute() {
+        let xml: &str = r#"<svg:svg xmlns:svg='http://www.w3.org/2000/svg' viewBox = "0 0 10 10" xviewBox="no">"#;
+
+        assert_eq!(attribute(xml, "xmlns:svg").unwrap(), "http://www.w3.org/2000/svg");
+        assert_eq!(attribute(xml, "viewBox").unwrap(), "0 0 10 10");
+        assert_eq!(attribute(xml, "height"), None);
+        assert_eq!(unescape("a & b &bogus; &lt;"), "a & b &bogus; <");
+    }
+
+    #[test]
+    #[cfg(any(feature = "pdf", feature = "office", feature = "svg"))]
+    fn test_elements() {
+        use super::{element, text};
+
+        let xml: &str = r#"<cp:coreProperties><dc:titles>no</dc:titles><dc:title>Q&amp;A &#233;t&#xE9;</dc:title>
+            <dc:creator><rdf:Seq><rdf:li>Ann</rdf:li><rdf:li>Bob</rdf:li></rdf:Seq></dc:creator>
+            <meta:document-statistic meta:page-count='3' meta:word-count="120"/><cp:empty/></cp:coreProperties>"#;
+
+        assert_eq!(text(xml, "dc:title").unwrap(), "Q&A été");
+        assert_eq!(text(xml, "dc:creator").unwrap(), "Ann");
+        assert_eq!(attribute(xml, "meta:page-count").unwrap(), "3");
+        assert_eq!(attribute(xml, "meta:word-count").unwrap(), "120");
+        assert_eq!(element(xml, "cp:empty"), Some(""));
+        assert_eq!(text(xml, "dc:subject"), None);
+    }
+}
@@ -0,0 +1,294 @@
+use std::io::{Read, Seek, SeekFrom};
+
+use flate2::read::DeflateDecoder;
+
+use crate::MetaError;
+
+const LOCAL_HEADER: u32 = 0x0403_4b50;
+const CENTRAL_HEADER: u32 = 0x0201_4b50;
+const END_OF_DIRECTORY: u32 = 0x0605_4b50;
+const ZIP64_LOCATOR: u32 = 0x0706_4b50;
+const ZIP64_END_OF_DIRECTORY: u32 = 0x0606_4b50;
+
+/// End of central directory record plus the largest possible archive comment.
+const TAIL_WINDOW: u64 = 22 + 0xFFFF;
+
+/// Central directories larger than this are not read.
+const MAX_DIRECTORY: u64 = 64 * 1024 * 1024;
+
+/// A member of a zip archive, as listed in the central directory.
+#[derive(Debug, Clone)]
+#[cfg_attr(not(feature = "container"), allow(dead_code))]
+pub struct Entry {
+    pub name: String,
+    pub method: u16,
+    pub encrypted: bool,
+    pub compressed_size: u64,
+    pub size: u64,
+
+    /// Offset of the local file header.
+    pub offset: u64,
+
+    /// Local time of the last change as "YYYY:MM:DD HH:MM:SS", zips carry no zone.
+    pub modified: Option<String>,
+    pub is_dir: bool,
+}
+
+pub fn read_u16(data: &[u8], at: usize) -> Option<u16> {
+    data.get(at..at + 2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+}
+
+pub fn read_u32(data: &[u8], at: usize) -> Option<u32> {
+    data.get(at..at + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+pub fn read_u64(data: &[u8], at: usize) -> Option<u64> {
+    data.get(at..at + 8).map(|b| u64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]))
+}
+
+/// An MS-DOS date and time pair (2 second resolution).
+fn dos_time(date: u16, time: u16) -> Option<String> {
+    let (month, day) = ((date >> 5) & 0x0F, date & 0x1F);
+    if month == 0 || month > 12 || day == 0 {
+        return None;
+    }
+    Some(format!("{:04}:{:02}:{:02} {:02}:{:02}:{:02}",
+        1980 + (date >> 9), month, day, time >> 11, (time >> 5) & 0x3F, (time & 0x1F) * 2))
+}
+
+/// Whether the stream starts with a local file header (or is an empty archive).
+pub fn is_zip<R: Read + Seek>(reader: &mut R) -> Result<bool, MetaError> {
+    let mut magic: [u8; 4] = [0; 4];
+    reader.seek(SeekFrom::Start(0))?;
+    if reader.read_exact(&mut magic).is_err() {
+        return Ok(false);
+    }
+    let magic: u32 = u32::from_le_bytes(magic);
+    Ok(magic == LOCAL_HEADER || magic == END_OF_DIRECTORY)
+}
+
+/// Location and size of the central directory, from the (ZIP64) end record.
+fn directory<R: Read + Seek>(reader: &mut R) -> Result<(u64, u64), MetaError> {
+    let file_size: u64 = reader.seek(SeekFrom::End(0))?;
+    let window: u64 = file_size.min(TAIL_WINDOW);
+    let mut tail: Vec<u8> = vec![0; window as usize];
+    reader.seek(SeekFrom::Start(file_size - window))?;
+    reader.read_exact(&mut tail)?;
+
+    let end: usize = match (0..tail.len().saturating_sub(21)).rev().find(|i| read_u32(&tail, *i) == Some(END_OF_DIRECTORY)) {
+        Some(e) => e,
+        None => return Err(MetaError::from("zip end of central directory not found"))
+    };
+    let size: u64 = read_u32(&tail, end + 12).unwrap_or(0) as u64;
+    let offset: u64 = read_u32(&tail, end + 16).unwrap_or(0) as u64;
+
+    if offset != 0xFFFF_FFFF && size != 0xFFFF_FFFF {
+        return Ok((offset, size));
+    }
+
+    // ZIP64 archives point at a second end record through a locator just in front
+    let locator: usize = end.checked_sub(20).ok_or("zip64 locator missing")?;
+    if read_u32(&tail, locator) != Some(ZIP64_LOCATOR) {
+        return Err(MetaError::from("zip64 locator missing"));
+    }
+    let record_offset: u64 = read_u64(&tail, locator + 8).unwrap_or(0);
+    let mut record: [u8; 56] = [0; 56];
+    reader.seek(SeekFrom::Start(record_offset))?;
+    reader.read_exact(&mut record)?;
+    if read_u32(&record, 0) != Some(ZIP64_END_OF_DIRECTORY) {
+        return Err(MetaError::from("zip64 end of central directory not found"));
+    }
+    Ok((read_u64(&record, 48).unwrap_or(0), read_u64(&record, 40).unwrap_or(0)))
+}
+
+/// List the members of a zip archive from its central directory.
+pub fn entries<R: Read + Seek>(reader: &mut R) -> Result<Vec<Entry>, MetaError> {
+    let (offset, size) = directory(reader)?;
+    if size > MAX_DIRECTORY {
+        return Err(MetaError::from("zip central directory too large"));
+    }
+
+    let mut data: Vec<u8> = vec![0; size as usize];
+    reader.seek(SeekFrom::Start(offset))?;
+    reader.read_exact(&mut data)?;
+
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut at: usize = 0;
+    while read_u32(&data, at) == Some(CENTRAL_HEADER) {
+        let field = |o: usize| read_u16(&data, at + o).unwrap_or(0);
+        let flags: u16 = field(8);
+        let (name_len, extra_len, comment_len) = (field(28) as usize, field(30) as usize, field(32) as usize);
+
+        let name_start: usize = at + 46;
+        let extra_start: usize = name_start + name_len;
+        let (name, extra) = match (data.get(name_start..extra_start), data.get(extra_start..extra_start + extra_len)) {
+            (Some(n), Some(e)) => (n, e),
+            _ => break
+        };
+
+        // bit 11 flags UTF-8, legacy CP437 names are near enough to ASCII
+        let name: String = String::from_utf8_lossy(name).into_owned();
+        let mut entry: Entry = Entry {
+            is_dir: name.ends_with('/'),
+            name,
+            method: field(10),
+            encrypted: flags & 1 != 0,
+            compressed_size: read_u32(&data, at + 20).unwrap_or(0) as u64,
+            size: read_u32(&data, at + 24).unwrap_or(0) as u64,
+            offset: read_u32(&data, at + 42).unwrap_or(0) as u64,
+            modified: dos_time(field(14), field(12)),
+        };
+
+        // ZIP64 extended information, only the fields saturated above are present
+        let mut e: usize = 0;
+        while e + 4 <= extra.len() {
+            let (id, len) = (read_u16(extra, e).unwrap_or(0), read_u16(extra, e + 2).unwrap_or(0) as usize);
+            if id == 0x0001 {
+                let mut f: usize = e + 4;
+                for value in [&mut entry.size, &mut entry.compressed_size, &mut entry.offset] {
+                    if *value == 0xFFFF_FFFF {
+                        if let Some(v) = read_u64(extra, f) {
+                            *value = v;
+                        }
+                        f += 8;
+                    }
+                }
+            }
+            e += 4 + len;
+        }
+
+        entries.push(entry);
+        at = extra_start + extra_len + comment_len;
+    }
+    Ok(entries)
+}
+
+#[cfg(feature = "office")]
+pub fn find<'a>(entries: &'a [Entry], name: &str) -> Option<&'a Entry> {
+    entries.iter().find(|e| e.name == name)
+}
+
+/// Stream a member's decompressed content.
+pub fn reader<'a, R: Read + Seek>(reader: &'a mut R, entry: &Entry) -> Result<Box<dyn Read + 'a>, MetaError> {
+    if entry.encrypted {
+        return Err(MetaError::from(format!("zip member {} is encrypted", entry.name)));
+    }
+
+    let mut header: [u8; 30] = [0; 30];
+    reader.seek(SeekFrom::Start(entry.offset))?;
+    reader.read_exact(&mut header)?;
+    if read_u32(&header, 0) != Some(LOCAL_HEADER) {
+        return Err(MetaError::from(format!("zip member {} has no local header", entry.name)));
+    }
+    let skip: u64 = read_u16(&header, 26).unwrap_or(0) as u64 + read_u16(&header, 28).unwrap_or(0) as u64;
+    reader.seek(SeekFrom::Current(skip as i64))?;
+
+    let compressed = reader.take(entry.compressed_size);
+    match entry.method {
+        0 => Ok(Box::new(compressed)),
+        8 => Ok(Box::new(DeflateDecoder::new(compressed))),
+        m => Err(MetaError::from(format!("zip member {} uses unsupported compression {}", entry.name, m)))
+    }
+}
+
+/// Read and decompress a member, keeping at most `limit` bytes.
+#[cfg(feature = "office")]
+pub fn read<R: Read + Seek>(reader: &mut R, entry: &Entry, limit: usize) -> Result<Vec<u8>, MetaError> {
+    let mut data: Vec<u8> = Vec::new();
+    self::reader(reader, entry)?.take(limit as u64).read_to_end(&mut data)?;
+    Ok(data)
+}
+
+#[cfg(test)]
+pub mod test {
+    use std::io::{Cursor, Write};
+
+    use flate2::{write::DeflateEncoder, Compression};
+
+    use super::{entries, is_zip};
+
+    /// Build an archive in memory, members are deflated when `deflate` is set.
+    pub fn archive(members: &[(&str, &[u8])], deflate: bool) -> Vec<u8> {
+        let mut data: Vec<u8> = Vec::new();
+        let mut directory: Vec<u8> = Vec::new();
+
+        for (name, content) in members {
+            let payload: Vec<u8> = match deflate {
+                true => {
+                    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+                    encoder.write_all(content).unwrap();
+                    encoder.finish().unwrap()
+                },
+                false => content.to_vec()
+            };
+            let method: u16 = if deflate { 8 } else { 0 };
+            let offset: u32 = data.len() as u32;
+
+            data.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+            data.extend_from_slice(&[20, 0, 0, 0]);
+            data.extend_from_slice(&method.to_le_bytes());
+            // 2021-06-15 13:45:30
+            data.extend_from_slice(&((13u16 << 11) | (45 << 5) | 15).to_le_bytes());
+            data.extend_from_slice(&((41u16 << 9) | (6 << 5) | 15).to_le_bytes());
+            data.extend_from_slice(&0u32.to_le_bytes());
+            data.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+            data.extend_from_slice(&(content.len() as u32).to_le_bytes());
+            data.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            data.extend_from_slice(&0u16.to_le_bytes());
+            data.extend_from_slice(name.as_bytes());
+            data.extend_from_slice(&payload);
+
+            directory.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+            directory.extend_from_slice(&[20, 0, 20, 0, 0, 0]);
+            directory.extend_from_slice(&method.to_le_bytes());
+            directory.extend_from_slice(&((13u16 << 11) | (45 << 5) | 15).to_le_bytes());
+            directory.extend_from_slice(&((41u16 << 9) | (6 << 5) | 15).to_le_bytes());
+            directory.extend_from_slice(&0u32.to_le_bytes());
+            directory.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+            directory.extend_from_slice(&(content.len() as u32).to_le_bytes());
+            directory.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            directory.extend_from_slice(&[0; 12]);
+            directory.extend_from_slice(&offset.to_le_bytes());
+            directory.extend_from_slice(name.as_bytes());
+        }
+
+        let offset: u32 = data.len() as u32;
+        data.extend_from_slice(&directory);
+        data.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+        data.extend_from_slice(&[0; 4]);
+        data.extend_from_slice(&(members.len() as u16).to_le_bytes());
+        data.extend_from_slice(&(members.len() as u16).to_le_bytes());
+        data.extend_from_slice(&(directory.len() as u32).to_le_bytes());
+        data.extend_from_slice(&offset.to_le_bytes());
+        data.extend_from_slice(&0u16.to_le_bytes());
+        data
+    }
+
+    #[test]
+    #[cfg(feature = "office")]
+    fn test_read_members() {
+        use super::{find, read};
+
+        for deflate in [false, true] {
+            let mut reader = Cursor::new(archive(&[("a.txt", b"hello hello hello"), ("dir/", b"")], deflate));
+            assert!(is_zip(&mut reader).unwrap());
+
+            let list = entries(&mut reader).unwrap();
+            assert_eq!(list.len(), 2);
+            assert_eq!(list[0].size, 17);
+            assert_eq!(list[0].modified.as_deref(), Some("2021:06:15 13:45:30"));
+            assert!(!list[0].is_dir && list[1].is_dir);
+
+            let a = find(&list, "a.txt").unwrap();
+            assert_eq!(read(&mut reader, a, 1024).unwrap(), b"hello hello hello");
+            assert_eq!(read(&mut reader, a, 5).unwrap(), b"hello");
+        }
+    }
+
+    #[test]
+    fn test_not_a_zip() {
+        let mut reader = Cursor::new(b"definitely not an archive".to_vec());
+        assert!(!is_zip(&mut reader).unwrap());
+        assert!(entries(&mut reader).is_err());
+    }
+}
@@ -1,7 +1,7 @@
 use std::path::PathBuf;
 
 use meta::MetaClass;
-use rusqlite::{params, OptionalExtension};
+use rusqlite::{params, OptionalExtension, Row};
 use serde::{Deserialize, Serialize};
 
 use crate::{Database, StorageError};
@@ -64,50 +64,179 @@ pub struct Asset {
     pub size_in_bytes: i64,
     pub status: Status,
     pub path: Option<String>,
-    pub partition: Option<usize>
+    pub partition: Option<usize>,
+
+    /// RFC 3339 capture time, without an offset when the zone is unknown.
+    pub captured_at: Option<String>,
+
+    /// Where the capture time was found, e.g. "exif" or "filename".
+    pub captured_source: Option<String>,
+
+    /// How the asset belongs to its parent, e.g. "sidecar". `None` for duplicates.
+    pub relation: Option<String>,
+
+    /// Capture location in decimal degrees.
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+
+    /// Where the location was found, e.g. "exif" or "takeout".
+    pub location_source: Option<String>,
+
+    /// ContentIdentifier shared by the still and video of a Live Photo.
+    pub content_id: Option<String>
 }
 
+/// Columns read by [`Asset::from_row`], in order.
+const COLUMNS: &str = r#"
+    id,
+    parent_id,
+    name,
+    class,
+    extension,
+    hash,
+    size,
+    status,
+    path,
+    partition,
+    captured_at,
+    captured_source,
+    relation,
+    latitude,
+    longitude,
+    location_source,
+    content_id"#;
+
 impl Asset {
+    fn from_row(row: &Row) -> rusqlite::Result<Asset> {
+        let class: String = row.get(3)?;
+        let status: String = row.get(7)?;
+
+        Ok(Asset {
+            id: row.get(0)?,
+            parent_id: row.get(1)?,
+            name: row.get(2)?,
+            class: class.into(),
+            extension: row.get(4)?,
+            hash: row.get(5)?,
+            size_in_bytes: row.get(6)?,
+            status: status.into(),
+            path: row.get(8)?,
+            partition: row.get(9)?,
+            captured_at: row.get(10)?,
+            captured_source: row.get(11)?,
+            relation: row.get(12)?,
+            latitude: row.get(13)?,
+            longitude: row.get(14)?,
+            location_source: row.get(15)?,
+            content_id: row.get(16)?
+        })
+    }
+
+    /// Find an asset by its primary hash or by its essence hash,
+    /// which stays the same when only the tags of a file were edited.
     pub fn find_by_hash(db: &Database, hash: &str) -> Result<Option<Asset>, StorageError> {
+        let mut stmt = db.conn.prepare(&format!(r#"
+            SELECT {}
+            FROM asset
+            WHERE hash = ?1
+                OR id IN (SELECT asset_id FROM asset_hash WHERE algorithm = 'essence' AND hash = ?1)
+            ORDER BY id
+            LIMIT 1
+            "#, COLUMNS))?;
+
+        let asset: Option<Asset> = stmt.query_row([hash.to_string()], Self::from_row).optional()?;
+
+        Ok(asset)
+    }
+
+    /// Find an asset by a secondary digest, e.g. ("sha256", "d7a8fb...").
+    pub fn find_by_digest(db: &Database, algorithm: &str, hash: &str) -> Result<Option<Asset>, StorageError> {
+        let mut stmt = db.conn.prepare(&format!(r#"
+            SELECT {}
+            FROM asset
+            WHERE id IN (SELECT asset_id FROM asset_hash WHERE algorithm = ?1 AND hash = ?2)
+            ORDER BY id
+            LIMIT 1
+            "#, COLUMNS))?;
+
+        let asset: Option<Asset> = stmt.query_row([algorithm.to_string(), hash.to_string()], Self::from_row).optional()?;
+
+        Ok(asset)
+    }
+
+    /// Top level assets with a Live Photo content identifier, e.g. the archived still of a video.
+    pub fn find_by_content_id(db: &Database, content_id: &str) -> Result<Vec<Asset>, StorageError> {
+        let mut stmt = db.conn.prepare(&format!(r#"
+            SELECT {}
+            FROM asset
+            WHERE content_id = ?1 AND parent_id IS NULL
+            ORDER BY id
+            "#, COLUMNS))?;
+
+        let rows = stmt.query_map([content_id.to_string()], Self::from_row)?;
+
+        let mut assets: Vec<Asset> = Vec::new();
+        for row in rows {
+            assets.push(row?);
+        }
+
+        Ok(assets)
+    }
+
+    /// Assets attached to this one with the given relation, e.g. its sidecars.
+    pub fn children(&self, db: &Database, relation: &str) -> Result<Vec<Asset>, StorageError> {
+        let mut stmt = db.conn.prepare(&format!(r#"
+            SELECT {}
+            FROM asset
+            WHERE parent_id = ?1 AND relation = ?2
+            ORDER BY id
+            "#, COLUMNS))?;
+
+        let rows = stmt.query_map(params![self.id, relation], Self::from_row)?;
+
+        let mut children: Vec<Asset> = Vec::new();
+        for row in rows {
+            children.push(row?);
+        }
+
+        Ok(children)
+    }
+
+    /// Store an additional digest for a created asset.
+    pub fn add_hash(&self, db: &Database, algorithm: &str, hash: &str) -> Result<(), StorageError> {
+        let mut stmt = db.conn.prepare(r#"
+            INSERT OR REPLACE INTO asset_hash (asset_id, algorithm, hash)
+            VALUES (?1, ?2, ?3)"#)?;
+
+        stmt.execute(params![
+            self.id,
+            algorithm,
+            hash
+        ])?;
+
+        Ok(())
+    }
+
+    /// All additional digests stored for the asset as (algorithm, hash).
+    pub fn hashes(&self, db: &Database) -> Result<Vec<(String, String)>, StorageError> {
         let mut stmt = db.conn.prepare(r#"
-            SELECT 
-                id, 
-                name,
-                class,
-                extension,
-                hash,
-                size,
-                status,
-                path,
-                partition
-            FROM asset where hash = ?1
+            SELECT algorithm, hash FROM asset_hash WHERE asset_id = ?1 ORDER BY algorithm
             "#)?;
 
-        let asset: Option<Asset> = stmt.query_row([hash.to_string()], |row| {
-            let class: String = row.get(2)?;
-            let status: String = row.get(6)?;
-            
-            Ok(Asset {
-                id: row.get(0)?,
-                parent_id: None,
-                name: row.get(1)?,
-                class: class.into(),
-                extension: row.get(3)?,
-                hash: row.get(4)?,
-                size_in_bytes: row.get(5)?,
-                status: status.into(),
-                path: row.get(7)?,
-                partition: row.get(8)?
-            })
-        }).optional()?;
+        let rows = stmt.query_map([self.id], |row| Ok((row.get(0)?, row.get(1)?)))?;
 
-        Ok(asset)
+        let mut hashes: Vec<(String, String)> = Vec::new();
+        for row in rows {
+            hashes.push(row?);
+        }
+
+        Ok(hashes)
     }
 
     pub fn create(&mut self, db: &Database) -> Result<(), StorageError> {
         let mut stmt = db.conn.prepare(r#"
-            INSERT INTO asset (parent_id, name, class, extension, hash, size, status, path, partition)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)"#)?;
+            INSERT INTO asset (parent_id, name, class, extension, hash, size, status, path, partition, captured_at, captured_source, relation, latitude, longitude, location_source, content_id)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)"#)?;
 
         let status: String = self.status.clone().into();
         let class: String = self.class.clone().into();
@@ -121,7 +250,14 @@ impl Asset {
             self.size_in_bytes,
             status,
             self.path,
-            self.partition
+            self.partition,
+            self.captured_at,
+            self.captured_source,
+            self.relation,
+            self.latitude,
+            self.longitude,
+            self.location_source,
+            self.content_id
         ])?;
 
         self.id = db.conn.last_insert_rowid();
@@ -153,4 +289,129 @@ impl Asset {
         path.push(&self.name);
         return path.display().to_string();
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use meta::MetaClass;
+    use rusqlite::Connection;
+    use crate::{Asset, Database, Status};
+
+    fn build_asset(name: &str, class: MetaClass, hash: &str) -> Asset {
+        Asset { 
+            id: 0, 
+            parent_id: None, 
+            name: name.to_string(), 
+            class,
+            extension: name.rsplit_once('.').map_or("", |(_, e)| e).to_string(), 
+            hash: hash.to_string(), 
+            size_in_bytes: 0, 
+            status: Status::Created, 
+            path: None, 
+            partition: None,
+            captured_at: None,
+            captured_source: None,
+            relation: None,
+            latitude: None,
+            longitude: None,
+            location_source: None,
+            content_id: None,
+        }
+    }
+
+    #[test]
+    fn find_by_digest() {
+        let db: Database = Database { conn: Connection::open_in_memory().unwrap() };
+        db.migrate().unwrap();
+
+        let mut asset: Asset = Asset {
+            captured_at: Some("2021-06-15T13:45:30+02:00".to_string()),
+            captured_source: Some("exif".to_string()),
+            latitude: Some(46.2044),
+            longitude: Some(6.1432),
+            location_source: Some("takeout".to_string()),
+            ..build_asset("fake.jpeg", MetaClass::Image, &uuid::Uuid::new_v4().to_string())
+        };
+        asset.create(&db).unwrap();
+        asset.add_hash(&db, "md5", "9e107d9d372bb6826bd81d3542a419d6").unwrap();
+
+        let found: Asset = Asset::find_by_digest(&db, "md5", "9e107d9d372bb6826bd81d3542a419d6").unwrap().unwrap();
+        assert_eq!(found.id, asset.id);
+        assert_eq!(found.captured_at, asset.captured_at);
+        assert_eq!(found.captured_source.as_deref(), Some("exif"));
+        assert_eq!((found.latitude, found.longitude), (Some(46.2044), Some(6.1432)));
+        assert_eq!(found.location_source.as_deref(), Some("takeout"));
+        assert!(Asset::find_by_digest(&db, "sha256", "9e107d9d372bb6826bd81d3542a419d6").unwrap().is_none());
+        assert_eq!(asset.hashes(&db).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn find_by_essence_hash() {
+        let db: Database = Database { conn: Connection::open_in_memory().unwrap() };
+        db.migrate().unwrap();
+
+        let mut asset: Asset = build_asset("fake.mp3", MetaClass::Audio, "whole-file");
+        asset.create(&db).unwrap();
+        asset.add_hash(&db, "essence", "frames-only").unwrap();
+
+        assert_eq!(Asset::find_by_hash(&db, "whole-file").unwrap().unwrap().id, asset.id);
+        assert_eq!(Asset::find_by_hash(&db, "frames-only").unwrap().unwrap().id, asset.id);
+        assert!(Asset::find_by_hash(&db, "something-else").unwrap().is_none());
+    }
+
+    #[test]
+    fn find_sidecars() {
+        let db: Database = Database { conn: Connection::open_in_memory().unwrap() };
+        db.migrate().unwrap();
+
+        let mut photo: Asset = build_asset("0b7e.heic", MetaClass::Image, "photo");
+        photo.create(&db).unwrap();
+
+        let mut sidecar: Asset = Asset {
+            id: 0,
+            parent_id: Some(photo.id),
+            name: "0b7e.aae".to_string(),
+            extension: "aae".to_string(),
+            hash: "edits".to_string(),
+            relation: Some("sidecar".to_string()),
+            ..photo.clone()
+        };
+        sidecar.create(&db).unwrap();
+
+        let children: Vec<Asset> = photo.children(&db, "sidecar").unwrap();
+        assert_eq!(children.len(), 1);
+        assert_eq!((children[0].parent_id, children[0].name.as_str()), (Some(photo.id), "0b7e.aae"));
+        assert_eq!(Asset::find_by_hash(&db, "edits").unwrap().unwrap().relation.as_deref(), Some("sidecar"));
+        assert!(sidecar.children(&db, "sidecar").unwrap().is_empty());
+    }
+
+    #[test]
+    fn find_by_content_id() {
+        let db: Database = Database { conn: Connection::open_in_memory().unwrap() };
+        db.migrate().unwrap();
+
+        let mut still: Asset = Asset {
+            content_id: Some("5F6C6E8A-2C1B-4A39-9D5E-0E1D3B7C4A21".to_string()),
+            ..build_asset("0b7e.heic", MetaClass::Image, "still")
+        };
+        still.create(&db).unwrap();
+
+        let mut video: Asset = Asset {
+            id: 0,
+            parent_id: Some(still.id),
+            name: "0b7e.mov".to_string(),
+            class: MetaClass::Video,
+            extension: "mov".to_string(),
+            hash: "video".to_string(),
+            relation: Some("live".to_string()),
+            ..still.clone()
+        };
+        video.create(&db).unwrap();
+
+        let found: Vec<Asset> = Asset::find_by_content_id(&db, "5F6C6E8A-2C1B-4A39-9D5E-0E1D3B7C4A21").unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].id, found[0].content_id.as_deref()), (still.id, Some("5F6C6E8A-2C1B-4A39-9D5E-0E1D3B7C4A21")));
+        assert_eq!(still.children(&db, "live").unwrap()[0].name, "0b7e.mov");
+        assert!(Asset::find_by_content_id(&db, "another").unwrap().is_empty());
+    }
+}
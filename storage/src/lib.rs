@@ -39,13 +39,60 @@ impl Database {
                     status TEXT NOT NULL,
                     path TEXT NULL,
                     partition INTEGER NULL,
+                    captured_at TEXT NULL,
+                    captured_source TEXT NULL,
+                    relation TEXT NULL,
+                    latitude REAL NULL,
+                    longitude REAL NULL,
+                    location_source TEXT NULL,
+                    content_id TEXT NULL,
 	                created_at timestamp DEFAULT CURRENT_TIMESTAMP NULL
                 )",
             (),
         )?;
+
+        // databases created before capture times were recorded
+        self.add_column("asset", "captured_at", "TEXT NULL")?;
+        self.add_column("asset", "captured_source", "TEXT NULL")?;
+
+        // databases created before sidecars were attached to their file
+        self.add_column("asset", "relation", "TEXT NULL")?;
+
+        // databases created before capture locations were recorded
+        self.add_column("asset", "latitude", "REAL NULL")?;
+        self.add_column("asset", "longitude", "REAL NULL")?;
+        self.add_column("asset", "location_source", "TEXT NULL")?;
+
+        // databases created before Live Photos were paired
+        self.add_column("asset", "content_id", "TEXT NULL")?;
+
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS 
+                asset_hash(
+                    asset_id INTEGER NOT NULL,
+                    algorithm TEXT NOT NULL,
+                    hash TEXT NOT NULL,
+                    PRIMARY KEY (asset_id, algorithm)
+                )",
+            (),
+        )?;
+
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS asset_hash_lookup ON asset_hash(algorithm, hash)",
+            (),
+        )?;
         
         Ok(())
     }
+
+    /// Add a column to an existing table, unless it is already there.
+    fn add_column(&self, table: &str, column: &str, definition: &str) -> Result<(), StorageError> {
+        let mut stmt = self.conn.prepare(&format!("SELECT 1 FROM pragma_table_info('{}') WHERE name = ?1", table))?;
+        if !stmt.exists([column])? {
+            self.conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), ())?;
+        }
+        Ok(())
+    }
 }
 
 
@@ -53,6 +100,37 @@ impl Database {
 mod test {
     use rusqlite::Connection;
 
+    use crate::Database;
+
+    #[test]
+    fn migrate_existing_database() {
+        let db: Database = Database { conn: Connection::open_in_memory().unwrap() };
+        db.conn.execute(
+            "CREATE TABLE asset(
+                id INTEGER primary key autoincrement,
+                parent_id INTEGER NULL,
+                name TEXT NOT NULL,
+                class TEXT NOT NULL,
+                extension TEXT NOT NULL,
+                hash TEXT NOT NULL,
+                size BIGINT NOT NULL,
+                status TEXT NOT NULL,
+                path TEXT NULL,
+                partition INTEGER NULL,
+                created_at timestamp DEFAULT CURRENT_TIMESTAMP NULL
+            )",
+            (),
+        ).unwrap();
+
+        db.migrate().unwrap();
+        db.migrate().unwrap();
+
+        let columns: usize = db.conn
+            .query_row("SELECT COUNT(*) FROM pragma_table_info('asset') WHERE name LIKE 'captured_%' OR name = 'relation' OR name IN ('latitude', 'longitude', 'location_source', 'content_id')", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(columns, 7);
+    }
+
     #[test]
     fn provision_db() {
         let conn: Connection = Connection::open("./../db_test.db3").unwrap();
@@ -138,6 +138,13 @@ mod test {
             status: Status::Available, 
             path: None, 
             partition: partition,
+            captured_at: None,
+            captured_source: None,
+            relation: None,
+            latitude: None,
+            longitude: None,
+            location_source: None,
+            content_id: None,
         }
     }
     